@@ -0,0 +1,23 @@
+//! Feeds arbitrary agenda markup to the parser: it may reject a page, but never panic.
+//!
+//! ```sh
+//! cargo +nightly fuzz run parse_timetable
+//! ```
+
+#![no_main]
+
+use std::sync::LazyLock;
+
+use chrono::NaiveDate;
+use crossfit_timetable::scraper::CrossfitScraper;
+use libfuzzer_sys::fuzz_target;
+use url::Url;
+
+static SCRAPER: LazyLock<CrossfitScraper> =
+    LazyLock::new(|| CrossfitScraper::new(Url::parse("https://gym.example.com").unwrap()));
+
+fuzz_target!(|html: &str| {
+    let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+    let source_url = SCRAPER.week_url(monday);
+    let _ = SCRAPER.parse_timetable_page(html, monday, None, &source_url);
+});
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::ClassItem;
+
+/// A class the token owner marked as attended.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AttendanceRecord {
+    pub class_id: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub date: NaiveDateTime,
+    pub event_name: String,
+    pub coach: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl AttendanceRecord {
+    pub fn new(class: &ClassItem, recorded_at: DateTime<Utc>) -> Self {
+        Self {
+            class_id: class.id.clone(),
+            date: class.date,
+            event_name: class.event_name.clone(),
+            coach: class.coach.clone(),
+            recorded_at,
+        }
+    }
+
+    fn week(&self) -> NaiveDate {
+        let day = self.date.date();
+        day - Duration::days(day.weekday().num_days_from_monday().into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AttendanceWeek {
+    /// Monday of the week
+    #[schema(value_type = String, format = "date")]
+    pub week: NaiveDate,
+    pub total: usize,
+    /// Attended classes per class name
+    pub by_type: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct AttendanceSummary {
+    pub total: usize,
+    /// Weeks with at least one attended class, oldest first
+    pub weeks: Vec<AttendanceWeek>,
+    pub classes: Vec<AttendanceRecord>,
+}
+
+pub fn summarize(mut records: Vec<AttendanceRecord>) -> AttendanceSummary {
+    records.sort_by_key(|record| record.date);
+    let mut weeks: BTreeMap<NaiveDate, AttendanceWeek> = BTreeMap::new();
+    for record in &records {
+        let week = weeks
+            .entry(record.week())
+            .or_insert_with(|| AttendanceWeek {
+                week: record.week(),
+                total: 0,
+                by_type: BTreeMap::new(),
+            });
+        week.total += 1;
+        *week.by_type.entry(record.event_name.clone()).or_default() += 1;
+    }
+
+    AttendanceSummary {
+        total: records.len(),
+        weeks: weeks.into_values().collect(),
+        classes: records,
+    }
+}
+
+/// One `week,event_name,count` row per week and class name.
+pub fn to_csv(summary: &AttendanceSummary) -> String {
+    let mut csv = String::from("week,event_name,count\n");
+    for week in &summary.weeks {
+        for (event_name, count) in &week.by_type {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                week.week,
+                csv_field(event_name),
+                count
+            ));
+        }
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(date: &str, event_name: &str) -> AttendanceRecord {
+        AttendanceRecord {
+            class_id: format!("{date}-{event_name}"),
+            date: NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name: event_name.to_string(),
+            coach: "Coach".to_string(),
+            recorded_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_summarize_by_week_and_type() {
+        let summary = summarize(vec![
+            record("2025-12-01 06:00", "WOD"),
+            record("2025-11-24 06:00", "WOD"),
+            record("2025-11-26 18:00", "HYROX"),
+            record("2025-11-28 06:00", "WOD"),
+        ]);
+
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.weeks.len(), 2);
+        assert_eq!(
+            summary.weeks[0].week,
+            NaiveDate::from_ymd_opt(2025, 11, 24).unwrap()
+        );
+        assert_eq!(summary.weeks[0].total, 3);
+        assert_eq!(summary.weeks[0].by_type["WOD"], 2);
+        assert_eq!(summary.weeks[1].total, 1);
+        assert_eq!(summary.classes[0].event_name, "WOD");
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let summary = summarize(vec![
+            record("2025-11-24 06:00", "WOD"),
+            record("2025-11-25 06:00", "Open Gym, free"),
+        ]);
+
+        assert_eq!(
+            to_csv(&summary),
+            "week,event_name,count\n2025-11-24,\"Open Gym, free\",1\n2025-11-24,WOD,1\n"
+        );
+    }
+}
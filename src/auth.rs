@@ -1,25 +1,272 @@
+use std::convert::Infallible;
+
 use crate::settings::Settings;
-use axum_extra::headers::Authorization;
-use axum_extra::headers::authorization::Bearer;
+use axum::extract::{OptionalFromRequestParts, Request, State};
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::headers::authorization::{Basic, Bearer};
+use axum_extra::headers::{Authorization, HeaderMapExt};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use tracing::warn;
 
+use crate::AppState;
 use crate::error::ApiError;
+use crate::models::Branding;
+use crate::preferences::is_preference_token;
+
+/// Credentials sent in the `Authorization` header.
+#[derive(Debug, Clone)]
+pub enum AuthHeader {
+    Bearer(Authorization<Bearer>),
+    Basic(Authorization<Basic>),
+}
+
+impl From<Authorization<Bearer>> for AuthHeader {
+    fn from(value: Authorization<Bearer>) -> Self {
+        AuthHeader::Bearer(value)
+    }
+}
+
+impl From<Authorization<Basic>> for AuthHeader {
+    fn from(value: Authorization<Basic>) -> Self {
+        AuthHeader::Basic(value)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for AuthHeader {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Option<Self>, Infallible> {
+        Ok(AuthHeader::from_headers(&parts.headers))
+    }
+}
+
+impl AuthHeader {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        if let Some(bearer) = headers.typed_get::<Authorization<Bearer>>() {
+            return Some(bearer.into());
+        }
+        headers.typed_get::<Authorization<Basic>>().map(Into::into)
+    }
+}
 
 pub fn verify_token(
     settings: &Settings,
-    auth: Option<Authorization<Bearer>>,
+    auth: Option<AuthHeader>,
     query_token: Option<&str>,
 ) -> Result<(), ApiError> {
-    let provided_token = auth
-        .map(|a| a.token().to_string())
-        .or_else(|| query_token.map(|s| s.to_string()));
+    let provided_token = match auth {
+        Some(AuthHeader::Basic(basic)) => return verify_basic(settings, &basic),
+        Some(AuthHeader::Bearer(bearer)) => Some(bearer.token().to_string()),
+        None => query_token.map(|s| s.to_string()),
+    };
     match provided_token {
         Some(token) if token == settings.auth_token => Ok(()),
+        Some(token) if is_previous_token(settings, &token, Utc::now()) => {
+            warn!("request authenticated with the previous auth token; update the client");
+            Ok(())
+        }
         _ => Err(ApiError::Unauthorized(
             "Invalid authentication token".into(),
         )),
     }
 }
 
+/// Accepts only the primary `APP_AUTH_TOKEN`, for routes that make the server fetch URLs the
+/// caller picks; OIDC users, Basic-auth users and other tokens are turned away.
+pub fn authorize_primary(
+    settings: &Settings,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<(), ApiError> {
+    let token = match &auth {
+        Some(AuthHeader::Bearer(bearer)) => Some(bearer.token()),
+        Some(AuthHeader::Basic(_)) => None,
+        None => query_token,
+    };
+    match token {
+        Some(token) if token == settings.auth_token => Ok(()),
+        _ => Err(ApiError::Unauthorized(
+            "This route requires the primary auth token".into(),
+        )),
+    }
+}
+
+// Basic auth is only accepted when both username and password are configured
+fn verify_basic(settings: &Settings, basic: &Authorization<Basic>) -> Result<(), ApiError> {
+    match (&settings.basic_auth_username, &settings.basic_auth_password) {
+        (Some(username), Some(password))
+            if basic.username() == username && basic.password() == password =>
+        {
+            Ok(())
+        }
+        (Some(_), Some(_)) => Err(ApiError::BasicAuthRequired(
+            "Invalid username or password".into(),
+        )),
+        _ => Err(ApiError::Unauthorized(
+            "Basic authentication is not enabled".into(),
+        )),
+    }
+}
+
+// During rotation the old token stays valid until the configured overlap window ends
+fn is_previous_token(settings: &Settings, token: &str, now: DateTime<Utc>) -> bool {
+    match (
+        &settings.previous_auth_token,
+        settings.previous_auth_token_valid_until,
+    ) {
+        (Some(previous), Some(valid_until)) => token == previous && now < valid_until,
+        (Some(previous), None) => token == previous,
+        _ => false,
+    }
+}
+
+/// Stable key for data owned by the presented static credential (e.g. favorites). Tokens are
+/// hashed so that they never end up in the database; OIDC users are keyed by `authorize_owner`.
+pub fn credential_owner(auth: Option<&AuthHeader>, query_token: Option<&str>) -> Option<String> {
+    let token = match auth {
+        Some(AuthHeader::Basic(basic)) => return Some(format!("basic:{}", basic.username())),
+        Some(AuthHeader::Bearer(bearer)) => bearer.token(),
+        None => query_token?,
+    };
+    let hash: String = Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Some(format!("token:{hash}"))
+}
+
+/// Accepts the static token first and falls back to the OIDC provider for Bearer tokens.
+pub async fn authorize(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<(), ApiError> {
+    authenticate(state, auth, query_token).await.map(|_| ())
+}
+
+// `authorize`, returning the subject of OIDC users
+async fn authenticate(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<Option<String>, ApiError> {
+    let bearer = match &auth {
+        Some(AuthHeader::Bearer(bearer)) => Some(bearer.token().to_string()),
+        _ => None,
+    };
+    let result = verify_token(&state.settings, auth, query_token);
+    if result.is_ok() {
+        return Ok(None);
+    }
+
+    match (&state.oidc, bearer) {
+        (Some(oidc), Some(token)) => oidc.validate(&token).await.map(Some).map_err(|err| {
+            warn!(error = %err, "OIDC token validation failed");
+            ApiError::Unauthorized("Invalid authentication token".into())
+        }),
+        // Let calendar clients that only speak Basic auth know they may retry with it
+        (_, None) if state.settings.basic_auth_enabled() => match result {
+            Err(ApiError::Unauthorized(message)) => Err(ApiError::BasicAuthRequired(message)),
+            other => other.map(|()| None),
+        },
+        _ => result.map(|()| None),
+    }
+}
+
+/// Authorization for the timetable and the caller's own data (favorites, attendance): besides
+/// the credentials `authorize` takes, it accepts the tokens given their own default parameters
+/// (`APP_TOKEN_PREFERENCES`), which are no admin credential.
+pub async fn authorize_member(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<(), ApiError> {
+    authenticate_member(state, auth, query_token)
+        .await
+        .map(|_| ())
+}
+
+/// Authenticates like `authorize_member` and returns the key the caller's own data is stored
+/// under: OIDC users by issuer and subject, so their data outlives rotating access tokens, and
+/// static credentials by `credential_owner`.
+pub async fn authorize_owner(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<String, ApiError> {
+    let owner = credential_owner(auth.as_ref(), query_token);
+    if let Some(subject) = authenticate_member(state, auth, query_token).await? {
+        let issuer = state
+            .settings
+            .oidc_issuer_url
+            .as_ref()
+            .map_or("", |url| url.as_str().trim_end_matches('/'));
+        return Ok(format!("oidc:{issuer}#{subject}"));
+    }
+    owner.ok_or_else(|| ApiError::Unauthorized("Missing authentication token".into()))
+}
+
+// `authorize_member`, returning the subject of OIDC users
+async fn authenticate_member(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<Option<String>, ApiError> {
+    let token = match &auth {
+        Some(AuthHeader::Bearer(bearer)) => Some(bearer.token()),
+        Some(AuthHeader::Basic(_)) => None,
+        None => query_token,
+    };
+    if token.is_some_and(|token| is_preference_token(&state.settings, token)) {
+        return Ok(None);
+    }
+    authenticate(state, auth, query_token).await
+}
+
+/// Authorization for the read-only data endpoints, which may be configured as public.
+pub async fn authorize_read(
+    state: &AppState,
+    auth: Option<AuthHeader>,
+    query_token: Option<&str>,
+) -> Result<(), ApiError> {
+    if state.settings.public_read {
+        return Ok(());
+    }
+    authorize_member(state, auth, query_token).await
+}
+
+/// Verifies a signed subscription link (`sid`, `exp`, `sig` query parameters).
+pub fn verify_share_link(
+    state: &AppState,
+    share_id: Option<&str>,
+    expires: Option<i64>,
+    weeks: u8,
+    branding: &Branding,
+    signature: &str,
+) -> Result<(), ApiError> {
+    let (Some(signer), Some(share_id), Some(expires)) = (&state.share, share_id, expires) else {
+        return Err(ApiError::Unauthorized("Invalid share link".into()));
+    };
+    let now = chrono::Utc::now().timestamp();
+    signer.verify(share_id, expires, weeks, branding, signature, now)?;
+    Ok(())
+}
+
+/// Middleware guarding routes that have no handler-level authentication (e.g. Swagger UI).
+pub async fn require_auth(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    authorize(&state, auth, None).await?;
+    Ok(next.run(request).await)
+}
+
 #[cfg(test)]
 mod tests {
     use url::Url;
@@ -39,9 +286,10 @@ mod tests {
             gym_longitude: 22.0026,
             gym_title: "CrossFit 2.0 Rzeszów".to_string(),
             gym_location: "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string(),
+            ..Settings::default()
         };
         let auth = Authorization::bearer("secret").unwrap();
-        assert!(verify_token(&settings, Some(auth), None).is_ok());
+        assert!(verify_token(&settings, Some(auth.into()), None).is_ok());
     }
 
     #[test]
@@ -57,8 +305,53 @@ mod tests {
             gym_longitude: 22.0026,
             gym_title: "CrossFit 2.0 Rzeszów".to_string(),
             gym_location: "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string(),
+            ..Settings::default()
         };
         assert!(verify_token(&settings, None, Some("secret")).is_ok());
         assert!(verify_token(&settings, None, Some("bad")).is_err());
     }
+
+    #[test]
+    fn test_verify_previous_token_within_overlap() {
+        let now = Utc::now();
+        let settings = Settings {
+            auth_token: "new-secret".to_string(),
+            previous_auth_token: Some("old-secret".to_string()),
+            previous_auth_token_valid_until: Some(now + chrono::Duration::days(7)),
+            ..Settings::default()
+        };
+        assert!(verify_token(&settings, None, Some("new-secret")).is_ok());
+        assert!(verify_token(&settings, None, Some("old-secret")).is_ok());
+        assert!(is_previous_token(&settings, "old-secret", now));
+        assert!(!is_previous_token(
+            &settings,
+            "old-secret",
+            now + chrono::Duration::days(8)
+        ));
+    }
+
+    #[test]
+    fn test_verify_basic_auth() {
+        let settings = Settings {
+            auth_token: "secret".to_string(),
+            basic_auth_username: Some("calendar".to_string()),
+            basic_auth_password: Some("hunter2".to_string()),
+            ..Settings::default()
+        };
+        let valid = Authorization::basic("calendar", "hunter2");
+        let invalid = Authorization::basic("calendar", "secret");
+        assert!(verify_token(&settings, Some(valid.into()), None).is_ok());
+        assert!(matches!(
+            verify_token(&settings, Some(invalid.into()), None),
+            Err(ApiError::BasicAuthRequired(_))
+        ));
+
+        // Without configured credentials Basic auth is rejected outright
+        let disabled = Settings::default();
+        let basic = Authorization::basic("calendar", "hunter2");
+        assert!(matches!(
+            verify_token(&disabled, Some(basic.into()), None),
+            Err(ApiError::Unauthorized(_))
+        ));
+    }
 }
@@ -0,0 +1,76 @@
+//! Shields.io-style SVG badges, e.g. `next class | WOD · Mon 18:00`, for embedding in web
+//! pages and status pages without JavaScript.
+
+// Message background of a badge with a class, without one, and when the timetable is down
+pub const COLOR_OK: &str = "#4c1";
+pub const COLOR_NONE: &str = "#9f9f9f";
+pub const COLOR_DOWN: &str = "#e05d44";
+
+// Horizontal padding around each half's text
+const PADDING: u32 = 10;
+
+/// The SVG of a flat badge with a grey `label` half and a `message` half in `color`.
+pub fn render(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label) + PADDING;
+    let message_width = text_width(message) + PADDING;
+    let width = label_width + message_width;
+    let title = escape(&format!("{label}: {message}"));
+    let (label, message, color) = (escape(label), escape(message), escape(color));
+    // Text positions are in tenths of a pixel, scaled down, as shields.io does for crisp text
+    let label_x = label_width * 5;
+    let message_x = label_width * 10 + message_width * 5;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{title}"><title>{title}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110" transform="scale(.1)"><text x="{label_x}" y="150" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="140">{label}</text><text x="{message_x}" y="150" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="140">{message}</text></g></svg>"##
+    )
+}
+
+// Approximate width of `text` in 11px Verdana; narrow and wide glyphs are told apart so long
+// class names don't overflow their half
+fn text_width(text: &str) -> u32 {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '|' | '!' | ' ' | '·' => 4,
+            'f' | 'r' | 't' | '(' | ')' | '-' => 5,
+            'm' | 'w' | 'M' | 'W' => 10,
+            c if c.is_uppercase() || c.is_ascii_digit() => 8,
+            _ => 7,
+        })
+        .sum()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_badge() {
+        let svg = render("next class", "WOD · Mon 18:00", COLOR_OK);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<title>next class: WOD · Mon 18:00</title>"));
+        assert!(svg.contains(r##"fill="#4c1""##));
+        let width = text_width("next class") + text_width("WOD · Mon 18:00") + 2 * PADDING;
+        assert!(svg.contains(&format!(r#"width="{width}""#)));
+    }
+
+    #[test]
+    fn test_render_escapes_text() {
+        let svg = render("next class", "Mobility & <Stretch>", COLOR_OK);
+
+        assert!(svg.contains("Mobility &amp; &lt;Stretch&gt;"));
+        assert!(!svg.contains("<Stretch>"));
+    }
+
+    #[test]
+    fn test_wider_text_gets_wider_half() {
+        assert!(text_width("Weightlifting") > text_width("WOD"));
+        assert!(text_width("WWW") > text_width("iii"));
+    }
+}
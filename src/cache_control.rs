@@ -0,0 +1,171 @@
+//! Configurable `Cache-Control`/`Expires` headers for successful responses.
+
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+use crate::settings::Settings;
+
+#[derive(Debug, Error)]
+pub enum CacheRuleError {
+    #[error("APP_CACHE_CONTROL entry {0:?} must look like `/path=directives`")]
+    Malformed(String),
+}
+
+/// `Cache-Control` directives per request path. A path ending in `*` matches every path
+/// starting with the part before it; the first matching rule wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheRules {
+    rules: Vec<(String, String)>,
+}
+
+impl CacheRules {
+    pub fn from_settings(settings: &Settings) -> Result<Self, CacheRuleError> {
+        let rules = settings
+            .cache_control
+            .iter()
+            .map(|rule| {
+                let (path, directives) = rule
+                    .split_once('=')
+                    .map(|(path, directives)| (path.trim(), directives.trim()))
+                    .filter(|(path, directives)| path.starts_with('/') && !directives.is_empty())
+                    .ok_or_else(|| CacheRuleError::Malformed(rule.clone()))?;
+                Ok((path.to_string(), directives.to_string()))
+            })
+            .collect::<Result<_, CacheRuleError>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The directives configured for `path`, if any.
+    pub fn directives_for(&self, path: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| match pattern.strip_suffix('*') {
+                Some(prefix) => path.starts_with(prefix),
+                None => path == pattern,
+            })
+            .map(|(_, directives)| directives.as_str())
+    }
+}
+
+/// The `Expires` value matching the `max-age` of `directives`, for HTTP/1.0 caches and
+/// calendar clients ignoring `Cache-Control`.
+pub fn expires_header(directives: &str, now: DateTime<Utc>) -> Option<String> {
+    let max_age = directives
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|seconds| seconds.trim().parse::<i64>().ok())?;
+    let expires = now + Duration::seconds(max_age);
+    Some(expires.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+#[cfg(feature = "server")]
+pub use middleware::cache_headers;
+
+#[cfg(feature = "server")]
+mod middleware {
+    use std::sync::Arc;
+
+    use axum::extract::{Request, State};
+    use axum::http::{HeaderValue, Method, header};
+    use axum::middleware::Next;
+    use axum::response::Response;
+    use chrono::Utc;
+
+    use super::{CacheRules, expires_header};
+
+    /// Adds the configured headers to successful `GET`/`HEAD` responses that don't set
+    /// `Cache-Control` themselves.
+    pub async fn cache_headers(
+        State(rules): State<Arc<CacheRules>>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let cacheable = matches!(*request.method(), Method::GET | Method::HEAD);
+        let directives = rules
+            .directives_for(request.uri().path())
+            .filter(|_| cacheable)
+            .map(str::to_string);
+        let mut response = next.run(request).await;
+
+        let Some(directives) = directives else {
+            return response;
+        };
+        if !response.status().is_success() || response.headers().contains_key(header::CACHE_CONTROL)
+        {
+            return response;
+        }
+        let Ok(value) = HeaderValue::from_str(&directives) else {
+            return response;
+        };
+        let headers = response.headers_mut();
+        headers.insert(header::CACHE_CONTROL, value);
+        if let Some(expires) = expires_header(&directives, Utc::now())
+            && let Ok(expires) = HeaderValue::from_str(&expires)
+        {
+            headers.insert(header::EXPIRES, expires);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn rules(rules: &[&str]) -> Result<CacheRules, CacheRuleError> {
+        CacheRules::from_settings(&Settings {
+            cache_control: rules.iter().map(|rule| rule.to_string()).collect(),
+            ..Settings::default()
+        })
+    }
+
+    #[test]
+    fn test_directives_for_path() {
+        let rules = rules(&[
+            "/timetable.ical = public, max-age=900",
+            "/timetable=private, max-age=60",
+            "/me/*=no-store",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            rules.directives_for("/timetable.ical"),
+            Some("public, max-age=900")
+        );
+        assert_eq!(
+            rules.directives_for("/timetable"),
+            Some("private, max-age=60")
+        );
+        assert_eq!(rules.directives_for("/me/favorites"), Some("no-store"));
+        assert_eq!(rules.directives_for("/timetable.csv"), None);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(matches!(
+            rules(&["public, max-age=900"]),
+            Err(CacheRuleError::Malformed(_))
+        ));
+        assert!(matches!(
+            rules(&["/timetable="]),
+            Err(CacheRuleError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_expires_header() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 6, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            expires_header("public, max-age=900", now).as_deref(),
+            Some("Mon, 06 Jan 2025 12:15:00 GMT")
+        );
+        assert_eq!(expires_header("no-store", now), None);
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use chrono::{NaiveDate, Utc};
+use thiserror::Error;
+
+use crate::fixtures::{self, FIXTURE_DIR};
+use crate::handlers::requested_mondays;
+use crate::ical::ICalExporter;
+use crate::models::dedup_classes;
+use crate::scraper::CrossfitScraper;
+use crate::settings::Settings;
+use crate::source;
+
+pub const USAGE: &str = "\
+Usage: crossfit-timetable [COMMAND]
+
+Commands:
+  serve             Run the HTTP API (default)
+  scrape            Print the timetable to stdout
+      --weeks <N>       Number of weeks starting with the current one, 1-6 (default: 1)
+      --from <DATE>     Date in the first week, YYYY-MM-DD (default: today)
+      --json | --ical   Output format (default: --json)
+  snapshot          Save the raw agenda pages as parser test fixtures
+      --weeks <N>       Number of weeks starting with the current one, 1-6 (default: 1)
+      --from <DATE>     Date in the first week, YYYY-MM-DD (default: today)
+      --dir <PATH>      Where to save them (default: tests/fixtures)
+  validate-config   Load the configuration and report problems
+  check-upstream    Fetch the current week once and report the result
+  help              Show this message
+
+Settings are read from APP_* variables and the config file, as for `serve`.";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CliError {
+    #[error("Unknown command `{0}`")]
+    UnknownCommand(String),
+    #[error("Unknown option `{0}`")]
+    UnknownOption(String),
+    #[error("Option `{0}` needs a value")]
+    MissingValue(&'static str),
+    #[error("Invalid value `{value}` for `{option}`")]
+    InvalidValue { option: &'static str, value: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Ical,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    Scrape {
+        weeks: u8,
+        from: Option<NaiveDate>,
+        format: OutputFormat,
+    },
+    Snapshot {
+        weeks: u8,
+        from: Option<NaiveDate>,
+        dir: PathBuf,
+    },
+    ValidateConfig,
+    CheckUpstream,
+    Help,
+}
+
+impl Command {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            None | Some("serve") => Command::Serve,
+            Some("scrape") => return parse_scrape(args),
+            Some("snapshot") => return parse_snapshot(args),
+            Some("validate-config") => Command::ValidateConfig,
+            Some("check-upstream") => Command::CheckUpstream,
+            Some("help" | "--help" | "-h") => return Ok(Command::Help),
+            Some(other) => return Err(CliError::UnknownCommand(other.to_string())),
+        };
+        match args.next() {
+            Some(extra) => Err(CliError::UnknownOption(extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+fn parse_scrape(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let (mut weeks, mut from, mut format) = (1, None, OutputFormat::Json);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => format = OutputFormat::Json,
+            "--ical" => format = OutputFormat::Ical,
+            "--weeks" => weeks = parse_weeks(&mut args)?,
+            "--from" => from = Some(parse_from(&mut args)?),
+            _ => return Err(CliError::UnknownOption(arg)),
+        }
+    }
+    Ok(Command::Scrape {
+        weeks,
+        from,
+        format,
+    })
+}
+
+fn parse_snapshot(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let (mut weeks, mut from, mut dir) = (1, None, PathBuf::from(FIXTURE_DIR));
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--weeks" => weeks = parse_weeks(&mut args)?,
+            "--from" => from = Some(parse_from(&mut args)?),
+            "--dir" => dir = args.next().ok_or(CliError::MissingValue("--dir"))?.into(),
+            _ => return Err(CliError::UnknownOption(arg)),
+        }
+    }
+    Ok(Command::Snapshot { weeks, from, dir })
+}
+
+fn parse_weeks(args: &mut impl Iterator<Item = String>) -> Result<u8, CliError> {
+    let value = args.next().ok_or(CliError::MissingValue("--weeks"))?;
+    value
+        .parse()
+        .ok()
+        .filter(|weeks| (1..=6).contains(weeks))
+        .ok_or(CliError::InvalidValue {
+            option: "--weeks",
+            value,
+        })
+}
+
+fn parse_from(args: &mut impl Iterator<Item = String>) -> Result<NaiveDate, CliError> {
+    let value = args.next().ok_or(CliError::MissingValue("--from"))?;
+    value.parse().map_err(|_| CliError::InvalidValue {
+        option: "--from",
+        value,
+    })
+}
+
+/// Runs the parsed command; `serve` blocks until the server stops.
+pub async fn execute(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Serve => crate::run().await,
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Command::Scrape {
+            weeks,
+            from,
+            format,
+        } => scrape(weeks, from, format).await,
+        Command::Snapshot { weeks, from, dir } => snapshot(weeks, from, dir).await,
+        Command::ValidateConfig => validate_config().await,
+        Command::CheckUpstream => check_upstream().await,
+    }
+}
+
+async fn scrape(
+    weeks: u8,
+    from: Option<NaiveDate>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::load()?;
+    let source = source::from_settings(&settings)?;
+    let location = match settings.location.clone() {
+        Some(location) => Some(location),
+        None => source.fetch_location().await,
+    };
+
+    let mut classes = Vec::new();
+    for monday in requested_mondays(from, weeks, settings.timezone) {
+        classes.extend(source.fetch_week(monday, location.clone()).await?);
+    }
+    dedup_classes(&mut classes);
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&classes)?),
+        OutputFormat::Ical => {
+            let calendar = ICalExporter::new().generate(&classes, &settings);
+            print!("{}", String::from_utf8_lossy(&calendar));
+        }
+    }
+    Ok(())
+}
+
+async fn snapshot(
+    weeks: u8,
+    from: Option<NaiveDate>,
+    dir: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::load()?;
+    let scraper = CrossfitScraper::from_settings(&settings)?;
+    for monday in requested_mondays(from, weeks, settings.timezone) {
+        let (monday, html) = scraper.fetch_week_html(Some(monday)).await?;
+        let path = fixtures::record(&dir, monday, &html, Utc::now())?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+async fn validate_config() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::from_env()?;
+    crate::check_settings(&settings).await?;
+    println!("Configuration OK");
+    Ok(())
+}
+
+async fn check_upstream() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::load()?;
+    let scraper = CrossfitScraper::from_settings(&settings)?;
+    let started = Instant::now();
+    let classes = scraper
+        .fetch_timetable(None, settings.location.clone())
+        .await?;
+    println!(
+        "{}: {} classes this week in {} ms",
+        settings.scraper_base_url,
+        classes.len(),
+        started.elapsed().as_millis()
+    );
+    if classes.is_empty() {
+        return Err("upstream returned no classes for the current week".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&[]), Ok(Command::Serve));
+        assert_eq!(parse(&["serve"]), Ok(Command::Serve));
+        assert_eq!(parse(&["validate-config"]), Ok(Command::ValidateConfig));
+        assert_eq!(parse(&["check-upstream"]), Ok(Command::CheckUpstream));
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(
+            parse(&["scrap"]),
+            Err(CliError::UnknownCommand("scrap".to_string()))
+        );
+        assert_eq!(
+            parse(&["serve", "--port"]),
+            Err(CliError::UnknownOption("--port".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_scrape() {
+        assert_eq!(
+            parse(&["scrape"]),
+            Ok(Command::Scrape {
+                weeks: 1,
+                from: None,
+                format: OutputFormat::Json
+            })
+        );
+        assert_eq!(
+            parse(&["scrape", "--ical", "--weeks", "2", "--from", "2025-11-24"]),
+            Ok(Command::Scrape {
+                weeks: 2,
+                from: NaiveDate::from_ymd_opt(2025, 11, 24),
+                format: OutputFormat::Ical
+            })
+        );
+        assert_eq!(
+            parse(&["scrape", "--weeks", "7"]),
+            Err(CliError::InvalidValue {
+                option: "--weeks",
+                value: "7".to_string()
+            })
+        );
+        assert_eq!(
+            parse(&["scrape", "--weeks"]),
+            Err(CliError::MissingValue("--weeks"))
+        );
+    }
+
+    #[test]
+    fn test_parse_snapshot() {
+        assert_eq!(
+            parse(&["snapshot"]),
+            Ok(Command::Snapshot {
+                weeks: 1,
+                from: None,
+                dir: PathBuf::from("tests/fixtures")
+            })
+        );
+        assert_eq!(
+            parse(&["snapshot", "--weeks", "2", "--dir", "/tmp/pages"]),
+            Ok(Command::Snapshot {
+                weeks: 2,
+                from: None,
+                dir: PathBuf::from("/tmp/pages")
+            })
+        );
+        assert_eq!(
+            parse(&["snapshot", "--ical"]),
+            Err(CliError::UnknownOption("--ical".to_string()))
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDateTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use crate::models::local_time;
+
+// Limits the search for the next run, e.g. for `0 0 30 2 *` which never fires
+const MAX_DAYS_AHEAD: u32 = 366 * 5;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CronError {
+    #[error("expected 5 or 6 fields, got {0}")]
+    FieldCount(usize),
+    #[error("invalid field `{0}`")]
+    InvalidField(String),
+}
+
+/// A cron expression: `minute hour day-of-month month day-of-week`, optionally preceded by
+/// a seconds field. Fields accept `*`, numbers, ranges (`6-22`), steps (`*/15`, `8-20/2`),
+/// comma-separated lists and three-letter month/day names. Sunday is `0` or `7`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Standard cron semantics: when both day fields are restricted, either may match
+    day_of_month_any: bool,
+    day_of_week_any: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+impl FromStr for CronSchedule {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let (seconds, rest) = match fields.len() {
+            5 => ("0", &fields[..]),
+            6 => (fields[0], &fields[1..]),
+            count => return Err(CronError::FieldCount(count)),
+        };
+
+        let mut days_of_week = parse_field(rest[4], 0, 7, &WEEKDAYS)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            seconds: parse_field(seconds, 0, 59, &[])?,
+            minutes: parse_field(rest[0], 0, 59, &[])?,
+            hours: parse_field(rest[1], 0, 23, &[])?,
+            days_of_month: parse_field(rest[2], 1, 31, &[])?,
+            months: parse_field(rest[3], 1, 12, &MONTHS)?,
+            days_of_week,
+            day_of_month_any: rest[2] == "*",
+            day_of_week_any: rest[4] == "*",
+        })
+    }
+}
+
+// Returns a bit mask of the allowed values; `names[i]` stands for `min + i`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField(field.to_string());
+    let value = |part: &str| -> Result<u32, CronError> {
+        let upper = part.to_ascii_uppercase();
+        if let Some(index) = names.iter().position(|name| *name == upper) {
+            return Ok(min + index as u32);
+        }
+        part.parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(invalid)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(invalid)?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else if step > 1 {
+            // `5/15` means every 15 starting at 5
+            (value(range)?, max)
+        } else {
+            let single = value(range)?;
+            (single, single)
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn contains(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+impl CronSchedule {
+    fn matches_day(&self, day: chrono::NaiveDate) -> bool {
+        if !contains(self.months, day.month()) {
+            return false;
+        }
+        let by_month_day = contains(self.days_of_month, day.day());
+        let by_weekday = contains(self.days_of_week, day.weekday().num_days_from_sunday());
+        match (self.day_of_month_any, self.day_of_week_any) {
+            (true, true) => true,
+            (true, false) => by_weekday,
+            (false, true) => by_month_day,
+            (false, false) => by_month_day || by_weekday,
+        }
+    }
+
+    /// The first time matching the schedule strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_nanosecond(0)? + Duration::seconds(1);
+        let mut day = start.date();
+        for _ in 0..MAX_DAYS_AHEAD {
+            if self.matches_day(day) {
+                for hour in (0..24).filter(|hour| contains(self.hours, *hour)) {
+                    for minute in (0..60).filter(|minute| contains(self.minutes, *minute)) {
+                        for second in (0..60).filter(|second| contains(self.seconds, *second)) {
+                            let candidate = day.and_hms_opt(hour, minute, second)?;
+                            if candidate >= start {
+                                return Some(candidate);
+                            }
+                        }
+                    }
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    /// The first time matching the schedule, read as wall-clock times of `after`'s zone,
+    /// strictly after `after`. A time repeated when the clocks go back fires once; a time
+    /// skipped when they go forward fires past the gap (2:30 at 3:30).
+    pub fn next_in(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let time_zone = after.timezone();
+        let mut from = after.naive_local();
+        loop {
+            let next = self.next_after(from)?;
+            let candidate = match time_zone.from_local_datetime(&next) {
+                LocalResult::Single(time) => time,
+                LocalResult::Ambiguous(first, second) if first > after => first,
+                LocalResult::Ambiguous(_, second) => second,
+                LocalResult::None => local_time(next, time_zone),
+            };
+            if candidate > after {
+                return Some(candidate);
+            }
+            from = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_weekly_schedule() {
+        let schedule: CronSchedule = "0 18 * * SUN".parse().unwrap();
+        // 2025-11-26 is a Wednesday
+        assert_eq!(
+            schedule.next_after(at("2025-11-26 10:00:00")),
+            Some(at("2025-11-30 18:00:00"))
+        );
+        assert_eq!(
+            schedule.next_after(at("2025-11-30 18:00:00")),
+            Some(at("2025-12-07 18:00:00"))
+        );
+        assert_eq!(schedule, "0 18 * * 7".parse().unwrap());
+    }
+
+    #[test]
+    fn test_ranges_steps_and_seconds() {
+        let schedule: CronSchedule = "30 */2 6-22 * * MON-FRI".parse().unwrap();
+        assert_eq!(
+            schedule.next_after(at("2025-11-28 22:59:00")),
+            Some(at("2025-12-01 06:00:30"))
+        );
+        assert_eq!(
+            schedule.next_after(at("2025-12-01 06:00:30")),
+            Some(at("2025-12-01 06:02:30"))
+        );
+    }
+
+    #[test]
+    fn test_day_of_month_or_weekday() {
+        // The 1st of the month or any Monday
+        let schedule: CronSchedule = "0 0 1 * 1".parse().unwrap();
+        assert_eq!(
+            schedule.next_after(at("2025-11-25 00:00:00")),
+            Some(at("2025-12-01 00:00:00"))
+        );
+        assert_eq!(
+            schedule.next_after(at("2025-12-01 00:00:00")),
+            Some(at("2025-12-08 00:00:00"))
+        );
+        assert_eq!(
+            "0 0 30 2 *"
+                .parse::<CronSchedule>()
+                .unwrap()
+                .next_after(at("2025-01-01 00:00:00")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_in_across_dst_changes() {
+        let warsaw = crate::models::GYM_TIME_ZONE;
+        let zoned = |value| local_time(at(value), warsaw);
+        let schedule: CronSchedule = "0 30 2 * * *".parse().unwrap();
+
+        // 2:30 doesn't exist on 2025-03-30 and fires once the clocks went forward
+        let next = schedule.next_in(zoned("2025-03-29 12:00:00")).unwrap();
+        assert_eq!(next.to_rfc3339(), "2025-03-30T03:30:00+02:00");
+        let next = schedule.next_in(next).unwrap();
+        assert_eq!(next.to_rfc3339(), "2025-03-31T02:30:00+02:00");
+
+        // 2:30 happens twice on 2025-10-26 but fires only the first time
+        let next = schedule.next_in(zoned("2025-10-25 12:00:00")).unwrap();
+        assert_eq!(next.to_rfc3339(), "2025-10-26T02:30:00+02:00");
+        let next = schedule.next_in(next).unwrap();
+        assert_eq!(next.to_rfc3339(), "2025-10-27T02:30:00+01:00");
+
+        // Started during the repeated hour, the second 2:45 is still ahead
+        let during = warsaw
+            .from_local_datetime(&at("2025-10-26 02:40:00"))
+            .latest()
+            .unwrap();
+        let every_quarter: CronSchedule = "0 */15 * * * *".parse().unwrap();
+        assert_eq!(
+            every_quarter.next_in(during).unwrap().to_rfc3339(),
+            "2025-10-26T02:45:00+01:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert_eq!(
+            "* * *".parse::<CronSchedule>(),
+            Err(CronError::FieldCount(3))
+        );
+        assert_eq!(
+            "0 24 * * *".parse::<CronSchedule>(),
+            Err(CronError::InvalidField("24".to_string()))
+        );
+        assert!("0 18 * * FUNDAY".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("0 20-8 * * *".parse::<CronSchedule>().is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::ClassItem;
+
+/// A single difference between two scrapes of the same week.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduleChange {
+    Added {
+        class: ClassItem,
+    },
+    Removed {
+        class: ClassItem,
+    },
+    TimeChanged {
+        class: ClassItem,
+        #[schema(value_type = String, format = "date-time")]
+        previous_date: NaiveDateTime,
+    },
+    CoachChanged {
+        class: ClassItem,
+        previous_coach: String,
+    },
+}
+
+/// Compares two scrapes of the same week. Classes are paired up in order of confidence:
+/// identical slots first, then the same class at the same time with another coach, then
+/// the same class and coach at the nearest other time; whatever is left was added or
+/// removed. This is the shared core of the change history and all notifications.
+pub fn diff_classes(previous: &[ClassItem], current: &[ClassItem]) -> Vec<ScheduleChange> {
+    let mut old: Vec<&ClassItem> = previous.iter().collect();
+    let mut new: Vec<&ClassItem> = current.iter().collect();
+    let mut changes = Vec::new();
+
+    take_pairs(&mut old, &mut new, |a, b| {
+        a.id == b.id && a.coach == b.coach
+    });
+
+    for (before, after) in take_pairs(&mut old, &mut new, |a, b| a.id == b.id) {
+        changes.push(ScheduleChange::CoachChanged {
+            class: after.clone(),
+            previous_coach: before.coach.clone(),
+        });
+    }
+
+    for (before, after) in take_nearest_pairs(&mut old, &mut new, |a, b| {
+        a.event_name == b.event_name && a.coach == b.coach
+    }) {
+        changes.push(ScheduleChange::TimeChanged {
+            class: after.clone(),
+            previous_date: before.date,
+        });
+    }
+
+    changes.extend(old.into_iter().map(|class| ScheduleChange::Removed {
+        class: class.clone(),
+    }));
+    changes.extend(new.into_iter().map(|class| ScheduleChange::Added {
+        class: class.clone(),
+    }));
+    changes.sort_by_key(|change| change.class().date);
+    changes
+}
+
+/// Compares a week to the one before, its classes moved forward by a week, so the changes
+/// show what differs from the usual schedule rather than every date.
+pub fn week_over_week(previous: &[ClassItem], current: &[ClassItem]) -> Vec<ScheduleChange> {
+    let shifted: Vec<ClassItem> = previous
+        .iter()
+        .map(|class| class.shifted(chrono::Duration::weeks(1)))
+        .collect();
+    diff_classes(&shifted, current)
+}
+
+// Removes and returns the first matching partner for each remaining old class
+fn take_pairs<'a>(
+    old: &mut Vec<&'a ClassItem>,
+    new: &mut Vec<&'a ClassItem>,
+    matches: impl Fn(&ClassItem, &ClassItem) -> bool,
+) -> Vec<(&'a ClassItem, &'a ClassItem)> {
+    let mut pairs = Vec::new();
+    old.retain(
+        |before| match new.iter().position(|after| matches(before, after)) {
+            Some(index) => {
+                pairs.push((*before, new.remove(index)));
+                false
+            }
+            None => true,
+        },
+    );
+    pairs
+}
+
+// Like `take_pairs`, but each old class is paired with the closest matching new class in
+// time, so a moved class isn't mistaken for an unrelated one added later in the week
+fn take_nearest_pairs<'a>(
+    old: &mut Vec<&'a ClassItem>,
+    new: &mut Vec<&'a ClassItem>,
+    matches: impl Fn(&ClassItem, &ClassItem) -> bool,
+) -> Vec<(&'a ClassItem, &'a ClassItem)> {
+    let mut pairs = Vec::new();
+    old.retain(|before| {
+        let nearest = new
+            .iter()
+            .enumerate()
+            .filter(|(_, after)| matches(before, after))
+            .min_by_key(|(_, after)| (after.date - before.date).abs())
+            .map(|(index, _)| index);
+        match nearest {
+            Some(index) => {
+                pairs.push((*before, new.remove(index)));
+                false
+            }
+            None => true,
+        }
+    });
+    pairs
+}
+
+impl ScheduleChange {
+    /// The class as it is now (or was, for removals).
+    pub fn class(&self) -> &ClassItem {
+        match self {
+            ScheduleChange::Added { class }
+            | ScheduleChange::Removed { class }
+            | ScheduleChange::TimeChanged { class, .. }
+            | ScheduleChange::CoachChanged { class, .. } => class,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScheduleChange::Added { .. } => "added",
+            ScheduleChange::Removed { .. } => "removed",
+            ScheduleChange::TimeChanged { .. } => "time_changed",
+            ScheduleChange::CoachChanged { .. } => "coach_changed",
+        }
+    }
+
+    /// One-line human readable description, used in notifications.
+    pub fn describe(&self) -> String {
+        match self {
+            ScheduleChange::Added { class } => format!(
+                "New: {} on {} ({})",
+                class.event_name,
+                class.date.format(DATE_FORMAT),
+                class.coach
+            ),
+            ScheduleChange::Removed { class } => format!(
+                "Cancelled: {} on {} ({})",
+                class.event_name,
+                class.date.format(DATE_FORMAT),
+                class.coach
+            ),
+            ScheduleChange::TimeChanged {
+                class,
+                previous_date,
+            } => format!(
+                "Moved: {} ({}) from {} to {}",
+                class.event_name,
+                class.coach,
+                previous_date.format(DATE_FORMAT),
+                class.date.format(DATE_FORMAT)
+            ),
+            ScheduleChange::CoachChanged {
+                class,
+                previous_coach,
+            } => format!(
+                "Coach change: {} on {}, {} → {}",
+                class.event_name,
+                class.date.format(DATE_FORMAT),
+                previous_coach,
+                class.coach
+            ),
+        }
+    }
+}
+
+const DATE_FORMAT: &str = "%a %d.%m %H:%M";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_identical_weeks_have_no_changes() {
+        let week = vec![
+            class("2025-11-24 06:00", "WOD", "Anna"),
+            class("2025-11-24 07:00", "WOD", "Anna"),
+        ];
+        assert!(diff_classes(&week, &week).is_empty());
+    }
+
+    #[test]
+    fn test_detects_each_kind_of_change() {
+        let previous = vec![
+            class("2025-11-24 06:00", "WOD", "Anna"),
+            class("2025-11-24 17:00", "HYROX", "Piotr"),
+            class("2025-11-25 18:00", "Open Gym", "Anna"),
+        ];
+        let current = vec![
+            class("2025-11-24 06:00", "WOD", "Marek"),
+            class("2025-11-24 18:00", "HYROX", "Piotr"),
+            class("2025-11-26 07:00", "Mobility", "Anna"),
+        ];
+
+        let changes = diff_classes(&previous, &current);
+
+        assert_eq!(
+            changes,
+            vec![
+                ScheduleChange::CoachChanged {
+                    class: current[0].clone(),
+                    previous_coach: "Anna".to_string(),
+                },
+                ScheduleChange::TimeChanged {
+                    class: current[1].clone(),
+                    previous_date: previous[1].date,
+                },
+                ScheduleChange::Removed {
+                    class: previous[2].clone(),
+                },
+                ScheduleChange::Added {
+                    class: current[2].clone(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let change = ScheduleChange::CoachChanged {
+            class: class("2025-11-24 06:00", "WOD", "Marek"),
+            previous_coach: "Anna".to_string(),
+        };
+        assert_eq!(
+            change.describe(),
+            "Coach change: WOD on Mon 24.11 06:00, Anna → Marek"
+        );
+    }
+
+    #[test]
+    fn test_empty_weeks() {
+        let week = vec![class("2025-11-24 06:00", "WOD", "Anna")];
+        assert!(diff_classes(&[], &[]).is_empty());
+        assert_eq!(
+            diff_classes(&[], &week),
+            vec![ScheduleChange::Added {
+                class: week[0].clone()
+            }]
+        );
+        assert_eq!(
+            diff_classes(&week, &[]),
+            vec![ScheduleChange::Removed {
+                class: week[0].clone()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_slots() {
+        // Two identical entries (e.g. two groups) and one of them disappears
+        let previous = vec![
+            class("2025-11-24 06:00", "WOD", "Anna"),
+            class("2025-11-24 06:00", "WOD", "Anna"),
+        ];
+        let current = vec![class("2025-11-24 06:00", "WOD", "Anna")];
+
+        assert_eq!(
+            diff_classes(&previous, &current),
+            vec![ScheduleChange::Removed {
+                class: previous[1].clone()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_moved_class_pairs_with_nearest_time() {
+        let previous = vec![class("2025-11-24 06:00", "WOD", "Anna")];
+        let current = vec![
+            class("2025-11-24 18:00", "WOD", "Anna"),
+            class("2025-11-24 07:00", "WOD", "Anna"),
+        ];
+
+        assert_eq!(
+            diff_classes(&previous, &current),
+            vec![
+                ScheduleChange::TimeChanged {
+                    class: current[1].clone(),
+                    previous_date: previous[0].date,
+                },
+                ScheduleChange::Added {
+                    class: current[0].clone(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serializes_with_kind_tag() {
+        let change = ScheduleChange::Added {
+            class: class("2025-11-24 06:00", "WOD", "Anna"),
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["kind"], "added");
+        assert_eq!(json["class"]["event_name"], "WOD");
+    }
+}
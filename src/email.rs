@@ -0,0 +1,465 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+use crate::cron::CronSchedule;
+use crate::diff::{ScheduleChange, week_over_week};
+use crate::models::{ClassItem, local_now};
+use crate::notify::{Notifier, NotifyError, reminder_text};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("SMTP connection error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("SMTP server timed out")]
+    Timeout,
+    #[error("SMTP server replied {code}: {message}")]
+    Rejected { code: u16, message: String },
+    #[error("Email digest is enabled but {0} is not set")]
+    MissingSetting(&'static str),
+    #[error("Invalid APP_EMAIL_DIGEST_SCHEDULE: {0}")]
+    InvalidSchedule(#[from] crate::cron::CronError),
+}
+
+/// Sends plain-text mail through an SMTP relay.
+///
+/// The connection is not encrypted, so point it at a trusted relay (a local Postfix,
+/// a mail sidecar or an internal smarthost) rather than a public submission server.
+pub struct SmtpMailer {
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+    from: String,
+    recipients: Vec<String>,
+}
+
+impl SmtpMailer {
+    /// Returns `None` unless an SMTP host is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, EmailError> {
+        let Some(host) = settings.smtp_host.clone() else {
+            return Ok(None);
+        };
+        let from = settings
+            .email_from
+            .clone()
+            .ok_or(EmailError::MissingSetting("APP_EMAIL_FROM"))?;
+        if settings.email_recipients.is_empty() {
+            return Err(EmailError::MissingSetting("APP_EMAIL_RECIPIENTS"));
+        }
+        Ok(Some(Self {
+            host,
+            port: settings.smtp_port,
+            credentials: settings
+                .smtp_username
+                .clone()
+                .zip(settings.smtp_password.clone()),
+            from,
+            recipients: settings.email_recipients.clone(),
+        }))
+    }
+
+    pub async fn send(&self, subject: &str, body: &str) -> Result<(), EmailError> {
+        tokio::time::timeout(SMTP_TIMEOUT, self.deliver(subject, body))
+            .await
+            .map_err(|_| EmailError::Timeout)?
+    }
+
+    async fn deliver(&self, subject: &str, body: &str) -> Result<(), EmailError> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let mut smtp = SmtpConnection {
+            stream: BufReader::new(stream),
+        };
+        smtp.expect(220).await?;
+        smtp.command("EHLO crossfit-timetable", 250).await?;
+        if let Some((username, password)) = &self.credentials {
+            let token = BASE64.encode(format!("\0{username}\0{password}"));
+            smtp.command(&format!("AUTH PLAIN {token}"), 235).await?;
+        }
+        smtp.command(&format!("MAIL FROM:<{}>", self.from), 250)
+            .await?;
+        for recipient in &self.recipients {
+            smtp.command(&format!("RCPT TO:<{recipient}>"), 250).await?;
+        }
+        smtp.command("DATA", 354).await?;
+        smtp.command(&self.message(subject, body), 250).await?;
+        smtp.command("QUIT", 221).await?;
+        Ok(())
+    }
+
+    fn message(&self, subject: &str, body: &str) -> String {
+        let mut message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+            self.from,
+            self.recipients.join(", "),
+            encode_header(subject),
+            Utc::now().to_rfc2822(),
+        );
+        for line in body.lines() {
+            // Dot-stuffing keeps a line starting with `.` from ending the message early
+            if line.starts_with('.') {
+                message.push('.');
+            }
+            message.push_str(line);
+            message.push_str("\r\n");
+        }
+        message.push('.');
+        message
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        let body: String = changes
+            .iter()
+            .map(|change| format!("{}\n", change.describe()))
+            .collect();
+        self.send(
+            &format!("CrossFit timetable changes: week of {monday}"),
+            &body,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        self.send(
+            &format!("Reminder: {} in {minutes_before} minutes", class.event_name),
+            &reminder_text(class, minutes_before),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+struct SmtpConnection {
+    stream: BufReader<TcpStream>,
+}
+
+impl SmtpConnection {
+    async fn command(&mut self, line: &str, expected: u16) -> Result<(), EmailError> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await?;
+        self.expect(expected).await
+    }
+
+    // Reads a (possibly multi-line) reply and checks its status code
+    async fn expect(&mut self, expected: u16) -> Result<(), EmailError> {
+        let mut message = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(EmailError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            let code = line.get(..3).and_then(|code| code.parse::<u16>().ok());
+            message.push_str(line.get(4..).unwrap_or_default().trim_end());
+            // `250-...` continues the reply, `250 ...` ends it
+            if line.as_bytes().get(3) == Some(&b'-') {
+                message.push(' ');
+                continue;
+            }
+            return match code {
+                Some(code) if code == expected || (expected == 250 && code == 251) => Ok(()),
+                code => Err(EmailError::Rejected {
+                    code: code.unwrap_or_default(),
+                    message,
+                }),
+            };
+        }
+    }
+}
+
+// RFC 2047 encoding so Polish characters survive in the subject
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+/// Plain-text schedule of a week grouped by day, optionally followed by the changes
+/// compared to the week before.
+pub fn render_digest(
+    monday: NaiveDate,
+    classes: &[ClassItem],
+    changes: Option<&[ScheduleChange]>,
+) -> String {
+    let mut body = format!("Timetable for the week of {monday}\n");
+    let mut current_day = None;
+    for class in classes {
+        let day = class.date.date();
+        if current_day != Some(day) {
+            body.push_str(&format!("\n{}\n", day.format("%A %d.%m")));
+            current_day = Some(day);
+        }
+        body.push_str(&format!(
+            "  {}  {} ({})\n",
+            class.date.format("%H:%M"),
+            class.event_name,
+            class.coach
+        ));
+    }
+    if classes.is_empty() {
+        body.push_str("\nNo classes scheduled.\n");
+    }
+
+    if let Some(changes) = changes {
+        body.push_str("\nCompared to last week:\n");
+        if changes.is_empty() {
+            body.push_str("  No changes\n");
+        }
+        for change in changes {
+            body.push_str(&format!("  {}\n", change.describe()));
+        }
+    }
+    body
+}
+
+/// Sends the digest of the upcoming week whenever `schedule` fires.
+pub async fn run_digest(
+    mailer: Arc<SmtpMailer>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    schedule: CronSchedule,
+    include_diff: bool,
+    time_zone: Tz,
+) {
+    loop {
+        let now = local_now(time_zone);
+        let Some(next) = schedule.next_in(now) else {
+            warn!("email digest schedule never fires again");
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        // A digest sent on Sunday evening covers the week starting the next day
+        let tomorrow = next.date_naive() + chrono::Duration::days(1);
+        let monday =
+            tomorrow - chrono::Duration::days(tomorrow.weekday().num_days_from_monday().into());
+        if let Err(err) =
+            send_digest(&mailer, source.as_ref(), &location, monday, include_diff).await
+        {
+            warn!(error = %err, %monday, "failed to send email digest");
+        }
+    }
+}
+
+async fn send_digest(
+    mailer: &SmtpMailer,
+    source: &dyn TimetableSource,
+    location: &Option<String>,
+    monday: NaiveDate,
+    include_diff: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let classes = source.fetch_week(monday, location.clone()).await?;
+    let changes = if include_diff {
+        let previous = source
+            .fetch_week(monday - chrono::Duration::weeks(1), location.clone())
+            .await?;
+        Some(week_over_week(&previous, &classes))
+    } else {
+        None
+    };
+
+    let body = render_digest(monday, &classes, changes.as_deref());
+    mailer
+        .send(&format!("CrossFit timetable: week of {monday}"), &body)
+        .await?;
+    info!(%monday, classes = classes.len(), "sent email digest");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_render_digest_with_changes() {
+        let previous = vec![
+            class("2025-11-17 06:00", "WOD", "Anna"),
+            class("2025-11-18 18:00", "HYROX", "Piotr"),
+        ];
+        let current = vec![
+            class("2025-11-24 06:00", "WOD", "Marek"),
+            class("2025-11-24 07:00", "WOD", "Anna"),
+            class("2025-11-25 18:00", "HYROX", "Piotr"),
+        ];
+        let changes = week_over_week(&previous, &current);
+
+        let body = render_digest(
+            NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            &current,
+            Some(&changes),
+        );
+
+        assert_eq!(
+            body,
+            "Timetable for the week of 2025-11-24\n\
+             \nMonday 24.11\n  06:00  WOD (Marek)\n  07:00  WOD (Anna)\n\
+             \nTuesday 25.11\n  18:00  HYROX (Piotr)\n\
+             \nCompared to last week:\n\
+             \x20 Coach change: WOD on Mon 24.11 06:00, Anna → Marek\n\
+             \x20 New: WOD on Mon 24.11 07:00 (Anna)\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_header() {
+        assert_eq!(encode_header("Week 48"), "Week 48");
+        assert_eq!(encode_header("Zajęcia"), "=?UTF-8?B?WmFqxJljaWE=?=");
+    }
+
+    #[test]
+    fn test_requires_sender_and_recipients() {
+        let settings = Settings {
+            smtp_host: Some("localhost".to_string()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            SmtpMailer::from_settings(&settings),
+            Err(EmailError::MissingSetting("APP_EMAIL_FROM"))
+        ));
+        assert!(
+            SmtpMailer::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    // Minimal SMTP server accepting one message and returning the transcript
+    async fn fake_smtp_server(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut transcript = String::new();
+        stream
+            .get_mut()
+            .write_all(b"220 localhost ESMTP\r\n")
+            .await
+            .unwrap();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap() == 0 {
+                return transcript;
+            }
+            transcript.push_str(&line);
+            let reply: &[u8] = if in_data {
+                if line != ".\r\n" {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if line.starts_with("EHLO") {
+                b"250-localhost\r\n250 AUTH PLAIN\r\n"
+            } else if line.starts_with("AUTH") {
+                b"235 ok\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                b"221 bye\r\n"
+            } else {
+                b"250 ok\r\n"
+            };
+            stream.get_mut().write_all(reply).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+        let settings = Settings {
+            smtp_host: Some("127.0.0.1".to_string()),
+            smtp_port: port,
+            smtp_username: Some("user".to_string()),
+            smtp_password: Some("secret".to_string()),
+            email_from: Some("timetable@example.com".to_string()),
+            email_recipients: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            ..Settings::default()
+        };
+        let mailer = SmtpMailer::from_settings(&settings).unwrap().unwrap();
+
+        mailer
+            .send("Week 48", "Monday\n.hidden line\n")
+            .await
+            .unwrap();
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("AUTH PLAIN AHVzZXIAc2VjcmV0\r\n"));
+        assert!(transcript.contains("MAIL FROM:<timetable@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<a@example.com>\r\nRCPT TO:<b@example.com>\r\n"));
+        assert!(transcript.contains("Subject: Week 48\r\n"));
+        assert!(transcript.contains("\r\n\r\nMonday\r\n..hidden line\r\n.\r\nQUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_recipient() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream
+                .write_all(b"220 hi\r\n250 ok\r\n250 ok\r\n550 no such user\r\n")
+                .await
+                .unwrap();
+            // Keep the connection open until the client gives up
+            let mut buffer = Vec::new();
+            let _ = tokio::io::AsyncReadExt::read_to_end(&mut stream, &mut buffer).await;
+        });
+        let settings = Settings {
+            smtp_host: Some("127.0.0.1".to_string()),
+            smtp_port: port,
+            email_from: Some("timetable@example.com".to_string()),
+            email_recipients: vec!["nobody@example.com".to_string()],
+            ..Settings::default()
+        };
+        let mailer = SmtpMailer::from_settings(&settings).unwrap().unwrap();
+
+        let result = mailer.send("Week 48", "body").await;
+
+        assert!(matches!(
+            result,
+            Err(EmailError::Rejected { code: 550, ref message }) if message == "no such user"
+        ));
+    }
+}
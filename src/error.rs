@@ -1,9 +1,20 @@
-use axum::http::StatusCode;
+use axum::Json;
+use axum::http::{StatusCode, header};
 use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::error;
+use utoipa::ToSchema;
 
+use crate::qr::QrError;
 use crate::scraper::ScrapeError;
+use crate::share::ShareError;
+use crate::sheets::SheetsError;
+use crate::storage::StoreError;
+
+// Seconds clients are asked to wait while the gym's timetable (or this service) is down for
+// maintenance
+pub const MAINTENANCE_RETRY_AFTER_SECS: u64 = 300;
 
 #[derive(Debug, Error)]
 pub enum ApiError {
@@ -11,21 +22,114 @@ pub enum ApiError {
     Unauthorized(String),
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Unauthorized: {0}")]
+    BasicAuthRequired(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     #[error("Not found: {0}")]
     NotFound(String),
+    /// The message and the seconds after which the client may retry
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String, u64),
+    /// The message and the seconds after which the client may retry
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String, u64),
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+/// Machine-readable kind of an error, one per status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    TooManyRequests,
+    ServiceUnavailable,
+    Internal,
+}
+
+/// JSON body of every error response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorCode,
+    #[schema(example = "weeks must be between 1 and 6")]
+    pub message: String,
+    /// Seconds to wait before retrying, also sent as `Retry-After`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    /// The `X-Request-Id` of the failed request, to quote when reporting a problem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::Unauthorized(_) | ApiError::BasicAuthRequired(_) => ErrorCode::Unauthorized,
+            ApiError::BadRequest(_) => ErrorCode::BadRequest,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::TooManyRequests(..) => ErrorCode::TooManyRequests,
+            ApiError::ServiceUnavailable(..) => ErrorCode::ServiceUnavailable,
+            ApiError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self.code() {
+            ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let (message, retry_after) = match self {
+            ApiError::TooManyRequests(message, retry_after)
+            | ApiError::ServiceUnavailable(message, retry_after) => (message, Some(*retry_after)),
+            ApiError::Unauthorized(message)
+            | ApiError::BadRequest(message)
+            | ApiError::BasicAuthRequired(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Internal(message) => (message, None),
+        };
+        ErrorBody {
+            error: self.code(),
+            message: message.clone(),
+            retry_after,
+            request_id: None,
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let status = match self {
-            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
-            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
-        (status, self.to_string()).into_response()
+        let mut response = (self.status(), Json(self.body())).into_response();
+        let headers = response.headers_mut();
+        match self {
+            ApiError::BasicAuthRequired(_) => {
+                headers.insert(
+                    header::WWW_AUTHENTICATE,
+                    header::HeaderValue::from_static(
+                        r#"Basic realm="crossfit-timetable", charset="UTF-8""#,
+                    ),
+                );
+            }
+            ApiError::TooManyRequests(_, retry_after)
+            | ApiError::ServiceUnavailable(_, retry_after) => {
+                headers.insert(header::RETRY_AFTER, retry_after.into());
+            }
+            _ => {}
+        }
+        response
     }
 }
 
@@ -36,6 +140,12 @@ impl From<ScrapeError> for ApiError {
                 ApiError::BadRequest(value.to_string())
             }
             ScrapeError::MissingTable => ApiError::Internal(value.to_string()),
+            ScrapeError::UpstreamMaintenance | ScrapeError::Paused => {
+                ApiError::ServiceUnavailable(value.to_string(), MAINTENANCE_RETRY_AFTER_SECS)
+            }
+            ScrapeError::RateLimited(retry_after) => {
+                ApiError::ServiceUnavailable(value.to_string(), retry_after.as_secs().max(1))
+            }
             ScrapeError::Http(err) => {
                 error!("HTTP error: {err}");
                 ApiError::Internal("Failed to fetch timetable".into())
@@ -43,3 +153,29 @@ impl From<ScrapeError> for ApiError {
         }
     }
 }
+
+impl From<ShareError> for ApiError {
+    fn from(value: ShareError) -> Self {
+        ApiError::Unauthorized(value.to_string())
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(value: StoreError) -> Self {
+        error!("Storage error: {value}");
+        ApiError::Internal("Failed to read stored timetable".into())
+    }
+}
+
+impl From<SheetsError> for ApiError {
+    fn from(value: SheetsError) -> Self {
+        error!("Google Sheets error: {value}");
+        ApiError::Internal("Failed to write the Google Sheet".into())
+    }
+}
+
+impl From<QrError> for ApiError {
+    fn from(value: QrError) -> Self {
+        ApiError::Internal(value.to_string())
+    }
+}
@@ -0,0 +1,147 @@
+//! Output formats of `/timetable.{format}`, looked up by name.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::hcal::HCalExporter;
+use crate::hours::OpenDay;
+use crate::ical::ICalExporter;
+use crate::jsonld::JsonLdExporter;
+use crate::models::{ClassItem, Closure};
+use crate::settings::Settings;
+
+/// Renders classes as a document of one format.
+pub trait Exporter: Send + Sync {
+    /// Name used as the extension of `/timetable.{format}`, e.g. `ical`.
+    fn format(&self) -> &'static str;
+
+    fn content_type(&self) -> &'static str;
+
+    /// File name offered for saving the document; inline documents have none.
+    fn file_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn render(&self, classes: &[ClassItem], settings: &Settings) -> Vec<u8>;
+
+    /// `render`, also showing the days the gym is closed; formats with no way to show them
+    /// leave them out.
+    fn render_with_closures(
+        &self,
+        classes: &[ClassItem],
+        _closures: &[Closure],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        self.render(classes, settings)
+    }
+
+    /// `render_with_closures`, also showing when the gym is open on each day; formats with no
+    /// way to show it leave it out.
+    fn render_with_opening_hours(
+        &self,
+        classes: &[ClassItem],
+        closures: &[Closure],
+        _open_days: &[OpenDay],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        self.render_with_closures(classes, closures, settings)
+    }
+}
+
+/// The available exporters by format; the default registry holds the built-in formats.
+#[derive(Clone)]
+pub struct ExporterRegistry {
+    exporters: BTreeMap<&'static str, Arc<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    pub fn empty() -> Self {
+        Self {
+            exporters: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an exporter, replacing any registered for the same format.
+    pub fn register(&mut self, exporter: impl Exporter + 'static) {
+        self.exporters.insert(exporter.format(), Arc::new(exporter));
+    }
+
+    pub fn get(&self, format: &str) -> Option<Arc<dyn Exporter>> {
+        self.exporters.get(format).cloned()
+    }
+
+    pub fn formats(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.exporters.keys().copied()
+    }
+}
+
+impl Default for ExporterRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(ICalExporter::new());
+        registry.register(JsonLdExporter);
+        registry.register(HCalExporter);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Csv;
+
+    impl Exporter for Csv {
+        fn format(&self) -> &'static str {
+            "csv"
+        }
+
+        fn content_type(&self) -> &'static str {
+            "text/csv"
+        }
+
+        fn render(&self, classes: &[ClassItem], _settings: &Settings) -> Vec<u8> {
+            classes
+                .iter()
+                .map(|class| format!("{},{}\n", class.date, class.event_name))
+                .collect::<String>()
+                .into_bytes()
+        }
+    }
+
+    #[test]
+    fn test_default_registry_has_built_in_formats() {
+        let registry = ExporterRegistry::default();
+        assert_eq!(
+            registry.formats().collect::<Vec<_>>(),
+            vec!["hcal", "ical", "jsonld"]
+        );
+        let ical = registry.get("ical").unwrap();
+        assert_eq!(ical.content_type(), "text/calendar");
+        assert_eq!(ical.file_name(), Some("crossfit_timetable.ics"));
+        assert_eq!(registry.get("jsonld").unwrap().file_name(), None);
+        assert!(registry.get("pdf").is_none());
+    }
+
+    #[test]
+    fn test_register_adds_a_format() {
+        let mut registry = ExporterRegistry::default();
+        registry.register(Csv);
+
+        let class = ClassItem::new(
+            chrono::NaiveDate::from_ymd_opt(2025, 11, 24)
+                .unwrap()
+                .and_hms_opt(6, 0, 0)
+                .unwrap(),
+            "WOD".to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        let body = registry
+            .get("csv")
+            .unwrap()
+            .render(&[class], &Settings::default());
+        assert_eq!(body, b"2025-11-24 06:00:00,WOD\n");
+    }
+}
@@ -0,0 +1,216 @@
+//! Rendered feeds kept for a short while, so a calendar client's `HEAD` before its `GET` (or
+//! a conditional `GET` answered with `304`) doesn't scrape and render the feed again.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use sha2::{Digest, Sha256};
+
+/// A rendered feed with its validators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedFeed {
+    pub content_type: String,
+    pub file_name: Option<String>,
+    pub body: Vec<u8>,
+    /// Quoted strong entity tag, derived from the body
+    pub etag: String,
+    /// When a feed with this body was first rendered, in whole seconds as HTTP dates have
+    pub last_modified: DateTime<Utc>,
+}
+
+impl RenderedFeed {
+    pub fn new(content_type: &str, file_name: Option<&str>, body: Vec<u8>) -> Self {
+        // iCalendar stamps every event with the time it was rendered; leaving those lines out
+        // keeps the tag of an unchanged feed
+        let mut hasher = Sha256::new();
+        for line in body
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.starts_with(b"DTSTAMP:"))
+        {
+            hasher.update(line);
+        }
+        let digest = hasher.finalize();
+        let etag = format!(
+            "\"{}\"",
+            digest[..16]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+        Self {
+            content_type: content_type.to_string(),
+            file_name: file_name.map(str::to_string),
+            body,
+            etag,
+            last_modified: Utc::now().trunc_subsecs(0),
+        }
+    }
+
+    /// `Last-Modified` as an HTTP date, e.g. `Mon, 24 Nov 2025 06:00:00 GMT`.
+    pub fn http_date(&self) -> String {
+        self.last_modified
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    /// Whether a client sending these conditional headers already has this feed.
+    pub fn not_modified(
+        &self,
+        if_none_match: Option<&str>,
+        if_modified_since: Option<&str>,
+    ) -> bool {
+        // `If-None-Match` wins over `If-Modified-Since` (RFC 9110, section 13.1.3)
+        if let Some(tags) = if_none_match {
+            return tags
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == self.etag);
+        }
+        if_modified_since
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| self.last_modified <= since)
+    }
+}
+
+// Upper bound on cached feeds, so a stream of distinct queries can't grow the cache unbounded
+const MAX_FEEDS: usize = 1_000;
+
+/// Rendered feeds by request, reused for `ttl`. A zero TTL keeps nothing.
+pub struct FeedCache {
+    ttl: Duration,
+    feeds: Mutex<HashMap<String, (Instant, Arc<RenderedFeed>)>>,
+}
+
+impl FeedCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            feeds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<RenderedFeed>> {
+        let feeds = self.feeds.lock().expect("feed cache lock poisoned");
+        feeds
+            .get(key)
+            .filter(|(rendered, _)| rendered.elapsed() < self.ttl)
+            .map(|(_, feed)| Arc::clone(feed))
+    }
+
+    /// Stores a freshly rendered feed, keeping the `Last-Modified` of an expired one with
+    /// the same body.
+    pub fn insert(&self, key: String, mut feed: RenderedFeed) -> Arc<RenderedFeed> {
+        let mut feeds = self.feeds.lock().expect("feed cache lock poisoned");
+        if let Some((_, previous)) = feeds.get(&key)
+            && previous.etag == feed.etag
+        {
+            feed.last_modified = previous.last_modified;
+        }
+        let feed = Arc::new(feed);
+        if self.ttl.is_zero() {
+            return feed;
+        }
+        // Expired feeds are only needed for their `Last-Modified`; drop the ones that outlived
+        // many TTLs, so one-off queries don't pile up
+        feeds.retain(|_, (rendered, _)| rendered.elapsed() < self.ttl * 10);
+        if feeds.len() >= MAX_FEEDS && !feeds.contains_key(&key) {
+            let oldest = feeds
+                .iter()
+                .min_by_key(|(_, (rendered, _))| *rendered)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                feeds.remove(&oldest);
+            }
+        }
+        feeds.insert(key, (Instant::now(), Arc::clone(&feed)));
+        feed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_modified() {
+        let feed = RenderedFeed::new("text/calendar", None, b"BEGIN:VCALENDAR".to_vec());
+        let weak = format!("W/{}", feed.etag);
+        let later = (feed.last_modified + chrono::Duration::hours(1))
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+
+        assert!(feed.not_modified(Some(&feed.etag), None));
+        assert!(feed.not_modified(Some(&format!("\"other\", {weak}")), None));
+        assert!(!feed.not_modified(Some("\"other\""), Some(&later)));
+        assert!(feed.not_modified(None, Some(&feed.http_date())));
+        assert!(feed.not_modified(None, Some(&later)));
+        assert!(!feed.not_modified(None, Some("Mon, 24 Nov 2025 06:00:00 GMT")));
+        assert!(!feed.not_modified(None, None));
+    }
+
+    #[test]
+    fn test_etag_ignores_render_time() {
+        let feed = |stamp: &str| {
+            let body = format!("BEGIN:VEVENT\r\nDTSTAMP:{stamp}\r\nSUMMARY:WOD\r\nEND:VEVENT");
+            RenderedFeed::new("text/calendar", None, body.into_bytes())
+        };
+
+        assert_eq!(feed("20251124T060000Z").etag, feed("20251124T070000Z").etag);
+        assert_ne!(
+            feed("20251124T060000Z").etag,
+            RenderedFeed::new("text/calendar", None, b"SUMMARY:HYROX".to_vec()).etag
+        );
+    }
+
+    #[test]
+    fn test_insert_keeps_last_modified_of_unchanged_feed() {
+        let cache = FeedCache::new(Duration::from_secs(60));
+        let mut first = RenderedFeed::new("text/calendar", None, b"a".to_vec());
+        first.last_modified -= chrono::Duration::days(1);
+        cache.insert("feed".to_string(), first.clone());
+
+        let same = cache.insert(
+            "feed".to_string(),
+            RenderedFeed::new("text/calendar", None, b"a".to_vec()),
+        );
+        let changed = cache.insert(
+            "feed".to_string(),
+            RenderedFeed::new("text/calendar", None, b"b".to_vec()),
+        );
+
+        assert_eq!(same.last_modified, first.last_modified);
+        assert!(changed.last_modified > first.last_modified);
+        assert_eq!(cache.get("feed"), Some(changed));
+    }
+
+    #[test]
+    fn test_evicts_oldest_feed_when_full() {
+        let cache = FeedCache::new(Duration::from_secs(60));
+        let feed = || RenderedFeed::new("text/calendar", None, b"a".to_vec());
+        for index in 0..MAX_FEEDS {
+            cache.insert(format!("feed-{index}"), feed());
+        }
+
+        cache.insert("feed-0".to_string(), feed());
+        cache.insert("new".to_string(), feed());
+
+        assert_eq!(cache.feeds.lock().unwrap().len(), MAX_FEEDS);
+        assert!(cache.get("feed-0").is_some());
+        assert!(cache.get("feed-1").is_none());
+        assert!(cache.get("new").is_some());
+    }
+
+    #[test]
+    fn test_zero_ttl_keeps_nothing() {
+        let cache = FeedCache::new(Duration::ZERO);
+
+        cache.insert(
+            "feed".to_string(),
+            RenderedFeed::new("text/calendar", None, b"a".to_vec()),
+        );
+
+        assert_eq!(cache.get("feed"), None);
+    }
+}
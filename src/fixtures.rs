@@ -0,0 +1,98 @@
+//! Raw agenda pages recorded from the upstream, replayed through the parser to catch markup
+//! changes. Files are named `<monday>_<recorded at>.html`, e.g.
+//! `2025-11-24_20251124T101500Z.html`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Where `crossfit-timetable snapshot` writes and the fixture test reads recordings.
+pub const FIXTURE_DIR: &str = "tests/fixtures";
+
+/// A recorded agenda page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub path: PathBuf,
+    /// Monday of the recorded week
+    pub monday: NaiveDate,
+    pub html: String,
+}
+
+pub fn file_name(monday: NaiveDate, recorded_at: DateTime<Utc>) -> String {
+    format!("{monday}_{}.html", recorded_at.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// Monday of the week recorded in the file called `name`.
+pub fn monday_of(name: &str) -> Option<NaiveDate> {
+    let (monday, _) = name.strip_suffix(".html")?.split_once('_')?;
+    monday.parse().ok()
+}
+
+/// Saves `html` as a recording of the week of `monday`, creating `dir` if needed.
+pub fn record(
+    dir: &Path,
+    monday: NaiveDate,
+    html: &str,
+    recorded_at: DateTime<Utc>,
+) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(file_name(monday, recorded_at));
+    std::fs::write(&path, html)?;
+    Ok(path)
+}
+
+/// Every recording in `dir`, oldest week first; none when `dir` doesn't exist. Files not
+/// named like a recording are skipped.
+pub fn load(dir: &Path) -> io::Result<Vec<Fixture>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut fixtures = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(monday) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(monday_of)
+        else {
+            continue;
+        };
+        let html = std::fs::read_to_string(&path)?;
+        fixtures.push(Fixture { path, monday, html });
+    }
+    fixtures.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(fixtures)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_record_and_load() {
+        let dir = std::env::temp_dir().join(format!("fixtures-{}", std::process::id()));
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let recorded_at = Utc.with_ymd_and_hms(2025, 11, 24, 10, 15, 0).unwrap();
+
+        let path = record(&dir, monday, "<table></table>", recorded_at).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a recording").unwrap();
+        let fixtures = load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(path.ends_with("2025-11-24_20251124T101500Z.html"));
+        assert_eq!(
+            fixtures,
+            [Fixture {
+                path,
+                monday,
+                html: "<table></table>".to_string()
+            }]
+        );
+        assert!(load(&dir).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+//! The gyms served by one instance, described in a `gyms.toml` keyed by gym id:
+//!
+//! ```toml
+//! [rzeszow]
+//! base_url = "https://crossfit2-rzeszow.cms.efitness.com.pl"
+//! latitude = 50.0386
+//! longitude = 22.0026
+//! title = "CrossFit 2.0 Rzeszów"
+//! address = "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland"
+//! timezone = "Europe/Warsaw"
+//!
+//! [rzeszow.selectors]
+//! event_name = "p.event_title"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono_tz::Tz;
+use config::{Config, ConfigError, File, FileFormat};
+use scraper::Selector;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::models::GYM_TIME_ZONE;
+use crate::scraper::{CLASS_LINK_SELECTOR, EVENT_NAME_SELECTOR, TABLE_SELECTOR};
+
+// Loaded when present and `APP_GYMS_FILE` is unset
+const DEFAULT_GYMS_FILE: &str = "gyms.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Gym {
+    pub base_url: Url,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub title: String,
+    pub address: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    // For gyms whose agenda markup differs from the eFitness default
+    #[serde(default)]
+    pub selectors: SelectorOverrides,
+}
+
+/// CSS selectors replacing the scraper's defaults; unset ones keep them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SelectorOverrides {
+    pub table: Option<String>,
+    pub event_name: Option<String>,
+    pub class_link: Option<String>,
+}
+
+impl SelectorOverrides {
+    pub fn table(&self) -> &str {
+        self.table.as_deref().unwrap_or(TABLE_SELECTOR)
+    }
+
+    pub fn event_name(&self) -> &str {
+        self.event_name.as_deref().unwrap_or(EVENT_NAME_SELECTOR)
+    }
+
+    pub fn class_link(&self) -> &str {
+        self.class_link.as_deref().unwrap_or(CLASS_LINK_SELECTOR)
+    }
+}
+
+fn default_timezone() -> Tz {
+    GYM_TIME_ZONE
+}
+
+/// The configured gyms by id; empty when no gyms file is used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gyms {
+    gyms: BTreeMap<String, Gym>,
+}
+
+impl Gyms {
+    /// Loads and validates `path`, which must then exist; without it, `gyms.toml` in the
+    /// working directory is used when present.
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        let file = match path {
+            Some(path) => File::from(Path::new(path))
+                .format(FileFormat::Toml)
+                .required(true),
+            None => File::new(DEFAULT_GYMS_FILE, FileFormat::Toml).required(false),
+        };
+        Self::build(Config::builder().add_source(file))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        Self::build(Config::builder().add_source(File::from_str(toml, FileFormat::Toml)))
+    }
+
+    fn build(
+        builder: config::builder::ConfigBuilder<config::builder::DefaultState>,
+    ) -> Result<Self, ConfigError> {
+        let gyms = Self {
+            gyms: builder.build()?.try_deserialize()?,
+        };
+        gyms.validate()?;
+        Ok(gyms)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Gym> {
+        self.gyms.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Gym)> {
+        self.gyms.iter().map(|(id, gym)| (id.as_str(), gym))
+    }
+
+    pub fn len(&self) -> usize {
+        self.gyms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gyms.is_empty()
+    }
+
+    /// Checks what the types don't rule out, reporting every problem at once.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        for (id, gym) in &self.gyms {
+            if !matches!(gym.base_url.scheme(), "http" | "https") || gym.base_url.host().is_none() {
+                problems.push(format!("gym `{id}`: base_url must be an http(s) URL"));
+            }
+            if !(-90.0..=90.0).contains(&gym.latitude) {
+                problems.push(format!("gym `{id}`: latitude must be between -90 and 90"));
+            }
+            if !(-180.0..=180.0).contains(&gym.longitude) {
+                problems.push(format!(
+                    "gym `{id}`: longitude must be between -180 and 180"
+                ));
+            }
+            let selectors = [
+                ("table", &gym.selectors.table),
+                ("event_name", &gym.selectors.event_name),
+                ("class_link", &gym.selectors.class_link),
+            ];
+            for (name, selector) in selectors {
+                if let Some(selector) = selector
+                    && Selector::parse(selector).is_err()
+                {
+                    problems.push(format!(
+                        "gym `{id}`: selectors.{name} `{selector}` is not a valid CSS selector"
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError::Message(format!(
+            "invalid gyms file:\n  - {}",
+            problems.join("\n  - ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GYMS: &str = r#"
+        [rzeszow]
+        base_url = "https://crossfit2-rzeszow.cms.efitness.com.pl"
+        latitude = 50.0386
+        longitude = 22.0026
+        title = "CrossFit 2.0 Rzeszów"
+        address = "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland"
+
+        [lisbon]
+        base_url = "https://box-lisboa.example.com"
+        latitude = 38.7223
+        longitude = -9.1393
+        title = "Box Lisboa"
+        address = "Rua Augusta 1, Lisboa"
+        timezone = "Europe/Lisbon"
+
+        [lisbon.selectors]
+        event_name = "p.event_title"
+    "#;
+
+    #[test]
+    fn test_parse_gyms() {
+        let gyms = Gyms::from_toml(GYMS).unwrap();
+
+        assert_eq!(gyms.len(), 2);
+        let ids: Vec<_> = gyms.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["lisbon", "rzeszow"]);
+
+        let rzeszow = gyms.get("rzeszow").unwrap();
+        assert_eq!(rzeszow.timezone, chrono_tz::Europe::Warsaw);
+        assert_eq!(rzeszow.selectors.event_name(), EVENT_NAME_SELECTOR);
+
+        let lisbon = gyms.get("lisbon").unwrap();
+        assert_eq!(lisbon.timezone, chrono_tz::Europe::Lisbon);
+        assert_eq!(lisbon.selectors.event_name(), "p.event_title");
+        assert_eq!(lisbon.selectors.table(), TABLE_SELECTOR);
+    }
+
+    #[test]
+    fn test_invalid_gyms_report_every_problem() {
+        let toml = r#"
+            [broken]
+            base_url = "ftp://gym.example.com"
+            latitude = 91.0
+            longitude = 0.0
+            title = "Broken"
+            address = "Nowhere"
+
+            [broken.selectors]
+            table = "table["
+        "#;
+
+        let err = Gyms::from_toml(toml).unwrap_err().to_string();
+
+        assert!(err.contains("gym `broken`: base_url"));
+        assert!(err.contains("gym `broken`: latitude"));
+        assert!(err.contains("selectors.table `table[`"));
+    }
+
+    #[test]
+    fn test_load_without_file() {
+        assert!(Gyms::load(None).unwrap().is_empty());
+        assert!(Gyms::load(Some("/nonexistent/gyms.toml")).is_err());
+    }
+}
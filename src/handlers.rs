@@ -1,17 +1,269 @@
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
-use axum_extra::extract::TypedHeader;
-use axum_extra::headers::{Authorization, authorization::Bearer};
-use chrono::{Datelike, Duration, Local, NaiveDate};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{FromRequest, Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use futures::future::try_join_all;
+use tracing::{info, warn};
+use url::form_urlencoded;
 
 use crate::{
-    AppState, auth::verify_token, error::ApiError, models::ClassItem, validation::validate_weeks,
+    AppState,
+    attendance::{self, AttendanceRecord, AttendanceSummary},
+    auth::{
+        AuthHeader, authorize, authorize_owner, authorize_primary, authorize_read,
+        verify_share_link,
+    },
+    badge,
+    diff::ScheduleChange,
+    error::{ApiError, ErrorBody},
+    export::Exporter,
+    feeds::RenderedFeed,
+    health::HealthReport,
+    hours::{self, OpenDay, OpeningHours},
+    i18n::Lang,
+    ical::ICalExporter,
+    ip_filter::is_public_address,
+    links::{ClassLinks, class_links},
+    maintenance::MaintenanceStatus,
+    models::{
+        Branding, ChangeRecord, ClassItem, ClassKind, Closure, DataSource, Favorites, IsoWeek,
+        NewClassTrigger, NextClass, ShareLink, ShareRequest, TimetableEnvelope, TimetableResponse,
+        dedup_classes, local_now, local_today, week_start,
+    },
+    preferences::PreferredQuery,
+    qr::QrCode,
+    recurring::{self, RecurringPattern},
+    scraper::{CrossfitScraper, ScrapeError, ScrapedWeek, pinned_client},
+    selftest::{ScrapePreview, SelfTest},
+    settings::Settings,
+    share::ShareSigner,
+    sheets::SheetsExport,
+    stats::Heatmap,
+    storage::{ClassStore, SNAPSHOT_VERSION, Snapshot},
+    suggest::{SuggestRequest, busy_from_ics, free_classes},
+    summary::render_summary,
+    validation::{
+        validate_alarm, validate_branding, validate_busy, validate_kinds, validate_limit,
+        validate_ttl_hours, validate_weeks,
+    },
 };
 
 #[derive(Debug, serde::Deserialize)]
 pub struct TimetableQuery {
     #[serde(default = "default_weeks")]
     pub weeks: u8,
+    // Any date in the first requested week (defaults to the current week)
+    pub from: Option<NaiveDate>,
+    // The first requested week by ISO week number instead, e.g. `2026-W03`
+    pub week: Option<IsoWeek>,
+    // Leave out classes earlier than today, e.g. for calendar apps that reject past events
+    #[serde(default)]
+    pub from_today: bool,
+    // Comma-separated class types, e.g. `hyrox,wod` (defaults to all)
+    pub kind: Option<String>,
+    // With `false`, `/timetable` answers `{classes, warnings}`, listing the upstream rows that
+    // could not be read
+    #[serde(default = "default_strict")]
+    pub strict: bool,
+    // Minutes before each class a reminder fires in iCalendar exports (overrides the setting)
+    pub alarm: Option<u32>,
+    // Add the gym's opening hours to iCalendar exports as all-day events
+    #[serde(default)]
+    pub opening_hours: bool,
+    // White-label branding of iCalendar exports (overriding the settings)
+    pub calendar_name: Option<String>,
+    pub event_prefix: Option<String>,
+    pub color: Option<String>,
+    // `pl` or `en` for day names, class types and descriptions (overrides the setting)
+    pub lang: Option<String>,
+    pub token: Option<String>,
+    // Signed share link parameters (see `POST /share`)
+    pub sid: Option<String>,
+    pub exp: Option<i64>,
+    pub sig: Option<String>,
+}
+
+impl TimetableQuery {
+    fn class_filter(&self, time_zone: Tz) -> Result<ClassFilter, ApiError> {
+        if self.from_today && (self.from.is_some() || self.week.is_some()) {
+            return Err(ApiError::BadRequest(
+                "from or week and from_today can't be combined".into(),
+            ));
+        }
+        Ok(ClassFilter {
+            kinds: validate_kinds(self.kind.as_deref())?,
+            not_before: self.from_today.then(|| local_today(time_zone)),
+        })
+    }
+
+    fn branding(&self) -> Branding {
+        Branding {
+            calendar_name: self.calendar_name.clone(),
+            event_prefix: self.event_prefix.clone(),
+            color: self.color.clone(),
+        }
+    }
+
+    // The settings exports are rendered with, carrying the requested reminder and branding
+    fn export_settings<'a>(&self, settings: &'a Settings) -> Result<Cow<'a, Settings>, ApiError> {
+        let branding = validate_branding(self.branding())?;
+        if self.alarm.is_none() && branding.is_empty() && self.lang.is_none() {
+            return Ok(Cow::Borrowed(settings));
+        }
+        let mut settings = settings.clone();
+        if let Some(lang) = &self.lang {
+            settings.lang = lang.parse().map_err(ApiError::BadRequest)?;
+        }
+        if let Some(minutes) = self.alarm {
+            settings.ical_alarm_minutes = Some(validate_alarm(minutes)?);
+        }
+        if let Some(name) = branding.calendar_name {
+            settings.ical_calendar_name = name;
+        }
+        if let Some(prefix) = branding.event_prefix {
+            settings.ical_event_prefix = prefix;
+        }
+        if let Some(color) = branding.color {
+            settings.ical_color = Some(color);
+        }
+        Ok(Cow::Owned(settings))
+    }
+
+    // Tells cached feeds apart by what they're rendered from; the credential and share link
+    // parameters stay out, so tokens aren't kept in memory
+    fn feed_key(&self, from: Option<NaiveDate>, filter: &ClassFilter) -> String {
+        format!(
+            "{}:{from:?}:{filter:?}:{:?}:{}:{:?}:{:?}",
+            self.weeks,
+            self.alarm,
+            self.opening_hours,
+            self.branding(),
+            self.lang
+        )
+    }
+}
+
+// Narrows the fetched weeks to the classes a timetable request asked for
+#[derive(Debug)]
+struct ClassFilter {
+    kinds: Vec<ClassKind>,
+    not_before: Option<NaiveDate>,
+}
+
+impl ClassFilter {
+    fn matches(&self, class: &ClassItem) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&class.kind))
+            && self.includes_day(class.date.date())
+    }
+
+    fn includes_day(&self, date: NaiveDate) -> bool {
+        self.not_before.is_none_or(|day| date >= day)
+    }
+}
+
+// Past weeks analyzed by `/recurring` and `/stats/heatmap`
+#[derive(Debug, serde::Deserialize)]
+pub struct AnalysisQuery {
+    #[serde(default = "default_analysis_weeks")]
+    pub weeks: u8,
+    // Any date in the first analyzed week (defaults to the weeks up to the current one)
+    pub from: Option<NaiveDate>,
+    pub week: Option<IsoWeek>,
+    pub kind: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ChangesQuery {
+    // Only changes detected at or after this instant (RFC 3339)
+    pub since: Option<DateTime<Utc>>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TriggerQuery {
+    #[serde(default = "default_trigger_limit")]
+    pub limit: usize,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AttendanceQuery {
+    // `json` (default) or `csv`
+    pub format: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct MarkAttendanceQuery {
+    // Day of the class (defaults to today), so only its week is looked up
+    pub date: Option<NaiveDate>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SheetsExportQuery {
+    #[serde(default = "default_weeks")]
+    pub weeks: u8,
+    pub from: Option<NaiveDate>,
+    pub week: Option<IsoWeek>,
+    // Defaults to `APP_GOOGLE_SHEETS_TAB`
+    pub tab: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SubscribeQrQuery {
+    #[serde(default = "default_weeks")]
+    pub weeks: u8,
+    // Lifetime of the signed link (defaults to `APP_SHARE_TTL_HOURS`)
+    pub ttl_hours: Option<u32>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SummaryQuery {
+    #[serde(default = "default_weeks")]
+    pub weeks: u8,
+    // Any date in the first summarized week (defaults to the current week)
+    pub from: Option<NaiveDate>,
+    pub week: Option<IsoWeek>,
+    // `pl` or `en` (defaults to `APP_LANG`)
+    pub lang: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default = "default_weeks")]
+    pub weeks: u8,
+    // Any date in the first searched week (defaults to the current week)
+    pub from: Option<NaiveDate>,
+    pub week: Option<IsoWeek>,
+    pub kind: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ScrapePreviewQuery {
+    // Root of the eFitness instance to scrape instead of `APP_SCRAPER_BASE_URL`
+    pub base_url: String,
+    // Any date in the scraped week (defaults to the current week)
+    pub from: Option<NaiveDate>,
+    pub week: Option<IsoWeek>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AuthQuery {
     pub token: Option<String>,
 }
 
@@ -19,7 +271,168 @@ fn default_weeks() -> u8 {
     1
 }
 
-#[utoipa::path(get, path = "/", tag = "timetable")]
+fn default_strict() -> bool {
+    true
+}
+
+// Last week and the current one, which the upstream still serves without a database
+fn default_analysis_weeks() -> u8 {
+    2
+}
+
+fn default_trigger_limit() -> usize {
+    50
+}
+
+/// The first requested day, given either as `from` or as an ISO `week`.
+fn first_day(
+    from: Option<NaiveDate>,
+    week: Option<IsoWeek>,
+) -> Result<Option<NaiveDate>, ApiError> {
+    match (from, week) {
+        (Some(_), Some(_)) => Err(ApiError::BadRequest(
+            "from and week can't be combined".into(),
+        )),
+        (from, week) => Ok(from.or(week.map(IsoWeek::monday))),
+    }
+}
+
+/// Mondays of `weeks` weeks from the week of `from`, or of today in `time_zone`.
+pub(crate) fn requested_mondays(
+    from: Option<NaiveDate>,
+    weeks: u8,
+    time_zone: Tz,
+) -> Vec<NaiveDate> {
+    let first_monday = week_start(from.unwrap_or_else(|| local_today(time_zone)));
+    (0..weeks)
+        .map(|i| first_monday + Duration::weeks(i.into()))
+        .collect()
+}
+
+/// Loads a week older than the upstream window from the store.
+async fn archived_week(state: &AppState, monday: NaiveDate) -> Result<Vec<ClassItem>, ApiError> {
+    let Some(store) = &state.store else {
+        return Err(ApiError::BadRequest(
+            "Weeks older than two weeks are only available with a configured database".into(),
+        ));
+    };
+    Ok(stored_week(store.as_ref(), monday, DataSource::Archive)
+        .await?
+        .unwrap_or_default())
+}
+
+/// The latest recorded scrape of the week, its classes marked with `source`.
+async fn stored_week(
+    store: &dyn ClassStore,
+    monday: NaiveDate,
+    source: DataSource,
+) -> Result<Option<Vec<ClassItem>>, ApiError> {
+    Ok(store.latest_week(monday).await?.map(|week| {
+        week.classes
+            .into_iter()
+            .map(|class| ClassItem {
+                source: Some(source),
+                ..class
+            })
+            .collect()
+    }))
+}
+
+/// Fetches the given weeks concurrently: live weeks are scraped and recorded when a store is
+/// configured, weeks past the upstream window are served from the store.
+async fn fetch_weeks(
+    state: &AppState,
+    mondays: Vec<NaiveDate>,
+) -> Result<Vec<ClassItem>, ApiError> {
+    Ok(fetch_weeks_with_warnings(state, mondays).await?.classes)
+}
+
+/// `fetch_weeks`, keeping the rows of scraped weeks that could not be read.
+async fn fetch_weeks_with_warnings(
+    state: &AppState,
+    mondays: Vec<NaiveDate>,
+) -> Result<ScrapedWeek, ApiError> {
+    let location = class_location(state).await;
+    let oldest_live = CrossfitScraper::oldest_available_date(local_today(state.settings.timezone));
+    let futures = mondays.into_iter().map(|monday| {
+        let location = location.clone();
+        async move {
+            if monday < oldest_live {
+                return Ok::<_, ApiError>(ScrapedWeek {
+                    classes: archived_week(state, monday).await?,
+                    ..ScrapedWeek::default()
+                });
+            }
+            let week = match state
+                .source
+                .fetch_week_with_warnings(monday, location)
+                .await
+            {
+                Ok(week) => week,
+                // A week scraped before the maintenance beats no timetable at all
+                Err(err @ (ScrapeError::UpstreamMaintenance | ScrapeError::Paused)) => {
+                    let stored = match &state.store {
+                        Some(store) => {
+                            stored_week(store.as_ref(), monday, DataSource::Cached).await?
+                        }
+                        None => None,
+                    };
+                    return match stored {
+                        Some(classes) => Ok(ScrapedWeek {
+                            classes,
+                            ..ScrapedWeek::default()
+                        }),
+                        None => Err(err.into()),
+                    };
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if let Some(store) = &state.store {
+                match store.record_week(monday, Utc::now(), &week.classes).await {
+                    Ok(changes) => notify_changes(state, monday, changes),
+                    // Persistence is best-effort; the live data is still served
+                    Err(err) => warn!(error = %err, %monday, "failed to record scraped week"),
+                }
+            }
+            Ok(week)
+        }
+    });
+
+    let mut result = ScrapedWeek::default();
+    for week in try_join_all(futures).await? {
+        result.classes.extend(week.classes);
+        result.warnings.extend(week.warnings);
+        result.closures.extend(week.closures);
+    }
+    // Overlapping weeks, or stored and fresh copies of a class, must not list it twice
+    dedup_classes(&mut result.classes);
+    Ok(result)
+}
+
+// Notifications are sent in the background so they never delay the response
+fn notify_changes(state: &AppState, monday: NaiveDate, changes: Vec<ScheduleChange>) {
+    if changes.is_empty() || state.notifier.is_empty() {
+        return;
+    }
+    let notifier = Arc::clone(&state.notifier);
+    tokio::spawn(async move { notifier.dispatch(monday, &changes).await });
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "root",
+    path = "/",
+    responses(
+        (status = 200, description = "Service name and the main endpoints", example = json!({
+            "message": "CrossFit Timetable API",
+            "endpoints": {
+                "/timetable": "Get timetable data as JSON",
+                "/timetable.ical": "Download timetable as iCal file"
+            }
+        }))
+    ),
+    tag = "timetable"
+)]
 pub async fn root() -> impl IntoResponse {
     Json(serde_json::json!({
         "message": "CrossFit Timetable API",
@@ -30,118 +443,1486 @@ pub async fn root() -> impl IntoResponse {
     }))
 }
 
-#[utoipa::path(get, path = "/healthz/live", tag = "timetable")]
+#[utoipa::path(
+    get,
+    operation_id = "healthzLive",
+    path = "/healthz/live",
+    responses(
+        (status = 200, description = "The process is up", example = json!({"status": "ok"}))
+    ),
+    tag = "timetable"
+)]
 pub async fn healthz_live() -> impl IntoResponse {
     Json(serde_json::json!({"status": "ok"}))
 }
 
-#[utoipa::path(get, path = "/healthz/ready", tag = "timetable")]
+#[utoipa::path(
+    get,
+    operation_id = "healthzReady",
+    path = "/healthz/ready",
+    responses(
+        (status = 200, description = "The service accepts requests", example = json!({"status": "ok"}))
+    ),
+    tag = "timetable"
+)]
 pub async fn healthz_ready() -> impl IntoResponse {
     Json(serde_json::json!({"status": "ok"}))
 }
 
 #[utoipa::path(
     get,
+    operation_id = "healthzDetail",
+    path = "/healthz/detail",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Scraper, background task and notifier health", body = HealthReport),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn healthz_detail(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+    let tasks = state
+        .tasks
+        .lock()
+        .expect("task status lock poisoned")
+        .clone();
+    Ok(Json(HealthReport::new(
+        state.source.stats(),
+        tasks,
+        state.notifier.health(),
+    )))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getTimetable",
     path = "/timetable",
     params(
         ("weeks" = u8, Query, description = "Number of weeks (1-6)"),
+        ("from" = Option<String>, Query, description = "Date in the first requested week (YYYY-MM-DD); weeks older than two weeks are served from the database"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("from_today" = Option<bool>, Query, description = "Start at the current day rather than Monday, leaving out earlier classes of this week"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("strict" = Option<bool>, Query, description = "With `false`, answer a `TimetableEnvelope` that also lists the upstream rows that could not be read"),
         ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
     ),
     responses(
-        (status = 200, description = "List of classes", body = [ClassItem]),
-        (status = 401, description = "Invalid authentication token"),
-        (status = 404, description = "No classes found")
+        (status = 200, description = "List of classes, or a `TimetableEnvelope` with `strict=false`", body = TimetableResponse, example = crate::openapi::timetable_example),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No classes found", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
     ),
-    security(("bearer_auth" = []), ("query_token" = [])), 
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
     tag = "timetable"
 )]
 pub async fn get_timetable(
     State(state): State<AppState>,
-    auth: Option<TypedHeader<Authorization<Bearer>>>,
-    axum::extract::Query(query): axum::extract::Query<TimetableQuery>,
+    auth: Option<AuthHeader>,
+    PreferredQuery(query): PreferredQuery<TimetableQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let auth_header = auth.map(|TypedHeader(a)| a);
-    verify_token(&state.settings, auth_header, query.token.as_deref())?;
+    authorize_read(&state, auth, query.token.as_deref()).await?;
 
     let weeks = validate_weeks(query.weeks)?;
+    let filter = query.class_filter(state.settings.timezone)?;
+    let from = first_day(query.from, query.week)?;
 
-    let today = Local::now().date_naive();
-    let current_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
-    let mondays: Vec<NaiveDate> = (0..weeks)
-        .map(|i| current_monday + Duration::weeks(i.into()))
-        .collect();
-
-    let futures = mondays
-        .into_iter()
-        .map(|monday| state.scraper.fetch_timetable(Some(monday), None));
-
-    let week_results: Vec<Vec<ClassItem>> = try_join_all(futures).await?;
-    let classes: Vec<ClassItem> = week_results.into_iter().flatten().collect();
+    let ScrapedWeek {
+        mut classes,
+        warnings,
+        mut closures,
+    } = fetch_weeks_with_warnings(
+        &state,
+        requested_mondays(from, weeks, state.settings.timezone),
+    )
+    .await?;
+    classes.retain(|class| filter.matches(class));
+    closures.retain(|closure| filter.includes_day(closure.date));
 
+    if !query.strict {
+        // Unreadable rows and closed days are worth reporting even when there are no classes
+        if classes.is_empty() && warnings.is_empty() && closures.is_empty() {
+            return Err(ApiError::NotFound("No classes found".into()));
+        }
+        return Ok(Json(TimetableResponse::Envelope(TimetableEnvelope {
+            classes,
+            warnings,
+            closures,
+        })));
+    }
     if classes.is_empty() {
         return Err(ApiError::NotFound("No classes found".into()));
     }
 
-    Ok(Json(classes))
+    Ok(Json(TimetableResponse::Classes(classes)))
 }
 
 #[utoipa::path(
     get,
-    path = "/timetable.ical",
+    operation_id = "getExport",
+    path = "/timetable.{format}",
     params(
+        ("format" = String, Path, description = "Output format: `ical` (iCal file), `jsonld` (schema.org Event structured data) or `hcal` (HTML page with h-event microformats)"),
         ("weeks" = u8, Query, description = "Number of weeks (1-6)"),
-        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+        ("from" = Option<String>, Query, description = "Date in the first requested week (YYYY-MM-DD); weeks older than two weeks are served from the database"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("from_today" = Option<bool>, Query, description = "Start at the current day rather than Monday, leaving out earlier classes of this week"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("alarm" = Option<u32>, Query, description = "Minutes (0-1440) before each class a reminder fires in `ical` exports"),
+        ("calendar_name" = Option<String>, Query, description = "Calendar name of `ical` exports (overrides the setting)"),
+        ("event_prefix" = Option<String>, Query, description = "Put before each class name in `ical` exports (overrides the setting)"),
+        ("color" = Option<String>, Query, description = "`#rrggbb` color of `ical` exports (overrides the setting)"),
+        ("lang" = Option<String>, Query, description = "`pl` or `en` for day names, class types and descriptions of `hcal` and `ical` exports (overrides `APP_LANG`)"),
+        ("opening_hours" = Option<bool>, Query, description = "Add an all-day \"Open Gym hours\" event for each day the gym is open to `ical` exports"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)"),
+        ("sid" = Option<String>, Query, description = "Share link id (signed subscription URL)"),
+        ("exp" = Option<i64>, Query, description = "Share link expiry as a Unix timestamp"),
+        ("sig" = Option<String>, Query, description = "Share link signature")
     ),
     responses(
-        (status = 200, description = "iCal file", content_type = "text/calendar"),
-        (status = 401, description = "Invalid authentication token"),
-        (status = 404, description = "No classes found")
+        (status = 200, description = "Timetable in the requested format", content(
+            (String = "text/calendar", example = crate::openapi::ical_example),
+            (String = "application/ld+json"),
+            (String = "text/html")
+        )),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "Unknown format or no classes found", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
     ),
-    security(("bearer_auth" = []), ("query_token" = [])),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
     tag = "timetable"
 )]
-pub async fn get_ical(
+pub async fn get_export(
     State(state): State<AppState>,
-    auth: Option<TypedHeader<Authorization<Bearer>>>,
-    axum::extract::Query(query): axum::extract::Query<TimetableQuery>,
-) -> Result<impl IntoResponse, ApiError> {
-    let auth_header = auth.map(|TypedHeader(a)| a);
-    verify_token(&state.settings, auth_header, query.token.as_deref())?;
+    Path(format): Path<String>,
+    auth: Option<AuthHeader>,
+    headers: HeaderMap,
+    PreferredQuery(query): PreferredQuery<TimetableQuery>,
+) -> Result<Response, ApiError> {
+    match &query.sig {
+        Some(sig) if !state.settings.public_read => verify_share_link(
+            &state,
+            query.sid.as_deref(),
+            query.exp,
+            query.weeks,
+            &query.branding(),
+            sig,
+        )?,
+        _ => {
+            authorize_read(&state, auth, query.token.as_deref()).await?;
+        }
+    }
+    let exporter = state
+        .exporters
+        .get(&format)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown format `{format}`")))?;
     let weeks = validate_weeks(query.weeks)?;
+    let filter = query.class_filter(state.settings.timezone)?;
+    let from = first_day(query.from, query.week)?;
+    let settings = query.export_settings(&state.settings)?;
 
-    let today = Local::now().date_naive();
-    let current_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
-    let mondays: Vec<NaiveDate> = (0..weeks)
-        .map(|i| current_monday + Duration::weeks(i.into()))
-        .collect();
+    // The query carries the token's defaults, so it tells feeds apart
+    let key = format!("{format}:{}", query.feed_key(from, &filter));
+    cached_feed(&state, &headers, key, async {
+        let mondays = requested_mondays(from, weeks, state.settings.timezone);
+        let ScrapedWeek {
+            mut classes,
+            mut closures,
+            ..
+        } = fetch_weeks_with_warnings(&state, mondays.clone()).await?;
+        classes.retain(|class| filter.matches(class));
+        closures.retain(|closure| filter.includes_day(closure.date));
 
-    let location = match &state.settings.location {
-        Some(loc) => Some(loc.clone()),
-        None => state.scraper.fetch_location().await,
-    };
-    let futures = mondays.into_iter().map(|monday| {
-        state
-            .scraper
-            .fetch_timetable(Some(monday), location.clone())
-    });
+        if classes.is_empty() && closures.is_empty() {
+            return Err(ApiError::NotFound("No classes found".into()));
+        }
+
+        let open_days = requested_open_days(&state, &query, &mondays, &closures, &filter).await?;
+        Ok(render_export(
+            exporter.as_ref(),
+            &classes,
+            &closures,
+            &open_days,
+            &settings,
+        ))
+    })
+    .await
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getLinks",
+    path = "/timetable/links",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks (1-6)"),
+        ("from" = Option<String>, Query, description = "Date in the first requested week (YYYY-MM-DD)"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("from_today" = Option<bool>, Query, description = "Start at the current day rather than Monday, leaving out earlier classes of this week"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Add-to-calendar links per class", body = [ClassLinks]),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No classes found", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_links(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    PreferredQuery(query): PreferredQuery<TimetableQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let weeks = validate_weeks(query.weeks)?;
+    let filter = query.class_filter(state.settings.timezone)?;
+    let from = first_day(query.from, query.week)?;
 
-    let week_results: Vec<Vec<ClassItem>> = try_join_all(futures).await?;
-    let classes: Vec<ClassItem> = week_results.into_iter().flatten().collect();
+    let mut classes = fetch_weeks(
+        &state,
+        requested_mondays(from, weeks, state.settings.timezone),
+    )
+    .await?;
+    classes.retain(|class| filter.matches(class));
 
     if classes.is_empty() {
         return Err(ApiError::NotFound("No classes found".into()));
     }
 
-    let body = state.exporter.generate(&classes, &state.settings);
+    let links: Vec<ClassLinks> = classes
+        .iter()
+        .map(|class| class_links(class, &state.settings))
+        .collect();
+    Ok(Json(links))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getNextClass",
+    path = "/shortcuts/next",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Next upcoming class", body = NextClass),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No upcoming classes", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_next_class(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    // Two weeks, so on Sunday evening the next class is still found in the following week
+    let now = local_now(state.settings.timezone).naive_local();
+    let classes = fetch_weeks(
+        &state,
+        requested_mondays(Some(now.date()), 2, state.settings.timezone),
+    )
+    .await?;
+
+    let next = next_class(&classes, now)
+        .ok_or_else(|| ApiError::NotFound("No upcoming classes".into()))?;
+    Ok(Json(NextClass::from(next)))
+}
+
+fn next_class(classes: &[ClassItem], now: NaiveDateTime) -> Option<&ClassItem> {
+    classes
+        .iter()
+        .filter(|class| class.date > now)
+        .min_by_key(|class| class.date)
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getNextClassBadge",
+    path = "/badge/next-class.svg",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Shields.io-style badge with the next class name and start, `none` without upcoming classes, or `unavailable` when the timetable can't be read", content_type = "image/svg+xml"),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_next_class_badge(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let now = local_now(state.settings.timezone).naive_local();
+    let classes = fetch_weeks(
+        &state,
+        requested_mondays(Some(now.date()), 2, state.settings.timezone),
+    )
+    .await;
+    // An image embedded in a page can't show an error, so failures get a badge of their own
+    let (message, color) = match &classes {
+        Ok(classes) => match next_class(classes, now) {
+            Some(class) => (
+                format!("{} · {}", class.event_name, class.date.format("%a %H:%M")),
+                badge::COLOR_OK,
+            ),
+            None => ("none".to_string(), badge::COLOR_NONE),
+        },
+        Err(err) => {
+            warn!(error = %err, "failed to read the timetable for the badge");
+            ("unavailable".to_string(), badge::COLOR_DOWN)
+        }
+    };
+    Ok((
+        [(header::CONTENT_TYPE, "image/svg+xml")],
+        badge::render("next class", &message, color),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getSummary",
+    path = "/summary",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks to summarize (1-6, default 1)"),
+        ("from" = Option<String>, Query, description = "Date in the first summarized week (YYYY-MM-DD); defaults to the current week"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("lang" = Option<String>, Query, description = "Language of the summary, `pl` or `en` (defaults to `APP_LANG`)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Plain-text summary of each week: classes per type, new classes and changes compared to the week before", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid weeks or language, or weeks past the upstream window without a database", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_summary(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<SummaryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+    let weeks = validate_weeks(query.weeks)?;
+    let lang: Lang = query
+        .lang
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(ApiError::BadRequest)?
+        .unwrap_or(state.settings.lang);
+
+    let from = first_day(query.from, query.week)?;
+    let mondays = requested_mondays(from, weeks, state.settings.timezone);
+    let (classes, before) = tokio::join!(
+        fetch_weeks(&state, mondays.clone()),
+        week_before(&state, mondays[0])
+    );
+    let classes = classes?;
+    let week_of = |monday: NaiveDate| -> Vec<ClassItem> {
+        classes
+            .iter()
+            .filter(|class| week_start(class.date.date()) == monday)
+            .cloned()
+            .collect()
+    };
+    let text = mondays
+        .iter()
+        .enumerate()
+        .map(|(index, &monday)| {
+            let previous = match index {
+                0 => before.clone(),
+                _ => Some(week_of(monday - Duration::weeks(1))),
+            };
+            render_summary(monday, &week_of(monday), previous.as_deref(), lang)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
     Ok((
-        StatusCode::OK,
         [
-            ("content-type", "text/calendar"),
-            (
-                "content-disposition",
-                "attachment; filename=crossfit_timetable.ics",
-            ),
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (header::CONTENT_LANGUAGE, lang.as_str()),
         ],
-        body,
+        text,
     ))
 }
+
+// The classes of the week before `monday`, to tell what changed in it; `None` when that week
+// is neither live nor stored, so the summary goes without the comparison
+async fn week_before(state: &AppState, monday: NaiveDate) -> Option<Vec<ClassItem>> {
+    let previous = monday - Duration::weeks(1);
+    let oldest_live = CrossfitScraper::oldest_available_date(local_today(state.settings.timezone));
+    if previous < oldest_live {
+        let store = state.store.as_ref()?;
+        return stored_week(store.as_ref(), previous, DataSource::Archive)
+            .await
+            .ok()
+            .flatten();
+    }
+    match fetch_weeks(state, vec![previous]).await {
+        Ok(classes) => Some(classes),
+        Err(err) => {
+            warn!(error = %err, %previous, "summary without the previous week");
+            None
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getRecurring",
+    path = "/recurring",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks to analyze (1-6, default 2)"),
+        ("from" = Option<String>, Query, description = "Date in the first analyzed week (YYYY-MM-DD); defaults to the weeks up to the current one"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Weekly pattern of the classes and the exceptions to it", body = RecurringPattern),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No classes found", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_recurring(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AnalysisQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let (mondays, classes) = analyzed_classes(&state, &query).await?;
+    Ok(Json(recurring::analyze(&classes, &mondays)))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getHeatmap",
+    path = "/stats/heatmap",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks to count (1-6, default 2)"),
+        ("from" = Option<String>, Query, description = "Date in the first counted week (YYYY-MM-DD); defaults to the weeks up to the current one"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Classes per weekday and start hour", body = Heatmap),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No classes found", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_heatmap(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AnalysisQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let (mondays, classes) = analyzed_classes(&state, &query).await?;
+    Ok(Json(Heatmap::new(&classes, mondays)))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getGymHours",
+    path = "/gym/hours",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "When the gym is open, Monday first", body = [OpeningHours]),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "The gym publishes no opening hours", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_gym_hours(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let hours = state.opening_hours.get(state.source.as_ref()).await?;
+    if hours.is_empty() {
+        return Err(ApiError::NotFound("No opening hours found".into()));
+    }
+    Ok(Json(hours))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "suggestClasses",
+    path = "/suggest",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks to search (1-6, default 1)"),
+        ("from" = Option<String>, Query, description = "Date in the first searched week (YYYY-MM-DD); defaults to the current week"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to suggest, e.g. `hyrox,wod`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    request_body(
+        description = "The member's busy intervals as JSON, or an iCalendar export of their calendar sent as `text/calendar`",
+        content(
+            (SuggestRequest = "application/json"),
+            (String = "text/calendar")
+        )
+    ),
+    responses(
+        (status = 200, description = "Classes yet to start that don't overlap any busy interval", body = Vec<ClassItem>),
+        (status = 400, description = "Unreadable busy intervals, invalid weeks or class types", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn suggest_classes(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<SuggestQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+    let weeks = validate_weeks(query.weeks)?;
+    let kinds = validate_kinds(query.kind.as_deref())?;
+    let from = first_day(query.from, query.week)?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/json");
+    let busy = if content_type.starts_with("text/calendar") {
+        let text = std::str::from_utf8(&body)
+            .map_err(|_| ApiError::BadRequest("iCalendar must be UTF-8".into()))?;
+        busy_from_ics(text, state.settings.timezone).map_err(ApiError::BadRequest)?
+    } else if content_type.starts_with("application/json") {
+        serde_json::from_slice::<SuggestRequest>(&body)
+            .map_err(|err| ApiError::BadRequest(format!("invalid busy intervals: {err}")))?
+            .busy
+    } else {
+        return Err(ApiError::BadRequest(format!(
+            "unsupported content type `{content_type}` (expected application/json or text/calendar)"
+        )));
+    };
+    let busy = validate_busy(busy)?;
+
+    let mut classes = fetch_weeks(
+        &state,
+        requested_mondays(from, weeks, state.settings.timezone),
+    )
+    .await?;
+    // Classes already under way can't be joined any more
+    let now = Utc::now();
+    classes.retain(|class| class.start > now && (kinds.is_empty() || kinds.contains(&class.kind)));
+    Ok(Json(free_classes(classes, &busy)))
+}
+
+/// The analyzed Mondays and the classes of those weeks matching `kind`.
+async fn analyzed_classes(
+    state: &AppState,
+    query: &AnalysisQuery,
+) -> Result<(Vec<NaiveDate>, Vec<ClassItem>), ApiError> {
+    let weeks = validate_weeks(query.weeks)?;
+    let kinds = validate_kinds(query.kind.as_deref())?;
+    let from = first_day(query.from, query.week)?.unwrap_or_else(|| {
+        local_today(state.settings.timezone) - Duration::weeks(i64::from(weeks) - 1)
+    });
+    let mondays = requested_mondays(Some(from), weeks, state.settings.timezone);
+
+    let mut classes = fetch_weeks(state, mondays.clone()).await?;
+    classes.retain(|class| kinds.is_empty() || kinds.contains(&class.kind));
+
+    if classes.is_empty() {
+        return Err(ApiError::NotFound("No classes found".into()));
+    }
+    Ok((mondays, classes))
+}
+
+/// `APP_LOCATION`, or else the address published by the source, looked up at most once per
+/// `APP_LOCATION_CACHE_MINUTES`.
+async fn class_location(state: &AppState) -> Option<String> {
+    match &state.settings.location {
+        Some(loc) => Some(loc.clone()),
+        None => state.location.get(state.source.as_ref()).await,
+    }
+}
+
+fn render_export(
+    exporter: &dyn Exporter,
+    classes: &[ClassItem],
+    closures: &[Closure],
+    open_days: &[OpenDay],
+    settings: &Settings,
+) -> RenderedFeed {
+    RenderedFeed::new(
+        exporter.content_type(),
+        exporter.file_name(),
+        exporter.render_with_opening_hours(classes, closures, open_days, settings),
+    )
+}
+
+// Serves the feed rendered for `key` while the feed cache holds it, rendering it otherwise;
+// `HEAD` requests get the same headers without a body
+async fn cached_feed(
+    state: &AppState,
+    headers: &HeaderMap,
+    key: String,
+    render: impl Future<Output = Result<RenderedFeed, ApiError>>,
+) -> Result<Response, ApiError> {
+    let feed = match state.feeds.get(&key) {
+        Some(feed) => feed,
+        None => state.feeds.insert(key, render.await?),
+    };
+    let validators = [
+        (header::ETAG, feed.etag.clone()),
+        (header::LAST_MODIFIED, feed.http_date()),
+    ];
+    let condition = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if feed.not_modified(
+        condition(header::IF_NONE_MATCH),
+        condition(header::IF_MODIFIED_SINCE),
+    ) {
+        return Ok((StatusCode::NOT_MODIFIED, validators).into_response());
+    }
+
+    let mut response = (
+        validators,
+        [
+            (header::CONTENT_TYPE, feed.content_type.clone()),
+            (header::CONTENT_LENGTH, feed.body.len().to_string()),
+        ],
+        feed.body.clone(),
+    )
+        .into_response();
+    if let Some(file_name) = &feed.file_name
+        && let Ok(value) = format!("attachment; filename={file_name}").parse()
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, value);
+    }
+    Ok(response)
+}
+
+// The open days of the requested weeks, when the export asked for `opening_hours`
+async fn requested_open_days(
+    state: &AppState,
+    query: &TimetableQuery,
+    mondays: &[NaiveDate],
+    closures: &[Closure],
+    filter: &ClassFilter,
+) -> Result<Vec<OpenDay>, ApiError> {
+    if !query.opening_hours {
+        return Ok(Vec::new());
+    }
+    let hours = state.opening_hours.get(state.source.as_ref()).await?;
+    let mut open_days = hours::open_days(&hours, mondays, closures);
+    open_days.retain(|day| filter.includes_day(day.date));
+    Ok(open_days)
+}
+
+fn required_store(state: &AppState, feature: &str) -> Result<Arc<dyn ClassStore>, ApiError> {
+    state
+        .store
+        .clone()
+        .ok_or_else(|| ApiError::NotFound(format!("{feature} require a configured database")))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getFavorites",
+    path = "/me/favorites",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Favorites saved for this token", body = Favorites),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_favorites(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let owner = authorize_owner(&state, auth, query.token.as_deref()).await?;
+    let store = required_store(&state, "Favorites")?;
+    Ok(Json(store.favorites(&owner).await?.unwrap_or_default()))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "setFavorites",
+    path = "/me/favorites",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    request_body = Favorites,
+    responses(
+        (status = 200, description = "Saved favorites", body = Favorites),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn set_favorites(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+    Json(favorites): Json<Favorites>,
+) -> Result<impl IntoResponse, ApiError> {
+    let owner = authorize_owner(&state, auth, query.token.as_deref()).await?;
+    let store = required_store(&state, "Favorites")?;
+    store.set_favorites(&owner, &favorites).await?;
+    Ok(Json(favorites))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getPersonalIcal",
+    path = "/me/timetable.ical",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks (1-6)"),
+        ("from_today" = Option<bool>, Query, description = "Start at the current day rather than Monday, leaving out earlier classes of this week"),
+        ("kind" = Option<String>, Query, description = "Comma-separated class types to include, e.g. `hyrox,wod`"),
+        ("alarm" = Option<u32>, Query, description = "Minutes (0-1440) before each class a reminder fires"),
+        ("calendar_name" = Option<String>, Query, description = "Calendar name (overrides the setting)"),
+        ("event_prefix" = Option<String>, Query, description = "Put before each class name (overrides the setting)"),
+        ("color" = Option<String>, Query, description = "`#rrggbb` calendar color (overrides the setting)"),
+        ("lang" = Option<String>, Query, description = "`pl` or `en` for class types and descriptions (overrides `APP_LANG`)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "iCal file with the classes matching the saved favorites", body = String, content_type = "text/calendar", example = crate::openapi::ical_example),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured or no matching classes", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_personal_ical(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    headers: HeaderMap,
+    PreferredQuery(query): PreferredQuery<TimetableQuery>,
+) -> Result<Response, ApiError> {
+    let owner = authorize_owner(&state, auth, query.token.as_deref()).await?;
+    let weeks = validate_weeks(query.weeks)?;
+    let filter = query.class_filter(state.settings.timezone)?;
+    let from = first_day(query.from, query.week)?;
+    let settings = query.export_settings(&state.settings)?;
+    let store = required_store(&state, "Favorites")?;
+    let favorites = store.favorites(&owner).await?.unwrap_or_default();
+
+    // Favorites are part of the key, so saving new ones shows up right away
+    let key = format!("me:{owner}:{favorites:?}:{}", query.feed_key(from, &filter));
+    cached_feed(&state, &headers, key, async {
+        let mondays = requested_mondays(from, weeks, state.settings.timezone);
+        let ScrapedWeek {
+            mut classes,
+            mut closures,
+            ..
+        } = fetch_weeks_with_warnings(&state, mondays.clone()).await?;
+        classes.retain(|class| favorites.matches(class) && filter.matches(class));
+        closures.retain(|closure| filter.includes_day(closure.date));
+
+        if classes.is_empty() && closures.is_empty() {
+            return Err(ApiError::NotFound("No matching classes found".into()));
+        }
+
+        let open_days = requested_open_days(&state, &query, &mondays, &closures, &filter).await?;
+        Ok(render_export(
+            &ICalExporter,
+            &classes,
+            &closures,
+            &open_days,
+            &settings,
+        ))
+    })
+    .await
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getChanges",
+    path = "/history/changes",
+    params(
+        ("since" = Option<String>, Query, description = "Only changes detected at or after this RFC 3339 timestamp"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Detected schedule changes, oldest first", body = [ChangeRecord], example = crate::openapi::changes_example),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_changes(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<ChangesQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let store = required_store(&state, "Change history")?;
+    let since = query.since.unwrap_or(DateTime::UNIX_EPOCH);
+    Ok(Json(store.changes_since(since).await?))
+}
+
+// How far back `/changes.rss` looks; feed readers poll far more often
+const CHANGES_FEED_DAYS: i64 = 30;
+
+#[utoipa::path(
+    get,
+    operation_id = "getChangesRss",
+    path = "/changes.rss",
+    params(
+        ("limit" = Option<usize>, Query, description = "Number of items (1-100, default 50)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "RSS 2.0 feed of schedule changes detected in the last 30 days, newest first", content_type = "application/rss+xml"),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_changes_rss(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<TriggerQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let limit = validate_limit(query.limit)?;
+    let store = required_store(&state, "Change history")?;
+    let since = Utc::now() - Duration::days(CHANGES_FEED_DAYS);
+    let mut changes = store.changes_since(since).await?;
+    changes.reverse();
+    changes.truncate(limit);
+
+    let link = public_url(&state, "history/changes")?
+        .unwrap_or_else(|| state.settings.scraper_base_url.to_string());
+    Ok((
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        crate::rss::changes_rss(&changes, &state.settings, &link),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getNewClassTriggers",
+    path = "/triggers/new-classes",
+    params(
+        ("limit" = Option<usize>, Query, description = "Number of items (1-100, default 50)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Recently added classes, newest first", body = [NewClassTrigger]),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_new_class_triggers(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<TriggerQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_read(&state, auth, query.token.as_deref()).await?;
+
+    let limit = validate_limit(query.limit)?;
+    let store = required_store(&state, "Triggers")?;
+    let added = store.recent_changes("added", limit).await?;
+    let items: Vec<NewClassTrigger> = added
+        .into_iter()
+        .map(|(id, record)| NewClassTrigger::new(id, record))
+        .collect();
+    Ok(Json(items))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "createShare",
+    path = "/share",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    request_body = ShareRequest,
+    responses(
+        (status = 200, description = "Signed calendar subscription link", body = ShareLink),
+        (status = 400, description = "Invalid weeks, branding or ttl_hours", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "Share links are not enabled", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn create_share(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+    Json(request): Json<ShareRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+
+    let Some(signer) = &state.share else {
+        return Err(ApiError::NotFound("Share links are not enabled".into()));
+    };
+    let weeks = validate_weeks(request.weeks.unwrap_or_else(default_weeks))?;
+    let ttl_hours = request.ttl_hours.unwrap_or(state.settings.share_ttl_hours);
+    let branding = validate_branding(request.branding)?;
+
+    Ok(Json(sign_share_link(
+        &state, signer, weeks, &branding, ttl_hours,
+    )?))
+}
+
+fn sign_share_link(
+    state: &AppState,
+    signer: &ShareSigner,
+    weeks: u8,
+    branding: &Branding,
+    ttl_hours: u32,
+) -> Result<ShareLink, ApiError> {
+    let ttl_hours = validate_ttl_hours(ttl_hours)?;
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let expires_at = Utc::now() + Duration::hours(ttl_hours.into());
+    let exp = expires_at.timestamp();
+    let sig = signer.sign(&id, exp, weeks, branding);
+
+    let query = form_urlencoded::Serializer::new(format!("weeks={weeks}"))
+        .extend_pairs(branding.query_pairs())
+        .append_pair("sid", &id)
+        .append_pair("exp", &exp.to_string())
+        .append_pair("sig", &sig)
+        .finish();
+    let path = format!("timetable.ical?{query}");
+    let url = public_url(state, &path)?.unwrap_or_else(|| format!("/{path}"));
+
+    Ok(ShareLink {
+        id,
+        url,
+        expires_at,
+    })
+}
+
+fn public_url(state: &AppState, path: &str) -> Result<Option<String>, ApiError> {
+    state
+        .settings
+        .public_url
+        .as_ref()
+        .map(|base| {
+            base.join(path)
+                .map(String::from)
+                .map_err(|err| ApiError::Internal(format!("Invalid public URL: {err}")))
+        })
+        .transpose()
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getSubscribeQr",
+    path = "/subscribe.qr.png",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks the subscription covers (1-6)"),
+        ("ttl_hours" = Option<u32>, Query, description = "Lifetime of the signed link (defaults to the configured share TTL)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "QR code of the webcal subscription URL", content_type = "image/png"),
+        (status = 400, description = "Public URL not configured, feed not shareable or invalid ttl_hours", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_subscribe_qr(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<SubscribeQrQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+
+    let weeks = validate_weeks(query.weeks)?;
+    if state.settings.public_url.is_none() {
+        return Err(ApiError::BadRequest(
+            "APP_PUBLIC_URL is required to print a subscription QR code".into(),
+        ));
+    }
+    // Signed links are preferred so the poster doesn't depend on public read access
+    let url = match &state.share {
+        Some(signer) => {
+            let ttl_hours = query.ttl_hours.unwrap_or(state.settings.share_ttl_hours);
+            sign_share_link(&state, signer, weeks, &Branding::default(), ttl_hours)?.url
+        }
+        None if state.settings.public_read => {
+            public_url(&state, &format!("timetable.ical?weeks={weeks}"))?.ok_or_else(|| {
+                ApiError::BadRequest(
+                    "APP_PUBLIC_URL is required to print a subscription QR code".into(),
+                )
+            })?
+        }
+        None => {
+            return Err(ApiError::BadRequest(
+                "Set APP_SHARE_SIGNING_KEY or enable APP_PUBLIC_READ to share the calendar".into(),
+            ));
+        }
+    };
+    let webcal = match url.split_once("://") {
+        Some((_, rest)) => format!("webcal://{rest}"),
+        None => url,
+    };
+
+    let png = QrCode::encode(webcal.as_bytes())?.to_png(8, 4);
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+#[utoipa::path(
+    delete,
+    operation_id = "revokeShare",
+    path = "/share/{id}",
+    params(
+        ("id" = String, Path, description = "Share link id"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 204, description = "Share link revoked"),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "Share links are not enabled", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn revoke_share(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    Path(id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+
+    let Some(signer) = &state.share else {
+        return Err(ApiError::NotFound("Share links are not enabled".into()));
+    };
+    // Stored first, so the revocation is not lost when the server restarts
+    if let Some(store) = &state.store {
+        store.revoke_share(&id).await?;
+    }
+    signer.revoke(&id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Classes are looked up in the latest recorded scrape of their week
+async fn find_stored_class(
+    store: &dyn ClassStore,
+    date: NaiveDate,
+    class_id: &str,
+) -> Result<Option<ClassItem>, ApiError> {
+    Ok(store
+        .latest_week(week_start(date))
+        .await?
+        .and_then(|week| week.classes.into_iter().find(|class| class.id == class_id)))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "recordAttendance",
+    path = "/me/attendance/{class_id}",
+    params(
+        ("class_id" = String, Path, description = "Class id (see `ClassItem::class_id`)"),
+        ("date" = Option<String>, Query, description = "Day of the class (YYYY-MM-DD, defaults to today)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Class marked as attended", body = AttendanceRecord),
+        (status = 400, description = "The class hasn't started yet", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "Unknown class or no database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn record_attendance(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    Path(class_id): Path<String>,
+    axum::extract::Query(query): axum::extract::Query<MarkAttendanceQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let owner = authorize_owner(&state, auth, query.token.as_deref()).await?;
+    let store = required_store(&state, "Attendance tracking")?;
+
+    let date = query
+        .date
+        .unwrap_or_else(|| local_today(state.settings.timezone));
+    let Some(class) = find_stored_class(store.as_ref(), date, &class_id).await? else {
+        return Err(ApiError::NotFound(format!("Unknown class {class_id}")));
+    };
+    let now = Utc::now();
+    if class.start > now {
+        return Err(ApiError::BadRequest(format!(
+            "Class {class_id} hasn't started yet"
+        )));
+    }
+    let record = AttendanceRecord::new(&class, now);
+    store.record_attendance(&owner, &record).await?;
+    Ok(Json(record))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getAttendance",
+    path = "/me/attendance",
+    params(
+        ("format" = Option<String>, Query, description = "`json` (default) or `csv`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Attended classes summarized by week and class name", body = AttendanceSummary),
+        (status = 400, description = "Unsupported format", body = ErrorBody),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "timetable"
+)]
+pub async fn get_attendance(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AttendanceQuery>,
+) -> Result<Response, ApiError> {
+    let owner = authorize_owner(&state, auth, query.token.as_deref()).await?;
+    let store = required_store(&state, "Attendance tracking")?;
+    let summary = attendance::summarize(store.attendance(&owner).await?);
+
+    match query.format.as_deref() {
+        None | Some("json") => Ok(Json(summary).into_response()),
+        Some("csv") => Ok((
+            StatusCode::OK,
+            [
+                ("content-type", "text/csv; charset=utf-8"),
+                ("content-disposition", "attachment; filename=attendance.csv"),
+            ],
+            attendance::to_csv(&summary),
+        )
+            .into_response()),
+        Some(other) => Err(ApiError::BadRequest(format!(
+            "Unsupported format {other}, expected json or csv"
+        ))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "selfTest",
+    path = "/admin/selftest",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "What the live agenda page holds and what the parser read from it; `ok` is false when no class or closure could be read", body = SelfTest),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody),
+        (status = 404, description = "The configured source has no upstream page", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn self_test(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+    let report = state.source.self_test().await.ok_or_else(|| {
+        ApiError::NotFound("The configured source has no upstream page to test".into())
+    })?;
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "previewScrape",
+    path = "/admin/scrape",
+    params(
+        ("base_url" = String, Query, description = "Root of the eFitness instance to scrape, e.g. `https://other-gym.cms.efitness.com.pl`"),
+        ("from" = Option<String>, Query, description = "Any date in the scraped week (YYYY-MM-DD, defaults to the current week)"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Classes, closures and unreadable rows parsed from the instance; `ok` is false with an `error` when nothing could be read", body = ScrapePreview),
+        (status = 400, description = "Invalid base URL, a host resolving to a non-public address or a week the upstream no longer serves", body = ErrorBody),
+        (status = 401, description = "Not the primary auth token", body = ErrorBody),
+        (status = 500, description = "Failed to set up the upstream HTTP client", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn preview_scrape(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<ScrapePreviewQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_primary(&state.settings, auth, query.token.as_deref())?;
+    let base_url = url::Url::parse(&query.base_url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some())
+        .ok_or_else(|| {
+            ApiError::BadRequest("base_url must be an http:// or https:// URL".into())
+        })?;
+    let pinned = public_addresses(&base_url, &state.settings).await?;
+    // The configured proxy, rules and time zone apply, as they would once the gym is configured.
+    // Detail pages and the headless browser would follow links to any host, so they stay off.
+    let settings = Settings {
+        scraper_base_url: base_url,
+        class_details: false,
+        webdriver_url: None,
+        ..state.settings.clone()
+    };
+    let mut scraper = CrossfitScraper::from_settings(&settings)
+        .map_err(|err| ApiError::Internal(err.to_string()))?;
+    if let Some((host, addrs)) = pinned {
+        let client = pinned_client(&settings, &host, &addrs)
+            .map_err(|err| ApiError::Internal(err.to_string()))?;
+        scraper = scraper.with_client(client);
+    }
+    let report = scraper
+        .preview(first_day(query.from, query.week)?.map(week_start))
+        .await?;
+    Ok(Json(report))
+}
+
+// The addresses a preview of `url` may connect to: any host but the configured upstream must
+// resolve to public addresses only, so the preview can't reach into the server's network
+async fn public_addresses(
+    url: &url::Url,
+    settings: &Settings,
+) -> Result<Option<(String, Vec<std::net::SocketAddr>)>, ApiError> {
+    if url.origin() == settings.scraper_base_url.origin() {
+        return Ok(None);
+    }
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(80);
+    // `lookup_host` takes bracket-less IPv6 literals
+    let name = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<_> = tokio::net::lookup_host((name, port))
+        .await
+        .map_err(|err| ApiError::BadRequest(format!("Could not resolve {host}: {err}")))?
+        .collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public_address(addr.ip())) {
+        return Err(ApiError::BadRequest(
+            "base_url must resolve to public addresses only".into(),
+        ));
+    }
+    Ok(Some((name.to_string(), addrs)))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "getMaintenance",
+    path = "/admin/maintenance",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Whether maintenance mode is on", body = MaintenanceStatus),
+        (status = 401, description = "Invalid authentication token", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("basic_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn get_maintenance(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, auth, query.token.as_deref()).await?;
+    Ok(Json(state.maintenance.status()))
+}
+
+#[utoipa::path(
+    put,
+    operation_id = "setMaintenance",
+    path = "/admin/maintenance",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    request_body = MaintenanceStatus,
+    responses(
+        (status = 200, description = "Maintenance mode switched; while on, data endpoints serve stored weeks, background scraping pauses and other writes are rejected", body = MaintenanceStatus),
+        (status = 401, description = "Not the primary auth token", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+    Json(status): Json<MaintenanceStatus>,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_primary(&state.settings, auth, query.token.as_deref())?;
+    info!(enabled = status.enabled, "switched maintenance mode");
+    state.maintenance.set(status);
+    Ok(Json(state.maintenance.status()))
+}
+
+#[utoipa::path(
+    get,
+    operation_id = "exportSnapshot",
+    path = "/admin/export",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "JSON archive of all stored data", body = Snapshot),
+        (status = 401, description = "Not the primary auth token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn export_snapshot(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Every member's favorites and attendance, so only the admin gets them
+    authorize_primary(&state.settings, auth, query.token.as_deref())?;
+    let store = required_store(&state, "Snapshots")?;
+    let snapshot = store.export().await?;
+
+    let filename = format!(
+        "attachment; filename=crossfit_timetable_{}.json",
+        Utc::now().format("%Y%m%d")
+    );
+    Ok(([(header::CONTENT_DISPOSITION, filename)], Json(snapshot)))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "exportGoogleSheets",
+    path = "/admin/export/google-sheets",
+    params(
+        ("weeks" = u8, Query, description = "Number of weeks (1-6)"),
+        ("from" = Option<String>, Query, description = "Date in the first exported week (YYYY-MM-DD)"),
+        ("week" = Option<String>, Query, description = "ISO week instead of `from`, e.g. `2026-W03`"),
+        ("tab" = Option<String>, Query, description = "Sheet tab to replace, created when missing (default: APP_GOOGLE_SHEETS_TAB)"),
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    responses(
+        (status = 200, description = "Weeks written to the spreadsheet", body = SheetsExport),
+        (status = 401, description = "Not the primary auth token", body = ErrorBody),
+        (status = 404, description = "No spreadsheet configured", body = ErrorBody),
+        (status = 503, description = "The gym's timetable is down for maintenance and no stored copy exists, or this instance is paused", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn export_google_sheets(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<SheetsExportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Writes with the service account's credentials, so only the admin may trigger it
+    authorize_primary(&state.settings, auth, query.token.as_deref())?;
+    let sheets = state.sheets.clone().ok_or_else(|| {
+        ApiError::NotFound("Google Sheets export requires APP_GOOGLE_SHEETS_SPREADSHEET_ID".into())
+    })?;
+    let weeks = validate_weeks(query.weeks)?;
+    let from = first_day(query.from, query.week)?;
+    let classes = fetch_weeks(
+        &state,
+        requested_mondays(from, weeks, state.settings.timezone),
+    )
+    .await?;
+    let tab = query
+        .tab
+        .as_deref()
+        .unwrap_or(&state.settings.google_sheets_tab);
+    Ok(Json(sheets.write(tab, &classes).await?))
+}
+
+#[utoipa::path(
+    post,
+    operation_id = "importSnapshot",
+    path = "/admin/import",
+    params(
+        ("token" = Option<String>, Query, description = "Authentication token (alternative to Bearer header)")
+    ),
+    request_body = Snapshot,
+    responses(
+        (status = 200, description = "Stored data replaced with the snapshot"),
+        (status = 400, description = "Invalid or oversized archive, or an unsupported snapshot version", body = ErrorBody),
+        (status = 401, description = "Not the primary auth token", body = ErrorBody),
+        (status = 404, description = "No database configured", body = ErrorBody)
+    ),
+    security(("bearer_auth" = []), ("query_token" = [])),
+    tag = "admin"
+)]
+pub async fn import_snapshot(
+    State(state): State<AppState>,
+    auth: Option<AuthHeader>,
+    axum::extract::Query(query): axum::extract::Query<AuthQuery>,
+    request: axum::extract::Request,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize_primary(&state.settings, auth, query.token.as_deref())?;
+    let store = required_store(&state, "Snapshots")?;
+    // Read only once the caller is known to be the admin, so nobody else can make the server
+    // buffer an archive of up to 256 MiB
+    let Json(snapshot) = Json::<Snapshot>::from_request(request, &state)
+        .await
+        .map_err(|rejection| ApiError::BadRequest(rejection.body_text()))?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(ApiError::BadRequest(format!(
+            "Unsupported snapshot version {}, expected {SNAPSHOT_VERSION}",
+            snapshot.version
+        )));
+    }
+
+    store.import(&snapshot).await?;
+    Ok(Json(serde_json::json!({
+        "weeks": snapshot.weeks.len(),
+        "changes": snapshot.changes.len(),
+        "favorites": snapshot.favorites.len(),
+        "attendance": snapshot.attendance.values().map(Vec::len).sum::<usize>(),
+    })))
+}
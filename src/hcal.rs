@@ -0,0 +1,141 @@
+//! HTML timetable marked up with h-event microformats.
+
+use chrono::SecondsFormat;
+
+use crate::export::Exporter;
+use crate::i18n::{Lang, Message, date_time};
+use crate::models::ClassItem;
+use crate::settings::Settings;
+
+/// A standalone page listing the classes as an `h-feed` of `h-event`s.
+pub fn timetable_hcal(classes: &[ClassItem], settings: &Settings) -> String {
+    let lang = settings.lang;
+    let title = match lang {
+        Lang::En => format!("{} {}", settings.gym_title, Message::Timetable.text(lang)),
+        Lang::Pl => format!("{} – {}", settings.gym_title, Message::Timetable.text(lang)),
+    };
+    let title = escape(&title);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n</head>\n<body>\n<main class=\"h-feed\">\n\
+         <h1 class=\"p-name\">{title}</h1>\n",
+        lang = lang.as_str()
+    );
+    for class in classes {
+        html.push_str(&class_event(class, settings));
+    }
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// `/timetable.hcal`: the page of `timetable_hcal`.
+pub struct HCalExporter;
+
+impl Exporter for HCalExporter {
+    fn format(&self) -> &'static str {
+        "hcal"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/html; charset=utf-8"
+    }
+
+    fn render(&self, classes: &[ClassItem], settings: &Settings) -> Vec<u8> {
+        timetable_hcal(classes, settings).into_bytes()
+    }
+}
+
+fn class_event(class: &ClassItem, settings: &Settings) -> String {
+    let (start, end) = (class.start, class.end);
+    let location = class.location.as_deref().unwrap_or(&settings.gym_location);
+    format!(
+        "<article class=\"h-event\">\n\
+         <h2 class=\"p-name\">{name}</h2>\n\
+         <data class=\"u-uid\" value=\"{uid}\"></data>\n\
+         <p><time class=\"dt-start\" datetime=\"{start}\">{start_text}</time> – \
+         <time class=\"dt-end\" datetime=\"{end}\">{end_text}</time></p>\n\
+         <p class=\"p-description\">{coach_label}: {coach}</p>\n\
+         <p class=\"p-location\">{location}</p>\n\
+         <a class=\"u-url\" href=\"{url}\">{details}</a>\n\
+         </article>\n",
+        name = escape(&class.event_name),
+        uid = class.id,
+        start = start.to_rfc3339_opts(SecondsFormat::Secs, false),
+        start_text = date_time(start.naive_local(), settings.lang),
+        end = end.to_rfc3339_opts(SecondsFormat::Secs, false),
+        end_text = end.format("%H:%M"),
+        coach_label = Message::Coach.text(settings.lang),
+        coach = escape(&class.coach),
+        details = Message::Details.text(settings.lang),
+        location = escape(location),
+        url = escape(&class.source_url),
+    )
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    #[test]
+    fn test_timetable_hcal() {
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00", "%Y-%m-%d %H:%M").unwrap(),
+            "WOD <Scaled>".to_string(),
+            "Anna".to_string(),
+            Some(45),
+            "https://example.com/?a=1&b=2".to_string(),
+        );
+
+        let html = timetable_hcal(std::slice::from_ref(&class), &Settings::default());
+
+        assert!(html.contains("<main class=\"h-feed\">"));
+        assert!(html.contains("<h2 class=\"p-name\">WOD &lt;Scaled&gt;</h2>"));
+        assert!(html.contains(&format!("value=\"{}\"", class.id)));
+        assert!(html.contains(
+            "<time class=\"dt-start\" datetime=\"2025-11-24T06:00:00+01:00\">Mon 24.11 06:00</time>"
+        ));
+        assert!(html.contains("datetime=\"2025-11-24T06:45:00+01:00\">06:45</time>"));
+        assert!(html.contains("<p class=\"p-description\">Coach: Anna</p>"));
+        assert!(html.contains("href=\"https://example.com/?a=1&amp;b=2\""));
+    }
+
+    #[test]
+    fn test_timetable_hcal_in_polish() {
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-26 06:00", "%Y-%m-%d %H:%M").unwrap(),
+            "WOD".to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        let settings = Settings {
+            lang: Lang::Pl,
+            ..Settings::default()
+        };
+
+        let html = timetable_hcal(&[class], &settings);
+
+        assert!(html.contains("<html lang=\"pl\">"));
+        assert!(html.contains("– plan zajęć</h1>"));
+        assert!(html.contains(">Śr 26.11 06:00</time>"));
+        assert!(html.contains("<p class=\"p-description\">Trener: Anna</p>"));
+        assert!(html.contains(">Szczegóły</a>"));
+    }
+}
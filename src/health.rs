@@ -0,0 +1,333 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use serde::Serialize;
+use tokio::task::JoinSet;
+use tracing::error;
+use utoipa::ToSchema;
+
+// Delay before a crashed task is started again, doubled for each crash in a row
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+// A task that ran this long before crashing is restarted with the shortest delay again
+const STABLE_RUN: Duration = Duration::from_secs(600);
+
+/// Outcome of the most recent attempts of a recurring operation (scrapes, deliveries).
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct Activity {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl Activity {
+    pub fn succeeded(&mut self) {
+        self.last_success = Some(Utc::now());
+    }
+
+    pub fn failed(&mut self, error: impl Display) {
+        self.last_failure = Some(Utc::now());
+        self.last_error = Some(error.to_string());
+    }
+
+    /// Whether the latest attempt failed.
+    pub fn is_failing(&self) -> bool {
+        match (self.last_success, self.last_failure) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(success), Some(failure)) => failure > success,
+        }
+    }
+}
+
+/// Upstream scrapes as seen by `CrossfitScraper`.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ScrapeStats {
+    #[serde(flatten)]
+    pub activity: Activity,
+    pub last_latency_ms: Option<u64>,
+    /// Responses in which the upstream asked us to slow down (HTTP 429)
+    pub rate_limited: u64,
+    /// No upstream requests are made before this time
+    pub backoff_until: Option<DateTime<Utc>>,
+    /// Scrapes that looked like the parser no longer matches the upstream markup
+    pub drift_alarms: u64,
+    /// Why the latest scrape looked that way; cleared by a scrape that looks normal
+    pub drift: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    /// Crashed, and waiting for its supervisor to start it again
+    Restarting,
+    Stopped,
+}
+
+/// A background task's state and the crashes its supervisor recovered from.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TaskStatus {
+    pub state: TaskState,
+    /// Times the task panicked and was restarted
+    pub restarts: u32,
+    pub last_crash: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl TaskStatus {
+    fn running() -> Self {
+        Self {
+            state: TaskState::Running,
+            restarts: 0,
+            last_crash: None,
+            last_error: None,
+        }
+    }
+}
+
+pub type TaskStatuses = Arc<Mutex<BTreeMap<&'static str, TaskStatus>>>;
+
+/// Background tasks started with the current configuration; dropping the set stops them.
+#[derive(Default)]
+pub struct BackgroundTasks {
+    set: JoinSet<()>,
+    statuses: TaskStatuses,
+}
+
+// Marks the task stopped however it ends: returning, panicking or being aborted
+struct StoppedOnDrop(TaskStatuses, &'static str);
+
+impl Drop for StoppedOnDrop {
+    fn drop(&mut self) {
+        if let Ok(mut statuses) = self.0.lock()
+            && let Some(status) = statuses.get_mut(self.1)
+        {
+            status.state = TaskState::Stopped;
+        }
+    }
+}
+
+fn update_status(statuses: &TaskStatuses, name: &str, update: impl FnOnce(&mut TaskStatus)) {
+    if let Some(status) = statuses
+        .lock()
+        .expect("task status lock poisoned")
+        .get_mut(name)
+    {
+        update(status);
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => format!("panicked: {message}"),
+        (_, Some(message)) => format!("panicked: {message}"),
+        _ => "panicked".to_string(),
+    }
+}
+
+impl BackgroundTasks {
+    /// Runs `task` once; it shows as stopped when it ends.
+    pub fn spawn(&mut self, name: &'static str, task: impl Future<Output = ()> + Send + 'static) {
+        self.statuses
+            .lock()
+            .expect("task status lock poisoned")
+            .insert(name, TaskStatus::running());
+        let guard = StoppedOnDrop(Arc::clone(&self.statuses), name);
+        self.set.spawn(async move {
+            let _guard = guard;
+            task.await;
+        });
+    }
+
+    /// `spawn` for a task `make_task` builds, building and starting it again with exponential
+    /// backoff whenever it panics. A task that returns is done, as with `spawn`.
+    pub fn supervise<F, Fut>(&mut self, name: &'static str, mut make_task: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let statuses = Arc::clone(&self.statuses);
+        self.spawn(name, async move {
+            let mut backoff = RESTART_BACKOFF;
+            loop {
+                let started = Instant::now();
+                let Err(panic) = AssertUnwindSafe(make_task()).catch_unwind().await else {
+                    return;
+                };
+                let error = panic_message(panic.as_ref());
+                if started.elapsed() >= STABLE_RUN {
+                    backoff = RESTART_BACKOFF;
+                }
+                error!(task = name, error = %error, restart_in_secs = backoff.as_secs(), "background task crashed");
+                update_status(&statuses, name, |status| {
+                    status.state = TaskState::Restarting;
+                    status.restarts += 1;
+                    status.last_crash = Some(Utc::now());
+                    status.last_error = Some(error);
+                });
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+                update_status(&statuses, name, |status| status.state = TaskState::Running);
+            }
+        });
+    }
+
+    pub fn statuses(&self) -> TaskStatuses {
+        Arc::clone(&self.statuses)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthReport {
+    /// `ok`, or `degraded` when the latest scrape or delivery failed, the parser seems out of
+    /// date or a task stopped or is restarting after a crash
+    pub status: &'static str,
+    pub scraper: ScrapeStats,
+    #[schema(value_type = BTreeMap<String, TaskStatus>)]
+    pub tasks: BTreeMap<&'static str, TaskStatus>,
+    #[schema(value_type = BTreeMap<String, Activity>)]
+    pub notifiers: BTreeMap<&'static str, Activity>,
+}
+
+impl HealthReport {
+    pub fn new(
+        scraper: ScrapeStats,
+        tasks: BTreeMap<&'static str, TaskStatus>,
+        notifiers: BTreeMap<&'static str, Activity>,
+    ) -> Self {
+        let degraded = scraper.activity.is_failing()
+            || scraper.drift.is_some()
+            || tasks
+                .values()
+                .any(|status| status.state != TaskState::Running)
+            || notifiers.values().any(Activity::is_failing);
+        Self {
+            status: if degraded { "degraded" } else { "ok" },
+            scraper,
+            tasks,
+            notifiers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_activity_reports_latest_attempt() {
+        let mut activity = Activity::default();
+        assert!(!activity.is_failing());
+
+        activity.failed("timeout");
+        assert!(activity.is_failing());
+        assert_eq!(activity.last_error.as_deref(), Some("timeout"));
+
+        std::thread::sleep(Duration::from_millis(2));
+        activity.succeeded();
+        assert!(!activity.is_failing());
+    }
+
+    #[tokio::test]
+    async fn test_task_statuses() {
+        let mut tasks = BackgroundTasks::default();
+        tasks.spawn("finishes", async {});
+        tasks.spawn("runs", std::future::pending());
+        let statuses = tasks.statuses();
+        tokio::task::yield_now().await;
+
+        assert_eq!(
+            statuses.lock().unwrap()["finishes"].state,
+            TaskState::Stopped
+        );
+        assert_eq!(statuses.lock().unwrap()["runs"].state, TaskState::Running);
+
+        drop(tasks);
+        tokio::task::yield_now().await;
+        assert_eq!(statuses.lock().unwrap()["runs"].state, TaskState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_supervised_task_restarts_after_crash() {
+        let mut tasks = BackgroundTasks::default();
+        let (started, mut starts) = tokio::sync::mpsc::unbounded_channel();
+        tasks.supervise("crashes", move || {
+            started.send(()).unwrap();
+            async { panic!("connection reset") }
+        });
+        let statuses = tasks.statuses();
+
+        starts.recv().await.unwrap();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+
+        let status = statuses.lock().unwrap()["crashes"].clone();
+        assert_eq!(status.state, TaskState::Restarting);
+        assert_eq!(status.restarts, 1);
+        assert_eq!(
+            status.last_error.as_deref(),
+            Some("panicked: connection reset")
+        );
+        assert!(status.last_crash.is_some());
+
+        drop(tasks);
+        tokio::task::yield_now().await;
+        assert_eq!(
+            statuses.lock().unwrap()["crashes"].state,
+            TaskState::Stopped
+        );
+    }
+
+    #[test]
+    fn test_report_status() {
+        let healthy = HealthReport::new(
+            ScrapeStats::default(),
+            BTreeMap::from([("reminders", TaskStatus::running())]),
+            BTreeMap::new(),
+        );
+        assert_eq!(healthy.status, "ok");
+
+        let restarting = HealthReport::new(
+            ScrapeStats::default(),
+            BTreeMap::from([(
+                "reminders",
+                TaskStatus {
+                    state: TaskState::Restarting,
+                    ..TaskStatus::running()
+                },
+            )]),
+            BTreeMap::new(),
+        );
+        assert_eq!(restarting.status, "degraded");
+
+        let mut failing = Activity::default();
+        failing.failed("HTTP 500");
+        let degraded = HealthReport::new(
+            ScrapeStats::default(),
+            BTreeMap::new(),
+            BTreeMap::from([("slack", failing)]),
+        );
+        assert_eq!(degraded.status, "degraded");
+
+        let drifted = HealthReport::new(
+            ScrapeStats {
+                drift: Some("no classes read".to_string()),
+                ..ScrapeStats::default()
+            },
+            BTreeMap::new(),
+            BTreeMap::new(),
+        );
+        assert_eq!(drifted.status, "degraded");
+    }
+}
@@ -0,0 +1,204 @@
+//! The gym's opening hours as published on its contact page, e.g. `Pon - Pt: 6:00 - 22:00`.
+
+use std::sync::LazyLock;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use regex::Regex;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::Closure;
+
+// Longer names first, so `Pon` isn't read as `Pn` plus leftovers
+const DAY: &str = r"poniedziałek|poniedzialek|pon|pn|wtorek|wt|środa|sroda|śr|sr|czwartek|czw|piątek|piatek|pt|sobota|sob|niedziela|niedz|nd|monday|mon|tuesday|tue|wednesday|wed|thursday|thu|friday|fri|saturday|sat|sunday|sun";
+
+// A day or range of days followed by a time range, e.g. `Pon - Pt: 6:00 - 22:00`
+static HOURS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(?P<from>{DAY})\.?(?:\s*[-–]\s*(?P<to>{DAY})\.?)?\s*:?\s*(?P<opens>\d{{1,2}}[:.]\d{{2}})\s*[-–]\s*(?P<closes>\d{{1,2}}[:.]\d{{2}})"
+    ))
+    .expect("opening hours pattern is valid")
+});
+
+/// A span of a weekday the gym is open.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct OpeningHours {
+    #[schema(value_type = String, example = "Mon")]
+    pub weekday: Weekday,
+    #[schema(value_type = String, example = "06:00")]
+    pub opens: NaiveTime,
+    #[schema(value_type = String, example = "22:00")]
+    pub closes: NaiveTime,
+}
+
+/// The opening hours of one date, shown as an all-day event in calendar feeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenDay {
+    pub date: NaiveDate,
+    pub hours: Vec<OpeningHours>,
+}
+
+impl OpenDay {
+    /// The spans of the day, e.g. `06:00–12:00, 16:00–22:00`.
+    pub fn label(&self) -> String {
+        self.hours
+            .iter()
+            .map(|span| {
+                format!(
+                    "{}–{}",
+                    span.opens.format("%H:%M"),
+                    span.closes.format("%H:%M")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The opening hours listed on a page, Monday first. Days listed as closed are left out.
+pub fn parse_opening_hours(html: &str) -> Vec<OpeningHours> {
+    let document = Html::parse_document(html);
+    // Text nodes are kept apart, so `22:00</p><p>Sob` doesn't read as one word
+    let text = document.root_element().text().collect::<Vec<_>>().join(" ");
+    let mut hours = Vec::new();
+    for found in HOURS.captures_iter(&text) {
+        let (Some(from), Some(opens), Some(closes)) = (
+            weekday_of(&found["from"]),
+            time_of(&found["opens"]),
+            time_of(&found["closes"]),
+        ) else {
+            continue;
+        };
+        let to = found
+            .name("to")
+            .and_then(|to| weekday_of(to.as_str()))
+            .unwrap_or(from);
+        let mut weekday = from;
+        loop {
+            hours.push(OpeningHours {
+                weekday,
+                opens,
+                closes,
+            });
+            if weekday == to {
+                break;
+            }
+            weekday = weekday.succ();
+        }
+    }
+    hours.sort_by_key(|span| (span.weekday.num_days_from_monday(), span.opens));
+    hours.dedup();
+    hours
+}
+
+/// The open dates of the weeks starting on `mondays`, skipping the days the gym is closed.
+pub fn open_days(
+    hours: &[OpeningHours],
+    mondays: &[NaiveDate],
+    closures: &[Closure],
+) -> Vec<OpenDay> {
+    mondays
+        .iter()
+        .flat_map(|monday| (0..7).map(move |day| *monday + Duration::days(day)))
+        .filter(|date| !closures.iter().any(|closure| closure.date == *date))
+        .map(|date| OpenDay {
+            date,
+            hours: hours
+                .iter()
+                .filter(|span| span.weekday == date.weekday())
+                .cloned()
+                .collect(),
+        })
+        .filter(|day| !day.hours.is_empty())
+        .collect()
+}
+
+fn weekday_of(name: &str) -> Option<Weekday> {
+    let name = name.to_lowercase();
+    let prefixes: [(Weekday, &[&str]); 7] = [
+        (Weekday::Mon, &["pon", "pn", "mon"]),
+        (Weekday::Tue, &["wt", "tue"]),
+        (Weekday::Wed, &["śr", "sr", "wed"]),
+        (Weekday::Thu, &["cz", "thu"]),
+        (Weekday::Fri, &["pi", "pt", "fri"]),
+        (Weekday::Sat, &["so", "sat"]),
+        (Weekday::Sun, &["nie", "nd", "sun"]),
+    ];
+    prefixes
+        .iter()
+        .find(|(_, names)| names.iter().any(|prefix| name.starts_with(prefix)))
+        .map(|(weekday, _)| *weekday)
+}
+
+fn time_of(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(&text.replace('.', ":"), "%H:%M").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(weekday: Weekday, opens: &str, closes: &str) -> OpeningHours {
+        OpeningHours {
+            weekday,
+            opens: opens.parse().unwrap(),
+            closes: closes.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_polish_contact_page() {
+        let html = r#"<div class="contact">
+            <h3>Godziny otwarcia</h3>
+            <p><strong>Pon - Pt:</strong> 6:00 - 22:00</p>
+            <p><strong>Sobota:</strong> 8.00–14.00</p>
+            <p><strong>Niedziela:</strong> nieczynne</p>
+        </div>"#;
+
+        let hours = parse_opening_hours(html);
+
+        assert_eq!(hours.len(), 6);
+        assert_eq!(hours[0], span(Weekday::Mon, "06:00", "22:00"));
+        assert_eq!(hours[4], span(Weekday::Fri, "06:00", "22:00"));
+        assert_eq!(hours[5], span(Weekday::Sat, "08:00", "14:00"));
+    }
+
+    #[test]
+    fn test_parse_split_days_in_english() {
+        let html =
+            "<ul><li>Mon 06:00-12:00</li><li>Mon 16:00-21:00</li><li>Sun 10:00-12:00</li></ul>";
+
+        let hours = parse_opening_hours(html);
+
+        assert_eq!(
+            hours,
+            [
+                span(Weekday::Mon, "06:00", "12:00"),
+                span(Weekday::Mon, "16:00", "21:00"),
+                span(Weekday::Sun, "10:00", "12:00"),
+            ]
+        );
+        assert!(parse_opening_hours("<p>Zapraszamy!</p>").is_empty());
+    }
+
+    #[test]
+    fn test_open_days_skip_closures() {
+        let hours = [
+            span(Weekday::Mon, "06:00", "12:00"),
+            span(Weekday::Mon, "16:00", "21:00"),
+            span(Weekday::Wed, "06:00", "22:00"),
+        ];
+        let monday: NaiveDate = "2025-12-22".parse().unwrap();
+        let closures = [Closure {
+            date: "2025-12-24".parse().unwrap(),
+            reason: "Wigilia".to_string(),
+        }];
+
+        let days = open_days(&hours, &[monday], &closures);
+
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].date, monday);
+        assert_eq!(days[0].label(), "06:00–12:00, 16:00–21:00");
+    }
+}
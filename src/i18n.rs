@@ -0,0 +1,173 @@
+//! Translations of the text this service generates around the upstream data: day names, class
+//! types and descriptions. The upstream data itself is Polish and stays as it is.
+
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::diff::ScheduleChange;
+use crate::models::ClassKind;
+
+/// Language of generated text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    Pl,
+    #[default]
+    En,
+}
+
+impl Lang {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::Pl => "pl",
+            Lang::En => "en",
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pl" => Ok(Lang::Pl),
+            "en" => Ok(Lang::En),
+            other => Err(format!("unknown language {other:?} (expected pl or en)")),
+        }
+    }
+}
+
+/// Fixed phrases of the generated outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    Timetable,
+    Coach,
+    Source,
+    Details,
+    CrossFitClass,
+    GymClosed,
+    OpeningHours,
+    ByType,
+    NewThisWeek,
+    ChangesSinceLastWeek,
+}
+
+impl Message {
+    pub fn text(self, lang: Lang) -> &'static str {
+        let (en, pl) = match self {
+            Message::Timetable => ("timetable", "plan zajęć"),
+            Message::Coach => ("Coach", "Trener"),
+            Message::Source => ("Source", "Źródło"),
+            Message::Details => ("Details", "Szczegóły"),
+            Message::CrossFitClass => ("CrossFit Class", "Zajęcia CrossFit"),
+            Message::GymClosed => ("Gym Closed", "Klub nieczynny"),
+            Message::OpeningHours => ("Open Gym hours", "Godziny otwarcia"),
+            Message::ByType => ("By type", "Według rodzaju"),
+            Message::NewThisWeek => ("New this week", "Nowe zajęcia"),
+            Message::ChangesSinceLastWeek => (
+                "Changes compared to last week",
+                "Zmiany względem poprzedniego tygodnia",
+            ),
+        };
+        match lang {
+            Lang::En => en,
+            Lang::Pl => pl,
+        }
+    }
+}
+
+/// Short day name, e.g. `Mon` or `Pn`.
+pub fn weekday(day: Weekday, lang: Lang) -> &'static str {
+    const EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    const PL: [&str; 7] = ["Pn", "Wt", "Śr", "Cz", "Pt", "Sb", "Nd"];
+    let names = match lang {
+        Lang::En => EN,
+        Lang::Pl => PL,
+    };
+    names[day.num_days_from_monday() as usize]
+}
+
+/// Start of a class as shown to members, e.g. `Mon 24.11 06:00`.
+pub fn date_time(date: NaiveDateTime, lang: Lang) -> String {
+    format!(
+        "{} {}",
+        weekday(date.weekday(), lang),
+        date.format("%d.%m %H:%M")
+    )
+}
+
+pub fn kind_label(kind: ClassKind, lang: Lang) -> &'static str {
+    match (lang, kind) {
+        (Lang::Pl, ClassKind::Weightlifting) => "Podnoszenie ciężarów",
+        (Lang::Pl, ClassKind::Kids) => "Dzieci",
+        (Lang::Pl, ClassKind::Other) => "Inne",
+        _ => kind.label(),
+    }
+}
+
+/// `ScheduleChange::describe` in `lang`.
+pub fn describe_change(change: &ScheduleChange, lang: Lang) -> String {
+    if lang == Lang::En {
+        return change.describe();
+    }
+    match change {
+        ScheduleChange::Added { class } => format!(
+            "Nowe: {} {} ({})",
+            class.event_name,
+            date_time(class.date, lang),
+            class.coach
+        ),
+        ScheduleChange::Removed { class } => format!(
+            "Odwołane: {} {} ({})",
+            class.event_name,
+            date_time(class.date, lang),
+            class.coach
+        ),
+        ScheduleChange::TimeChanged {
+            class,
+            previous_date,
+        } => format!(
+            "Przeniesione: {} ({}) z {} na {}",
+            class.event_name,
+            class.coach,
+            date_time(*previous_date, lang),
+            date_time(class.date, lang)
+        ),
+        ScheduleChange::CoachChanged {
+            class,
+            previous_coach,
+        } => format!(
+            "Zmiana trenera: {} {}, {} → {}",
+            class.event_name,
+            date_time(class.date, lang),
+            previous_coach,
+            class.coach
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_time() {
+        let date = NaiveDateTime::parse_from_str("2025-11-26 06:00", "%Y-%m-%d %H:%M").unwrap();
+
+        // English matches chrono's `%a`, used before translations existed
+        assert_eq!(
+            date_time(date, Lang::En),
+            date.format("%a %d.%m %H:%M").to_string()
+        );
+        assert_eq!(date_time(date, Lang::Pl), "Śr 26.11 06:00");
+    }
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!("PL".parse(), Ok(Lang::Pl));
+        assert_eq!("en".parse(), Ok(Lang::En));
+        assert!("de".parse::<Lang>().is_err());
+    }
+}
@@ -1,7 +1,10 @@
-use chrono::Duration;
-use icalendar::{Calendar, Component, Event, EventLike, Property};
+use chrono::{Duration, Utc};
+use icalendar::{Alarm, Calendar, Component, Event, EventLike, Property, Trigger};
 
-use crate::models::ClassItem;
+use crate::export::Exporter;
+use crate::hours::OpenDay;
+use crate::i18n::{Message, kind_label};
+use crate::models::{ClassItem, Closure};
 use crate::settings::Settings;
 
 #[derive(Clone, Default)]
@@ -37,40 +40,65 @@ impl ICalExporter {
     }
 
     pub fn generate(&self, classes: &[ClassItem], settings: &Settings) -> Vec<u8> {
-        if classes.is_empty() {
+        self.generate_with_closures(classes, &[], settings)
+    }
+
+    /// `generate`, adding an all-day "Gym Closed" event for each closed day.
+    pub fn generate_with_closures(
+        &self,
+        classes: &[ClassItem],
+        closures: &[Closure],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        self.generate_with_opening_hours(classes, closures, &[], settings)
+    }
+
+    /// `generate_with_closures`, adding an all-day "Open Gym hours" event for each open day.
+    pub fn generate_with_opening_hours(
+        &self,
+        classes: &[ClassItem],
+        closures: &[Closure],
+        open_days: &[OpenDay],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        if classes.is_empty() && closures.is_empty() && open_days.is_empty() {
             return Vec::new();
         }
 
         let mut calendar = Calendar::new();
-        calendar.name("CrossFit 2.0 Rzeszów Timetable");
+        calendar.name(&settings.ical_calendar_name);
+        if let Some(color) = &settings.ical_color {
+            // Apple Calendar's color of the subscription; RFC 7986 `COLOR` only takes CSS names
+            calendar.append_property(Property::new("X-APPLE-CALENDAR-COLOR", color));
+        }
 
         for item in classes {
-            let start = item.date;
-            let end_dt = if let Some(duration) = item.duration_min {
-                item.date + Duration::minutes(duration as i64)
-            } else {
-                item.date + Duration::hours(1)
-            };
-
             let mut event = Event::new();
-            event.summary(&format!("CrossFit: {}", item.event_name));
-            event.starts(start);
-            event.ends(end_dt);
+            let summary = format!("{}{}", settings.ical_event_prefix, item.event_name);
+            event.summary(&summary);
+            // UTC times are unambiguous without shipping a VTIMEZONE definition
+            event.starts(item.start.with_timezone(&Utc));
+            event.ends(item.end.with_timezone(&Utc));
             let location = item
                 .location
                 .clone()
                 .unwrap_or_else(|| settings.gym_location.clone());
             event.location(&location);
-            event.description(&format!(
-                "CrossFit Class\nCoach: {}\nSource: {}",
-                item.coach, item.source_url
-            ));
-            event.uid(&format!(
-                "{}-{}-{}-crossfit-timetable",
-                item.date.format("%Y%m%dT%H%M%S"),
-                item.event_name.replace(' ', "-"),
-                item.coach.replace(' ', "-")
-            ));
+            event.add_property("CATEGORIES", kind_label(item.kind, settings.lang));
+            let mut description = format!(
+                "{}\n{}: {}\n{}: {}",
+                Message::CrossFitClass.text(settings.lang),
+                Message::Coach.text(settings.lang),
+                item.coach,
+                Message::Source.text(settings.lang),
+                item.source_url
+            );
+            if let Some(text) = &item.description {
+                description.push_str(&format!("\n\n{text}"));
+            }
+            event.description(&description);
+            // Stays the same when the coach changes, so calendars update the event in place
+            event.uid(&format!("{}@crossfit-timetable", item.id));
 
             // Add X-APPLE-STRUCTURED-LOCATION for enhanced Apple Calendar support
             let structured_location = Self::create_structured_location(
@@ -81,6 +109,39 @@ impl ICalExporter {
             );
             event.append_property(structured_location);
 
+            if let Some(minutes) = settings.ical_alarm_minutes {
+                event.alarm(Alarm::display(
+                    &summary,
+                    Trigger::before_start(Duration::minutes(minutes.into())),
+                ));
+            }
+
+            calendar.push(event);
+        }
+
+        for closure in closures {
+            let mut event = Event::new();
+            event.summary(Message::GymClosed.text(settings.lang));
+            event.all_day(closure.date);
+            event.description(&closure.reason);
+            event.location(&settings.gym_location);
+            // Free time, so the day doesn't show as busy
+            event.add_property("TRANSP", "TRANSPARENT");
+            event.uid(&format!("closed-{}@crossfit-timetable", closure.date));
+            calendar.push(event);
+        }
+
+        for day in open_days {
+            let mut event = Event::new();
+            event.summary(&format!(
+                "{} {}",
+                Message::OpeningHours.text(settings.lang),
+                day.label()
+            ));
+            event.all_day(day.date);
+            event.location(&settings.gym_location);
+            event.add_property("TRANSP", "TRANSPARENT");
+            event.uid(&format!("open-{}@crossfit-timetable", day.date));
             calendar.push(event);
         }
 
@@ -88,6 +149,43 @@ impl ICalExporter {
     }
 }
 
+impl Exporter for ICalExporter {
+    fn format(&self) -> &'static str {
+        "ical"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/calendar"
+    }
+
+    fn file_name(&self) -> Option<&'static str> {
+        Some("crossfit_timetable.ics")
+    }
+
+    fn render(&self, classes: &[ClassItem], settings: &Settings) -> Vec<u8> {
+        self.generate(classes, settings)
+    }
+
+    fn render_with_closures(
+        &self,
+        classes: &[ClassItem],
+        closures: &[Closure],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        self.generate_with_closures(classes, closures, settings)
+    }
+
+    fn render_with_opening_hours(
+        &self,
+        classes: &[ClassItem],
+        closures: &[Closure],
+        open_days: &[OpenDay],
+        settings: &Settings,
+    ) -> Vec<u8> {
+        self.generate_with_opening_hours(classes, closures, open_days, settings)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::NaiveDateTime;
@@ -107,6 +205,7 @@ mod tests {
             gym_longitude: 22.0026,
             gym_title: "CrossFit 2.0 Rzeszów".to_string(),
             gym_location: "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string(),
+            ..Settings::default()
         }
     }
 
@@ -114,19 +213,130 @@ mod tests {
     fn test_generate_single_class() {
         let exporter = ICalExporter::new();
         let settings = create_test_settings();
-        let class = ClassItem {
-            date: NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S")
-                .unwrap(),
-            event_name: "WOD".to_string(),
-            coach: "Coach".to_string(),
-            duration_min: Some(60),
-            source_url: "https://example.com".to_string(),
-            location: None,
-        };
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            "WOD".to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        let class_id = class.id.clone();
         let bytes = exporter.generate(&[class], &settings);
         let body = String::from_utf8(bytes).unwrap();
         assert!(body.contains("BEGIN:VEVENT"));
         assert!(body.contains("CrossFit: WOD"));
+        assert!(body.contains("DTSTART:20251124T050000Z"));
+        assert!(body.contains("DTEND:20251124T060000Z"));
+        assert!(body.contains(&format!("UID:{}@crossfit-timetable", class_id)));
+    }
+
+    #[test]
+    fn test_generate_alarm() {
+        let exporter = ICalExporter::new();
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            "WOD".to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        let without = exporter.generate(std::slice::from_ref(&class), &create_test_settings());
+        let settings = Settings {
+            ical_alarm_minutes: Some(30),
+            ..create_test_settings()
+        };
+        let body = String::from_utf8(exporter.generate(&[class], &settings)).unwrap();
+        assert!(!String::from_utf8(without).unwrap().contains("BEGIN:VALARM"));
+        assert!(body.contains("BEGIN:VALARM"));
+        assert!(body.contains("ACTION:DISPLAY"));
+        assert!(body.contains("TRIGGER;RELATED=START:-PT1800S"));
+    }
+
+    #[test]
+    fn test_generate_in_polish() {
+        let exporter = ICalExporter::new();
+        let mut class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            "Kids".to_string(),
+            "Ola".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        class.kind = crate::models::ClassKind::Kids;
+        let closure = Closure {
+            date: chrono::NaiveDate::from_ymd_opt(2025, 12, 25).unwrap(),
+            reason: "Boże Narodzenie".to_string(),
+        };
+        let settings = Settings {
+            lang: crate::i18n::Lang::Pl,
+            ..create_test_settings()
+        };
+
+        let bytes = exporter.generate_with_closures(&[class], &[closure], &settings);
+        let body = String::from_utf8(bytes).unwrap();
+
+        assert!(body.contains("CATEGORIES:Dzieci"));
+        assert!(body.contains("DESCRIPTION:Zajęcia CrossFit\\nTrener: Ola\\nŹródło: "));
+        assert!(body.contains("SUMMARY:Klub nieczynny"));
+    }
+
+    #[test]
+    fn test_generate_branding() {
+        let exporter = ICalExporter::new();
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            "WOD".to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        let settings = Settings {
+            ical_calendar_name: "Morning Crew".to_string(),
+            ical_event_prefix: "MC: ".to_string(),
+            ical_color: Some("#ff6600".to_string()),
+            ..create_test_settings()
+        };
+
+        let body = String::from_utf8(exporter.generate(&[class], &settings)).unwrap();
+
+        assert!(body.contains("X-WR-CALNAME:Morning Crew"));
+        assert!(body.contains("X-APPLE-CALENDAR-COLOR:#ff6600"));
+        assert!(body.contains("SUMMARY:MC: WOD"));
+        assert!(!body.contains("CrossFit: WOD"));
+    }
+
+    #[test]
+    fn test_generate_closures() {
+        let exporter = ICalExporter::new();
+        let closure = Closure {
+            date: chrono::NaiveDate::from_ymd_opt(2025, 12, 25).unwrap(),
+            reason: "Nieczynne - Boże Narodzenie".to_string(),
+        };
+        let bytes = exporter.generate_with_closures(&[], &[closure], &create_test_settings());
+        let body = String::from_utf8(bytes).unwrap();
+        assert!(body.contains("SUMMARY:Gym Closed"));
+        assert!(body.contains("DTSTART;VALUE=DATE:20251225"));
+        assert!(body.contains("DESCRIPTION:Nieczynne - Boże Narodzenie"));
+        assert!(body.contains("UID:closed-2025-12-25@crossfit-timetable"));
+    }
+
+    #[test]
+    fn test_generate_opening_hours() {
+        let exporter = ICalExporter::new();
+        let day = OpenDay {
+            date: chrono::NaiveDate::from_ymd_opt(2025, 12, 27).unwrap(),
+            hours: vec![crate::hours::OpeningHours {
+                weekday: chrono::Weekday::Sat,
+                opens: "08:00".parse().unwrap(),
+                closes: "14:00".parse().unwrap(),
+            }],
+        };
+        let bytes = exporter.generate_with_opening_hours(&[], &[], &[day], &create_test_settings());
+        let body = String::from_utf8(bytes).unwrap();
+        assert!(body.contains("SUMMARY:Open Gym hours 08:00–14:00"));
+        assert!(body.contains("DTSTART;VALUE=DATE:20251227"));
+        assert!(body.contains("TRANSP:TRANSPARENT"));
+        assert!(body.contains("UID:open-2025-12-27@crossfit-timetable"));
     }
 
     #[test]
@@ -142,13 +352,14 @@ mod tests {
         let exporter = ICalExporter::new();
         let settings = create_test_settings();
         let class = ClassItem {
-            date: NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S")
-                .unwrap(),
-            event_name: "WOD".to_string(),
-            coach: "Coach".to_string(),
-            duration_min: Some(60),
-            source_url: "https://example.com".to_string(),
             location: Some("Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string()),
+            ..ClassItem::new(
+                NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+                "WOD".to_string(),
+                "Coach".to_string(),
+                Some(60),
+                "https://example.com".to_string(),
+            )
         };
         let bytes = exporter.generate(&[class], &settings);
         let body = String::from_utf8(bytes).unwrap();
@@ -0,0 +1,206 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use ipnet::IpNet;
+use tracing::warn;
+
+use crate::AppState;
+use crate::error::ApiError;
+
+/// Resolves the real client address and enforces the optional CIDR allowlist.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allowlist: Vec<IpNet>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl IpFilter {
+    pub fn new(
+        allowlist: &[String],
+        trusted_proxies: &[String],
+    ) -> Result<Self, ipnet::AddrParseError> {
+        Ok(Self {
+            allowlist: parse_networks(allowlist)?,
+            trusted_proxies: parse_networks(trusted_proxies)?,
+        })
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Returns the client address, following `X-Forwarded-For` only through trusted proxies.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        // IPv4 clients of a dual-stack listener arrive as `::ffff:a.b.c.d`
+        let peer = peer.to_canonical();
+        if !self.is_trusted_proxy(peer) {
+            return peer;
+        }
+
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+
+        // Walk from the closest hop outwards; the first untrusted address is the client
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.is_trusted_proxy(**ip))
+            .or_else(|| forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+
+    /// Client address of a request, if the server was started with connection info.
+    pub fn request_ip(&self, request: &Request) -> Option<IpAddr> {
+        let ConnectInfo(peer) = request.extensions().get::<ConnectInfo<SocketAddr>>()?;
+        Some(self.client_ip(peer.ip(), request.headers()))
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        self.allowlist.is_empty() || self.allowlist.iter().any(|net| net.contains(&ip))
+    }
+
+    pub fn has_allowlist(&self) -> bool {
+        !self.allowlist.is_empty()
+    }
+}
+
+/// Whether `ip` is reachable on the public internet, as opposed to loopback, private,
+/// link-local (e.g. cloud metadata services), shared, multicast or unspecified addresses.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 100.64.0.0/10, carrier-grade NAT
+                || (first == 100 && (64..128).contains(&second)))
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || ip.is_multicast())
+        }
+    }
+}
+
+fn parse_networks(values: &[String]) -> Result<Vec<IpNet>, ipnet::AddrParseError> {
+    values
+        .iter()
+        .map(|value| {
+            // Plain addresses are accepted as single-host networks
+            value
+                .parse::<IpNet>()
+                .or_else(|err| value.parse::<IpAddr>().map(IpNet::from).map_err(|_| err))
+        })
+        .collect()
+}
+
+pub async fn ip_allowlist(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.ip_filter.has_allowlist() {
+        return next.run(request).await;
+    }
+
+    match state.ip_filter.request_ip(&request) {
+        Some(ip) if state.ip_filter.is_allowed(ip) => next.run(request).await,
+        ip => {
+            warn!(client_ip = ?ip, path = %request.uri().path(), "request rejected by IP allowlist");
+            ApiError::Forbidden("Client address not allowed".into()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_allowlist_cidr() {
+        let filter = IpFilter::new(&["192.168.1.0/24".into(), "fd00::/8".into()], &[]).unwrap();
+
+        assert!(filter.is_allowed("192.168.1.42".parse().unwrap()));
+        assert!(filter.is_allowed("fd12::1".parse().unwrap()));
+        assert!(!filter.is_allowed("10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_forwarded_for_only_from_trusted_proxy() {
+        let filter = IpFilter::new(&[], &["10.0.0.1".into()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.7, 192.168.1.20, 10.0.0.1"),
+        );
+
+        // From the trusted proxy the right-most untrusted hop is the client
+        assert_eq!(
+            filter.client_ip("10.0.0.1".parse().unwrap(), &headers),
+            "192.168.1.20".parse::<IpAddr>().unwrap()
+        );
+        // Anyone else can't spoof their address via the header
+        assert_eq!(
+            filter.client_ip("198.51.100.9".parse().unwrap(), &headers),
+            "198.51.100.9".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_v4_mapped_peer_is_ipv4() {
+        let filter = IpFilter::new(&[], &["10.0.0.1".into()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7"));
+
+        assert_eq!(
+            filter.client_ip("::ffff:10.0.0.1".parse().unwrap(), &headers),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            filter.client_ip("::ffff:198.51.100.9".parse().unwrap(), &HeaderMap::new()),
+            "198.51.100.9".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_public_address() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_network() {
+        assert!(IpFilter::new(&["not-a-network".into()], &[]).is_err());
+    }
+}
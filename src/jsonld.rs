@@ -0,0 +1,112 @@
+//! schema.org structured data for embedding the timetable in a gym website.
+
+use chrono::SecondsFormat;
+use serde_json::{Value, json};
+
+use crate::export::Exporter;
+use crate::models::ClassItem;
+use crate::settings::Settings;
+
+/// A JSON-LD document with one `Event` per class, each held at a `SportsActivityLocation`.
+pub fn timetable_jsonld(classes: &[ClassItem], settings: &Settings) -> Value {
+    let events: Vec<Value> = classes
+        .iter()
+        .map(|class| class_event(class, settings))
+        .collect();
+    json!({
+        "@context": "https://schema.org",
+        "@graph": events,
+    })
+}
+
+/// `/timetable.jsonld`: the document of `timetable_jsonld`.
+pub struct JsonLdExporter;
+
+impl Exporter for JsonLdExporter {
+    fn format(&self) -> &'static str {
+        "jsonld"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/ld+json"
+    }
+
+    fn render(&self, classes: &[ClassItem], settings: &Settings) -> Vec<u8> {
+        timetable_jsonld(classes, settings).to_string().into_bytes()
+    }
+}
+
+fn class_event(class: &ClassItem, settings: &Settings) -> Value {
+    let address = class.location.as_deref().unwrap_or(&settings.gym_location);
+    json!({
+        "@type": "Event",
+        "identifier": class.id,
+        "name": format!("CrossFit: {}", class.event_name),
+        "description": format!("{} with {}", class.event_name, class.coach),
+        "startDate": class.start.to_rfc3339_opts(SecondsFormat::Secs, false),
+        "endDate": class.end.to_rfc3339_opts(SecondsFormat::Secs, false),
+        "eventStatus": "https://schema.org/EventScheduled",
+        "eventAttendanceMode": "https://schema.org/OfflineEventAttendanceMode",
+        "url": class.source_url,
+        "location": {
+            "@type": "SportsActivityLocation",
+            "name": settings.gym_title,
+            "address": address,
+            "geo": {
+                "@type": "GeoCoordinates",
+                "latitude": settings.gym_latitude,
+                "longitude": settings.gym_longitude,
+            },
+        },
+        "performer": {
+            "@type": "Person",
+            "name": class.coach,
+        },
+        "organizer": {
+            "@type": "SportsOrganization",
+            "name": settings.gym_title,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn class(date: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            "WOD".to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_timetable_jsonld() {
+        let settings = Settings::default();
+        let document = timetable_jsonld(
+            &[class("2025-11-24 06:00"), class("2025-06-02 18:00")],
+            &settings,
+        );
+
+        assert_eq!(document["@context"], "https://schema.org");
+        let event = &document["@graph"][0];
+        assert_eq!(event["@type"], "Event");
+        assert_eq!(event["name"], "CrossFit: WOD");
+        assert_eq!(event["startDate"], "2025-11-24T06:00:00+01:00");
+        assert_eq!(event["endDate"], "2025-11-24T07:00:00+01:00");
+        assert_eq!(event["performer"]["name"], "Anna");
+        assert_eq!(event["location"]["@type"], "SportsActivityLocation");
+        assert_eq!(event["location"]["address"], settings.gym_location);
+        assert_eq!(event["location"]["geo"]["latitude"], settings.gym_latitude);
+        // Summer time
+        assert_eq!(
+            document["@graph"][1]["startDate"],
+            "2025-06-02T18:00:00+02:00"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Classification of classes by their (mostly Polish) names.
+
+use thiserror::Error;
+
+use crate::models::ClassKind;
+use crate::settings::Settings;
+
+#[derive(Debug, Error)]
+pub enum KindRuleError {
+    #[error("APP_CLASS_KIND_RULES entry {0:?} must look like `pattern=kind`")]
+    Malformed(String),
+    #[error("APP_CLASS_KIND_RULES: {0}")]
+    UnknownKind(String),
+}
+
+// Checked in order, so e.g. "CrossFit Kids" is a kids class rather than a WOD
+const BUILT_IN_RULES: &[(&str, ClassKind)] = &[
+    ("kids", ClassKind::Kids),
+    ("dzieci", ClassKind::Kids),
+    ("junior", ClassKind::Kids),
+    ("hyrox", ClassKind::Hyrox),
+    ("weightlifting", ClassKind::Weightlifting),
+    ("olimpijsk", ClassKind::Weightlifting),
+    ("podnoszenie", ClassKind::Weightlifting),
+    ("open gym", ClassKind::OpenGym),
+    ("open box", ClassKind::OpenGym),
+    ("wolny trening", ClassKind::OpenGym),
+    ("wod", ClassKind::Wod),
+    ("crossfit", ClassKind::Wod),
+];
+
+/// Maps class names to a [`ClassKind`] by case-insensitive substring rules. Configured rules
+/// are checked before the built-in ones; names matching no rule are `Other`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KindRules {
+    rules: Vec<(String, ClassKind)>,
+}
+
+impl KindRules {
+    pub fn from_settings(settings: &Settings) -> Result<Self, KindRuleError> {
+        let rules = settings
+            .class_kind_rules
+            .iter()
+            .map(|rule| {
+                let (pattern, kind) = rule
+                    .split_once('=')
+                    .filter(|(pattern, _)| !pattern.trim().is_empty())
+                    .ok_or_else(|| KindRuleError::Malformed(rule.clone()))?;
+                let kind = kind.parse().map_err(KindRuleError::UnknownKind)?;
+                Ok((pattern.trim().to_lowercase(), kind))
+            })
+            .collect::<Result<_, KindRuleError>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn classify(&self, event_name: &str) -> ClassKind {
+        let name = event_name.to_lowercase();
+        let configured = self
+            .rules
+            .iter()
+            .map(|(pattern, kind)| (pattern.as_str(), *kind));
+        let built_in = BUILT_IN_RULES.iter().copied();
+        configured
+            .chain(built_in)
+            .find(|(pattern, _)| name.contains(pattern))
+            .map_or(ClassKind::Other, |(_, kind)| kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_rules() {
+        let rules = KindRules::default();
+
+        assert_eq!(rules.classify("WOD"), ClassKind::Wod);
+        assert_eq!(rules.classify("CrossFit"), ClassKind::Wod);
+        assert_eq!(rules.classify("HYROX"), ClassKind::Hyrox);
+        assert_eq!(
+            rules.classify("Podnoszenie ciężarów"),
+            ClassKind::Weightlifting
+        );
+        assert_eq!(rules.classify("Open Gym"), ClassKind::OpenGym);
+        assert_eq!(rules.classify("CrossFit Kids"), ClassKind::Kids);
+        assert_eq!(rules.classify("Mobility"), ClassKind::Other);
+    }
+
+    #[test]
+    fn test_configured_rules_take_precedence() {
+        let settings = Settings {
+            class_kind_rules: vec![
+                "mobility = open_gym".to_string(),
+                "Hyrox Kids=kids".to_string(),
+            ],
+            ..Settings::default()
+        };
+
+        let rules = KindRules::from_settings(&settings).unwrap();
+
+        assert_eq!(rules.classify("Mobility"), ClassKind::OpenGym);
+        assert_eq!(rules.classify("HYROX KIDS"), ClassKind::Kids);
+        assert_eq!(rules.classify("HYROX"), ClassKind::Hyrox);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let rules = |rule: &str| {
+            KindRules::from_settings(&Settings {
+                class_kind_rules: vec![rule.to_string()],
+                ..Settings::default()
+            })
+        };
+
+        assert!(matches!(
+            rules("mobility"),
+            Err(KindRuleError::Malformed(_))
+        ));
+        assert!(matches!(rules("=wod"), Err(KindRuleError::Malformed(_))));
+        assert!(matches!(
+            rules("mobility=yoga"),
+            Err(KindRuleError::UnknownKind(message)) if message.contains("open_gym")
+        ));
+    }
+}
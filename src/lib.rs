@@ -1,85 +1,85 @@
+pub mod attendance;
+#[cfg(feature = "server")]
 pub mod auth;
+pub mod badge;
+pub mod cache_control;
+#[cfg(feature = "server")]
+pub mod cli;
+pub mod cron;
+pub mod diff;
+pub mod email;
+#[cfg(feature = "server")]
 pub mod error;
+pub mod export;
+pub mod feeds;
+pub mod fixtures;
+pub mod gyms;
+#[cfg(feature = "server")]
 pub mod handlers;
+pub mod hcal;
+pub mod health;
+pub mod hours;
+pub mod i18n;
 pub mod ical;
+#[cfg(feature = "server")]
+pub mod ip_filter;
+pub mod jsonld;
+pub mod kinds;
+#[cfg(feature = "server")]
+pub mod limits;
+pub mod links;
+#[cfg(feature = "server")]
+pub mod listener;
+pub mod logging;
+pub mod maintenance;
+pub mod matrix;
 pub mod models;
+pub mod mqtt;
+pub mod notify;
+pub mod notion;
+pub mod oidc;
+#[cfg(feature = "server")]
 pub mod openapi;
+pub mod otel;
+pub mod outlook;
+pub mod preferences;
+pub mod push;
+pub mod qr;
+#[cfg(feature = "server")]
+pub mod rate_limit;
+pub mod recurring;
+pub mod refresh;
+#[cfg(feature = "server")]
+pub mod reload;
+pub mod reminders;
+#[cfg(feature = "server")]
+pub mod request_id;
+pub mod retention;
+pub mod rss;
+pub mod rules;
 pub mod scraper;
+pub mod selftest;
+#[cfg(feature = "server")]
+mod server;
 pub mod settings;
+pub mod share;
+pub mod sheets;
+pub mod slack;
+pub mod source;
+pub mod stats;
+pub mod storage;
+pub mod suggest;
+pub mod summary;
+pub mod telegram;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "server")]
 pub mod validation;
+#[cfg(feature = "headless")]
+pub mod webdriver;
 
-use std::net::SocketAddr;
-use std::sync::Arc;
-
-use axum::{Router, routing::get};
-use handlers::{get_ical, get_timetable, healthz_live, healthz_ready, root};
-use tower_http::LatencyUnit;
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::{Level, info};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
-
-use crate::ical::ICalExporter;
-use crate::openapi::ApiDoc;
-use crate::scraper::CrossfitScraper;
-use crate::settings::Settings;
-
-#[derive(Clone)]
-pub struct AppState {
-    pub settings: Settings,
-    pub scraper: Arc<CrossfitScraper>,
-    pub exporter: Arc<ICalExporter>,
-}
-
-pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let settings = Settings::from_env()?;
-
-    let env_filter = if settings.debug { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .without_time()
-        .init();
-
-    let state = AppState {
-        settings: settings.clone(),
-        scraper: Arc::new(CrossfitScraper::new(settings.scraper_base_url.clone())),
-        exporter: Arc::new(ICalExporter::new()),
-    };
-
-    let app = build_router(state.clone());
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], state.settings.port));
-    info!("Starting CrossFit Timetable API on {addr}");
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-    Ok(())
-}
-
-pub fn build_router(state: AppState) -> Router {
-    let trace_layer = TraceLayer::new_for_http()
-        .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-        .on_response(
-            DefaultOnResponse::new()
-                .level(Level::INFO)
-                .latency_unit(LatencyUnit::Millis),
-        );
-
-    let mut router = Router::new()
-        .route("/", get(root))
-        .route("/healthz/live", get(healthz_live))
-        .route("/healthz/ready", get(healthz_ready))
-        .route("/timetable", get(get_timetable))
-        .route("/timetable.ical", get(get_ical))
-        .with_state(state.clone());
-
-    if state.settings.enable_swagger {
-        let openapi = ApiDoc::openapi();
-        let swagger = SwaggerUi::new("/docs").url("/openapi.json", openapi);
-        router = router.merge(swagger);
-    }
-
-    router.layer(trace_layer)
-}
+#[cfg(feature = "server")]
+pub use server::{AppState, ServerBuilder, check_settings, router, run};
 
 #[cfg(test)]
 mod tests {}
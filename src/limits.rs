@@ -0,0 +1,55 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::warn;
+
+use crate::AppState;
+
+/// Rejects requests whose URI (path and query) is longer than `max_uri_bytes` with 414.
+pub async fn limit_uri_length(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let max = state.settings.max_uri_bytes;
+    match uri_length(&request) {
+        len if len > max => {
+            warn!(
+                uri_bytes = len,
+                max_uri_bytes = max,
+                "request rejected: URI too long"
+            );
+            (
+                StatusCode::URI_TOO_LONG,
+                format!("URI too long: at most {max} bytes are accepted"),
+            )
+                .into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+fn uri_length(request: &Request) -> usize {
+    request
+        .uri()
+        .path_and_query()
+        .map_or(0, |path_and_query| path_and_query.as_str().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn test_uri_length_counts_path_and_query() {
+        let request = Request::builder()
+            .uri("http://localhost:8080/timetable?weeks=2")
+            .body(Body::empty())
+            .unwrap();
+
+        assert_eq!(uri_length(&request), "/timetable?weeks=2".len());
+    }
+}
@@ -0,0 +1,114 @@
+//! Prefilled "add event" URLs for Google Calendar and Outlook on the web.
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use serde::Serialize;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::models::ClassItem;
+use crate::settings::Settings;
+
+const GOOGLE_URL: &str = "https://calendar.google.com/calendar/render";
+const OUTLOOK_URL: &str = "https://outlook.live.com/calendar/0/deeplink/compose";
+
+/// One-click links adding a single class to a personal calendar.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClassLinks {
+    pub class_id: String,
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T06:00:00")]
+    pub date: NaiveDateTime,
+    pub event_name: String,
+    pub coach: String,
+    pub google: String,
+    pub outlook: String,
+}
+
+pub fn class_links(class: &ClassItem, settings: &Settings) -> ClassLinks {
+    let title = format!("CrossFit: {}", class.event_name);
+    let location = class.location.as_deref().unwrap_or(&settings.gym_location);
+    let details = format!("Coach: {}\nSource: {}", class.coach, class.source_url);
+
+    let mut google = Url::parse(GOOGLE_URL).expect("Google Calendar URL is valid");
+    google
+        .query_pairs_mut()
+        .append_pair("action", "TEMPLATE")
+        .append_pair("text", &title)
+        .append_pair(
+            "dates",
+            &format!(
+                "{}/{}",
+                utc(class.start, "%Y%m%dT%H%M%SZ"),
+                utc(class.end, "%Y%m%dT%H%M%SZ")
+            ),
+        )
+        .append_pair("location", location)
+        .append_pair("details", &details);
+
+    let mut outlook = Url::parse(OUTLOOK_URL).expect("Outlook URL is valid");
+    outlook
+        .query_pairs_mut()
+        .append_pair("path", "/calendar/action/compose")
+        .append_pair("rru", "addevent")
+        .append_pair("subject", &title)
+        .append_pair("startdt", &utc(class.start, "%Y-%m-%dT%H:%M:%SZ"))
+        .append_pair("enddt", &utc(class.end, "%Y-%m-%dT%H:%M:%SZ"))
+        .append_pair("location", location)
+        .append_pair("body", &details);
+
+    ClassLinks {
+        class_id: class.id.clone(),
+        date: class.date,
+        event_name: class.event_name.clone(),
+        coach: class.coach.clone(),
+        google: google.into(),
+        outlook: outlook.into(),
+    }
+}
+
+// Times are sent in UTC, which both services accept regardless of the gym's time zone
+fn utc(time: DateTime<FixedOffset>, format: &str) -> String {
+    time.with_timezone(&Utc).format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(url: &str, key: &str) -> String {
+        Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_class_links() {
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00", "%Y-%m-%d %H:%M").unwrap(),
+            "WOD".to_string(),
+            "Anna Nowak".to_string(),
+            Some(45),
+            "https://example.com".to_string(),
+        );
+
+        let links = class_links(&class, &Settings::default());
+
+        assert_eq!(links.class_id, class.id);
+        assert!(links.google.starts_with(GOOGLE_URL));
+        assert_eq!(query(&links.google, "text"), "CrossFit: WOD");
+        // Warsaw is UTC+1 in November
+        assert_eq!(
+            query(&links.google, "dates"),
+            "20251124T050000Z/20251124T054500Z"
+        );
+        assert_eq!(
+            query(&links.google, "location"),
+            Settings::default().gym_location
+        );
+        assert_eq!(query(&links.outlook, "startdt"), "2025-11-24T05:00:00Z");
+        assert_eq!(query(&links.outlook, "enddt"), "2025-11-24T05:45:00Z");
+        assert!(query(&links.outlook, "body").contains("Anna Nowak"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener, UnixListener, UnixStream};
+use tracing::warn;
+
+// Pending connections the kernel queues before `accept`, as tokio's `TcpListener::bind`
+const BACKLOG: i32 = 1024;
+
+// Peers on the Unix socket share the host, so they are reported as loopback. This keeps
+// `ConnectInfo<SocketAddr>` available to the IP filter and rate limiter; add 127.0.0.1 to
+// APP_TRUSTED_PROXIES to honor X-Forwarded-For from a local reverse proxy.
+const UNIX_PEER: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// Binds `port` on each of `addrs`. The IPv6 wildcard `::` is dual-stack, taking IPv4
+/// connections as v4-mapped addresses, and falls back to `0.0.0.0` on hosts without IPv6.
+pub fn bind_tcp(addrs: &[IpAddr], port: u16) -> io::Result<Vec<TcpListener>> {
+    addrs
+        .iter()
+        .map(|&ip| {
+            let addr = SocketAddr::new(ip, port);
+            match bind_socket(addr) {
+                Err(err) if ip == IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED) => {
+                    warn!(error = %err, "cannot listen on IPv6, falling back to IPv4 only");
+                    bind_socket(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))
+                        .map_err(|_| err)
+                }
+                result => result,
+            }
+        })
+        .collect()
+}
+
+fn bind_socket(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    // Linux defaults to dual-stack, but `net.ipv6.bindv6only` and other systems may not
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Accepts connections on a Unix domain socket for `axum::serve`.
+pub struct UnixSocketListener(UnixListener);
+
+impl UnixSocketListener {
+    /// Binds `path`, replacing a socket left behind by a previous run.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        match std::fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        UnixListener::bind(path).map(Self)
+    }
+}
+
+impl axum::serve::Listener for UnixSocketListener {
+    type Io = UnixStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            match self.0.accept().await {
+                Ok((stream, _)) => return (stream, UNIX_PEER),
+                Err(err) => {
+                    // Usually fd exhaustion; back off instead of spinning
+                    warn!(error = %err, "failed to accept connection");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(UNIX_PEER)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::ConnectInfo;
+    use axum::serve::ListenerExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serves_over_unix_socket() {
+        let dir = std::env::temp_dir().join(format!("unix-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timetable.sock");
+        // A stale socket from an earlier run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let listener = UnixSocketListener::bind(&path).unwrap();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                peer.ip().to_string()
+            }),
+        );
+        tokio::spawn(async move {
+            axum::serve(
+                listener.tap_io(|_| {}),
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("127.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_accepts_ipv4() {
+        // Without IPv6 on the host this checks the IPv4 fallback instead
+        let listeners = bind_tcp(&[IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED)], 0).unwrap();
+        let [listener] = listeners.as_slice() else {
+            panic!("expected one listener");
+        };
+        let addr = listener.local_addr().unwrap();
+
+        let connect = tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, addr.port()));
+        let (stream, accepted) = tokio::join!(connect, listener.accept());
+
+        stream.unwrap();
+        let (_, peer) = accepted.unwrap();
+        assert_eq!(peer.ip().to_canonical(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[tokio::test]
+    async fn test_binds_every_address() {
+        let addrs = [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)),
+        ];
+
+        let listeners = bind_tcp(&addrs, 0).unwrap();
+
+        let bound: Vec<_> = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap().ip())
+            .collect();
+        assert_eq!(bound, addrs);
+    }
+
+    #[test]
+    fn test_refuses_to_replace_regular_file() {
+        let path = std::env::temp_dir().join(format!("unix-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "data").unwrap();
+
+        let result = UnixSocketListener::bind(&path);
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+    }
+}
@@ -1,6 +1,13 @@
-use crossfit_timetable::run;
+use crossfit_timetable::cli::{self, Command, USAGE};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    run().await
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    cli::execute(command).await
 }
@@ -0,0 +1,244 @@
+//! Maintenance mode, e.g. during an upstream migration: nothing is scraped (data endpoints
+//! serve the weeks stored earlier, background tasks pause), every response carries an
+//! `X-Maintenance` banner and writes are rejected.
+
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::health::ScrapeStats;
+use crate::hours::OpeningHours;
+use crate::models::ClassItem;
+use crate::scraper::{ScrapeError, ScrapedWeek};
+use crate::selftest::SelfTest;
+use crate::source::TimetableSource;
+
+pub const MAINTENANCE_HEADER: &str = "x-maintenance";
+
+// The banner when maintenance mode was switched on without a message
+const DEFAULT_BANNER: &str = "Maintenance in progress; serving stored data";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    /// Sent to clients in the `X-Maintenance` header while enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Moving to the new eFitness site")]
+    pub message: Option<String>,
+}
+
+/// Whether the service is in maintenance mode; switched at runtime through
+/// `/admin/maintenance` and kept across configuration reloads.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    status: RwLock<MaintenanceStatus>,
+}
+
+impl Maintenance {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            status: RwLock::new(MaintenanceStatus {
+                enabled,
+                message: None,
+            }),
+        }
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.status
+            .read()
+            .expect("maintenance lock poisoned")
+            .clone()
+    }
+
+    pub fn set(&self, status: MaintenanceStatus) {
+        *self.status.write().expect("maintenance lock poisoned") = status;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.status
+            .read()
+            .expect("maintenance lock poisoned")
+            .enabled
+    }
+
+    /// The `X-Maintenance` header value while enabled.
+    pub fn banner(&self) -> Option<String> {
+        let status = self.status();
+        status
+            .enabled
+            .then(|| status.message.unwrap_or_else(|| DEFAULT_BANNER.to_string()))
+    }
+}
+
+/// A source that reads nothing upstream while maintenance mode is on. The admin self-test
+/// still reaches the upstream, to check whether it's back.
+pub struct PausableSource {
+    inner: Arc<dyn TimetableSource>,
+    maintenance: Arc<Maintenance>,
+}
+
+impl PausableSource {
+    pub fn new(inner: Arc<dyn TimetableSource>, maintenance: Arc<Maintenance>) -> Self {
+        Self { inner, maintenance }
+    }
+
+    fn check(&self) -> Result<(), ScrapeError> {
+        if self.maintenance.is_enabled() {
+            return Err(ScrapeError::Paused);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TimetableSource for PausableSource {
+    async fn fetch_week(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError> {
+        self.check()?;
+        self.inner.fetch_week(monday, location).await
+    }
+
+    async fn fetch_week_with_warnings(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<ScrapedWeek, ScrapeError> {
+        self.check()?;
+        self.inner.fetch_week_with_warnings(monday, location).await
+    }
+
+    async fn fetch_location(&self) -> Option<String> {
+        self.check().ok()?;
+        self.inner.fetch_location().await
+    }
+
+    async fn fetch_opening_hours(&self) -> Result<Vec<OpeningHours>, ScrapeError> {
+        self.check()?;
+        self.inner.fetch_opening_hours().await
+    }
+
+    async fn self_test(&self) -> Option<SelfTest> {
+        self.inner.self_test().await
+    }
+
+    fn stats(&self) -> ScrapeStats {
+        self.inner.stats()
+    }
+}
+
+#[cfg(feature = "server")]
+pub use middleware::maintenance_guard;
+
+#[cfg(feature = "server")]
+mod middleware {
+    use axum::extract::{Request, State};
+    use axum::http::{HeaderValue, Method};
+    use axum::middleware::Next;
+    use axum::response::{IntoResponse, Response};
+
+    use super::MAINTENANCE_HEADER;
+    use crate::AppState;
+    use crate::error::{ApiError, MAINTENANCE_RETRY_AFTER_SECS};
+
+    // Every write other than switching maintenance mode itself is rejected
+    fn is_write(method: &Method, path: &str) -> bool {
+        !matches!(*method, Method::GET | Method::HEAD) && path != "/admin/maintenance"
+    }
+
+    /// Adds the `X-Maintenance` banner to every response while maintenance mode is on, and
+    /// turns writes away.
+    pub async fn maintenance_guard(
+        State(state): State<AppState>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let Some(banner) = state.maintenance.banner() else {
+            return next.run(request).await;
+        };
+        let mut response = if is_write(request.method(), request.uri().path()) {
+            ApiError::ServiceUnavailable(
+                "writes are disabled in maintenance mode".into(),
+                MAINTENANCE_RETRY_AFTER_SECS,
+            )
+            .into_response()
+        } else {
+            next.run(request).await
+        };
+        // Header values can't hold line breaks or non-ASCII text
+        let banner: String = banner
+            .chars()
+            .map(|c| if c.is_ascii_graphic() { c } else { ' ' })
+            .collect();
+        if let Ok(value) = HeaderValue::from_str(&banner) {
+            response.headers_mut().insert(MAINTENANCE_HEADER, value);
+        }
+        response
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_is_write() {
+            assert!(is_write(&Method::POST, "/admin/import"));
+            assert!(is_write(&Method::POST, "/admin/export/google-sheets"));
+            assert!(is_write(&Method::POST, "/me/favorites"));
+            assert!(is_write(&Method::DELETE, "/share/abc"));
+            assert!(!is_write(&Method::GET, "/admin/export"));
+            assert!(!is_write(&Method::HEAD, "/timetable"));
+            assert!(!is_write(&Method::PUT, "/admin/maintenance"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::FakeSource;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pausable_source_stops_scraping_while_enabled() {
+        let maintenance = Arc::new(Maintenance::new(true));
+        let source = PausableSource::new(Arc::new(FakeSource::default()), maintenance.clone());
+        let monday: NaiveDate = "2025-11-24".parse().unwrap();
+
+        assert!(matches!(
+            source.fetch_week(monday, None).await,
+            Err(ScrapeError::Paused)
+        ));
+        assert!(matches!(
+            source.fetch_opening_hours().await,
+            Err(ScrapeError::Paused)
+        ));
+
+        maintenance.set(MaintenanceStatus::default());
+        assert!(!source.fetch_week(monday, None).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_banner() {
+        let maintenance = Maintenance::default();
+        assert_eq!(maintenance.banner(), None);
+
+        maintenance.set(MaintenanceStatus {
+            enabled: true,
+            message: None,
+        });
+        assert_eq!(maintenance.banner().as_deref(), Some(DEFAULT_BANNER));
+
+        maintenance.set(MaintenanceStatus {
+            enabled: true,
+            message: Some("Back at 10:00".to_string()),
+        });
+        assert_eq!(maintenance.banner().as_deref(), Some("Back at 10:00"));
+    }
+}
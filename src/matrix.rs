@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::Tz;
+use serde_json::json;
+use thiserror::Error;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::cron::CronSchedule;
+use crate::diff::ScheduleChange;
+use crate::models::{ClassItem, local_now};
+use crate::notify::{Notifier, NotifyError, reminder_text};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+#[derive(Debug, Error)]
+pub enum MatrixError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Matrix homeserver replied {status}: {body}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    #[error("Matrix notifications are enabled but {0} is not set")]
+    MissingSetting(&'static str),
+    #[error("APP_MATRIX_HOMESERVER_URL must be an http(s) URL")]
+    InvalidHomeserver,
+}
+
+/// Posts schedule changes and a daily summary to a Matrix room through the client-server API.
+pub struct MatrixNotifier {
+    client: reqwest::Client,
+    homeserver_url: Url,
+    access_token: String,
+    room_id: String,
+}
+
+impl MatrixNotifier {
+    /// Returns `None` unless a homeserver is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, MatrixError> {
+        let Some(homeserver_url) = settings.matrix_homeserver_url.clone() else {
+            return Ok(None);
+        };
+        if homeserver_url.cannot_be_a_base() {
+            return Err(MatrixError::InvalidHomeserver);
+        }
+        let access_token = settings
+            .matrix_access_token
+            .clone()
+            .ok_or(MatrixError::MissingSetting("APP_MATRIX_ACCESS_TOKEN"))?;
+        let room_id = settings
+            .matrix_room_id
+            .clone()
+            .ok_or(MatrixError::MissingSetting("APP_MATRIX_ROOM_ID"))?;
+        Ok(Some(Self {
+            client: reqwest::Client::new(),
+            homeserver_url,
+            access_token,
+            room_id,
+        }))
+    }
+
+    pub async fn send_daily_summary(
+        &self,
+        day: NaiveDate,
+        classes: &[ClassItem],
+    ) -> Result<(), MatrixError> {
+        self.send(&daily_summary_text(day, classes)).await
+    }
+
+    async fn send(&self, text: &str) -> Result<(), MatrixError> {
+        let mut url = self.homeserver_url.clone();
+        url.path_segments_mut()
+            .expect("checked in from_settings")
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3", "rooms", &self.room_id, "send"])
+            .extend(["m.room.message", &uuid::Uuid::new_v4().to_string()]);
+        // Bots post notices, which other bots are expected not to react to
+        let response = self
+            .client
+            .put(url)
+            .bearer_auth(&self.access_token)
+            .json(&json!({"msgtype": "m.notice", "body": text}))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(MatrixError::Rejected { status, body });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let lines: Vec<String> = changes
+            .iter()
+            .map(|change| format!("• {}", change.describe()))
+            .collect();
+        let text = format!(
+            "Schedule changes for the week of {monday}:\n{}",
+            lines.join("\n")
+        );
+        Ok(self.send(&text).await?)
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        Ok(self.send(&reminder_text(class, minutes_before)).await?)
+    }
+}
+
+fn daily_summary_text(day: NaiveDate, classes: &[ClassItem]) -> String {
+    let mut text = format!("Classes on {}:", day.format("%A %d.%m"));
+    if classes.is_empty() {
+        text.push_str("\nNo classes scheduled");
+    }
+    for class in classes {
+        text.push_str(&format!(
+            "\n{}  {} — {}",
+            class.date.format("%H:%M"),
+            class.event_name,
+            class.coach
+        ));
+    }
+    text
+}
+
+/// Posts the day's classes whenever `schedule` fires.
+pub async fn run_daily_summary(
+    notifier: Arc<MatrixNotifier>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    schedule: CronSchedule,
+    time_zone: Tz,
+) {
+    loop {
+        let now = local_now(time_zone);
+        let Some(next) = schedule.next_in(now) else {
+            warn!("Matrix summary schedule never fires again");
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        let day = next.date_naive();
+        let monday = day - chrono::Duration::days(day.weekday().num_days_from_monday().into());
+        let classes = match source.fetch_week(monday, location.clone()).await {
+            Ok(classes) => classes,
+            Err(err) => {
+                warn!(error = %err, %day, "failed to fetch timetable for Matrix");
+                continue;
+            }
+        };
+        let today: Vec<ClassItem> = classes
+            .into_iter()
+            .filter(|class| class.date.date() == day)
+            .collect();
+        match notifier.send_daily_summary(day, &today).await {
+            Ok(()) => info!(%day, classes = today.len(), "posted daily summary to Matrix"),
+            Err(err) => warn!(error = %err, "failed to post daily summary to Matrix"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn notifier(homeserver_url: &str) -> MatrixNotifier {
+        let settings = Settings {
+            matrix_homeserver_url: Some(Url::parse(homeserver_url).unwrap()),
+            matrix_access_token: Some("syt_secret".to_string()),
+            matrix_room_id: Some("!room:example.org".to_string()),
+            ..Settings::default()
+        };
+        MatrixNotifier::from_settings(&settings).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_disabled_or_incomplete_settings() {
+        assert!(
+            MatrixNotifier::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+        let settings = Settings {
+            matrix_homeserver_url: Some(Url::parse("https://matrix.org").unwrap()),
+            matrix_access_token: Some("syt_secret".to_string()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            MatrixNotifier::from_settings(&settings),
+            Err(MatrixError::MissingSetting("APP_MATRIX_ROOM_ID"))
+        ));
+    }
+
+    #[test]
+    fn test_daily_summary_text() {
+        let day = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        assert_eq!(
+            daily_summary_text(
+                day,
+                &[
+                    class("2025-11-24 06:00", "WOD", "Anna"),
+                    class("2025-11-24 18:00", "HYROX", "Piotr"),
+                ]
+            ),
+            "Classes on Monday 24.11:\n06:00  WOD — Anna\n18:00  HYROX — Piotr"
+        );
+        assert_eq!(
+            daily_summary_text(day, &[]),
+            "Classes on Monday 24.11:\nNo classes scheduled"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notify_changes_sends_notice() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(PUT)
+                .path_includes("/_matrix/client/v3/rooms/!room:example.org/send/m.room.message/")
+                .header("authorization", "Bearer syt_secret")
+                .json_body_includes(r#"{"msgtype": "m.notice"}"#)
+                .body_includes("• Cancelled: WOD on Mon 24.11 06:00 (Anna)");
+            then.status(200).json_body(json!({"event_id": "$abc"}));
+        });
+
+        notifier(&server.base_url())
+            .notify_changes(
+                NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+                &[ScheduleChange::Removed {
+                    class: class("2025-11-24 06:00", "WOD", "Anna"),
+                }],
+            )
+            .await
+            .unwrap();
+
+        mock.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_message() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(PUT);
+            then.status(403)
+                .json_body(json!({"errcode": "M_FORBIDDEN", "error": "not in room"}));
+        });
+
+        let result = notifier(&server.base_url())
+            .send_daily_summary(NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(), &[])
+            .await;
+
+        assert!(
+            matches!(result, Err(MatrixError::Rejected { status, body }) if status == 403 && body.contains("M_FORBIDDEN"))
+        );
+    }
+}
@@ -1,14 +1,785 @@
-use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 
+use crate::diff::ScheduleChange;
+use crate::kinds::KindRules;
+
+/// Default time zone of the class times published by the gym (`APP_TIMEZONE`).
+pub const GYM_TIME_ZONE: Tz = chrono_tz::Europe::Warsaw;
+
+/// The current time in `time_zone`, whatever zone the server itself runs in.
+pub fn local_now(time_zone: Tz) -> DateTime<Tz> {
+    Utc::now().with_timezone(&time_zone)
+}
+
+/// The current date in `time_zone`.
+pub fn local_today(time_zone: Tz) -> NaiveDate {
+    local_now(time_zone).date_naive()
+}
+
+/// The Monday of the week of `date`.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday().into())
+}
+
+/// An ISO 8601 week, written `2026-W03`: weeks start on Monday and belong to the year of
+/// their Thursday, so the first days of January can fall in the previous year's last week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IsoWeek {
+    pub year: i32,
+    pub week: u32,
+}
+
+impl IsoWeek {
+    /// The week `date` falls in.
+    pub fn of(date: NaiveDate) -> Self {
+        let week = date.iso_week();
+        Self {
+            year: week.year(),
+            week: week.week(),
+        }
+    }
+
+    pub fn monday(self) -> NaiveDate {
+        NaiveDate::from_isoywd_opt(self.year, self.week, Weekday::Mon).expect("parsed weeks exist")
+    }
+}
+
+impl FromStr for IsoWeek {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid ISO week {value:?} (expected e.g. 2026-W03)");
+        let (year, week) = value
+            .split_once("-W")
+            .or_else(|| value.split_once("-w"))
+            .ok_or_else(invalid)?;
+        let year = year.parse().map_err(|_| invalid())?;
+        let week = week.parse().map_err(|_| invalid())?;
+        // Week 53 only exists in years with 53 Thursdays
+        NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(invalid)?;
+        Ok(Self { year, week })
+    }
+}
+
+impl TryFrom<String> for IsoWeek {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::fmt::Display for IsoWeek {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.week)
+    }
+}
+
+/// Attaches the zone's UTC offset to a local time. A time repeated when the clocks go back
+/// is read as the first of the two; a time skipped when they go forward is moved past the gap.
+pub fn local_time(local: NaiveDateTime, time_zone: Tz) -> DateTime<Tz> {
+    match time_zone.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time,
+        LocalResult::None => time_zone
+            .from_local_datetime(&(local + Duration::hours(1)))
+            .earliest()
+            .unwrap_or_else(|| time_zone.from_utc_datetime(&local)),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(from = "StoredClassItem")]
 pub struct ClassItem {
+    /// Deterministic id of the class slot, stable across scrapes as long as its start time
+    /// and name don't change
+    #[schema(example = "3f2a9c1e7b4d8a60")]
+    pub id: String,
+    /// Local start time as listed by the gym (prefer `start`)
     #[schema(value_type = String, format = "date-time", example = "2025-11-24T06:00:00")]
     pub date: NaiveDateTime,
+    /// ISO 8601 week of `date`, as accepted by the `week` query parameter
+    #[schema(example = "2025-W48")]
+    pub iso_week: String,
+    #[schema(value_type = String, example = "Mon")]
+    pub weekday: Weekday,
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T06:00:00+01:00")]
+    pub start: DateTime<FixedOffset>,
+    /// Start plus the duration, or one hour when the gym doesn't list one
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T07:00:00+01:00")]
+    pub end: DateTime<FixedOffset>,
+    #[schema(example = "WOD")]
     pub event_name: String,
+    pub kind: ClassKind,
+    #[schema(example = "Anna Nowak")]
     pub coach: String,
+    #[schema(example = 60)]
     pub duration_min: Option<u32>,
+    #[schema(
+        example = "https://crossfit2-rzeszow.cms.efitness.com.pl/kalendarz-zajec?day=2025-11-24&view=Agenda"
+    )]
     pub source_url: String,
+    #[schema(example = "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland")]
     pub location: Option<String>,
+    /// Text of the class detail page, when `APP_CLASS_DETAILS` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Set to `archive` for classes served from stored history rather than the live timetable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<DataSource>,
+}
+
+// Classes recorded before `id`, `start` and `end` were added only carry the local `date`;
+// the id is always recomputed, the times (in the default zone) when missing. Older classes
+// are classified by the built-in rules.
+#[derive(Deserialize)]
+struct StoredClassItem {
+    date: NaiveDateTime,
+    #[serde(default)]
+    start: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    end: Option<DateTime<FixedOffset>>,
+    event_name: String,
+    #[serde(default)]
+    kind: Option<ClassKind>,
+    coach: String,
+    duration_min: Option<u32>,
+    source_url: String,
+    location: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    source: Option<DataSource>,
+}
+
+impl From<StoredClassItem> for ClassItem {
+    fn from(stored: StoredClassItem) -> Self {
+        let class = Self::new(
+            stored.date,
+            stored.event_name,
+            stored.coach,
+            stored.duration_min,
+            stored.source_url,
+        );
+        Self {
+            start: stored.start.unwrap_or(class.start),
+            end: stored.end.unwrap_or(class.end),
+            kind: stored.kind.unwrap_or(class.kind),
+            location: stored.location,
+            description: stored.description,
+            source: stored.source,
+            ..class
+        }
+    }
+}
+impl ClassItem {
+    /// A class at a local time of the gym's time zone, classified by the built-in rules.
+    pub fn new(
+        date: NaiveDateTime,
+        event_name: String,
+        coach: String,
+        duration_min: Option<u32>,
+        source_url: String,
+    ) -> Self {
+        let (start, end) = class_times(date, duration_min, GYM_TIME_ZONE);
+        Self {
+            id: class_id(date, &event_name),
+            date,
+            iso_week: IsoWeek::of(date.date()).to_string(),
+            weekday: date.weekday(),
+            start,
+            end,
+            kind: KindRules::default().classify(&event_name),
+            event_name,
+            coach,
+            duration_min,
+            source_url,
+            location: None,
+            description: None,
+            source: None,
+        }
+    }
+
+    /// The same class `by` later, e.g. a week on.
+    pub fn shifted(&self, by: Duration) -> Self {
+        Self {
+            id: class_id(self.date + by, &self.event_name),
+            date: self.date + by,
+            iso_week: IsoWeek::of((self.date + by).date()).to_string(),
+            weekday: (self.date + by).weekday(),
+            start: self.start + by,
+            end: self.end + by,
+            ..self.clone()
+        }
+    }
+
+    /// The same class published under another name.
+    pub fn renamed(self, event_name: String) -> Self {
+        Self {
+            id: class_id(self.date, &event_name),
+            event_name,
+            ..self
+        }
+    }
+
+    /// The same class lasting `minutes`.
+    pub fn with_duration(self, minutes: u32) -> Self {
+        Self {
+            end: self.start + Duration::minutes(i64::from(minutes)),
+            duration_min: Some(minutes),
+            ..self
+        }
+    }
+
+    /// Re-reads the local start time in another time zone.
+    pub fn with_time_zone(mut self, time_zone: Tz) -> Self {
+        (self.start, self.end) = class_times(self.date, self.duration_min, time_zone);
+        self
+    }
+}
+
+/// Drops every class whose id was already seen, keeping the first occurrence in place.
+pub fn dedup_classes(classes: &mut Vec<ClassItem>) {
+    let mut seen = HashSet::new();
+    classes.retain(|class| seen.insert(class.id.clone()));
+}
+
+fn class_id(date: NaiveDateTime, event_name: &str) -> String {
+    let digest = Sha256::digest(format!("{date}|{event_name}").as_bytes());
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn class_times(
+    date: NaiveDateTime,
+    duration_min: Option<u32>,
+    time_zone: Tz,
+) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+    let end = date + Duration::minutes(i64::from(duration_min.unwrap_or(60)));
+    (
+        local_time(date, time_zone).fixed_offset(),
+        local_time(end, time_zone).fixed_offset(),
+    )
+}
+
+/// Type of a class, derived from its name by [`KindRules`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassKind {
+    Wod,
+    Hyrox,
+    Weightlifting,
+    OpenGym,
+    Kids,
+    Other,
+}
+
+impl ClassKind {
+    pub const ALL: [ClassKind; 6] = [
+        ClassKind::Wod,
+        ClassKind::Hyrox,
+        ClassKind::Weightlifting,
+        ClassKind::OpenGym,
+        ClassKind::Kids,
+        ClassKind::Other,
+    ];
+
+    /// The name used in query parameters and rules, e.g. `open_gym`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClassKind::Wod => "wod",
+            ClassKind::Hyrox => "hyrox",
+            ClassKind::Weightlifting => "weightlifting",
+            ClassKind::OpenGym => "open_gym",
+            ClassKind::Kids => "kids",
+            ClassKind::Other => "other",
+        }
+    }
+
+    /// Human-readable name, e.g. for iCal `CATEGORIES`.
+    pub fn label(self) -> &'static str {
+        match self {
+            ClassKind::Wod => "WOD",
+            ClassKind::Hyrox => "HYROX",
+            ClassKind::Weightlifting => "Weightlifting",
+            ClassKind::OpenGym => "Open Gym",
+            ClassKind::Kids => "Kids",
+            ClassKind::Other => "Other",
+        }
+    }
+}
+
+impl FromStr for ClassKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        ClassKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| {
+                let names: Vec<&str> = ClassKind::ALL.iter().map(|kind| kind.as_str()).collect();
+                format!(
+                    "unknown class kind {value:?} (expected {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    Archive,
+    /// The last stored scrape, served while the gym's timetable is down for maintenance
+    Cached,
+}
+
+/// A row of the upstream timetable that could not be read and was left out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ParseWarning {
+    /// Day the row belongs to, when known
+    pub date: Option<NaiveDate>,
+    pub reason: String,
+    /// The row's markup, shortened
+    pub html: String,
+}
+
+impl ParseWarning {
+    const MAX_HTML_CHARS: usize = 300;
+
+    pub fn new(date: Option<NaiveDate>, reason: &str, html: &str) -> Self {
+        let html = html.split_whitespace().collect::<Vec<_>>().join(" ");
+        let html = match html.char_indices().nth(Self::MAX_HTML_CHARS) {
+            Some((end, _)) => format!("{}…", &html[..end]),
+            None => html,
+        };
+        Self {
+            date,
+            reason: reason.to_string(),
+            html,
+        }
+    }
+}
+
+/// A day the gym announces it is closed, e.g. a public holiday.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Closure {
+    #[schema(value_type = String, format = "date", example = "2025-12-25")]
+    pub date: NaiveDate,
+    /// The gym's notice as listed in the timetable
+    #[schema(example = "Nieczynne - Boże Narodzenie")]
+    pub reason: String,
+}
+
+/// `/timetable` with `strict=false`: the classes, the upstream rows left out and the days the
+/// gym is closed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TimetableEnvelope {
+    pub classes: Vec<ClassItem>,
+    pub warnings: Vec<ParseWarning>,
+    #[serde(default)]
+    pub closures: Vec<Closure>,
+}
+
+/// The body of `/timetable`: the classes, or a `TimetableEnvelope` with `strict=false`.
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum TimetableResponse {
+    Classes(Vec<ClassItem>),
+    Envelope(TimetableEnvelope),
+}
+
+/// The next upcoming class as a flat object, easy to read from the iOS Shortcuts app.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct NextClass {
+    #[schema(example = "WOD")]
+    pub name: String,
+    /// Local start time in ISO 8601
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T06:00:00")]
+    pub start: NaiveDateTime,
+    #[schema(example = "Anna Nowak")]
+    pub coach: String,
+}
+
+impl From<&ClassItem> for NextClass {
+    fn from(class: &ClassItem) -> Self {
+        Self {
+            name: class.event_name.clone(),
+            start: class.date,
+            coach: class.coach.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ShareRequest {
+    /// Number of weeks the shared feed covers (1-6, default 1)
+    pub weeks: Option<u8>,
+    /// Link lifetime in hours (defaults to the configured share TTL)
+    pub ttl_hours: Option<u32>,
+    /// Calendar branding of the shared feed, covered by the link's signature
+    #[serde(flatten)]
+    pub branding: Branding,
+}
+
+/// White-label branding of an iCalendar feed, replacing the configured calendar name, event
+/// prefix and color; unset fields keep them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Branding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Morning Crew WODs")]
+    pub calendar_name: Option<String>,
+    /// Put before each class name, e.g. `MC: WOD`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "MC: ")]
+    pub event_prefix: Option<String>,
+    /// `#rrggbb` color calendar apps show the feed in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "#ff6600")]
+    pub color: Option<String>,
+}
+
+impl Branding {
+    pub fn is_empty(&self) -> bool {
+        self.calendar_name.is_none() && self.event_prefix.is_none() && self.color.is_none()
+    }
+
+    /// The set fields as query parameters, in a fixed order.
+    pub fn query_pairs(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("calendar_name", &self.calendar_name),
+            ("event_prefix", &self.event_prefix),
+            ("color", &self.color),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.as_deref()?)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShareLink {
+    pub id: String,
+    pub url: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A schedule change detected between two scrapes of the same week.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ChangeRecord {
+    #[schema(value_type = String, format = "date-time")]
+    pub detected_at: DateTime<Utc>,
+    /// Monday of the affected week
+    #[schema(value_type = String, format = "date")]
+    pub week: NaiveDate,
+    #[serde(flatten)]
+    pub change: ScheduleChange,
+}
+
+/// A class added to the timetable, shaped for Zapier and IFTTT polling triggers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct NewClassTrigger {
+    /// Increasing id of the logged change; Zapier deduplicates polled items by it
+    pub id: u64,
+    #[schema(value_type = String, format = "date-time")]
+    pub detected_at: DateTime<Utc>,
+    pub class_id: String,
+    // The class's own `id` is sent as `class_id`, since `id` identifies the change
+    #[serde(flatten, serialize_with = "serialize_without_id")]
+    pub class: ClassItem,
+    pub meta: TriggerMeta,
+}
+
+/// The same deduplication id in the `meta` object IFTTT expects.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TriggerMeta {
+    pub id: String,
+    /// Unix timestamp of `detected_at`
+    pub timestamp: i64,
+}
+
+impl NewClassTrigger {
+    pub fn new(id: u64, record: ChangeRecord) -> Self {
+        let class = record.change.class().clone();
+        Self {
+            id,
+            detected_at: record.detected_at,
+            class_id: class.id.clone(),
+            class,
+            meta: TriggerMeta {
+                id: id.to_string(),
+                timestamp: record.detected_at.timestamp(),
+            },
+        }
+    }
+}
+
+fn serialize_without_id<S: Serializer>(
+    class: &ClassItem,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut value = serde_json::to_value(class).map_err(serde::ser::Error::custom)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("id");
+    }
+    value.serialize(serializer)
+}
+
+/// Preferred classes of one token, used to filter the personal calendar feed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Favorites {
+    /// Class names to include, case-insensitive (empty matches every class)
+    #[serde(default)]
+    pub event_names: Vec<String>,
+    /// Class types to include (empty matches every type)
+    #[serde(default)]
+    pub kinds: Vec<ClassKind>,
+    /// Days of the week to include, e.g. `["Mon", "Wed"]` (empty matches every day)
+    #[serde(default)]
+    #[schema(value_type = Vec<String>, example = json!(["Mon", "Wed"]))]
+    pub weekdays: Vec<Weekday>,
+    /// Earliest start time, inclusive
+    #[schema(value_type = Option<String>, example = "06:00")]
+    pub start_after: Option<NaiveTime>,
+    /// Latest start time, inclusive
+    #[schema(value_type = Option<String>, example = "09:00")]
+    pub start_before: Option<NaiveTime>,
+}
+
+impl Favorites {
+    /// Whether no criterion is set, so every class matches.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, class: &ClassItem) -> bool {
+        let time = class.date.time();
+        (self.event_names.is_empty()
+            || self
+                .event_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&class.event_name)))
+            && (self.kinds.is_empty() || self.kinds.contains(&class.kind))
+            && (self.weekdays.is_empty() || self.weekdays.contains(&class.date.weekday()))
+            && self.start_after.is_none_or(|after| time >= after)
+            && self.start_before.is_none_or(|before| time <= before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(date: &str, event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_parse_warning_shortens_html() {
+        let warning = ParseWarning::new(None, "missing class name", "<tr>\n  <td>x</td>\n</tr>");
+        assert_eq!(warning.html, "<tr> <td>x</td> </tr>");
+
+        let long = format!("<td>{}</td>", "ż".repeat(400));
+        let warning = ParseWarning::new(None, "missing class name", &long);
+        assert_eq!(warning.html.chars().count(), 301);
+        assert!(warning.html.ends_with('…'));
+    }
+
+    #[test]
+    fn test_dedup_classes_keeps_first_occurrence() {
+        let mut classes = vec![
+            class("2025-11-24 06:00", "WOD"),
+            class("2025-11-24 07:00", "HYROX"),
+            ClassItem {
+                coach: "Other".to_string(),
+                ..class("2025-11-24 06:00", "WOD")
+            },
+            class("2025-11-24 07:00", "HYROX"),
+        ];
+
+        dedup_classes(&mut classes);
+
+        assert_eq!(classes.len(), 2);
+        assert_eq!(classes[0].event_name, "WOD");
+        assert_eq!(classes[0].coach, "Coach");
+        assert_eq!(classes[1].event_name, "HYROX");
+    }
+
+    #[test]
+    fn test_favorites_matching() {
+        let favorites = Favorites {
+            event_names: vec!["hyrox".to_string()],
+            kinds: Vec::new(),
+            weekdays: vec![Weekday::Mon, Weekday::Wed],
+            start_after: NaiveTime::from_hms_opt(17, 0, 0),
+            start_before: None,
+        };
+
+        // 2025-11-24 is a Monday
+        assert!(favorites.matches(&class("2025-11-24 18:00", "HYROX")));
+        assert!(!favorites.matches(&class("2025-11-24 18:00", "WOD")));
+        assert!(!favorites.matches(&class("2025-11-24 06:00", "HYROX")));
+        assert!(!favorites.matches(&class("2025-11-25 18:00", "HYROX")));
+        assert!(Favorites::default().matches(&class("2025-11-25 06:00", "WOD")));
+    }
+
+    #[test]
+    fn test_class_times() {
+        let winter = class("2025-11-24 06:00", "WOD");
+        assert_eq!(winter.start.to_rfc3339(), "2025-11-24T06:00:00+01:00");
+        assert_eq!(winter.end.to_rfc3339(), "2025-11-24T07:00:00+01:00");
+
+        // The class ends after clocks went back on 2025-10-26
+        let overnight = ClassItem {
+            duration_min: Some(180),
+            ..class("2025-10-26 01:00", "Night WOD")
+        }
+        .with_time_zone(GYM_TIME_ZONE);
+        assert_eq!(overnight.start.to_rfc3339(), "2025-10-26T01:00:00+02:00");
+        assert_eq!(overnight.end.to_rfc3339(), "2025-10-26T04:00:00+01:00");
+
+        let utc = winter.with_time_zone(chrono_tz::UTC);
+        assert_eq!(utc.start.to_rfc3339(), "2025-11-24T06:00:00+00:00");
+    }
+
+    #[test]
+    fn test_local_time_across_dst_changes() {
+        let at = |value| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        let time = |value| local_time(at(value), GYM_TIME_ZONE).to_rfc3339();
+
+        // Clocks go forward from 2:00 to 3:00 on 2025-03-30
+        assert_eq!(time("2025-03-30 01:30"), "2025-03-30T01:30:00+01:00");
+        assert_eq!(time("2025-03-30 02:30"), "2025-03-30T03:30:00+02:00");
+        assert_eq!(time("2025-03-30 03:30"), "2025-03-30T03:30:00+02:00");
+        // and back from 3:00 to 2:00 on 2025-10-26, repeating the hour
+        assert_eq!(time("2025-10-26 02:30"), "2025-10-26T02:30:00+02:00");
+        assert_eq!(time("2025-10-26 03:30"), "2025-10-26T03:30:00+01:00");
+    }
+
+    #[test]
+    fn test_week_start() {
+        let date = |value| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+        // The week around the October DST change
+        assert_eq!(week_start(date("2025-10-26")), date("2025-10-20"));
+        assert_eq!(week_start(date("2025-10-27")), date("2025-10-27"));
+        assert_eq!(week_start(date("2025-03-30")), date("2025-03-24"));
+    }
+
+    #[test]
+    fn test_iso_week() {
+        let date = |value| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+
+        let week: IsoWeek = "2026-W03".parse().unwrap();
+        assert_eq!(week.monday(), date("2026-01-12"));
+        assert_eq!(week.to_string(), "2026-W03");
+        // 1 January 2027 is a Friday, so it belongs to the last week of 2026
+        assert_eq!(IsoWeek::of(date("2027-01-01")).to_string(), "2026-W53");
+        assert_eq!(
+            "2026-W53".parse::<IsoWeek>().unwrap().monday(),
+            date("2026-12-28")
+        );
+        assert!("2025-W53".parse::<IsoWeek>().is_err());
+        assert!("2026-03".parse::<IsoWeek>().is_err());
+        assert!("2026-W00".parse::<IsoWeek>().is_err());
+    }
+
+    #[test]
+    fn test_class_week_fields() {
+        let class = class("2026-01-01 18:00", "WOD");
+
+        assert_eq!(class.iso_week, "2026-W01");
+        assert_eq!(class.weekday, Weekday::Thu);
+        let moved = class.shifted(Duration::days(4));
+        assert_eq!(moved.iso_week, "2026-W02");
+        assert_eq!(moved.weekday, Weekday::Mon);
+        let json = serde_json::to_value(&moved).unwrap();
+        assert_eq!(json["iso_week"], "2026-W02");
+        assert_eq!(json["weekday"], "Mon");
+    }
+
+    #[test]
+    fn test_class_without_times_deserializes() {
+        let stored = r#"{"date": "2025-06-02T18:00:00", "event_name": "WOD", "coach": "Anna",
+            "duration_min": null, "source_url": "https://example.com", "location": null}"#;
+
+        let class: ClassItem = serde_json::from_str(stored).unwrap();
+
+        assert_eq!(class.start.to_rfc3339(), "2025-06-02T18:00:00+02:00");
+        assert_eq!(class.end.to_rfc3339(), "2025-06-02T19:00:00+02:00");
+        let json = serde_json::to_value(&class).unwrap();
+        assert_eq!(json["start"], "2025-06-02T18:00:00+02:00");
+        assert_eq!(json["date"], "2025-06-02T18:00:00");
+    }
+
+    #[test]
+    fn test_stored_times_keep_their_zone() {
+        let class = class("2025-06-02 18:00", "WOD").with_time_zone(chrono_tz::America::New_York);
+
+        let stored: ClassItem =
+            serde_json::from_value(serde_json::to_value(&class).unwrap()).unwrap();
+
+        assert_eq!(stored.start.to_rfc3339(), "2025-06-02T18:00:00-04:00");
+        assert_eq!(stored, class);
+    }
+
+    #[test]
+    fn test_favorites_by_kind() {
+        let favorites = Favorites {
+            kinds: vec![ClassKind::Hyrox, ClassKind::Weightlifting],
+            ..Favorites::default()
+        };
+
+        assert!(favorites.matches(&class("2025-11-24 18:00", "HYROX")));
+        assert!(favorites.matches(&class("2025-11-24 18:00", "Podnoszenie ciężarów")));
+        assert!(!favorites.matches(&class("2025-11-24 18:00", "WOD")));
+    }
+
+    #[test]
+    fn test_class_kind_names() {
+        for kind in ClassKind::ALL {
+            assert_eq!(kind.as_str().parse::<ClassKind>(), Ok(kind));
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::Value::from(kind.as_str())
+            );
+        }
+        assert!("yoga".parse::<ClassKind>().is_err());
+    }
+
+    #[test]
+    fn test_class_id_is_stable() {
+        let class_a = class("2025-11-24 06:00", "WOD");
+        let other_coach = ClassItem {
+            coach: "Someone else".to_string(),
+            ..class_a.clone()
+        };
+
+        assert_eq!(class_a.id.len(), 16);
+        assert_eq!(class_a.id, other_coach.id);
+        assert_ne!(class_a.id, class("2025-11-24 07:00", "WOD").id);
+        assert_ne!(class_a.id, class("2025-11-24 06:00", "HYROX").id);
+        // Ids already stored by clients must not change
+        assert_eq!(class_a.id, "9fb8debe3383b089");
+        assert_eq!(
+            class_a.shifted(Duration::weeks(1)).id,
+            class("2025-12-01 06:00", "WOD").id
+        );
+    }
 }
@@ -0,0 +1,555 @@
+//! Publishes the schedule to an MQTT 3.1.1 broker, so smart-home displays and automations
+//! can subscribe instead of polling the HTTP API.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use serde_json::json;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+use crate::diff::ScheduleChange;
+use crate::models::{ClassItem, local_now, local_today, week_start};
+use crate::notify::{Notifier, NotifyError};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+const MQTT_TIMEOUT: Duration = Duration::from_secs(30);
+const KEEP_ALIVE_SECS: u16 = 60;
+const DISCONNECT: [u8; 2] = [0xE0, 0x00];
+
+#[derive(Debug, Error)]
+pub enum MqttError {
+    #[error("MQTT connection error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("MQTT broker timed out")]
+    Timeout,
+    #[error("Invalid APP_MQTT_BROKER_URL: {0}")]
+    InvalidUrl(String),
+    #[error("MQTT broker refused the connection: {0}")]
+    Refused(&'static str),
+    #[error("Unexpected reply from the MQTT broker")]
+    Protocol,
+    #[cfg(feature = "tls")]
+    #[error(transparent)]
+    Tls(#[from] crate::tls::TlsError),
+    #[cfg(not(feature = "tls"))]
+    #[error("mqtts:// brokers need HTTPS support (build with --features tls)")]
+    TlsUnsupported,
+}
+
+#[cfg(feature = "tls")]
+type TlsConfig = Arc<crate::tls::TlsContext>;
+// Without the `tls` feature a publisher can't hold a TLS configuration
+#[cfg(not(feature = "tls"))]
+type TlsConfig = std::convert::Infallible;
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub retain: bool,
+}
+
+/// Connects to the broker for every batch of messages and publishes them with QoS 0.
+pub struct MqttPublisher {
+    host: String,
+    port: u16,
+    tls: Option<TlsConfig>,
+    credentials: Option<(String, String)>,
+    client_id: String,
+    topic_prefix: String,
+}
+
+impl MqttPublisher {
+    /// Returns `None` unless a broker URL is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, MqttError> {
+        let Some(url) = &settings.mqtt_broker_url else {
+            return Ok(None);
+        };
+        let (tls, default_port) = match url.scheme() {
+            "mqtt" => (None, 1883),
+            "mqtts" => (Some(tls_config(settings.mqtt_ca_path.as_deref())?), 8883),
+            other => {
+                return Err(MqttError::InvalidUrl(format!(
+                    "unsupported scheme `{other}`, expected mqtt or mqtts"
+                )));
+            }
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| MqttError::InvalidUrl("missing host".to_string()))?;
+        let client_id = uuid::Uuid::new_v4().simple().to_string();
+        Ok(Some(Self {
+            host: host.trim_matches(['[', ']']).to_string(),
+            port: url.port().unwrap_or(default_port),
+            tls,
+            credentials: settings
+                .mqtt_username
+                .clone()
+                .zip(settings.mqtt_password.clone()),
+            // Brokers only have to accept client ids of up to 23 characters
+            client_id: format!("crossfit-{}", &client_id[..12]),
+            topic_prefix: settings.mqtt_topic_prefix.trim_end_matches('/').to_string(),
+        }))
+    }
+
+    pub async fn publish(&self, messages: &[Message]) -> Result<(), MqttError> {
+        tokio::time::timeout(MQTT_TIMEOUT, self.deliver(messages))
+            .await
+            .map_err(|_| MqttError::Timeout)?
+    }
+
+    /// Retained "next class" and per-day messages for the week of `now`.
+    pub async fn publish_schedule(
+        &self,
+        classes: &[ClassItem],
+        now: NaiveDateTime,
+    ) -> Result<(), MqttError> {
+        self.publish(&schedule_messages(&self.topic_prefix, classes, now))
+            .await
+    }
+
+    async fn deliver(&self, messages: &[Message]) -> Result<(), MqttError> {
+        let mut stream = self.open().await?;
+        let credentials = self
+            .credentials
+            .as_ref()
+            .map(|(user, password)| (user.as_str(), password.as_str()));
+        stream
+            .write_all(&connect_packet(&self.client_id, credentials))
+            .await?;
+
+        let mut connack = [0u8; 4];
+        stream.read_exact(&mut connack).await?;
+        if connack[..2] != [0x20, 0x02] {
+            return Err(MqttError::Protocol);
+        }
+        match connack[3] {
+            0 => {}
+            1 => return Err(MqttError::Refused("unsupported protocol version")),
+            2 => return Err(MqttError::Refused("client identifier rejected")),
+            3 => return Err(MqttError::Refused("server unavailable")),
+            4 => return Err(MqttError::Refused("bad user name or password")),
+            5 => return Err(MqttError::Refused("not authorized")),
+            _ => return Err(MqttError::Protocol),
+        }
+
+        for message in messages {
+            stream
+                .write_all(&publish_packet(
+                    &message.topic,
+                    &message.payload,
+                    message.retain,
+                ))
+                .await?;
+        }
+        // The broker handles packets in order, so everything above is published first
+        stream.write_all(&DISCONNECT).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    async fn open(&self) -> Result<Box<dyn Connection>, MqttError> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        match &self.tls {
+            None => Ok(Box::new(tcp)),
+            #[cfg(feature = "tls")]
+            Some(context) => Ok(Box::new(
+                crate::tls::TlsStream::connect(tcp, context, &self.host).await?,
+            )),
+            #[cfg(not(feature = "tls"))]
+            Some(never) => match *never {},
+        }
+    }
+
+    fn topic(&self, suffix: &str) -> String {
+        format!("{}/{suffix}", self.topic_prefix)
+    }
+}
+
+#[cfg(feature = "tls")]
+fn tls_config(ca_path: Option<&str>) -> Result<TlsConfig, MqttError> {
+    let context = crate::tls::TlsContext::client(ca_path.map(std::path::Path::new))?;
+    Ok(Arc::new(context))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_config(_ca_path: Option<&str>) -> Result<TlsConfig, MqttError> {
+    Err(MqttError::TlsUnsupported)
+}
+
+#[async_trait]
+impl Notifier for MqttPublisher {
+    fn name(&self) -> &'static str {
+        "mqtt"
+    }
+
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let payload = json!({ "week": monday, "changes": changes });
+        let message = Message {
+            topic: self.topic("changes"),
+            payload: payload.to_string().into_bytes(),
+            retain: false,
+        };
+        Ok(self.publish(&[message]).await?)
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        let payload = json!({ "minutes_before": minutes_before, "class": class });
+        let message = Message {
+            topic: self.topic("reminders"),
+            payload: payload.to_string().into_bytes(),
+            retain: false,
+        };
+        Ok(self.publish(&[message]).await?)
+    }
+}
+
+/// `<prefix>/next_class` (`null` when nothing is scheduled), `<prefix>/schedule/today` and
+/// `<prefix>/schedule/<YYYY-MM-DD>` for every day of the current week, all retained.
+pub fn schedule_messages(prefix: &str, classes: &[ClassItem], now: NaiveDateTime) -> Vec<Message> {
+    let retained = |topic: String, payload: String| Message {
+        topic,
+        payload: payload.into_bytes(),
+        retain: true,
+    };
+    let next_class = classes
+        .iter()
+        .filter(|class| class.date > now)
+        .min_by_key(|class| class.date);
+    let mut messages = vec![retained(
+        format!("{prefix}/next_class"),
+        json!(next_class).to_string(),
+    )];
+
+    let today = now.date();
+    let monday = today - chrono::Duration::days(i64::from(today.weekday().num_days_from_monday()));
+    for day in monday.iter_days().take(7) {
+        let mut day_classes: Vec<&ClassItem> = classes
+            .iter()
+            .filter(|class| class.date.date() == day)
+            .collect();
+        day_classes.sort_by_key(|class| class.date);
+        let payload = json!(day_classes).to_string();
+        if day == today {
+            messages.push(retained(
+                format!("{prefix}/schedule/today"),
+                payload.clone(),
+            ));
+        }
+        messages.push(retained(format!("{prefix}/schedule/{day}"), payload));
+    }
+    messages
+}
+
+/// Refreshes the retained schedule messages every `interval`.
+pub async fn run_publisher(
+    publisher: Arc<MqttPublisher>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    interval: Duration,
+    time_zone: Tz,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        // The next class after the last one of the week is on the following Monday
+        let monday = week_start(local_today(time_zone));
+        let classes = match source.fetch_weeks(monday, 2, location.clone()).await {
+            Ok(classes) => classes,
+            Err(err) => {
+                warn!(error = %err, "failed to fetch timetable for MQTT");
+                continue;
+            }
+        };
+        match publisher
+            .publish_schedule(&classes, local_now(time_zone).naive_local())
+            .await
+        {
+            Ok(()) => info!(classes = classes.len(), "published schedule to MQTT"),
+            Err(err) => warn!(error = %err, "failed to publish schedule to MQTT"),
+        }
+    }
+}
+
+fn push_remaining_length(packet: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn push_string(body: &mut Vec<u8>, value: &[u8]) {
+    body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    body.extend_from_slice(value);
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    push_remaining_length(&mut packet, body.len());
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn connect_packet(client_id: &str, credentials: Option<(&str, &str)>) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, b"MQTT");
+    // Protocol level 4 is MQTT 3.1.1
+    body.push(4);
+    let clean_session = 0x02;
+    let flags = match credentials {
+        Some(_) => clean_session | 0x80 | 0x40,
+        None => clean_session,
+    };
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    push_string(&mut body, client_id.as_bytes());
+    if let Some((user, password)) = credentials {
+        push_string(&mut body, user.as_bytes());
+        push_string(&mut body, password.as_bytes());
+    }
+    packet(0x10, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, topic.as_bytes());
+    body.extend_from_slice(payload);
+    packet(if retain { 0x31 } else { 0x30 }, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let (mut len, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= usize::from(byte & 0x7F) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    // Accepts one connection, replies to CONNECT with `return_code` and collects the
+    // PUBLISH packets until DISCONNECT
+    async fn broker(return_code: u8) -> (u16, tokio::task::JoinHandle<Vec<Message>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x10);
+            assert_eq!(&body[..7], b"\x00\x04MQTT\x04");
+            stream
+                .write_all(&[0x20, 0x02, 0, return_code])
+                .await
+                .unwrap();
+            if return_code != 0 {
+                return Vec::new();
+            }
+
+            let mut messages = Vec::new();
+            loop {
+                let (header, body) = read_packet(&mut stream).await;
+                if header == 0xE0 {
+                    return messages;
+                }
+                let topic_len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                messages.push(Message {
+                    topic: String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap(),
+                    payload: body[2 + topic_len..].to_vec(),
+                    retain: header & 0x01 == 1,
+                });
+            }
+        });
+        (port, handle)
+    }
+
+    fn publisher(port: u16) -> MqttPublisher {
+        let settings = Settings {
+            mqtt_broker_url: Some(format!("mqtt://127.0.0.1:{port}").parse().unwrap()),
+            mqtt_topic_prefix: "gym/".to_string(),
+            mqtt_username: Some("display".to_string()),
+            mqtt_password: Some("secret".to_string()),
+            ..Settings::default()
+        };
+        MqttPublisher::from_settings(&settings).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_remaining_length() {
+        for (len, expected) in [
+            (0, vec![0x00]),
+            (127, vec![0x7F]),
+            (128, vec![0x80, 0x01]),
+            (16_383, vec![0xFF, 0x7F]),
+            (16_384, vec![0x80, 0x80, 0x01]),
+        ] {
+            let mut encoded = Vec::new();
+            push_remaining_length(&mut encoded, len);
+            assert_eq!(encoded, expected, "length {len}");
+        }
+    }
+
+    #[test]
+    fn test_packets() {
+        assert_eq!(
+            connect_packet("c1", Some(("u", "p"))),
+            b"\x10\x14\x00\x04MQTT\x04\xC2\x00\x3C\x00\x02c1\x00\x01u\x00\x01p"
+        );
+        assert_eq!(publish_packet("a/b", b"[]", true), b"\x31\x07\x00\x03a/b[]");
+    }
+
+    #[test]
+    fn test_from_settings() {
+        assert!(
+            MqttPublisher::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+        let settings = Settings {
+            mqtt_broker_url: Some("http://broker.local".parse().unwrap()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            MqttPublisher::from_settings(&settings),
+            Err(MqttError::InvalidUrl(_))
+        ));
+        let settings = Settings {
+            mqtt_broker_url: Some("mqtt://broker.local".parse().unwrap()),
+            ..Settings::default()
+        };
+        let publisher = MqttPublisher::from_settings(&settings).unwrap().unwrap();
+        assert_eq!(publisher.port, 1883);
+        assert!(publisher.client_id.len() <= 23);
+    }
+
+    #[test]
+    fn test_schedule_messages() {
+        let classes = vec![
+            class("2025-11-26 18:00", "HYROX"),
+            class("2025-11-26 07:00", "WOD"),
+            class("2025-11-24 07:00", "WOD"),
+            class("2025-12-01 07:00", "Open Box"),
+        ];
+        let now = NaiveDateTime::parse_from_str("2025-11-26 09:00", "%Y-%m-%d %H:%M").unwrap();
+
+        let messages = schedule_messages("gym", &classes, now);
+
+        assert!(messages.iter().all(|message| message.retain));
+        let payload = |topic: &str| -> serde_json::Value {
+            let message = messages.iter().find(|m| m.topic == topic).unwrap();
+            serde_json::from_slice(&message.payload).unwrap()
+        };
+        assert_eq!(payload("gym/next_class")["event_name"], "HYROX");
+        let today = payload("gym/schedule/today");
+        assert_eq!(today, payload("gym/schedule/2025-11-26"));
+        assert_eq!(today[0]["event_name"], "WOD");
+        assert_eq!(today[1]["event_name"], "HYROX");
+        assert_eq!(payload("gym/schedule/2025-11-30"), json!([]));
+        // Monday to Sunday plus `today` and `next_class`
+        assert_eq!(messages.len(), 9);
+
+        let late = NaiveDateTime::parse_from_str("2025-12-01 08:00", "%Y-%m-%d %H:%M").unwrap();
+        let messages = schedule_messages("gym", &classes, late);
+        assert_eq!(messages[0].payload, b"null");
+    }
+
+    #[tokio::test]
+    async fn test_publishes_to_broker() {
+        let (port, broker) = broker(0).await;
+        let publisher = publisher(port);
+        let now = NaiveDateTime::parse_from_str("2025-11-26 09:00", "%Y-%m-%d %H:%M").unwrap();
+
+        publisher
+            .publish_schedule(&[class("2025-11-26 18:00", "HYROX")], now)
+            .await
+            .unwrap();
+
+        let messages = broker.await.unwrap();
+        assert_eq!(messages.len(), 9);
+        assert_eq!(messages[0].topic, "gym/next_class");
+        assert!(messages[0].retain);
+    }
+
+    #[tokio::test]
+    async fn test_change_events_are_not_retained() {
+        let (port, broker) = broker(0).await;
+        let publisher = publisher(port);
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+
+        publisher
+            .notify_changes(
+                monday,
+                &[ScheduleChange::Removed {
+                    class: class("2025-11-26 18:00", "HYROX"),
+                }],
+            )
+            .await
+            .unwrap();
+
+        let messages = broker.await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].topic, "gym/changes");
+        assert!(!messages[0].retain);
+        let payload: serde_json::Value = serde_json::from_slice(&messages[0].payload).unwrap();
+        assert_eq!(payload["week"], "2025-11-24");
+        assert_eq!(payload["changes"][0]["class"]["event_name"], "HYROX");
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection() {
+        let (port, _broker) = broker(4).await;
+
+        let result = publisher(port).publish(&[]).await;
+
+        assert!(matches!(
+            result,
+            Err(MqttError::Refused("bad user name or password"))
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use futures::future::join_all;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::diff::ScheduleChange;
+use crate::email::EmailError;
+use crate::health::Activity;
+use crate::matrix::MatrixError;
+use crate::models::ClassItem;
+use crate::mqtt::MqttError;
+use crate::push::PushError;
+use crate::slack::SlackError;
+use crate::telegram::TelegramError;
+
+const CHANGE_KINDS: [&str; 4] = ["added", "removed", "time_changed", "coach_changed"];
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error(transparent)]
+    Telegram(#[from] TelegramError),
+    #[error(transparent)]
+    Push(#[from] PushError),
+    #[error(transparent)]
+    Slack(#[from] SlackError),
+    #[error(transparent)]
+    Email(#[from] EmailError),
+    #[error(transparent)]
+    Mqtt(#[from] MqttError),
+    #[error(transparent)]
+    Matrix(#[from] MatrixError),
+    #[error(
+        "Unknown change kind `{0}` (expected one of added, removed, time_changed, coach_changed)"
+    )]
+    UnknownKind(String),
+}
+
+/// A channel that is told about schedule changes as they are detected.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Short channel name used in logs.
+    fn name(&self) -> &'static str;
+
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError>;
+
+    /// Announces a class starting in `minutes_before` minutes.
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError>;
+}
+
+pub fn reminder_text(class: &ClassItem, minutes_before: u32) -> String {
+    format!(
+        "Reminder: {} with {} starts at {} (in {minutes_before} minutes)",
+        class.event_name,
+        class.coach,
+        class.date.format("%H:%M")
+    )
+}
+
+/// Restricts which changes a channel receives; an empty list allows everything.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeFilter {
+    event_names: Vec<String>,
+    kinds: Vec<String>,
+}
+
+impl ChangeFilter {
+    pub fn new(event_names: &[String], kinds: &[String]) -> Result<Self, NotifyError> {
+        let kinds = kinds
+            .iter()
+            .map(|kind| {
+                let kind = kind.to_ascii_lowercase();
+                CHANGE_KINDS
+                    .contains(&kind.as_str())
+                    .then_some(kind.clone())
+                    .ok_or(NotifyError::UnknownKind(kind))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            event_names: event_names.to_vec(),
+            kinds,
+        })
+    }
+
+    pub fn matches(&self, change: &ScheduleChange) -> bool {
+        self.matches_event(&change.class().event_name)
+            && (self.kinds.is_empty() || self.kinds.iter().any(|kind| kind == change.kind()))
+    }
+
+    /// Whether the channel wants to hear about this class at all (used for reminders).
+    pub fn matches_event(&self, event_name: &str) -> bool {
+        self.event_names.is_empty()
+            || self
+                .event_names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(event_name))
+    }
+}
+
+struct Channel {
+    notifier: Arc<dyn Notifier>,
+    filter: ChangeFilter,
+    activity: Mutex<Activity>,
+}
+
+/// Fans detected changes out to every configured channel, retrying failed deliveries
+/// with exponential backoff.
+pub struct Dispatcher {
+    channels: Vec<Channel>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new(3, Duration::from_secs(2))
+    }
+}
+
+impl Dispatcher {
+    pub fn new(retries: u32, retry_delay: Duration) -> Self {
+        Self {
+            channels: Vec::new(),
+            retries,
+            retry_delay,
+        }
+    }
+
+    pub fn add(&mut self, notifier: Arc<dyn Notifier>, filter: ChangeFilter) {
+        self.channels.push(Channel {
+            notifier,
+            filter,
+            activity: Mutex::default(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Latest delivery outcome per channel.
+    pub fn health(&self) -> BTreeMap<&'static str, Activity> {
+        self.channels
+            .iter()
+            .map(|channel| {
+                let activity = channel.activity.lock().expect("activity lock poisoned");
+                (channel.notifier.name(), activity.clone())
+            })
+            .collect()
+    }
+
+    /// Delivers the changes to all channels concurrently; failures are logged, not returned.
+    pub async fn dispatch(&self, monday: NaiveDate, changes: &[ScheduleChange]) {
+        join_all(self.channels.iter().map(|channel| async move {
+            let selected: Vec<ScheduleChange> = changes
+                .iter()
+                .filter(|change| channel.filter.matches(change))
+                .cloned()
+                .collect();
+            if !selected.is_empty() {
+                self.with_retries(channel, || {
+                    channel.notifier.notify_changes(monday, &selected)
+                })
+                .await;
+            }
+        }))
+        .await;
+    }
+
+    /// Sends a reminder to every channel interested in the class.
+    pub async fn remind(&self, class: &ClassItem, minutes_before: u32) {
+        join_all(
+            self.channels
+                .iter()
+                .filter(|channel| channel.filter.matches_event(&class.event_name))
+                .map(|channel| {
+                    self.with_retries(channel, || channel.notifier.remind(class, minutes_before))
+                }),
+        )
+        .await;
+    }
+
+    async fn with_retries<F>(&self, channel: &Channel, send: impl Fn() -> F)
+    where
+        F: Future<Output = Result<(), NotifyError>>,
+    {
+        let name = channel.notifier.name();
+        let mut delay = self.retry_delay;
+        for attempt in 0..=self.retries {
+            let result = send().await;
+            {
+                let mut activity = channel.activity.lock().expect("activity lock poisoned");
+                match &result {
+                    Ok(()) => activity.succeeded(),
+                    Err(err) => activity.failed(err),
+                }
+            }
+            match result {
+                Ok(()) => {
+                    info!(channel = name, "sent notification");
+                    return;
+                }
+                Err(err) if attempt < self.retries => {
+                    warn!(
+                        channel = name,
+                        error = %err,
+                        attempt = attempt + 1,
+                        "notification failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(err) => {
+                    warn!(channel = name, error = %err, "giving up on notification");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    // Records delivered changes and fails the first `failures` attempts
+    struct RecordingNotifier {
+        failures: Mutex<u32>,
+        delivered: Mutex<Vec<ScheduleChange>>,
+        reminded: Mutex<Vec<String>>,
+    }
+
+    impl RecordingNotifier {
+        fn new(failures: u32) -> Arc<Self> {
+            Arc::new(Self {
+                failures: Mutex::new(failures),
+                delivered: Mutex::new(Vec::new()),
+                reminded: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn notify_changes(
+            &self,
+            _monday: NaiveDate,
+            changes: &[ScheduleChange],
+        ) -> Result<(), NotifyError> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(NotifyError::UnknownKind("simulated failure".to_string()));
+            }
+            self.delivered.lock().unwrap().extend_from_slice(changes);
+            Ok(())
+        }
+
+        async fn remind(&self, class: &ClassItem, _minutes_before: u32) -> Result<(), NotifyError> {
+            self.reminded.lock().unwrap().push(class.event_name.clone());
+            Ok(())
+        }
+    }
+
+    fn class(event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00", "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 11, 24).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_filters_per_channel() {
+        let everything = RecordingNotifier::new(0);
+        let hyrox_cancellations = RecordingNotifier::new(0);
+        let mut dispatcher = Dispatcher::default();
+        dispatcher.add(everything.clone(), ChangeFilter::default());
+        dispatcher.add(
+            hyrox_cancellations.clone(),
+            ChangeFilter::new(&["hyrox".to_string()], &["removed".to_string()]).unwrap(),
+        );
+        let changes = vec![
+            ScheduleChange::Removed {
+                class: class("HYROX"),
+            },
+            ScheduleChange::Added {
+                class: class("HYROX"),
+            },
+            ScheduleChange::Removed {
+                class: class("WOD"),
+            },
+        ];
+
+        dispatcher.dispatch(monday(), &changes).await;
+
+        assert_eq!(everything.delivered.lock().unwrap().len(), 3);
+        assert_eq!(
+            *hyrox_cancellations.delivered.lock().unwrap(),
+            vec![changes[0].clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_deliveries() {
+        let flaky = RecordingNotifier::new(2);
+        let broken = RecordingNotifier::new(10);
+        let mut dispatcher = Dispatcher::new(2, Duration::from_millis(1));
+        dispatcher.add(flaky.clone(), ChangeFilter::default());
+        dispatcher.add(broken.clone(), ChangeFilter::default());
+
+        dispatcher
+            .dispatch(
+                monday(),
+                &[ScheduleChange::Added {
+                    class: class("WOD"),
+                }],
+            )
+            .await;
+
+        assert_eq!(flaky.delivered.lock().unwrap().len(), 1);
+        assert!(broken.delivered.lock().unwrap().is_empty());
+        // One attempt plus two retries
+        assert_eq!(*broken.failures.lock().unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_health_tracks_latest_delivery() {
+        let broken = RecordingNotifier::new(1);
+        let mut dispatcher = Dispatcher::new(0, Duration::from_millis(1));
+        dispatcher.add(broken.clone(), ChangeFilter::default());
+        let changes = [ScheduleChange::Added {
+            class: class("WOD"),
+        }];
+
+        dispatcher.dispatch(monday(), &changes).await;
+        assert!(dispatcher.health()["recording"].is_failing());
+
+        dispatcher.dispatch(monday(), &changes).await;
+        let health = dispatcher.health();
+        assert!(!health["recording"].is_failing());
+        assert!(health["recording"].last_error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reminders_respect_event_filter() {
+        let everything = RecordingNotifier::new(0);
+        let hyrox_only = RecordingNotifier::new(0);
+        let mut dispatcher = Dispatcher::default();
+        dispatcher.add(everything.clone(), ChangeFilter::default());
+        dispatcher.add(
+            hyrox_only.clone(),
+            ChangeFilter::new(&["HYROX".to_string()], &["removed".to_string()]).unwrap(),
+        );
+
+        dispatcher.remind(&class("WOD"), 30).await;
+        dispatcher.remind(&class("HYROX"), 30).await;
+
+        assert_eq!(*everything.reminded.lock().unwrap(), vec!["WOD", "HYROX"]);
+        // Kind filters apply to changes only
+        assert_eq!(*hyrox_only.reminded.lock().unwrap(), vec!["HYROX"]);
+    }
+
+    #[test]
+    fn test_unknown_kind() {
+        assert!(matches!(
+            ChangeFilter::new(&[], &["cancelled".to_string()]),
+            Err(NotifyError::UnknownKind(kind)) if kind == "cancelled"
+        ));
+    }
+}
@@ -0,0 +1,443 @@
+//! Upserts classes into a Notion database, so a community wiki can embed the current week.
+//!
+//! The database needs these properties: `Name` (title), `Date` (date), `Type` (select),
+//! `Coach` (text), `Status` (select) and `Class ID` (text). Rows without a class id are
+//! left alone.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, SecondsFormat};
+use chrono_tz::Tz;
+use serde_json::{Value, json};
+use thiserror::Error;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::models::{ClassItem, local_today, week_start};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+const API_URL: &str = "https://api.notion.com/v1/";
+const NOTION_VERSION: &str = "2022-06-28";
+const SCHEDULED: &str = "Scheduled";
+const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Error)]
+pub enum NotionError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Notion replied {status}: {body}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    #[error("Notion sync is enabled but APP_NOTION_DATABASE_ID is not set")]
+    MissingDatabase,
+    #[error("APP_NOTION_SYNC_WEEKS must be between 1 and 6")]
+    InvalidWeeks,
+}
+
+/// Writes made by one sync.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncStats {
+    pub created: usize,
+    pub updated: usize,
+    pub cancelled: usize,
+}
+
+// The compared columns of a row: name, start, end, coach and status
+type Row = (String, String, String, String, String);
+
+struct Page {
+    id: String,
+    row: Row,
+}
+
+pub struct NotionSync {
+    client: reqwest::Client,
+    api_url: Url,
+    token: String,
+    database_id: String,
+}
+
+impl NotionSync {
+    /// Returns `None` unless an integration token is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, NotionError> {
+        let Some(token) = settings.notion_token.clone() else {
+            return Ok(None);
+        };
+        if !(1..=6).contains(&settings.notion_sync_weeks) {
+            return Err(NotionError::InvalidWeeks);
+        }
+        Ok(Some(Self {
+            client: reqwest::Client::new(),
+            api_url: Url::parse(API_URL).expect("Notion URL is valid"),
+            token,
+            database_id: settings
+                .notion_database_id
+                .clone()
+                .ok_or(NotionError::MissingDatabase)?,
+        }))
+    }
+
+    /// Makes the rows dated between `from` and `until` match `classes`: new classes are
+    /// added, changed ones updated and vanished ones marked cancelled.
+    pub async fn sync(
+        &self,
+        classes: &[ClassItem],
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<SyncStats, NotionError> {
+        let mut pages = self.query(from, until).await?;
+        let mut stats = SyncStats::default();
+
+        for class in classes {
+            let day = class.date.date();
+            if day < from || day >= until {
+                continue;
+            }
+            let properties = properties(class, SCHEDULED);
+            match pages.remove(&class.id) {
+                Some(page) if page.row == row(&properties) => {}
+                Some(page) => {
+                    self.update(&page.id, properties).await?;
+                    stats.updated += 1;
+                }
+                None => {
+                    let body = json!({
+                        "parent": { "database_id": self.database_id },
+                        "properties": properties,
+                    });
+                    self.send(self.client.post(self.api("pages")).json(&body))
+                        .await?;
+                    stats.created += 1;
+                }
+            }
+        }
+        for page in pages.into_values() {
+            if page.row.4 != CANCELLED {
+                let status = json!({ "Status": { "select": { "name": CANCELLED } } });
+                self.update(&page.id, status).await?;
+                stats.cancelled += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    async fn update(&self, page_id: &str, properties: Value) -> Result<(), NotionError> {
+        let url = self.api(&format!("pages/{page_id}"));
+        let body = json!({ "properties": properties });
+        self.send(self.client.patch(url).json(&body)).await?;
+        Ok(())
+    }
+
+    // Rows with a class id in the window, keyed by class id
+    async fn query(
+        &self,
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<BTreeMap<String, Page>, NotionError> {
+        let url = self.api(&format!("databases/{}/query", self.database_id));
+        let mut pages = BTreeMap::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut body = json!({
+                "filter": { "and": [
+                    { "property": "Date", "date": { "on_or_after": from.to_string() } },
+                    { "property": "Date", "date": { "before": until.to_string() } },
+                ] },
+                "page_size": 100,
+            });
+            if let Some(cursor) = &cursor {
+                body["start_cursor"] = json!(cursor);
+            }
+            let result: Value = self
+                .send(self.client.post(url.clone()).json(&body))
+                .await?
+                .json()
+                .await?;
+            for page in result["results"].as_array().into_iter().flatten() {
+                let properties = &page["properties"];
+                let class_id = plain_text(&properties["Class ID"]["rich_text"]);
+                if let (Some(id), false) = (page["id"].as_str(), class_id.is_empty()) {
+                    let page = Page {
+                        id: id.to_string(),
+                        row: row(properties),
+                    };
+                    pages.insert(class_id, page);
+                }
+            }
+            cursor = result["next_cursor"].as_str().map(str::to_string);
+            if result["has_more"] != json!(true) || cursor.is_none() {
+                return Ok(pages);
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, NotionError> {
+        let response = request
+            .bearer_auth(&self.token)
+            .header("Notion-Version", NOTION_VERSION)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(NotionError::Rejected { status, body });
+        }
+        Ok(response)
+    }
+
+    fn api(&self, path: &str) -> Url {
+        self.api_url.join(path).expect("Notion path is a valid URL")
+    }
+}
+
+fn properties(class: &ClassItem, status: &str) -> Value {
+    json!({
+        "Name": { "title": [{ "text": { "content": class.event_name } }] },
+        "Date": { "date": { "start": rfc3339(class.start), "end": rfc3339(class.end) } },
+        "Type": { "select": { "name": class.event_name } },
+        "Coach": { "rich_text": [{ "text": { "content": class.coach } }] },
+        "Status": { "select": { "name": status } },
+        "Class ID": { "rich_text": [{ "text": { "content": class.id } }] },
+    })
+}
+
+fn rfc3339(time: DateTime<FixedOffset>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+// Works for both what we send and what Notion returns, which adds milliseconds to dates
+fn row(properties: &Value) -> Row {
+    let minute = |value: &Value| {
+        value
+            .as_str()
+            .unwrap_or_default()
+            .chars()
+            .take(16)
+            .collect()
+    };
+    (
+        plain_text(&properties["Name"]["title"]),
+        minute(&properties["Date"]["date"]["start"]),
+        minute(&properties["Date"]["date"]["end"]),
+        plain_text(&properties["Coach"]["rich_text"]),
+        properties["Status"]["select"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+fn plain_text(rich_text: &Value) -> String {
+    rich_text
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|part| {
+            part["plain_text"]
+                .as_str()
+                .or_else(|| part["text"]["content"].as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Syncs the current week and the following `weeks - 1` every `interval`.
+pub async fn run_sync(
+    sync: Arc<NotionSync>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    weeks: u8,
+    interval: Duration,
+    time_zone: Tz,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let monday = week_start(local_today(time_zone));
+        // A partial timetable would cancel the missing week's classes
+        let classes = match source.fetch_weeks(monday, weeks, location.clone()).await {
+            Ok(classes) => classes,
+            Err(err) => {
+                warn!(error = %err, "failed to fetch timetable for Notion sync");
+                continue;
+            }
+        };
+        let until = monday + chrono::Duration::weeks(i64::from(weeks));
+        match sync.sync(&classes, monday, until).await {
+            Ok(stats) => info!(
+                created = stats.created,
+                updated = stats.updated,
+                cancelled = stats.cancelled,
+                "synced Notion database"
+            ),
+            Err(err) => warn!(error = %err, "failed to sync Notion database"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn sync(server: &MockServer) -> NotionSync {
+        let settings = Settings {
+            notion_token: Some("secret_abc".to_string()),
+            notion_database_id: Some("db1".to_string()),
+            ..Settings::default()
+        };
+        let mut sync = NotionSync::from_settings(&settings).unwrap().unwrap();
+        sync.api_url = Url::parse(&server.url("/v1/")).unwrap();
+        sync
+    }
+
+    // A row as Notion returns it: plain text next to the content, dates with milliseconds
+    fn page(id: &str, class: &ClassItem, status: &str) -> Value {
+        let mut properties = properties(class, status);
+        for name in ["Coach", "Class ID"] {
+            let text = properties[name]["rich_text"][0]["text"]["content"].clone();
+            properties[name]["rich_text"][0]["plain_text"] = text;
+        }
+        let start = properties["Date"]["date"]["start"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        properties["Date"]["date"]["start"] =
+            json!(format!("{}.000{}", &start[..19], &start[19..]));
+        json!({ "id": id, "properties": properties })
+    }
+
+    #[test]
+    fn test_from_settings() {
+        assert!(
+            NotionSync::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+        let settings = Settings {
+            notion_token: Some("secret_abc".to_string()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            NotionSync::from_settings(&settings),
+            Err(NotionError::MissingDatabase)
+        ));
+    }
+
+    #[test]
+    fn test_returned_row_matches_sent_properties() {
+        let class = class("2025-11-26 18:00", "HYROX", "Anna");
+        let sent = properties(&class, SCHEDULED);
+
+        assert_eq!(
+            row(&page("p1", &class, SCHEDULED)["properties"]),
+            row(&sent)
+        );
+        assert_eq!(row(&sent).0, "HYROX");
+        assert!(row(&sent).1.starts_with("2025-11-26T18:00"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_upserts_and_cancels() {
+        let server = MockServer::start();
+        let sync = sync(&server);
+        let unchanged = class("2025-11-24 07:00", "WOD", "Anna");
+        let coach_changed = class("2025-11-25 07:00", "WOD", "Piotr");
+        let added = class("2025-11-26 18:00", "HYROX", "Anna");
+        let removed = class("2025-11-27 18:00", "HYROX", "Anna");
+        let previous_coach = ClassItem {
+            coach: "Anna".to_string(),
+            ..coach_changed.clone()
+        };
+
+        let first_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/databases/db1/query")
+                .header("authorization", "Bearer secret_abc")
+                .header("notion-version", NOTION_VERSION)
+                .body_includes(r#""on_or_after":"2025-11-24""#)
+                .body_excludes("start_cursor");
+            then.status(200).json_body(json!({
+                "results": [
+                    page("p1", &unchanged, SCHEDULED),
+                    page("p2", &previous_coach, SCHEDULED),
+                    { "id": "manual", "properties": { "Name": { "title": [] } } }
+                ],
+                "has_more": true,
+                "next_cursor": "c2"
+            }));
+        });
+        let second_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/databases/db1/query")
+                .body_includes(r#""start_cursor":"c2""#);
+            then.status(200).json_body(json!({
+                "results": [page("p3", &removed, SCHEDULED)],
+                "has_more": false,
+                "next_cursor": null
+            }));
+        });
+        let update = server.mock(|when, then| {
+            when.method(PATCH)
+                .path("/v1/pages/p2")
+                .body_includes("Piotr");
+            then.status(200).json_body(json!({}));
+        });
+        let create = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/pages")
+                .body_includes(r#""database_id":"db1""#)
+                .body_includes(&added.id);
+            then.status(200).json_body(json!({}));
+        });
+        let cancel = server.mock(|when, then| {
+            when.method(PATCH).path("/v1/pages/p3").json_body(
+                json!({ "properties": { "Status": { "select": { "name": "Cancelled" } } } }),
+            );
+            then.status(200).json_body(json!({}));
+        });
+
+        let stats = sync
+            .sync(
+                &[unchanged, coach_changed, added],
+                NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 12, 1).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            SyncStats {
+                created: 1,
+                updated: 1,
+                cancelled: 1
+            }
+        );
+        first_page.assert_calls(1);
+        second_page.assert_calls(1);
+        update.assert_calls(1);
+        create.assert_calls(1);
+        cancel.assert_calls(1);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::OnceCell;
+use url::Url;
+
+// Accepted tokens are remembered for a short while so calendar clients polling
+// the feed don't turn every request into a userinfo round-trip
+const TOKEN_CACHE_TTL: Duration = Duration::from_secs(60);
+// Rejected tokens briefly too, so a client retrying a dead token doesn't hammer the provider
+const REJECTED_TOKEN_CACHE_TTL: Duration = Duration::from_secs(10);
+// Upper bound on remembered tokens; the oldest is forgotten first
+const MAX_CACHED_TOKENS: usize = 1_000;
+// A slow provider shouldn't hold up requests indefinitely
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid issuer URL: {0}")]
+    InvalidIssuer(#[from] url::ParseError),
+    #[error("Token rejected by identity provider")]
+    Rejected,
+    #[error("Discovery document names issuer {found}, expected {expected}")]
+    IssuerMismatch { expected: String, found: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryDocument {
+    pub issuer: String,
+    pub userinfo_endpoint: Url,
+    pub authorization_endpoint: Option<Url>,
+    pub token_endpoint: Option<Url>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfo {
+    sub: String,
+}
+
+// A token's subject (`None` when rejected) and when it was checked
+type CheckedToken = (Option<String>, Instant);
+
+pub struct OidcValidator {
+    client: reqwest::Client,
+    issuer_url: Url,
+    discovery: OnceCell<DiscoveryDocument>,
+    // Keyed by the SHA-256 of the token, so the tokens themselves aren't kept in memory
+    checked: Mutex<HashMap<[u8; 32], CheckedToken>>,
+}
+
+impl OidcValidator {
+    pub fn new(issuer_url: Url) -> Self {
+        Self {
+            // Like `Client::new`, this only fails when the TLS backend can't be initialized
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("failed to initialize the HTTP client"),
+            issuer_url,
+            discovery: OnceCell::new(),
+            checked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn discovery_url(&self) -> Result<Url, OidcError> {
+        let base = self.issuer_url.as_str().trim_end_matches('/');
+        Ok(Url::parse(&format!(
+            "{base}/.well-known/openid-configuration"
+        ))?)
+    }
+
+    pub async fn discovery(&self) -> Result<&DiscoveryDocument, OidcError> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = self.discovery_url()?;
+                let document = self
+                    .client
+                    .get(url.as_str())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<DiscoveryDocument>()
+                    .await?;
+                // OpenID Connect Discovery 4.3: the document must name the issuer it was fetched
+                // for; Url adds a trailing slash to bare hosts, so that one is not compared
+                let expected = self.issuer_url.as_str().trim_end_matches('/');
+                if document.issuer.trim_end_matches('/') != expected {
+                    return Err(OidcError::IssuerMismatch {
+                        expected: expected.to_string(),
+                        found: document.issuer,
+                    });
+                }
+                Ok(document)
+            })
+            .await
+    }
+
+    /// Validates an access token by presenting it to the provider's userinfo endpoint and
+    /// returns the user's subject (`sub`), which stays the same as their tokens rotate.
+    pub async fn validate(&self, token: &str) -> Result<String, OidcError> {
+        let key: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if let Some(subject) = self.cached(&key) {
+            return subject.ok_or(OidcError::Rejected);
+        }
+
+        let discovery = self.discovery().await?;
+        let response = self
+            .client
+            .get(discovery.userinfo_endpoint.as_str())
+            .bearer_auth(token)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            self.remember(key, None);
+            return Err(OidcError::Rejected);
+        }
+        let subject = response.json::<UserInfo>().await?.sub;
+        self.remember(key, Some(subject.clone()));
+        Ok(subject)
+    }
+
+    // `Some(None)` for a recently rejected token
+    fn cached(&self, key: &[u8; 32]) -> Option<Option<String>> {
+        let checked = self.checked.lock().expect("token cache lock poisoned");
+        checked
+            .get(key)
+            .filter(|entry| is_fresh(entry))
+            .map(|(subject, _)| subject.clone())
+    }
+
+    fn remember(&self, key: [u8; 32], subject: Option<String>) {
+        let mut checked = self.checked.lock().expect("token cache lock poisoned");
+        checked.retain(|_, entry| is_fresh(entry));
+        if checked.len() >= MAX_CACHED_TOKENS && !checked.contains_key(&key) {
+            let oldest = checked
+                .iter()
+                .min_by_key(|(_, (_, seen))| *seen)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                checked.remove(&oldest);
+            }
+        }
+        checked.insert(key, (subject, Instant::now()));
+    }
+}
+
+fn is_fresh((subject, seen): &CheckedToken) -> bool {
+    let ttl = match subject {
+        Some(_) => TOKEN_CACHE_TTL,
+        None => REJECTED_TOKEN_CACHE_TTL,
+    };
+    seen.elapsed() < ttl
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn mock_provider(server: &MockServer) {
+        let userinfo = format!("{}/userinfo", server.base_url());
+        server.mock(|when, then| {
+            when.method(GET).path("/.well-known/openid-configuration");
+            then.status(200).json_body(serde_json::json!({
+                "issuer": server.base_url(),
+                "userinfo_endpoint": userinfo,
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/userinfo")
+                .header("authorization", "Bearer good-token");
+            then.status(200)
+                .json_body(serde_json::json!({"sub": "member"}));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/userinfo");
+            then.status(401);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_discovery_document() {
+        let server = MockServer::start();
+        mock_provider(&server);
+        let validator = OidcValidator::new(Url::parse(&server.base_url()).unwrap());
+
+        let discovery = validator.discovery().await.unwrap();
+
+        assert_eq!(discovery.issuer, server.base_url());
+        assert!(discovery.userinfo_endpoint.path().ends_with("/userinfo"));
+    }
+
+    #[tokio::test]
+    async fn test_discovery_rejects_other_issuer() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/.well-known/openid-configuration");
+            then.status(200).json_body(serde_json::json!({
+                "issuer": "https://attacker.example.com",
+                "userinfo_endpoint": format!("{}/userinfo", server.base_url()),
+            }));
+        });
+        let validator = OidcValidator::new(Url::parse(&server.base_url()).unwrap());
+
+        assert!(matches!(
+            validator.discovery().await,
+            Err(OidcError::IssuerMismatch { .. })
+        ));
+        assert!(validator.validate("good-token").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_accepts_and_rejects() {
+        let server = MockServer::start();
+        mock_provider(&server);
+        let validator = OidcValidator::new(Url::parse(&server.base_url()).unwrap());
+
+        assert_eq!(validator.validate("good-token").await.unwrap(), "member");
+        // Served from the cache the second time
+        assert_eq!(validator.validate("good-token").await.unwrap(), "member");
+        assert!(matches!(
+            validator.validate("bad-token").await,
+            Err(OidcError::Rejected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_is_cached() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/.well-known/openid-configuration");
+            then.status(200).json_body(serde_json::json!({
+                "issuer": server.base_url(),
+                "userinfo_endpoint": format!("{}/userinfo", server.base_url()),
+            }));
+        });
+        let userinfo = server.mock(|when, then| {
+            when.method(GET).path("/userinfo");
+            then.status(401);
+        });
+        let validator = OidcValidator::new(Url::parse(&server.base_url()).unwrap());
+
+        for _ in 0..3 {
+            assert!(matches!(
+                validator.validate("bad-token").await,
+                Err(OidcError::Rejected)
+            ));
+        }
+
+        userinfo.assert_calls(1);
+        assert_eq!(validator.checked.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_token_cache_is_bounded() {
+        let validator = OidcValidator::new(Url::parse("https://id.example.com").unwrap());
+        for index in 0..MAX_CACHED_TOKENS as u32 {
+            let mut key = [0; 32];
+            key[..4].copy_from_slice(&index.to_be_bytes());
+            validator.remember(key, Some(format!("user-{index}")));
+        }
+
+        validator.remember([0xff; 32], Some("newest".into()));
+
+        let checked = validator.checked.lock().unwrap();
+        assert_eq!(checked.len(), MAX_CACHED_TOKENS);
+        assert!(!checked.contains_key(&[0; 32]));
+        assert!(checked.contains_key(&[0xff; 32]));
+    }
+}
@@ -1,7 +1,87 @@
+use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+use utoipa::openapi::content::ContentBuilder;
+use utoipa::openapi::header::HeaderBuilder;
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::response::{Response, ResponseBuilder};
 use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Object, Ref, RefOr, Type};
 use utoipa::{Modify, OpenApi};
 
-use crate::models::ClassItem;
+use crate::attendance::{AttendanceRecord, AttendanceSummary, AttendanceWeek};
+use crate::diff::ScheduleChange;
+use crate::error::{ErrorBody, ErrorCode};
+use crate::health::{Activity, HealthReport, ScrapeStats, TaskState, TaskStatus};
+use crate::hours::OpeningHours;
+use crate::ical::ICalExporter;
+use crate::links::ClassLinks;
+use crate::maintenance::MaintenanceStatus;
+use crate::models::{
+    Branding, ChangeRecord, ClassItem, ClassKind, Closure, DataSource, Favorites, NewClassTrigger,
+    NextClass, ParseWarning, ShareLink, ShareRequest, TimetableEnvelope, TimetableResponse,
+    TriggerMeta,
+};
+use crate::recurring::{
+    ExceptionKind, PatternException, RecurringClass, RecurringPattern, RecurringSlot,
+};
+use crate::selftest::{ScrapePreview, SelectorMatch, SelfTest};
+use crate::settings::Settings;
+use crate::sheets::SheetsExport;
+use crate::stats::Heatmap;
+use crate::storage::{Snapshot, StoredWeek};
+use crate::suggest::{BusyInterval, SuggestRequest};
+
+// Two classes of a Monday morning, rendered by the same code as real responses so the
+// examples can't drift from the schemas
+fn example_classes() -> Vec<ClassItem> {
+    let settings = Settings::default();
+    let class = |time: &str, name: &str, coach: &str| {
+        let date = NaiveDate::from_ymd_opt(2025, 11, 24)
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap());
+        let mut class = ClassItem::new(
+            date,
+            name.to_string(),
+            coach.to_string(),
+            Some(60),
+            format!(
+                "{}kalendarz-zajec?day=2025-11-24&view=Agenda",
+                settings.scraper_base_url
+            ),
+        );
+        class.location = Some(settings.gym_location.clone());
+        class
+    };
+    vec![
+        class("06:00", "WOD", "Anna Nowak"),
+        class("07:00", "HYROX", "Marek Kowalski"),
+    ]
+}
+
+/// Example of `/timetable`.
+pub fn timetable_example() -> serde_json::Value {
+    serde_json::to_value(example_classes()).expect("classes serialize")
+}
+
+/// Example of an iCal feed.
+pub fn ical_example() -> serde_json::Value {
+    let ical = ICalExporter::new().generate(&example_classes(), &Settings::default());
+    String::from_utf8(ical).expect("iCal is UTF-8").into()
+}
+
+/// Example of `/history/changes`.
+pub fn changes_example() -> serde_json::Value {
+    let mut classes = example_classes();
+    let class = classes.remove(0);
+    let change = ChangeRecord {
+        detected_at: Utc.with_ymd_and_hms(2025, 11, 23, 18, 30, 0).unwrap(),
+        week: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+        change: ScheduleChange::CoachChanged {
+            class,
+            previous_coach: "Marek Kowalski".to_string(),
+        },
+    };
+    serde_json::to_value([change]).expect("changes serialize")
+}
 
 pub struct SecurityAddon;
 
@@ -17,6 +97,10 @@ impl Modify for SecurityAddon {
                     .build(),
             ),
         );
+        components.add_security_scheme(
+            "basic_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
         components.add_security_scheme(
             "query_token",
             SecurityScheme::ApiKey(ApiKey::Query(ApiKeyValue::new("token"))),
@@ -24,19 +108,175 @@ impl Modify for SecurityAddon {
     }
 }
 
+// Routes outside the rate limiter, so probes never get throttled
+const UNLIMITED_PATHS: &[&str] = &["/", "/healthz/live", "/healthz/ready"];
+
+/// Documents the errors the middleware adds to every route: `429` once a client's rate
+/// limit is used up, and `503` for writes in maintenance mode.
+pub struct ErrorResponsesAddon;
+
+impl Modify for ErrorResponsesAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let integer_header = |description: &str| {
+            HeaderBuilder::new()
+                .schema(Object::with_type(Type::Integer))
+                .description(Some(description))
+                .build()
+        };
+        let error_body = ContentBuilder::new()
+            .schema(Some(Ref::from_schema_name("ErrorBody")))
+            .build();
+        let retry_after = "Seconds to wait before retrying";
+
+        let components = openapi.components.as_mut().unwrap();
+        components.responses.insert(
+            "TooManyRequests".to_string(),
+            RefOr::T(
+                ResponseBuilder::new()
+                    .description("Rate limit exhausted; see `APP_RATE_LIMIT_REQUESTS`")
+                    .header("Retry-After", integer_header(retry_after))
+                    .header(
+                        "X-RateLimit-Limit",
+                        integer_header("Requests allowed per window"),
+                    )
+                    .header(
+                        "X-RateLimit-Remaining",
+                        integer_header("Requests left in the current window"),
+                    )
+                    .header(
+                        "X-RateLimit-Reset",
+                        integer_header("Seconds until the window resets"),
+                    )
+                    .content("application/json", error_body.clone())
+                    .build(),
+            ),
+        );
+        components.responses.insert(
+            "MaintenanceMode".to_string(),
+            RefOr::T(
+                ResponseBuilder::new()
+                    .description("Writes are disabled in maintenance mode")
+                    .header("Retry-After", integer_header(retry_after))
+                    .content("application/json", error_body)
+                    .build(),
+            ),
+        );
+
+        let add = |operation: &mut Operation, status: &str, name: &str| {
+            operation
+                .responses
+                .responses
+                .entry(status.to_string())
+                .or_insert_with(|| RefOr::<Response>::Ref(Ref::from_response_name(name)));
+        };
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !UNLIMITED_PATHS.contains(&path.as_str()) {
+                let operations = [
+                    &mut item.get,
+                    &mut item.post,
+                    &mut item.put,
+                    &mut item.delete,
+                ];
+                for operation in operations.into_iter().flatten() {
+                    add(operation, "429", "TooManyRequests");
+                }
+            }
+            // Mirrors `maintenance::is_write`
+            if path != "/admin/maintenance" {
+                let writes = [&mut item.post, &mut item.put, &mut item.delete];
+                for operation in writes.into_iter().flatten() {
+                    add(operation, "503", "MaintenanceMode");
+                }
+            }
+        }
+    }
+}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::handlers::root,
         crate::handlers::healthz_live,
         crate::handlers::healthz_ready,
+        crate::handlers::healthz_detail,
         crate::handlers::get_timetable,
-        crate::handlers::get_ical
+        crate::handlers::get_export,
+        crate::handlers::get_links,
+        crate::handlers::get_next_class,
+        crate::handlers::get_next_class_badge,
+        crate::handlers::get_summary,
+        crate::handlers::suggest_classes,
+        crate::handlers::get_recurring,
+        crate::handlers::get_heatmap,
+        crate::handlers::get_gym_hours,
+        crate::handlers::get_changes,
+        crate::handlers::get_changes_rss,
+        crate::handlers::get_new_class_triggers,
+        crate::handlers::get_favorites,
+        crate::handlers::set_favorites,
+        crate::handlers::get_personal_ical,
+        crate::handlers::record_attendance,
+        crate::handlers::get_attendance,
+        crate::handlers::self_test,
+        crate::handlers::preview_scrape,
+        crate::handlers::get_maintenance,
+        crate::handlers::set_maintenance,
+        crate::handlers::export_snapshot,
+        crate::handlers::export_google_sheets,
+        crate::handlers::import_snapshot,
+        crate::handlers::create_share,
+        crate::handlers::revoke_share,
+        crate::handlers::get_subscribe_qr
     ),
-    components(schemas(ClassItem)),
+    components(schemas(
+        ClassItem,
+        TimetableEnvelope,
+        TimetableResponse,
+        ParseWarning,
+        Closure,
+        ClassLinks,
+        NextClass,
+        RecurringPattern,
+        RecurringClass,
+        RecurringSlot,
+        PatternException,
+        ExceptionKind,
+        Heatmap,
+        SuggestRequest,
+        BusyInterval,
+        OpeningHours,
+        ClassKind,
+        DataSource,
+        ChangeRecord,
+        NewClassTrigger,
+        TriggerMeta,
+        ScheduleChange,
+        Favorites,
+        AttendanceRecord,
+        AttendanceWeek,
+        AttendanceSummary,
+        StoredWeek,
+        Snapshot,
+        ShareRequest,
+        Branding,
+        ShareLink,
+        SheetsExport,
+        HealthReport,
+        SelfTest,
+        ScrapePreview,
+        SelectorMatch,
+        MaintenanceStatus,
+        ScrapeStats,
+        Activity,
+        TaskState,
+        TaskStatus,
+        ErrorBody,
+        ErrorCode
+    )),
     tags(
-        (name = "timetable", description = "CrossFit timetable operations")
+        (name = "timetable", description = "CrossFit timetable operations"),
+        (name = "admin", description = "Maintenance of the stored data")
     ),
-    modifiers(&SecurityAddon),
+    modifiers(&SecurityAddon, &ErrorResponsesAddon),
 )]
 pub struct ApiDoc;
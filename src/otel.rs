@@ -0,0 +1,446 @@
+//! Span export to an OpenTelemetry collector using OTLP over HTTP with JSON encoding.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Metadata, Span, Subscriber, warn};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry};
+use url::Url;
+
+const BATCH_SIZE: usize = 512;
+const BATCH_TIMEOUT: Duration = Duration::from_secs(5);
+// Spans are dropped rather than blocking requests when the collector falls behind
+const QUEUE_SIZE: usize = 4096;
+
+// OTLP span kinds
+const KIND_INTERNAL: u8 = 1;
+const KIND_SERVER: u8 = 2;
+const KIND_CLIENT: u8 = 3;
+const STATUS_ERROR: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+enum AttributeValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone)]
+struct SpanRecord {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    kind: u8,
+    error: bool,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+impl Visit for SpanRecord {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            // Conventions shared with tracing-opentelemetry
+            "otel.kind" => {
+                self.kind = match value {
+                    "server" => KIND_SERVER,
+                    "client" => KIND_CLIENT,
+                    _ => KIND_INTERNAL,
+                }
+            }
+            "otel.status_code" => self.error = value.eq_ignore_ascii_case("error"),
+            name => self.set(name, AttributeValue::String(value.to_string())),
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field.name(), AttributeValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match i64::try_from(value) {
+            Ok(value) => self.set(field.name(), AttributeValue::Int(value)),
+            Err(_) => self.set(field.name(), AttributeValue::String(value.to_string())),
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field.name(), AttributeValue::Double(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field.name(), AttributeValue::Bool(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+impl SpanRecord {
+    fn set(&mut self, key: &'static str, value: AttributeValue) {
+        match self.attributes.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.attributes.push((key, value)),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut span = json!({
+            "traceId": hex(&self.trace_id),
+            "spanId": hex(&self.span_id),
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": self
+                .attributes
+                .iter()
+                .map(|(key, value)| attribute(key, value))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(parent) = self.parent_span_id {
+            span["parentSpanId"] = json!(hex(&parent));
+        }
+        if self.error {
+            span["status"] = json!({"code": STATUS_ERROR});
+        }
+        span
+    }
+}
+
+fn attribute(key: &str, value: &AttributeValue) -> Value {
+    // int64 values are strings in the OTLP JSON mapping
+    let value = match value {
+        AttributeValue::String(value) => json!({"stringValue": value}),
+        AttributeValue::Int(value) => json!({"intValue": value.to_string()}),
+        AttributeValue::Double(value) => json!({"doubleValue": value}),
+        AttributeValue::Bool(value) => json!({"boolValue": value}),
+    };
+    json!({"key": key, "value": value})
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(&uuid::Uuid::new_v4().as_bytes()[..N]);
+    bytes
+}
+
+tokio::task_local! {
+    /// Id of the API request handled by the current task.
+    pub static REQUEST_ID: String;
+}
+
+/// Request id to forward on upstream requests, when called while handling an API request.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID
+        .try_with(Clone::clone)
+        .ok()
+        .filter(|id| !id.is_empty())
+}
+
+/// W3C `traceparent` header value naming `span` as the parent, so upstream services can join
+/// the trace. `None` when spans are not exported.
+pub fn traceparent(span: &Span) -> Option<String> {
+    span.with_subscriber(|(id, dispatch)| {
+        let span = dispatch.downcast_ref::<Registry>()?.span(id)?;
+        let extensions = span.extensions();
+        let record = extensions.get::<SpanRecord>()?;
+        Some(format!(
+            "00-{}-{}-01",
+            hex(&record.trace_id),
+            hex(&record.span_id)
+        ))
+    })
+    .flatten()
+}
+
+/// A `tracing` layer recording this crate's spans and queueing them for export.
+pub struct OtlpLayer {
+    sender: mpsc::Sender<SpanRecord>,
+}
+
+impl OtlpLayer {
+    /// Starts a background exporter posting batches to `<endpoint>/v1/traces`.
+    pub fn new(endpoint: Url, service_name: String) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(export_loop(receiver, endpoint, service_name));
+        Self { sender }
+    }
+
+    // Library spans (hyper, reqwest) would only add noise, and exporting them could
+    // recursively trace the exporter's own requests
+    fn exports(metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !Self::exports(attrs.metadata()) {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        // The nearest exported ancestor is the parent, even across unexported spans
+        let parent = span.scope().skip(1).find_map(|ancestor| {
+            ancestor
+                .extensions()
+                .get::<SpanRecord>()
+                .map(|record| (record.trace_id, record.span_id))
+        });
+        let now = SystemTime::now();
+        let mut record = SpanRecord {
+            trace_id: parent.map_or_else(random_bytes, |(trace_id, _)| trace_id),
+            span_id: random_bytes(),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            name: attrs.metadata().name(),
+            kind: KIND_INTERNAL,
+            error: false,
+            start: now,
+            end: now,
+            attributes: Vec::new(),
+        };
+        attrs.record(&mut record);
+        span.extensions_mut().insert(record);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(record) = span.extensions_mut().get_mut::<SpanRecord>()
+        {
+            values.record(record);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(mut record) = ctx
+            .span(&id)
+            .and_then(|span| span.extensions_mut().remove::<SpanRecord>())
+        else {
+            return;
+        };
+        record.end = SystemTime::now();
+        let _ = self.sender.try_send(record);
+    }
+}
+
+fn export_body(service_name: &str, spans: &[SpanRecord]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [attribute(
+                    "service.name",
+                    &AttributeValue::String(service_name.to_string()),
+                )],
+            },
+            "scopeSpans": [{
+                "scope": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans.iter().map(SpanRecord::to_json).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+async fn export_loop(
+    mut receiver: mpsc::Receiver<SpanRecord>,
+    endpoint: Url,
+    service_name: String,
+) {
+    let client = reqwest::Client::new();
+    let url = match endpoint.join("v1/traces") {
+        Ok(url) => url,
+        Err(err) => {
+            warn!(error = %err, "invalid OTLP endpoint, spans are not exported");
+            return;
+        }
+    };
+    while let Some(first) = receiver.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + BATCH_TIMEOUT;
+        while batch.len() < BATCH_SIZE {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(record)) => batch.push(record),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let result = client
+            .post(url.clone())
+            .json(&export_body(&service_name, &batch))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(err) = result {
+            warn!(error = %err, spans = batch.len(), "failed to export spans");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    fn record_spans(f: impl FnOnce()) -> Vec<SpanRecord> {
+        let (sender, mut receiver) = mpsc::channel(16);
+        let subscriber = tracing_subscriber::registry().with(OtlpLayer { sender });
+        tracing::subscriber::with_default(subscriber, f);
+        let mut spans = Vec::new();
+        while let Ok(span) = receiver.try_recv() {
+            spans.push(span);
+        }
+        spans
+    }
+
+    #[test]
+    fn test_records_nested_spans() {
+        let spans = record_spans(|| {
+            let request = tracing::info_span!("request", otel.kind = "server", uri = "/timetable");
+            let _request = request.enter();
+            let scrape = tracing::info_span!(
+                "scrape",
+                otel.kind = "client",
+                http.status_code = tracing::field::Empty,
+                otel.status_code = tracing::field::Empty,
+            );
+            scrape.record("http.status_code", 502);
+            scrape.record("otel.status_code", "error");
+        });
+
+        let [scrape, request] = spans.as_slice() else {
+            panic!("expected two spans, got {spans:?}");
+        };
+        assert_eq!(request.name, "request");
+        assert_eq!(request.kind, KIND_SERVER);
+        assert_eq!(request.parent_span_id, None);
+        assert_eq!(
+            request.attributes,
+            vec![("uri", AttributeValue::String("/timetable".to_string()))]
+        );
+        assert_eq!(scrape.kind, KIND_CLIENT);
+        assert_eq!(scrape.trace_id, request.trace_id);
+        assert_eq!(scrape.parent_span_id, Some(request.span_id));
+        assert_eq!(
+            scrape.attributes,
+            vec![("http.status_code", AttributeValue::Int(502))]
+        );
+        assert!(scrape.error);
+    }
+
+    #[test]
+    fn test_traceparent_of_exported_span() {
+        let mut header = None;
+        let spans = record_spans(|| {
+            let scrape = tracing::info_span!("scrape", otel.kind = "client");
+            header = traceparent(&scrape);
+        });
+
+        let [scrape] = spans.as_slice() else {
+            panic!("expected one span, got {spans:?}");
+        };
+        assert_eq!(
+            header.unwrap(),
+            format!("00-{}-{}-01", hex(&scrape.trace_id), hex(&scrape.span_id))
+        );
+        assert_eq!(traceparent(&Span::none()), None);
+    }
+
+    #[test]
+    fn test_ignores_library_spans() {
+        let spans = record_spans(|| {
+            let _span = tracing::info_span!(target: "hyper::client", "connect").entered();
+        });
+        assert!(spans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exports_batches() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/otlp/v1/traces")
+                .body_includes(r#""stringValue":"timetable-test""#)
+                .body_includes(r#""name":"scrape""#);
+            then.status(200);
+        });
+        let (sender, receiver) = mpsc::channel(16);
+        let export = tokio::spawn(export_loop(
+            receiver,
+            Url::parse(&server.url("/otlp/")).unwrap(),
+            "timetable-test".to_string(),
+        ));
+        let now = SystemTime::now();
+        sender
+            .send(SpanRecord {
+                trace_id: [1; 16],
+                span_id: [2; 8],
+                parent_span_id: None,
+                name: "scrape",
+                kind: KIND_CLIENT,
+                error: false,
+                start: now,
+                end: now,
+                attributes: Vec::new(),
+            })
+            .await
+            .unwrap();
+        drop(sender);
+
+        export.await.unwrap();
+
+        mock.assert_calls(1);
+    }
+
+    #[test]
+    fn test_span_json() {
+        let start = UNIX_EPOCH + Duration::from_millis(1500);
+        let span = SpanRecord {
+            trace_id: [0xab; 16],
+            span_id: [0x01; 8],
+            parent_span_id: Some([0x02; 8]),
+            name: "scrape",
+            kind: KIND_CLIENT,
+            error: true,
+            start,
+            end: start + Duration::from_millis(250),
+            attributes: vec![("duration_ms", AttributeValue::Int(250))],
+        };
+
+        let json = span.to_json();
+
+        assert_eq!(json["traceId"], "abababababababababababababababab");
+        assert_eq!(json["parentSpanId"], "0202020202020202");
+        assert_eq!(json["startTimeUnixNano"], "1500000000");
+        assert_eq!(json["endTimeUnixNano"], "1750000000");
+        assert_eq!(
+            json["attributes"][0],
+            json!({"key": "duration_ms", "value": {"intValue": "250"}})
+        );
+        assert_eq!(json["status"]["code"], STATUS_ERROR);
+    }
+}
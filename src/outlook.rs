@@ -0,0 +1,532 @@
+//! Mirrors the timetable into a Microsoft 365 calendar through the Microsoft Graph API,
+//! for members whose tenant doesn't allow ICS subscriptions.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::models::{ClassItem, local_today, week_start};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+const LOGIN_URL: &str = "https://login.microsoftonline.com/";
+const GRAPH_URL: &str = "https://graph.microsoft.com/v1.0/";
+// Marks the events this service owns; the value is `<class id> <content fingerprint>`
+const CLASS_PROPERTY: &str =
+    "String {5d0c2f7e-8a4b-4c61-9f3e-2b7a1c9e4d10} Name CrossfitTimetableClass";
+
+#[derive(Debug, Error)]
+pub enum OutlookError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Microsoft Graph replied {status}: {body}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    #[error("Outlook sync is enabled but {0} is not set")]
+    MissingSetting(&'static str),
+    #[error("Invalid APP_OUTLOOK_TIME_ZONE `{0}`")]
+    InvalidTimeZone(String),
+    #[error("APP_OUTLOOK_SYNC_WEEKS must be between 1 and 6")]
+    InvalidWeeks,
+}
+
+/// Writes made by one sync.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncStats {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+struct RemoteEvent {
+    id: String,
+    fingerprint: String,
+}
+
+/// Keeps the events of one Outlook calendar in line with the timetable, using
+/// app-only (client credentials) authentication.
+pub struct OutlookSync {
+    client: reqwest::Client,
+    login_url: Url,
+    graph_url: Url,
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+    // `users/<user>/calendar` or `users/<user>/calendars/<id>`
+    calendar_path: String,
+    time_zone: Tz,
+    gym_location: String,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl OutlookSync {
+    /// Returns `None` unless a tenant is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, OutlookError> {
+        let Some(tenant_id) = settings.outlook_tenant_id.clone() else {
+            return Ok(None);
+        };
+        let required =
+            |value: &Option<String>, name| value.clone().ok_or(OutlookError::MissingSetting(name));
+        let user = required(&settings.outlook_user, "APP_OUTLOOK_USER")?;
+        if !(1..=6).contains(&settings.outlook_sync_weeks) {
+            return Err(OutlookError::InvalidWeeks);
+        }
+        let calendar_path = match &settings.outlook_calendar_id {
+            Some(id) => format!("users/{user}/calendars/{id}"),
+            None => format!("users/{user}/calendar"),
+        };
+        Ok(Some(Self {
+            client: reqwest::Client::new(),
+            login_url: Url::parse(LOGIN_URL).expect("login URL is valid"),
+            graph_url: Url::parse(GRAPH_URL).expect("Graph URL is valid"),
+            tenant_id,
+            client_id: required(&settings.outlook_client_id, "APP_OUTLOOK_CLIENT_ID")?,
+            client_secret: required(&settings.outlook_client_secret, "APP_OUTLOOK_CLIENT_SECRET")?,
+            calendar_path,
+            time_zone: settings
+                .outlook_time_zone
+                .parse()
+                .map_err(|_| OutlookError::InvalidTimeZone(settings.outlook_time_zone.clone()))?,
+            gym_location: settings.gym_location.clone(),
+            token: Mutex::new(None),
+        }))
+    }
+
+    /// Creates, updates and deletes the service's events between `from` and `until` so
+    /// they match `classes`; events added by hand are left alone.
+    pub async fn sync(
+        &self,
+        classes: &[ClassItem],
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<SyncStats, OutlookError> {
+        let token = self.access_token().await?;
+        let mut remote = self.list_events(&token, from, until).await?;
+        let mut stats = SyncStats::default();
+
+        for class in classes {
+            let day = class.date.date();
+            if day < from || day >= until {
+                continue;
+            }
+            let event = self.event(class);
+            let fingerprint = fingerprint(&event);
+            let event = with_marker(event, &class.id, &fingerprint);
+            match remote.remove(&class.id) {
+                Some(existing) if existing.fingerprint == fingerprint => {}
+                Some(existing) => {
+                    let url = self.graph(&format!("{}/events/{}", self.calendar_path, existing.id));
+                    self.send(self.client.patch(url).json(&event), &token)
+                        .await?;
+                    stats.updated += 1;
+                }
+                None => {
+                    let url = self.graph(&format!("{}/events", self.calendar_path));
+                    self.send(self.client.post(url).json(&event), &token)
+                        .await?;
+                    stats.created += 1;
+                }
+            }
+        }
+        // Whatever is left was cancelled or moved out of the window
+        for stale in remote.into_values() {
+            let url = self.graph(&format!("{}/events/{}", self.calendar_path, stale.id));
+            self.send(self.client.delete(url), &token).await?;
+            stats.deleted += 1;
+        }
+        Ok(stats)
+    }
+
+    fn event(&self, class: &ClassItem) -> Value {
+        let location = class.location.as_deref().unwrap_or(&self.gym_location);
+        json!({
+            "subject": format!("CrossFit: {}", class.event_name),
+            "body": {
+                "contentType": "text",
+                "content": format!("CrossFit Class\nCoach: {}\nSource: {}", class.coach, class.source_url),
+            },
+            "start": self.date_time(class.start.naive_local()),
+            "end": self.date_time(class.end.naive_local()),
+            "location": { "displayName": location },
+            "isReminderOn": false,
+        })
+    }
+
+    fn date_time(&self, date: NaiveDateTime) -> Value {
+        json!({
+            "dateTime": date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            "timeZone": self.time_zone.name(),
+        })
+    }
+
+    // Events of this service in the window, keyed by class id
+    async fn list_events(
+        &self,
+        token: &str,
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<BTreeMap<String, RemoteEvent>, OutlookError> {
+        let mut url = self.graph(&format!("{}/calendarView", self.calendar_path));
+        url.query_pairs_mut()
+            .append_pair("startDateTime", &self.utc_midnight(from))
+            .append_pair("endDateTime", &self.utc_midnight(until))
+            .append_pair("$select", "id")
+            .append_pair(
+                "$expand",
+                &format!("singleValueExtendedProperties($filter=id eq '{CLASS_PROPERTY}')"),
+            )
+            .append_pair("$top", "100");
+
+        let mut events = BTreeMap::new();
+        let mut next = Some(url);
+        while let Some(url) = next {
+            let page: Value = self.send(self.client.get(url), token).await?.json().await?;
+            for event in page["value"].as_array().into_iter().flatten() {
+                let marker = event["singleValueExtendedProperties"][0]["value"].as_str();
+                let (Some(id), Some((class_id, fingerprint))) =
+                    (event["id"].as_str(), marker.and_then(|m| m.split_once(' ')))
+                else {
+                    continue;
+                };
+                events.insert(
+                    class_id.to_string(),
+                    RemoteEvent {
+                        id: id.to_string(),
+                        fingerprint: fingerprint.to_string(),
+                    },
+                );
+            }
+            next = page["@odata.nextLink"]
+                .as_str()
+                .and_then(|link| Url::parse(link).ok());
+        }
+        Ok(events)
+    }
+
+    fn utc_midnight(&self, day: NaiveDate) -> String {
+        let midnight = day.and_hms_opt(0, 0, 0).expect("midnight is valid");
+        let local = self
+            .time_zone
+            .from_local_datetime(&midnight)
+            .earliest()
+            .unwrap_or_else(|| self.time_zone.from_utc_datetime(&midnight));
+        local
+            .with_timezone(&Utc)
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string()
+    }
+
+    async fn access_token(&self) -> Result<String, OutlookError> {
+        if let Some((token, expires)) = &*self.token.lock().expect("token lock poisoned")
+            && Instant::now() < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let url = self
+            .login_url
+            .join(&format!("{}/oauth2/v2.0/token", self.tenant_id))
+            .expect("tenant path is a valid URL");
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "client_credentials")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("client_secret", &self.client_secret)
+            .append_pair("scope", "https://graph.microsoft.com/.default")
+            .finish();
+        let response = self
+            .client
+            .post(url)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(form)
+            .send()
+            .await?;
+        let body: Value = check(response).await?.json().await?;
+        let token = body["access_token"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        // Renew a minute early so a token never expires mid-sync
+        let lifetime = body["expires_in"]
+            .as_u64()
+            .unwrap_or(3600)
+            .saturating_sub(60);
+        *self.token.lock().expect("token lock poisoned") = Some((
+            token.clone(),
+            Instant::now() + Duration::from_secs(lifetime),
+        ));
+        Ok(token)
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        token: &str,
+    ) -> Result<reqwest::Response, OutlookError> {
+        check(request.bearer_auth(token).send().await?).await
+    }
+
+    fn graph(&self, path: &str) -> Url {
+        self.graph_url
+            .join(path)
+            .expect("Graph path is a valid URL")
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, OutlookError> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(OutlookError::Rejected { status, body });
+    }
+    Ok(response)
+}
+
+fn fingerprint(event: &Value) -> String {
+    let digest = Sha256::digest(event.to_string().as_bytes());
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn with_marker(mut event: Value, class_id: &str, fingerprint: &str) -> Value {
+    event["singleValueExtendedProperties"] = json!([{
+        "id": CLASS_PROPERTY,
+        "value": format!("{class_id} {fingerprint}"),
+    }]);
+    event
+}
+
+/// Syncs the current week and the following `weeks - 1` every `interval`.
+pub async fn run_sync(
+    sync: Arc<OutlookSync>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    weeks: u8,
+    interval: Duration,
+    time_zone: Tz,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let monday = week_start(local_today(time_zone));
+        // A partial timetable would delete the missing week's events
+        let classes = match source.fetch_weeks(monday, weeks, location.clone()).await {
+            Ok(classes) => classes,
+            Err(err) => {
+                warn!(error = %err, "failed to fetch timetable for Outlook sync");
+                continue;
+            }
+        };
+        let until = monday + chrono::Duration::weeks(i64::from(weeks));
+        match sync.sync(&classes, monday, until).await {
+            Ok(stats) => info!(
+                created = stats.created,
+                updated = stats.updated,
+                deleted = stats.deleted,
+                "synced Outlook calendar"
+            ),
+            Err(err) => warn!(error = %err, "failed to sync Outlook calendar"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            outlook_tenant_id: Some("tenant".to_string()),
+            outlook_client_id: Some("app".to_string()),
+            outlook_client_secret: Some("secret".to_string()),
+            outlook_user: Some("member@example.com".to_string()),
+            ..Settings::default()
+        }
+    }
+
+    fn sync(server: &MockServer) -> OutlookSync {
+        let mut sync = OutlookSync::from_settings(&settings()).unwrap().unwrap();
+        sync.login_url = Url::parse(&server.url("/login/")).unwrap();
+        sync.graph_url = Url::parse(&server.url("/graph/")).unwrap();
+        sync
+    }
+
+    fn mock_token(server: &MockServer) -> httpmock::Mock<'_> {
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/login/tenant/oauth2/v2.0/token")
+                .body_includes("grant_type=client_credentials")
+                .body_includes("client_secret=secret");
+            then.status(200)
+                .json_body(json!({"access_token": "graph-token", "expires_in": 3600}));
+        })
+    }
+
+    fn week() -> (NaiveDate, NaiveDate) {
+        (
+            NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 12, 1).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_from_settings() {
+        assert!(
+            OutlookSync::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+        let settings = Settings {
+            outlook_user: None,
+            ..settings()
+        };
+        assert!(matches!(
+            OutlookSync::from_settings(&settings),
+            Err(OutlookError::MissingSetting("APP_OUTLOOK_USER"))
+        ));
+        let settings = Settings {
+            outlook_time_zone: "Mars/Olympus".to_string(),
+            ..self::settings()
+        };
+        assert!(matches!(
+            OutlookSync::from_settings(&settings),
+            Err(OutlookError::InvalidTimeZone(_))
+        ));
+    }
+
+    #[test]
+    fn test_window_bounds_in_utc() {
+        let server = MockServer::start();
+        let sync = sync(&server);
+
+        // Warsaw is UTC+1 in winter and UTC+2 in summer
+        assert_eq!(
+            sync.utc_midnight(NaiveDate::from_ymd_opt(2025, 11, 24).unwrap()),
+            "2025-11-23T23:00:00Z"
+        );
+        assert_eq!(
+            sync.utc_midnight(NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()),
+            "2025-06-01T22:00:00Z"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_writes_only_differences() {
+        let server = MockServer::start();
+        let sync = sync(&server);
+        let unchanged = class("2025-11-24 07:00", "WOD", "Anna");
+        let coach_changed = class("2025-11-25 07:00", "WOD", "Piotr");
+        let added = class("2025-11-26 18:00", "HYROX", "Anna");
+        let outside_window = class("2025-12-02 07:00", "WOD", "Anna");
+        let marker = |class: &ClassItem, fingerprint: &str| json!([{"id": CLASS_PROPERTY, "value": format!("{} {fingerprint}", class.id)}]);
+        let current = fingerprint(&sync.event(&unchanged));
+
+        let token = mock_token(&server);
+        let first_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/graph/users/member@example.com/calendar/calendarView")
+                .query_param("startDateTime", "2025-11-23T23:00:00Z")
+                .query_param("endDateTime", "2025-11-30T23:00:00Z")
+                .query_param_missing("page")
+                .header("authorization", "Bearer graph-token");
+            then.status(200).json_body(json!({
+                "value": [
+                    {"id": "ev-1", "singleValueExtendedProperties": marker(&unchanged, &current)},
+                    {"id": "ev-2", "singleValueExtendedProperties": marker(&coach_changed, "outdated")},
+                    {"id": "manual"}
+                ],
+                "@odata.nextLink": server.url("/graph/users/member@example.com/calendar/calendarView?page=2")
+            }));
+        });
+        let second_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/graph/users/member@example.com/calendar/calendarView")
+                .query_param("page", "2");
+            then.status(200).json_body(json!({
+                "value": [{"id": "ev-3", "singleValueExtendedProperties": [{"id": CLASS_PROPERTY, "value": "cancelled 0000"}]}]
+            }));
+        });
+        let update = server.mock(|when, then| {
+            when.method(PATCH)
+                .path("/graph/users/member@example.com/calendar/events/ev-2")
+                .body_includes("Coach: Piotr");
+            then.status(200).json_body(json!({}));
+        });
+        let create = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graph/users/member@example.com/calendar/events")
+                .body_includes("CrossFit: HYROX")
+                .body_includes("Europe/Warsaw")
+                .body_includes(&added.id);
+            then.status(201).json_body(json!({}));
+        });
+        let delete = server.mock(|when, then| {
+            when.method(DELETE)
+                .path("/graph/users/member@example.com/calendar/events/ev-3");
+            then.status(204);
+        });
+
+        let (from, until) = week();
+        let stats = sync
+            .sync(
+                &[unchanged, coach_changed, added, outside_window],
+                from,
+                until,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            SyncStats {
+                created: 1,
+                updated: 1,
+                deleted: 1
+            }
+        );
+        token.assert_calls(1);
+        first_page.assert_calls(1);
+        second_page.assert_calls(1);
+        update.assert_calls(1);
+        create.assert_calls(1);
+        delete.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_request() {
+        let server = MockServer::start();
+        let sync = sync(&server);
+        server.mock(|when, then| {
+            when.method(POST).path("/login/tenant/oauth2/v2.0/token");
+            then.status(401).body("invalid_client");
+        });
+
+        let (from, until) = week();
+        let result = sync.sync(&[], from, until).await;
+
+        assert!(matches!(
+            result,
+            Err(OutlookError::Rejected { status, .. }) if status == 401
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+//! Per-token default query parameters, so every subscriber's link stays short.
+
+use thiserror::Error;
+use url::form_urlencoded;
+
+use crate::settings::Settings;
+
+// Parameters a token may default; credentials and the start date stay per request
+const PREFERENCE_KEYS: &[&str] = &[
+    "weeks",
+    "from_today",
+    "kind",
+    "strict",
+    "alarm",
+    "calendar_name",
+    "event_prefix",
+    "color",
+    "lang",
+];
+
+#[derive(Debug, Error)]
+pub enum PreferenceError {
+    #[error("APP_TOKEN_PREFERENCES entry must look like `token?weeks=2&kind=hyrox`")]
+    Malformed,
+    #[error("APP_TOKEN_PREFERENCES: unsupported parameter `{0}` (expected one of {keys})", keys = PREFERENCE_KEYS.join(", "))]
+    UnknownParameter(String),
+}
+
+/// Default query parameters keyed by token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenPreferences {
+    tokens: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl TokenPreferences {
+    pub fn from_settings(settings: &Settings) -> Result<Self, PreferenceError> {
+        let tokens = settings
+            .token_preferences
+            .iter()
+            .map(|entry| {
+                // Entries are left out of errors, as they carry tokens
+                let (token, query) = entry
+                    .split_once('?')
+                    .map(|(token, query)| (token.trim(), query.trim()))
+                    .filter(|(token, _)| !token.is_empty())
+                    .ok_or(PreferenceError::Malformed)?;
+                let params = form_urlencoded::parse(query.as_bytes())
+                    .map(|(key, value)| {
+                        if PREFERENCE_KEYS.contains(&key.as_ref()) {
+                            Ok((key.into_owned(), value.into_owned()))
+                        } else {
+                            Err(PreferenceError::UnknownParameter(key.into_owned()))
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok((token.to_string(), params))
+            })
+            .collect::<Result<_, PreferenceError>>()?;
+        Ok(Self { tokens })
+    }
+
+    /// The default parameters of `token`, if it is configured.
+    pub fn defaults_for(&self, token: &str) -> Option<&[(String, String)]> {
+        self.tokens
+            .iter()
+            .find(|(configured, _)| configured == token)
+            .map(|(_, params)| params.as_slice())
+    }
+}
+
+/// Whether `token` is one of the tokens listed in `APP_TOKEN_PREFERENCES`.
+pub fn is_preference_token(settings: &Settings, token: &str) -> bool {
+    !token.is_empty()
+        && settings.token_preferences.iter().any(|entry| {
+            entry
+                .split_once('?')
+                .is_some_and(|(configured, _)| configured.trim() == token)
+        })
+}
+
+/// Appends the `defaults` whose keys don't already occur in `query`.
+pub fn apply_defaults(query: &str, defaults: &[(String, String)]) -> String {
+    let given: Vec<_> = form_urlencoded::parse(query.as_bytes())
+        .map(|(key, _)| key.into_owned())
+        .collect();
+    form_urlencoded::Serializer::new(query.to_string())
+        .extend_pairs(defaults.iter().filter(|(key, _)| !given.contains(key)))
+        .finish()
+}
+
+#[cfg(feature = "server")]
+pub use extract::PreferredQuery;
+
+#[cfg(feature = "server")]
+mod extract {
+    use axum::extract::rejection::QueryRejection;
+    use axum::extract::{FromRequestParts, Query};
+    use axum::http::Uri;
+    use axum::http::request::Parts;
+    use axum_extra::headers::authorization::Bearer;
+    use axum_extra::headers::{Authorization, HeaderMapExt};
+    use serde::de::DeserializeOwned;
+    use url::form_urlencoded;
+
+    use super::{TokenPreferences, apply_defaults};
+    use crate::AppState;
+
+    /// Like `Query`, with the defaults configured for the request's token filled in.
+    pub struct PreferredQuery<T>(pub T);
+
+    impl<T: DeserializeOwned> FromRequestParts<AppState> for PreferredQuery<T> {
+        type Rejection = QueryRejection;
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            state: &AppState,
+        ) -> Result<Self, Self::Rejection> {
+            let query = parts.uri.query().unwrap_or_default();
+            let token = match parts.headers.typed_get::<Authorization<Bearer>>() {
+                Some(bearer) => Some(bearer.token().to_string()),
+                None => form_urlencoded::parse(query.as_bytes())
+                    .find(|(key, _)| key == "token")
+                    .map(|(_, value)| value.into_owned()),
+            };
+            let preferences = TokenPreferences::from_settings(&state.settings).unwrap_or_default();
+            let defaults = token.and_then(|token| {
+                preferences
+                    .defaults_for(&token)
+                    .map(|defaults| apply_defaults(query, defaults))
+            });
+
+            let Some(query) = defaults else {
+                return Query::try_from_uri(&parts.uri).map(|Query(value)| Self(value));
+            };
+            // The serializer only produces valid query strings
+            let uri = Uri::try_from(format!("/?{query}")).unwrap_or_else(|_| parts.uri.clone());
+            Query::try_from_uri(&uri).map(|Query(value)| Self(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preferences(entries: &[&str]) -> Result<TokenPreferences, PreferenceError> {
+        TokenPreferences::from_settings(&Settings {
+            token_preferences: entries.iter().map(|entry| entry.to_string()).collect(),
+            ..Settings::default()
+        })
+    }
+
+    #[test]
+    fn test_defaults_for_token() {
+        let preferences =
+            preferences(&["alice?weeks=2&kind=hyrox,wod", " bob ? alarm=30 "]).unwrap();
+
+        assert_eq!(
+            preferences.defaults_for("alice"),
+            Some(
+                [
+                    ("weeks".to_string(), "2".to_string()),
+                    ("kind".to_string(), "hyrox,wod".to_string())
+                ]
+                .as_slice()
+            )
+        );
+        assert_eq!(
+            preferences.defaults_for("bob"),
+            Some([("alarm".to_string(), "30".to_string())].as_slice())
+        );
+        assert_eq!(preferences.defaults_for("carol"), None);
+    }
+
+    #[test]
+    fn test_invalid_preferences() {
+        assert!(matches!(
+            preferences(&["weeks=2"]),
+            Err(PreferenceError::Malformed)
+        ));
+        assert!(matches!(
+            preferences(&["?weeks=2"]),
+            Err(PreferenceError::Malformed)
+        ));
+        assert!(matches!(
+            preferences(&["alice?token=other"]),
+            Err(PreferenceError::UnknownParameter(key)) if key == "token"
+        ));
+    }
+
+    #[test]
+    fn test_apply_defaults_keeps_given_parameters() {
+        let defaults = [
+            ("weeks".to_string(), "2".to_string()),
+            ("kind".to_string(), "hyrox".to_string()),
+        ];
+
+        assert_eq!(
+            apply_defaults("token=alice&weeks=4", &defaults),
+            "token=alice&weeks=4&kind=hyrox"
+        );
+        assert_eq!(apply_defaults("", &defaults), "weeks=2&kind=hyrox");
+    }
+
+    #[test]
+    fn test_is_preference_token() {
+        let settings = Settings {
+            token_preferences: vec!["alice?weeks=2".to_string()],
+            ..Settings::default()
+        };
+
+        assert!(is_preference_token(&settings, "alice"));
+        assert!(!is_preference_token(&settings, "weeks=2"));
+    }
+}
@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use thiserror::Error;
+use url::Url;
+
+use crate::diff::ScheduleChange;
+use crate::models::ClassItem;
+use crate::notify::{Notifier, NotifyError, reminder_text};
+use crate::settings::Settings;
+
+#[derive(Debug, Error)]
+pub enum PushError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Push server replied {0}")]
+    Status(reqwest::StatusCode),
+    #[error("Gotify notifications require APP_GOTIFY_TOKEN")]
+    MissingGotifyToken,
+}
+
+enum PushTarget {
+    // Full topic URL, e.g. https://ntfy.sh/crossfit-rzeszow
+    Ntfy {
+        topic_url: Url,
+        token: Option<String>,
+    },
+    Gotify {
+        server_url: Url,
+        token: String,
+    },
+}
+
+/// Phone push notifications through self-hosted ntfy or Gotify servers.
+pub struct PushNotifier {
+    client: reqwest::Client,
+    targets: Vec<PushTarget>,
+}
+
+impl PushNotifier {
+    /// Returns `None` when neither ntfy nor Gotify is configured.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, PushError> {
+        let mut targets = Vec::new();
+        if let Some(topic_url) = &settings.ntfy_url {
+            targets.push(PushTarget::Ntfy {
+                topic_url: topic_url.clone(),
+                token: settings.ntfy_token.clone(),
+            });
+        }
+        if let Some(server_url) = &settings.gotify_url {
+            targets.push(PushTarget::Gotify {
+                server_url: server_url.clone(),
+                token: settings
+                    .gotify_token
+                    .clone()
+                    .ok_or(PushError::MissingGotifyToken)?,
+            });
+        }
+        if targets.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            client: reqwest::Client::new(),
+            targets,
+        }))
+    }
+
+    /// Sends one push per change. Every target is tried; the first failure is returned.
+    async fn push_changes(&self, changes: &[ScheduleChange]) -> Result<(), PushError> {
+        let mut result = Ok(());
+        for change in changes {
+            let title = format!("{} schedule change", change.class().event_name);
+            // Cancellations matter most to someone already planning to attend
+            let urgent = matches!(change, ScheduleChange::Removed { .. });
+            let outcome = self.push_all(&title, &change.describe(), urgent).await;
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
+    }
+
+    async fn push_all(&self, title: &str, message: &str, urgent: bool) -> Result<(), PushError> {
+        let mut result = Ok(());
+        for target in &self.targets {
+            if let Err(err) = self.send(target, title, message, urgent).await
+                && result.is_ok()
+            {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    async fn send(
+        &self,
+        target: &PushTarget,
+        title: &str,
+        message: &str,
+        urgent: bool,
+    ) -> Result<(), PushError> {
+        let request = match target {
+            PushTarget::Ntfy { topic_url, token } => {
+                let request = self
+                    .client
+                    .post(topic_url.clone())
+                    .header("Title", title)
+                    .header("Tags", "calendar")
+                    .header("Priority", if urgent { "high" } else { "default" })
+                    .body(message.to_string());
+                match token {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                }
+            }
+            PushTarget::Gotify { server_url, token } => self
+                .client
+                .post(server_url.join("message").expect("valid Gotify path"))
+                .header("X-Gotify-Key", token)
+                .json(&serde_json::json!({
+                    "title": title,
+                    "message": message,
+                    "priority": if urgent { 8 } else { 5 },
+                })),
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(PushError::Status(response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for PushNotifier {
+    fn name(&self) -> &'static str {
+        "push"
+    }
+
+    async fn notify_changes(
+        &self,
+        _monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        Ok(self.push_changes(changes).await?)
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        let title = format!("{} in {minutes_before} min", class.event_name);
+        Ok(self
+            .push_all(&title, &reminder_text(class, minutes_before), false)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn removed(event_name: &str) -> ScheduleChange {
+        ScheduleChange::Removed {
+            class: ClassItem::new(
+                NaiveDateTime::parse_from_str("2025-11-24 06:00", "%Y-%m-%d %H:%M").unwrap(),
+                event_name.to_string(),
+                "Anna".to_string(),
+                Some(60),
+                "https://example.com".to_string(),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ntfy_and_gotify() {
+        let server = MockServer::start();
+        let ntfy = server.mock(|when, then| {
+            when.method(POST)
+                .path("/crossfit")
+                .header("Title", "HYROX schedule change")
+                .header("Priority", "high")
+                .header("Authorization", "Bearer tk_123")
+                .body("Cancelled: HYROX on Mon 24.11 06:00 (Anna)");
+            then.status(200);
+        });
+        let gotify = server.mock(|when, then| {
+            when.method(POST)
+                .path("/message")
+                .header("X-Gotify-Key", "app-token")
+                .json_body_includes(r#"{"priority": 8}"#);
+            then.status(200);
+        });
+        let settings = Settings {
+            ntfy_url: Some(Url::parse(&server.url("/crossfit")).unwrap()),
+            ntfy_token: Some("tk_123".to_string()),
+            gotify_url: Some(Url::parse(&server.base_url()).unwrap()),
+            gotify_token: Some("app-token".to_string()),
+            ..Settings::default()
+        };
+        let notifier = PushNotifier::from_settings(&settings).unwrap().unwrap();
+
+        notifier.push_changes(&[removed("HYROX")]).await.unwrap();
+
+        ntfy.assert_calls(1);
+        gotify.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_server_error() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST);
+            then.status(403);
+        });
+        let settings = Settings {
+            ntfy_url: Some(Url::parse(&server.url("/crossfit")).unwrap()),
+            ..Settings::default()
+        };
+        let notifier = PushNotifier::from_settings(&settings).unwrap().unwrap();
+
+        let result = notifier.push_changes(&[removed("WOD")]).await;
+
+        assert!(matches!(result, Err(PushError::Status(status)) if status == 403));
+    }
+
+    #[test]
+    fn test_gotify_requires_token() {
+        let settings = Settings {
+            gotify_url: Some(Url::parse("https://gotify.example.com").unwrap()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            PushNotifier::from_settings(&settings),
+            Err(PushError::MissingGotifyToken)
+        ));
+        assert!(
+            PushNotifier::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
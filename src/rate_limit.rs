@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::AppState;
+use crate::auth::{AuthHeader, credential_owner, verify_token};
+use crate::error::ApiError;
+use crate::preferences::is_preference_token;
+
+// Clients counted at once; beyond this, requests of new clients are limited until windows end
+const MAX_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_after: Duration,
+}
+
+impl RateLimitStatus {
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        headers.insert("x-ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from(self.reset_after.as_secs()),
+        );
+    }
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Fixed-window request counter keyed by token or client IP.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a request for `key`; returns `Err` with the status once the limit is exhausted.
+    pub fn check(&self, key: &str) -> Result<RateLimitStatus, RateLimitStatus> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<RateLimitStatus, RateLimitStatus> {
+        let mut windows = self.windows.lock().expect("rate limit lock poisoned");
+        if !windows.contains_key(key) {
+            windows.retain(|_, w| now.duration_since(w.started) < self.window);
+            if windows.len() >= MAX_CLIENTS {
+                let oldest = windows.values().map(|w| w.started).min().unwrap_or(now);
+                return Err(RateLimitStatus {
+                    limit: self.limit,
+                    remaining: 0,
+                    reset_after: self.window.saturating_sub(now.duration_since(oldest)),
+                });
+            }
+        }
+        let window = windows.entry(key.to_string()).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.count = 0;
+        }
+
+        let reset_after = self.window - now.duration_since(window.started);
+        if window.count >= self.limit {
+            return Err(RateLimitStatus {
+                limit: self.limit,
+                remaining: 0,
+                reset_after,
+            });
+        }
+        window.count += 1;
+        Ok(RateLimitStatus {
+            limit: self.limit,
+            remaining: self.limit - window.count,
+            reset_after,
+        })
+    }
+}
+
+// Requests are counted per credential once it verifies, otherwise per client IP: a key taken
+// from any presented value would give each made-up token a budget of its own
+fn client_key(state: &AppState, request: &Request) -> String {
+    let auth = AuthHeader::from_headers(request.headers());
+    let query_token = request.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    let presented = match &auth {
+        Some(AuthHeader::Bearer(bearer)) => Some(bearer.token()),
+        Some(AuthHeader::Basic(_)) => None,
+        None => query_token.as_deref(),
+    };
+    let verified = presented.is_some_and(|token| is_preference_token(&state.settings, token))
+        || verify_token(&state.settings, auth.clone(), query_token.as_deref()).is_ok();
+    if verified && let Some(owner) = credential_owner(auth.as_ref(), query_token.as_deref()) {
+        return owner;
+    }
+
+    match state.ip_filter.request_ip(request) {
+        Some(ip) => format!("ip:{ip}"),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(&client_key(&state, &request)) {
+        // Clients can pace themselves by the budget left
+        Ok(status) => {
+            let mut response = next.run(request).await;
+            status.apply_headers(response.headers_mut());
+            response
+        }
+        Err(status) => {
+            let retry_after = status.reset_after.as_secs().max(1);
+            let mut response =
+                ApiError::TooManyRequests(format!("retry in {retry_after} seconds"), retry_after)
+                    .into_response();
+            status.apply_headers(response.headers_mut());
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_exhausted_within_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at("a", now).unwrap().remaining, 1);
+        assert_eq!(limiter.check_at("a", now).unwrap().remaining, 0);
+        assert!(limiter.check_at("a", now).is_err());
+        // Other clients have their own budget
+        assert!(limiter.check_at("b", now).is_ok());
+    }
+
+    #[test]
+    fn test_clients_capped() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+        for client in 0..MAX_CLIENTS {
+            assert!(limiter.check_at(&client.to_string(), now).is_ok());
+        }
+
+        assert!(limiter.check_at("new", now).is_err());
+        assert_eq!(limiter.windows.lock().unwrap().len(), MAX_CLIENTS);
+        // Once the windows end there is room again
+        assert!(
+            limiter
+                .check_at("new", now + Duration::from_secs(61))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_window_resets() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_err());
+        assert!(limiter.check_at("a", now + Duration::from_secs(61)).is_ok());
+    }
+}
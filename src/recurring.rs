@@ -0,0 +1,244 @@
+//! The stable weekly pattern of the timetable, read from several weeks of classes.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{ClassItem, ClassKind, week_start};
+
+/// A weekday and start time a class is held at in most of the analyzed weeks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RecurringSlot {
+    #[schema(value_type = String, example = "Mon")]
+    pub weekday: Weekday,
+    #[schema(value_type = String, example = "06:00")]
+    pub time: NaiveTime,
+    /// Analyzed weeks the class was held in at this slot
+    pub weeks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RecurringClass {
+    pub event_name: String,
+    pub kind: ClassKind,
+    /// The slots in one line, e.g. `WOD: Mon/Wed/Fri 06:00, 17:00; Sat 09:00`
+    #[schema(example = "WOD: Mon/Wed/Fri 06:00, 17:00; Sat 09:00")]
+    pub summary: String,
+    /// Slots in weekday and time order
+    pub slots: Vec<RecurringSlot>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExceptionKind {
+    /// A class outside the weekly pattern
+    Extra,
+    /// A recurring class that didn't take place that week
+    Missing,
+}
+
+/// A class that deviates from the weekly pattern.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PatternException {
+    pub kind: ExceptionKind,
+    pub event_name: String,
+    /// Local start time
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T06:00:00")]
+    pub date: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct RecurringPattern {
+    /// Mondays of the analyzed weeks
+    #[schema(value_type = Vec<String>, format = "date")]
+    pub weeks: Vec<NaiveDate>,
+    /// Classes with at least one recurring slot, by name
+    pub classes: Vec<RecurringClass>,
+    /// Deviations from the pattern, oldest first
+    pub exceptions: Vec<PatternException>,
+}
+
+// Weeks a class was held in, per day (from Monday) and start time
+type WeeksPerSlot = BTreeMap<(u32, NaiveTime), BTreeSet<NaiveDate>>;
+
+/// Finds the slots each class is held at in more than half of the weeks starting on `mondays`.
+pub fn analyze(classes: &[ClassItem], mondays: &[NaiveDate]) -> RecurringPattern {
+    let mut held: BTreeMap<&str, (ClassKind, WeeksPerSlot)> = BTreeMap::new();
+    for class in classes {
+        let (_, slots) = held
+            .entry(class.event_name.as_str())
+            .or_insert_with(|| (class.kind, BTreeMap::new()));
+        slots
+            .entry(slot_of(class.date))
+            .or_default()
+            .insert(week_start(class.date.date()));
+    }
+
+    let mut recurring = Vec::new();
+    let mut exceptions = Vec::new();
+    for (event_name, (kind, slots)) in held {
+        let mut stable = Vec::new();
+        for ((day, time), weeks) in slots {
+            let date = |monday: &NaiveDate| (*monday + Duration::days(day.into())).and_time(time);
+            if weeks.len() * 2 > mondays.len() {
+                exceptions.extend(mondays.iter().filter(|monday| !weeks.contains(monday)).map(
+                    |monday| PatternException {
+                        kind: ExceptionKind::Missing,
+                        event_name: event_name.to_string(),
+                        date: date(monday),
+                    },
+                ));
+                stable.push(RecurringSlot {
+                    weekday: Weekday::try_from(day as u8).expect("days from Monday are below 7"),
+                    time,
+                    weeks: weeks.len(),
+                });
+            } else {
+                exceptions.extend(weeks.iter().map(|monday| PatternException {
+                    kind: ExceptionKind::Extra,
+                    event_name: event_name.to_string(),
+                    date: date(monday),
+                }));
+            }
+        }
+        if !stable.is_empty() {
+            recurring.push(RecurringClass {
+                event_name: event_name.to_string(),
+                kind,
+                summary: summary(event_name, &stable),
+                slots: stable,
+            });
+        }
+    }
+    exceptions.sort_by(|a, b| a.date.cmp(&b.date).then(a.event_name.cmp(&b.event_name)));
+
+    RecurringPattern {
+        weeks: mondays.to_vec(),
+        classes: recurring,
+        exceptions,
+    }
+}
+
+// `Weekday` isn't ordered, so slots are keyed by the days from Monday
+fn slot_of(date: NaiveDateTime) -> (u32, NaiveTime) {
+    (date.weekday().num_days_from_monday(), date.time())
+}
+
+// Days sharing the same start times are listed together: `WOD: Mon/Wed 06:00, 17:00; Sat 09:00`
+fn summary(event_name: &str, slots: &[RecurringSlot]) -> String {
+    let mut days: Vec<(Weekday, Vec<NaiveTime>)> = Vec::new();
+    for slot in slots {
+        match days.last_mut() {
+            Some((weekday, times)) if *weekday == slot.weekday => times.push(slot.time),
+            _ => days.push((slot.weekday, vec![slot.time])),
+        }
+    }
+    let mut groups: Vec<(Vec<Weekday>, Vec<NaiveTime>)> = Vec::new();
+    for (weekday, times) in days {
+        match groups
+            .iter_mut()
+            .find(|(_, group_times)| *group_times == times)
+        {
+            Some((weekdays, _)) => weekdays.push(weekday),
+            None => groups.push((vec![weekday], times)),
+        }
+    }
+    let groups: Vec<String> = groups
+        .into_iter()
+        .map(|(weekdays, times)| {
+            let weekdays: Vec<String> = weekdays.iter().map(Weekday::to_string).collect();
+            let times: Vec<String> = times
+                .iter()
+                .map(|time| time.format("%H:%M").to_string())
+                .collect();
+            format!("{} {}", weekdays.join("/"), times.join(", "))
+        })
+        .collect();
+    format!("{event_name}: {}", groups.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(date: &str, event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn mondays() -> Vec<NaiveDate> {
+        ["2025-11-17", "2025-11-24", "2025-12-01"]
+            .iter()
+            .map(|day| day.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_summary_groups_days_with_the_same_times() {
+        let mut classes = Vec::new();
+        for monday in ["2025-11-17", "2025-11-24", "2025-12-01"] {
+            let monday: NaiveDate = monday.parse().unwrap();
+            for day in [0, 2, 4] {
+                for time in ["06:00", "17:00", "18:00"] {
+                    let date = monday + Duration::days(day);
+                    classes.push(class(&format!("{date} {time}"), "WOD"));
+                }
+            }
+            classes.push(class(
+                &format!("{} 09:00", monday + Duration::days(5)),
+                "WOD",
+            ));
+        }
+
+        let pattern = analyze(&classes, &mondays());
+
+        assert_eq!(pattern.classes.len(), 1);
+        assert_eq!(
+            pattern.classes[0].summary,
+            "WOD: Mon/Wed/Fri 06:00, 17:00, 18:00; Sat 09:00"
+        );
+        assert_eq!(pattern.classes[0].slots.len(), 10);
+        assert!(pattern.classes[0].slots.iter().all(|slot| slot.weeks == 3));
+        assert!(pattern.exceptions.is_empty());
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let classes = vec![
+            class("2025-11-17 06:00", "WOD"),
+            class("2025-11-24 06:00", "WOD"),
+            class("2025-11-26 19:00", "Open Gym"),
+        ];
+
+        let pattern = analyze(&classes, &mondays());
+
+        let names: Vec<_> = pattern
+            .classes
+            .iter()
+            .map(|class| class.summary.as_str())
+            .collect();
+        assert_eq!(names, ["WOD: Mon 06:00"]);
+        assert_eq!(
+            pattern.exceptions,
+            [
+                PatternException {
+                    kind: ExceptionKind::Extra,
+                    event_name: "Open Gym".to_string(),
+                    date: "2025-11-26T19:00:00".parse().unwrap(),
+                },
+                PatternException {
+                    kind: ExceptionKind::Missing,
+                    event_name: "WOD".to_string(),
+                    date: "2025-12-01T06:00:00".parse().unwrap(),
+                },
+            ]
+        );
+    }
+}
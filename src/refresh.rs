@@ -0,0 +1,106 @@
+//! Background scrapes on a cron schedule, recording the live weeks so schedule changes are
+//! detected and announced without waiting for a client to ask.
+
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use tracing::{info, warn};
+
+use crate::cron::CronSchedule;
+use crate::models::{local_now, week_start};
+use crate::notify::Dispatcher;
+use crate::source::TimetableSource;
+use crate::storage::ClassStore;
+
+/// Refreshes the timetable whenever `schedule` fires.
+pub async fn run_refresh(
+    source: Arc<dyn TimetableSource>,
+    store: Option<Arc<dyn ClassStore>>,
+    location: Option<String>,
+    dispatcher: Arc<Dispatcher>,
+    schedule: CronSchedule,
+    time_zone: Tz,
+) {
+    loop {
+        let now = local_now(time_zone);
+        let Some(next) = schedule.next_in(now) else {
+            warn!("refresh schedule never fires again");
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        refresh(
+            source.as_ref(),
+            store.as_deref(),
+            location.clone(),
+            &dispatcher,
+            next.date_naive(),
+        )
+        .await;
+    }
+}
+
+/// Scrapes the week of `today` and the next one, recording them (and announcing what
+/// changed) when a database is configured.
+pub async fn refresh(
+    source: &dyn TimetableSource,
+    store: Option<&dyn ClassStore>,
+    location: Option<String>,
+    dispatcher: &Dispatcher,
+    today: NaiveDate,
+) {
+    let monday = week_start(today);
+    for monday in [monday, monday + Duration::weeks(1)] {
+        let week = match source
+            .fetch_week_with_warnings(monday, location.clone())
+            .await
+        {
+            Ok(week) => week,
+            Err(err) => {
+                warn!(error = %err, %monday, "failed to refresh timetable");
+                continue;
+            }
+        };
+        info!(%monday, classes = week.classes.len(), "refreshed timetable");
+        let Some(store) = store else {
+            continue;
+        };
+        match store.record_week(monday, Utc::now(), &week.classes).await {
+            Ok(changes) if !changes.is_empty() => dispatcher.dispatch(monday, &changes).await,
+            Ok(_) => {}
+            Err(err) => warn!(error = %err, %monday, "failed to record refreshed week"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::FakeSource;
+    use crate::storage::MemoryStore;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refresh_records_current_and_next_week() {
+        let store = MemoryStore::new();
+        let today: NaiveDate = "2025-11-26".parse().unwrap();
+
+        refresh(
+            &FakeSource::default(),
+            Some(&store),
+            None,
+            &Dispatcher::default(),
+            today,
+        )
+        .await;
+
+        let mondays: Vec<NaiveDate> = ["2025-11-24", "2025-12-01"]
+            .iter()
+            .map(|day| day.parse().unwrap())
+            .collect();
+        assert_eq!(store.weeks().await.unwrap(), mondays);
+        let week = store.latest_week(mondays[1]).await.unwrap().unwrap();
+        assert!(!week.classes.is_empty());
+    }
+}
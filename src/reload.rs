@@ -0,0 +1,105 @@
+use std::sync::{Arc, RwLock};
+
+use axum::Router;
+use axum::extract::Request;
+use tower::{ServiceExt, service_fn};
+
+use crate::settings::Settings;
+
+/// Serves every request with the most recently installed router, so a reload takes
+/// effect on the next request without closing open connections.
+#[derive(Clone)]
+pub struct SwappableRouter(Arc<RwLock<Router>>);
+
+impl SwappableRouter {
+    pub fn new(router: Router) -> Self {
+        Self(Arc::new(RwLock::new(router)))
+    }
+
+    pub fn replace(&self, router: Router) {
+        *self.0.write().expect("router lock poisoned") = router;
+    }
+
+    /// A router forwarding everything to the current one; `ConnectInfo` and other
+    /// connection extensions pass through untouched.
+    pub fn router(&self) -> Router {
+        let current = Arc::clone(&self.0);
+        Router::new().fallback_service(service_fn(move |request: Request| {
+            let router = current.read().expect("router lock poisoned").clone();
+            router.oneshot(request)
+        }))
+    }
+}
+
+/// Settings that only take effect after a restart and differ between `old` and `new`.
+pub fn restart_required(old: &Settings, new: &Settings) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    let mut check = |name, differs: bool| {
+        if differs {
+            changed.push(name);
+        }
+    };
+    check("APP_BIND_ADDR", old.bind_addr != new.bind_addr);
+    check("APP_PORT", old.port != new.port);
+    check(
+        "APP_UNIX_SOCKET_PATH",
+        old.unix_socket_path != new.unix_socket_path,
+    );
+    check(
+        "APP_TLS_CERT_PATH / APP_TLS_KEY_PATH",
+        old.tls_cert_path != new.tls_cert_path || old.tls_key_path != new.tls_key_path,
+    );
+    check("APP_DATABASE_URL", old.database_url != new.database_url);
+    check("APP_DEBUG", old.debug != new.debug);
+    check("APP_LOG_FORMAT", old.log_format != new.log_format);
+    check(
+        "APP_OTLP_ENDPOINT",
+        old.otlp_endpoint != new.otlp_endpoint || old.otlp_service_name != new.otlp_service_name,
+    );
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+
+    use super::*;
+
+    async fn body(router: &Router, uri: &str) -> String {
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replaced_router_serves_next_request() {
+        let swappable = SwappableRouter::new(Router::new().route("/", get(|| async { "old" })));
+        let app = swappable.router();
+        assert_eq!(body(&app, "/").await, "old");
+
+        swappable.replace(Router::new().route("/new", get(|| async { "new" })));
+
+        assert_eq!(body(&app, "/new").await, "new");
+        assert_eq!(body(&app, "/").await, "");
+    }
+
+    #[test]
+    fn test_restart_required() {
+        let old = Settings::default();
+        let new = Settings {
+            auth_token: "rotated".to_string(),
+            port: 9000,
+            ..Settings::default()
+        };
+
+        assert_eq!(restart_required(&old, &new), vec!["APP_PORT"]);
+        assert!(restart_required(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use tracing::warn;
+
+use crate::models::{ClassItem, Favorites, local_now};
+use crate::notify::Dispatcher;
+use crate::source::TimetableSource;
+use crate::storage::ClassStore;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Upcoming classes are re-scraped this often so late schedule edits are picked up
+const REFRESH_INTERVAL: chrono::Duration = chrono::Duration::minutes(30);
+
+/// Classes starting in `(from, to]` that match anyone's favorites. Favorites without any
+/// criterion are left out, or one member clearing theirs would announce every class.
+pub fn due_classes<'a>(
+    classes: &'a [ClassItem],
+    favorites: &BTreeMap<String, Favorites>,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Vec<&'a ClassItem> {
+    classes
+        .iter()
+        .filter(|class| class.date > from && class.date <= to)
+        .filter(|class| {
+            favorites
+                .values()
+                .any(|favorites| !favorites.is_empty() && favorites.matches(class))
+        })
+        .collect()
+}
+
+/// Sends a reminder `lead_minutes` before each class matching stored favorites.
+/// Every class is announced once, however many owners favorite it.
+pub async fn run_reminders(
+    store: Arc<dyn ClassStore>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    dispatcher: Arc<Dispatcher>,
+    lead_minutes: u32,
+    time_zone: Tz,
+) {
+    let lead = chrono::Duration::minutes(lead_minutes.into());
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    let mut last_check = local_now(time_zone).naive_local();
+    let mut cached: Option<(NaiveDate, NaiveDateTime, Vec<ClassItem>)> = None;
+    let mut reminded: HashSet<String> = HashSet::new();
+
+    loop {
+        interval.tick().await;
+        let now = local_now(time_zone).naive_local();
+        let (from, to) = (last_check + lead, now + lead);
+        last_check = now;
+
+        let due_day = to.date();
+        let monday =
+            due_day - chrono::Duration::days(due_day.weekday().num_days_from_monday().into());
+        let stale = cached
+            .as_ref()
+            .is_none_or(|(week, fetched, _)| *week != monday || now - *fetched > REFRESH_INTERVAL);
+        if stale {
+            match source.fetch_week(monday, location.clone()).await {
+                Ok(classes) => cached = Some((monday, now, classes)),
+                Err(err) => warn!(error = %err, %monday, "failed to fetch timetable for reminders"),
+            }
+        }
+        let Some((_, _, classes)) = &cached else {
+            continue;
+        };
+
+        let favorites = match store.all_favorites().await {
+            Ok(favorites) => favorites,
+            Err(err) => {
+                warn!(error = %err, "failed to load favorites for reminders");
+                continue;
+            }
+        };
+        for class in due_classes(classes, &favorites, from, to) {
+            if reminded.insert(class.id.clone()) {
+                dispatcher.remind(class, lead_minutes).await;
+            }
+        }
+        // Forget classes that have started so the set doesn't grow forever
+        reminded.retain(|id| {
+            classes
+                .iter()
+                .any(|class| class.id == *id && class.date > now)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_due_classes() {
+        let at = |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        let classes = vec![
+            class("2025-11-24 06:00", "WOD"),
+            class("2025-11-24 06:00", "HYROX"),
+            class("2025-11-24 07:00", "WOD"),
+            class("2025-11-25 06:00", "WOD"),
+        ];
+        let favorites = BTreeMap::from([
+            (
+                "token:a".to_string(),
+                Favorites {
+                    event_names: vec!["wod".to_string()],
+                    weekdays: vec![Weekday::Mon],
+                    ..Favorites::default()
+                },
+            ),
+            (
+                "token:b".to_string(),
+                Favorites {
+                    event_names: vec!["WOD".to_string()],
+                    ..Favorites::default()
+                },
+            ),
+        ]);
+
+        let due = due_classes(
+            &classes,
+            &favorites,
+            at("2025-11-24 05:59"),
+            at("2025-11-24 06:59"),
+        );
+
+        assert_eq!(due, vec![&classes[0]]);
+        assert!(
+            due_classes(
+                &classes,
+                &BTreeMap::new(),
+                at("2025-11-24 00:00"),
+                at("2025-11-26 00:00")
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_empty_favorites_make_nothing_due() {
+        let at = |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        let classes = vec![class("2025-11-24 06:00", "WOD")];
+        let favorites = BTreeMap::from([("token:a".to_string(), Favorites::default())]);
+
+        let due = due_classes(
+            &classes,
+            &favorites,
+            at("2025-11-24 05:59"),
+            at("2025-11-24 06:59"),
+        );
+
+        assert!(due.is_empty());
+    }
+}
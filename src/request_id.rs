@@ -0,0 +1,139 @@
+use axum::body::{Body, HttpBody};
+use axum::extract::Request;
+use axum::http::{HeaderName, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::{Span, info_span, warn};
+
+use crate::error::ErrorBody;
+use crate::otel;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Error bodies are short messages; anything larger is passed through untouched
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// Request id assigned by `SetRequestIdLayer` (or sent by the client).
+pub fn request_id<B>(request: &axum::http::Request<B>) -> &str {
+    request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Trace span for `TraceLayer` carrying the request id, so every log line of a request
+/// can be found by the id a user reports.
+pub fn make_span<B>(request: &axum::http::Request<B>) -> Span {
+    info_span!(
+        "request",
+        otel.kind = "server",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        request_id = request_id(request),
+    )
+}
+
+/// Adds the request id to error responses so it shows up in screenshots: as `request_id` of
+/// an `ErrorBody`, or appended to plain-text errors of the middleware. Also makes the id
+/// available to `otel::current_request_id` while the request is handled.
+pub async fn request_id_in_errors(request: Request, next: Next) -> Response {
+    let id = request_id(&request).to_string();
+    let response = otel::REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let is_json = content_type.starts_with("application/json");
+    if id.is_empty()
+        || !(is_json || content_type.starts_with("text/plain"))
+        || !(response.status().is_client_error() || response.status().is_server_error())
+    {
+        return response;
+    }
+    // Reading a body beyond the limit would lose it, so bodies of unknown or larger size
+    // are passed through before anything is read
+    if response
+        .body()
+        .size_hint()
+        .upper()
+        .is_none_or(|size| size > MAX_ERROR_BODY as u64)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let mut body = match axum::body::to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(body) => body.to_vec(),
+        Err(err) => {
+            warn!(error = %err, "failed to read error response body");
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    if is_json {
+        // Other JSON bodies, e.g. axum's rejections, are left as they are
+        match serde_json::from_slice::<ErrorBody>(&body) {
+            Ok(mut error) => {
+                error.request_id = Some(id);
+                body = serde_json::to_vec(&error).expect("error body serializes");
+            }
+            Err(_) => return Response::from_parts(parts, Body::from(body)),
+        }
+    } else {
+        body.extend_from_slice(format!(" (request id: {id})").as_bytes());
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn call(router: Router) -> String {
+        let response = router
+            .layer(axum::middleware::from_fn(request_id_in_errors))
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_appended_to_plain_text_error() {
+        let router = Router::new().route(
+            "/",
+            get(|| async { (StatusCode::BAD_GATEWAY, "upstream down") }),
+        );
+
+        assert_eq!(call(router).await, "upstream down (request id: abc)");
+    }
+
+    #[tokio::test]
+    async fn test_large_error_body_passed_through() {
+        let large = "x".repeat(MAX_ERROR_BODY + 1);
+        let body = large.clone();
+        let router = Router::new().route(
+            "/",
+            get(move || async move { (StatusCode::INTERNAL_SERVER_ERROR, body) }),
+        );
+
+        assert_eq!(call(router).await, large);
+    }
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::storage::ClassStore;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Periodically removes stored scrapes and change history older than `retention_days`.
+pub async fn run_janitor(store: Arc<dyn ClassStore>, retention_days: u32) {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let before = Utc::now() - chrono::Duration::days(retention_days.into());
+        match store.prune(before).await {
+            Ok(stats) => info!(
+                pruned_scrapes = stats.scrapes,
+                pruned_classes = stats.classes,
+                pruned_changes = stats.changes,
+                %before,
+                "pruned stored history"
+            ),
+            Err(err) => warn!(error = %err, "failed to prune stored history"),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! RSS 2.0 feed of detected schedule changes, for members who follow the gym in a feed reader.
+
+use sha2::{Digest, Sha256};
+
+use crate::hcal::escape;
+use crate::models::ChangeRecord;
+use crate::settings::Settings;
+
+/// The channel of `changes` (newest first), linking to `link`.
+pub fn changes_rss(changes: &[ChangeRecord], settings: &Settings, link: &str) -> String {
+    let title = escape(&format!("{} schedule changes", settings.gym_title));
+    let link = escape(link);
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n\
+         <title>{title}</title>\n<link>{link}</link>\n\
+         <description>Added, cancelled and moved classes and coach changes</description>\n"
+    );
+    if let Some(latest) = changes.first() {
+        xml.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            latest.detected_at.to_rfc2822()
+        ));
+    }
+    for record in changes {
+        xml.push_str(&change_item(record, &link));
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn change_item(record: &ChangeRecord, link: &str) -> String {
+    let class = record.change.class();
+    format!(
+        "<item>\n<title>{title}</title>\n<link>{link}</link>\n\
+         <description>{description}</description>\n<category>{kind}</category>\n\
+         <guid isPermaLink=\"false\">{guid}</guid>\n<pubDate>{published}</pubDate>\n</item>\n",
+        title = escape(&record.change.describe()),
+        description = escape(&format!(
+            "{} with {}, week of {}",
+            class.event_name,
+            class.coach,
+            record.week.format("%d.%m.%Y")
+        )),
+        kind = record.change.kind(),
+        guid = guid(record),
+        published = record.detected_at.to_rfc2822(),
+    )
+}
+
+// Stable across requests, so readers show each change once
+fn guid(record: &ChangeRecord) -> String {
+    let digest = Sha256::digest(format!(
+        "{}:{}:{}",
+        record.detected_at.timestamp(),
+        record.change.kind(),
+        record.change.describe()
+    ));
+    digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::diff::ScheduleChange;
+    use crate::models::ClassItem;
+
+    fn record(coach: &str, previous_coach: &str) -> ChangeRecord {
+        ChangeRecord {
+            detected_at: Utc.with_ymd_and_hms(2025, 11, 20, 12, 0, 0).unwrap(),
+            week: "2025-11-24".parse().unwrap(),
+            change: ScheduleChange::CoachChanged {
+                class: ClassItem::new(
+                    NaiveDateTime::parse_from_str("2025-11-24 06:00", "%Y-%m-%d %H:%M").unwrap(),
+                    "WOD & Mobility".to_string(),
+                    coach.to_string(),
+                    Some(60),
+                    "https://example.com".to_string(),
+                ),
+                previous_coach: previous_coach.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_changes_rss() {
+        let xml = changes_rss(
+            &[record("Marek", "Anna")],
+            &Settings::default(),
+            "https://gym.example.com/history/changes",
+        );
+
+        assert!(
+            xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">")
+        );
+        assert!(xml.contains("<title>CrossFit 2.0 Rzeszów schedule changes</title>"));
+        assert!(xml.contains(
+            "<title>Coach change: WOD &amp; Mobility on Mon 24.11 06:00, Anna → Marek</title>"
+        ));
+        assert!(xml.contains("<category>coach_changed</category>"));
+        assert!(xml.contains("<pubDate>Thu, 20 Nov 2025 12:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<lastBuildDate>Thu, 20 Nov 2025 12:00:00 +0000</lastBuildDate>"));
+    }
+
+    #[test]
+    fn test_guid_is_stable_per_change() {
+        assert_eq!(
+            guid(&record("Marek", "Anna")),
+            guid(&record("Marek", "Anna"))
+        );
+        assert_ne!(guid(&record("Marek", "Anna")), guid(&record("Ola", "Anna")));
+    }
+
+    #[test]
+    fn test_empty_feed() {
+        let xml = changes_rss(&[], &Settings::default(), "https://gym.example.com/");
+
+        assert!(!xml.contains("<item>"));
+        assert!(!xml.contains("<lastBuildDate>"));
+    }
+}
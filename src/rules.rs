@@ -0,0 +1,263 @@
+//! Settings-driven corrections applied to every class a source reads, before anything else
+//! (handlers, feeds, notifications, the database) sees it.
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use crate::kinds::KindRuleError;
+use crate::models::{ClassItem, ClassKind};
+use crate::settings::Settings;
+
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error(transparent)]
+    Kind(#[from] KindRuleError),
+    #[error("APP_RENAME_RULES entry {0:?} must look like `pattern=new name`")]
+    MalformedRename(String),
+    #[error("APP_RENAME_RULES pattern {0:?} is not a valid regex: {1}")]
+    InvalidPattern(String, regex::Error),
+    #[error("APP_DURATION_OVERRIDES entry {0:?} must look like `class=minutes`")]
+    MalformedDuration(String),
+}
+
+// A class name (matched whole, ignoring case) or, when the entry names one, a class type
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Kind(ClassKind),
+    Name(String),
+}
+
+impl Selector {
+    fn parse(entry: &str) -> Self {
+        match entry.parse() {
+            Ok(kind) => Selector::Kind(kind),
+            Err(_) => Selector::Name(entry.trim().to_lowercase()),
+        }
+    }
+
+    fn matches(&self, class: &ClassItem) -> bool {
+        match self {
+            Selector::Kind(kind) => class.kind == *kind,
+            Selector::Name(name) => class.event_name.to_lowercase() == *name,
+        }
+    }
+}
+
+/// Renames classes whose whole name matches a case-insensitive regex (the first matching
+/// rule wins and `$1`-style references insert its capture groups), overrides their
+/// duration, then drops the excluded classes.
+#[derive(Debug, Clone, Default)]
+pub struct ClassRules {
+    renames: Vec<(Regex, String)>,
+    durations: Vec<(Selector, u32)>,
+    excluded: Vec<Selector>,
+}
+
+impl ClassRules {
+    pub fn from_settings(settings: &Settings) -> Result<Self, RuleError> {
+        let renames = settings
+            .rename_rules
+            .iter()
+            .map(|rule| {
+                let (pattern, name) = rule
+                    .split_once('=')
+                    .map(|(pattern, name)| (pattern.trim(), name.trim()))
+                    .filter(|(pattern, name)| !pattern.is_empty() && !name.is_empty())
+                    .ok_or_else(|| RuleError::MalformedRename(rule.clone()))?;
+                let regex = RegexBuilder::new(&format!("^(?:{pattern})$"))
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|err| RuleError::InvalidPattern(pattern.to_string(), err))?;
+                Ok((regex, name.to_string()))
+            })
+            .collect::<Result<_, RuleError>>()?;
+        let durations = settings
+            .duration_overrides
+            .iter()
+            .map(|rule| {
+                rule.split_once('=')
+                    .filter(|(class, _)| !class.trim().is_empty())
+                    .and_then(|(class, minutes)| {
+                        let minutes = minutes.trim().parse().ok().filter(|&m| m > 0)?;
+                        Some((Selector::parse(class), minutes))
+                    })
+                    .ok_or_else(|| RuleError::MalformedDuration(rule.clone()))
+            })
+            .collect::<Result<_, RuleError>>()?;
+        Ok(Self {
+            renames,
+            durations,
+            excluded: settings
+                .excluded_events
+                .iter()
+                .map(|entry| Selector::parse(entry))
+                .collect(),
+        })
+    }
+
+    /// The name `event_name` is published under.
+    pub fn rename(&self, event_name: &str) -> Option<String> {
+        self.renames
+            .iter()
+            .find(|(regex, _)| regex.is_match(event_name))
+            .map(|(regex, name)| regex.replace(event_name, name.as_str()).into_owned())
+    }
+
+    /// Whether `class` is left out of every response and feed. Names are matched after renaming.
+    pub fn is_excluded(&self, class: &ClassItem) -> bool {
+        self.excluded.iter().any(|selector| selector.matches(class))
+    }
+
+    /// The configured length of `class` in minutes, if any.
+    pub fn duration(&self, class: &ClassItem) -> Option<u32> {
+        self.durations
+            .iter()
+            .find(|(selector, _)| selector.matches(class))
+            .map(|&(_, minutes)| minutes)
+    }
+
+    /// Applies the rules to freshly read classes. The kind stays the one of the upstream name.
+    pub fn apply(&self, classes: Vec<ClassItem>) -> Vec<ClassItem> {
+        classes
+            .into_iter()
+            .map(|class| match self.rename(&class.event_name) {
+                Some(name) => class.renamed(name),
+                None => class,
+            })
+            .map(|class| match self.duration(&class) {
+                Some(minutes) => class.with_duration(minutes),
+                None => class,
+            })
+            .filter(|class| !self.is_excluded(class))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn rules(rules: &[&str]) -> Result<ClassRules, RuleError> {
+        ClassRules::from_settings(&Settings {
+            rename_rules: rules.iter().map(|rule| rule.to_string()).collect(),
+            ..Settings::default()
+        })
+    }
+
+    fn class(event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 18:00", "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_rename_whole_names() {
+        let rules = rules(&[
+            "WOD start=Beginners WOD",
+            r"Olimp(?:ijskie)? (\d+)=Weightlifting $1",
+        ])
+        .unwrap();
+
+        assert_eq!(rules.rename("wod START").as_deref(), Some("Beginners WOD"));
+        assert_eq!(
+            rules.rename("Olimpijskie 2").as_deref(),
+            Some("Weightlifting 2")
+        );
+        // Only whole names match
+        assert_eq!(rules.rename("WOD start 2"), None);
+        assert_eq!(rules.rename("WOD"), None);
+    }
+
+    #[test]
+    fn test_apply_recomputes_id() {
+        let rules = rules(&["WOD start=Beginners WOD"]).unwrap();
+        let original = class("WOD start");
+
+        let renamed = rules.apply(vec![original.clone(), class("HYROX")]);
+
+        assert_eq!(renamed[0].event_name, "Beginners WOD");
+        assert_ne!(renamed[0].id, original.id);
+        assert_eq!(renamed[0].id, class("Beginners WOD").id);
+        assert_eq!(renamed[0].kind, ClassKind::Wod);
+        assert_eq!(renamed[1].event_name, "HYROX");
+    }
+
+    #[test]
+    fn test_excluded_events() {
+        let rules = ClassRules::from_settings(&Settings {
+            rename_rules: vec!["Open Box=Open Gym".to_string()],
+            excluded_events: vec!["open gym".to_string(), "Kids".to_string()],
+            ..Settings::default()
+        })
+        .unwrap();
+
+        let classes = rules.apply(vec![
+            class("WOD"),
+            class("Open Box"),
+            class("CrossFit Kids"),
+            class("Open Gym Plus"),
+        ]);
+
+        let names: Vec<_> = classes
+            .iter()
+            .map(|class| class.event_name.as_str())
+            .collect();
+        assert_eq!(names, ["WOD", "Open Gym Plus"]);
+    }
+
+    #[test]
+    fn test_duration_overrides() {
+        let rules = ClassRules::from_settings(&Settings {
+            duration_overrides: vec!["Open Gym=90".to_string(), "kids=45".to_string()],
+            ..Settings::default()
+        })
+        .unwrap();
+        let blank = ClassItem {
+            duration_min: None,
+            ..class("Open Gym")
+        };
+
+        let classes = rules.apply(vec![blank, class("CrossFit Kids"), class("WOD")]);
+
+        assert_eq!(classes[0].duration_min, Some(90));
+        assert_eq!(
+            classes[0].end - classes[0].start,
+            chrono::Duration::minutes(90)
+        );
+        assert_eq!(classes[1].duration_min, Some(45));
+        assert_eq!(classes[2].duration_min, Some(60));
+        assert_eq!(
+            classes[2].end - classes[2].start,
+            chrono::Duration::minutes(60)
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(matches!(
+            rules(&["Beginners WOD"]),
+            Err(RuleError::MalformedRename(_))
+        ));
+        assert!(matches!(
+            rules(&["WOD start="]),
+            Err(RuleError::MalformedRename(_))
+        ));
+        assert!(matches!(
+            rules(&["WOD (start=Beginners WOD"]),
+            Err(RuleError::InvalidPattern(..))
+        ));
+        for rule in ["Open Gym", "Open Gym=long", "Open Gym=0", "=90"] {
+            let result = ClassRules::from_settings(&Settings {
+                duration_overrides: vec![rule.to_string()],
+                ..Settings::default()
+            });
+            assert!(matches!(result, Err(RuleError::MalformedDuration(_))));
+        }
+    }
+}
@@ -1,12 +1,31 @@
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use futures::future::join_all;
 use regex::Regex;
 use scraper::{Html, Selector};
 use thiserror::Error;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{Instrument, Span, field, info_span};
 use url::Url;
 
-use crate::models::ClassItem;
+use crate::health::ScrapeStats;
+use crate::hours::{OpeningHours, parse_opening_hours};
+use crate::kinds::KindRules;
+use crate::models::{
+    ClassItem, Closure, GYM_TIME_ZONE, ParseWarning, dedup_classes, local_today, week_start,
+};
+use crate::otel;
+use crate::rules::{ClassRules, RuleError};
+use crate::selftest::{ScrapePreview, SelfTest};
+use crate::settings::Settings;
+use crate::source::SourceError;
+#[cfg(feature = "headless")]
+use crate::webdriver::WebDriverRenderer;
 
 #[derive(Debug, Error)]
 pub enum ScrapeError {
@@ -18,6 +37,192 @@ pub enum ScrapeError {
     Http(#[from] reqwest::Error),
     #[error("Table with class schedule not found on the page")]
     MissingTable,
+    #[error("The gym's timetable is down for maintenance")]
+    UpstreamMaintenance,
+    #[error("The gym's timetable is rate limiting us; retry in {} seconds", .0.as_secs())]
+    RateLimited(Duration),
+    #[error("Scraping is paused while the service is in maintenance mode")]
+    Paused,
+}
+
+/// Classes read from an agenda page, with the rows that could not be read and the days the
+/// gym announced it is closed.
+#[derive(Debug, Default)]
+pub struct ScrapedWeek {
+    pub classes: Vec<ClassItem>,
+    pub warnings: Vec<ParseWarning>,
+    pub closures: Vec<Closure>,
+}
+
+// A scrape finding less than this share of the previous scrape's classes raises a drift
+// alarm, once the previous scrape found at least `DRIFT_MIN_CLASSES`
+const DRIFT_RATIO: f64 = 0.5;
+const DRIFT_MIN_CLASSES: usize = 4;
+
+// Upstream requests in flight at once unless configured otherwise
+const DEFAULT_MAX_CONCURRENCY: usize = 2;
+
+// Pause after a 429 without a usable `Retry-After`, and the longest pause we accept
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+/// The pause requested by a `Retry-After` value, given in seconds or as an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => (DateTime::parse_from_rfc2822(value)
+            .ok()?
+            .with_timezone(&Utc)
+            - now)
+            .to_std()
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_BACKOFF))
+}
+
+// Phrases of eFitness holding pages, matched case-insensitively
+const MAINTENANCE_MARKERS: [&str; 5] = [
+    "przerwa techniczna",
+    "prace serwisowe",
+    "prace techniczne",
+    "maintenance",
+    "temporarily unavailable",
+];
+
+/// Whether `html` is a maintenance page rather than the timetable.
+fn is_maintenance_page(html: &str) -> bool {
+    let text = html.to_lowercase();
+    MAINTENANCE_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+// Words of the notices the agenda lists instead of classes on days the gym is closed,
+// matched case-insensitively
+const CLOSURE_MARKERS: [&str; 6] = [
+    "nieczynn",
+    "zamknięt",
+    "brak zajęć",
+    "dzień wolny",
+    "closed",
+    "holiday",
+];
+
+/// The notice of a row announcing a closed day: one with a closure marker and no class time.
+fn closure_notice(text: &str) -> Option<String> {
+    let notice = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = notice.to_lowercase();
+    let has_time = lower
+        .split(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .any(|word| word.len() >= 4 && word.contains(':'));
+    (!has_time && CLOSURE_MARKERS.iter().any(|marker| lower.contains(marker))).then_some(notice)
+}
+
+// Description of each detail page by URL, with when it was read
+type DescriptionCache = HashMap<String, (Instant, Option<String>)>;
+
+// The agenda table, and the class name and detail link in its rows
+pub(crate) const TABLE_SELECTOR: &str = "table.calendar_table_agenda";
+pub(crate) const EVENT_NAME_SELECTOR: &str = "p.event_name";
+pub(crate) const CLASS_LINK_SELECTOR: &str = "a.schedule-agenda-link";
+
+// Elements of a class detail page holding its description, most specific first
+const DESCRIPTION_SELECTORS: [&str; 4] = [
+    ".event_description",
+    ".event-description",
+    ".class-description",
+    "[itemprop=description]",
+];
+
+/// The description on a class detail page, its whitespace collapsed.
+fn parse_description(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    DESCRIPTION_SELECTORS.iter().find_map(|selector| {
+        let selector = Selector::parse(selector).ok()?;
+        let element = document.select(&selector).next()?;
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+// Scrapers built from equal connection settings, e.g. after a configuration reload or for
+// `/admin/scrape`, share one client and so its pool of open upstream connections
+static SHARED_CLIENT: Mutex<Option<(ClientSettings, reqwest::Client)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq)]
+struct ClientSettings {
+    proxy_url: Option<Url>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    pool_max_idle: usize,
+    pool_idle_secs: u64,
+    tcp_keepalive_secs: u64,
+}
+
+impl ClientSettings {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            proxy_url: settings.scrape_proxy_url.clone(),
+            proxy_username: settings.scrape_proxy_username.clone(),
+            proxy_password: settings.scrape_proxy_password.clone(),
+            pool_max_idle: settings.scrape_pool_max_idle,
+            pool_idle_secs: settings.scrape_pool_idle_secs,
+            tcp_keepalive_secs: settings.scrape_tcp_keepalive_secs,
+        }
+    }
+
+    fn build(&self) -> Result<reqwest::Client, reqwest::Error> {
+        self.builder()?.build()
+    }
+
+    fn builder(&self) -> Result<reqwest::ClientBuilder, reqwest::Error> {
+        let keepalive =
+            (self.tcp_keepalive_secs > 0).then(|| Duration::from_secs(self.tcp_keepalive_secs));
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(self.pool_max_idle)
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_secs))
+            .tcp_keepalive(keepalive);
+        if let Some(url) = &self.proxy_url {
+            let mut proxy = reqwest::Proxy::all(url.as_str())?;
+            if let Some(username) = &self.proxy_username {
+                let password = self.proxy_password.as_deref().unwrap_or_default();
+                proxy = proxy.basic_auth(username, password);
+            }
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder)
+    }
+}
+
+/// The upstream HTTP client tuned by `settings`, shared with the scrapers built before from the
+/// same connection settings.
+pub fn upstream_client(settings: &Settings) -> Result<reqwest::Client, reqwest::Error> {
+    let wanted = ClientSettings::new(settings);
+    let mut shared = SHARED_CLIENT.lock().expect("shared client lock poisoned");
+    if let Some((current, client)) = shared.as_ref()
+        && *current == wanted
+    {
+        return Ok(client.clone());
+    }
+    let client = wanted.build()?;
+    *shared = Some((wanted, client.clone()));
+    Ok(client)
+}
+
+/// An upstream client tuned by `settings` that reaches `host` only at `addrs` and follows no
+/// redirects, for scraping a host picked by the caller without being steered elsewhere.
+pub fn pinned_client(
+    settings: &Settings,
+    host: &str,
+    addrs: &[SocketAddr],
+) -> Result<reqwest::Client, reqwest::Error> {
+    ClientSettings::new(settings)
+        .builder()?
+        .resolve_to_addrs(host, addrs)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
 }
 
 #[derive(Clone)]
@@ -25,6 +230,25 @@ pub struct CrossfitScraper {
     client: reqwest::Client,
     base_url: Arc<Url>,
     date_regex: Regex,
+    stats: Arc<Mutex<ScrapeStats>>,
+    // Classes found by the latest scrape of each week, to notice sudden drops
+    class_counts: Arc<Mutex<HashMap<NaiveDate, usize>>>,
+    time_zone: Tz,
+    kind_rules: KindRules,
+    class_rules: ClassRules,
+    // Shortest spacing of upstream requests, and when the next one may be sent; shared by
+    // clones so every scrape queues behind the same slot
+    min_interval: Duration,
+    next_request: Arc<Mutex<Option<Instant>>>,
+    // Upstream requests in flight at once, across all clones; unlimited when unset
+    permits: Option<Arc<Semaphore>>,
+    // Descriptions read from class detail pages by URL, kept for `detail_ttl`; detail pages
+    // aren't fetched when unset
+    detail_ttl: Option<Duration>,
+    descriptions: Arc<Mutex<DescriptionCache>>,
+    // Renders pages lacking the table in a headless browser
+    #[cfg(feature = "headless")]
+    browser: Option<WebDriverRenderer>,
 }
 
 impl CrossfitScraper {
@@ -33,24 +257,161 @@ impl CrossfitScraper {
             client: reqwest::Client::new(),
             base_url: Arc::new(base_url),
             date_regex: Regex::new(r"\d{4}-\d{2}-\d{2}").expect("regex compiles"),
+            stats: Arc::default(),
+            class_counts: Arc::default(),
+            time_zone: GYM_TIME_ZONE,
+            kind_rules: KindRules::default(),
+            class_rules: ClassRules::default(),
+            min_interval: Duration::ZERO,
+            next_request: Arc::default(),
+            permits: Some(Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY))),
+            detail_ttl: None,
+            descriptions: Arc::default(),
+            #[cfg(feature = "headless")]
+            browser: None,
+        }
+    }
+
+    /// A scraper of the configured timetable, classifying and correcting classes by the
+    /// configured rules.
+    pub fn from_settings(settings: &Settings) -> Result<Self, SourceError> {
+        let mut scraper =
+            Self::new(settings.scraper_base_url.clone()).with_client(upstream_client(settings)?);
+        #[cfg(feature = "headless")]
+        if let Some(url) = &settings.webdriver_url {
+            let wait = Duration::from_secs(settings.webdriver_wait_secs);
+            scraper = scraper.with_browser(WebDriverRenderer::new(url.clone(), wait));
+        }
+        #[cfg(not(feature = "headless"))]
+        if settings.webdriver_url.is_some() {
+            return Err(SourceError::HeadlessUnsupported);
         }
+        if settings.class_details {
+            let ttl = Duration::from_secs(settings.class_details_cache_hours * 3600);
+            scraper = scraper.with_class_details(ttl);
+        }
+        Ok(scraper
+            .with_kind_rules(KindRules::from_settings(settings).map_err(RuleError::from)?)
+            .with_class_rules(ClassRules::from_settings(settings)?)
+            .with_time_zone(settings.timezone)
+            .with_min_interval(Duration::from_millis(settings.scrape_min_interval_ms))
+            .with_max_concurrency(settings.scrape_max_concurrency))
+    }
+
+    /// Sends upstream requests with `client`, e.g. one from `upstream_client`.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sends every upstream request through `proxy` (HTTP, HTTPS or SOCKS5).
+    pub fn with_proxy(mut self, proxy: reqwest::Proxy) -> Result<Self, reqwest::Error> {
+        self.client = reqwest::Client::builder().proxy(proxy).build()?;
+        Ok(self)
     }
 
-    pub fn get_valid_monday(target: Option<NaiveDate>) -> Result<NaiveDate, ScrapeError> {
-        let today = chrono::Local::now().date_naive();
-        let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+    /// Falls back to rendering the page in `browser` when the fetched HTML has no timetable,
+    /// as with skins building the calendar in JavaScript.
+    #[cfg(feature = "headless")]
+    pub fn with_browser(mut self, browser: WebDriverRenderer) -> Self {
+        self.browser = Some(browser);
+        self
+    }
 
+    /// Follows the detail link of each class for its description, reading each page at most
+    /// once per `ttl`.
+    pub fn with_class_details(mut self, ttl: Duration) -> Self {
+        self.detail_ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_kind_rules(mut self, kind_rules: KindRules) -> Self {
+        self.kind_rules = kind_rules;
+        self
+    }
+
+    pub fn with_class_rules(mut self, class_rules: ClassRules) -> Self {
+        self.class_rules = class_rules;
+        self
+    }
+
+    /// Time zone the timetable's local times are read in.
+    pub fn with_time_zone(mut self, time_zone: Tz) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    /// Spaces upstream requests at least `min_interval` apart; further ones wait their turn.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Sends at most `max` upstream requests at once (unlimited with 0); further ones wait for
+    /// a free permit, so multi-week requests are spread out.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.permits = (max > 0).then(|| Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    // Takes a concurrency permit, then reserves the next request slot and sleeps until it
+    // comes, so a burst of fetches is spread out instead of hitting the gym's site at once
+    async fn wait_for_turn(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.permits {
+            Some(permits) => Some(permits.acquire().await.expect("semaphore is never closed")),
+            None => None,
+        };
+        if self.min_interval.is_zero() {
+            return permit;
+        }
+        let wait = {
+            let mut next = self
+                .next_request
+                .lock()
+                .expect("request slot lock poisoned");
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + self.min_interval);
+            slot - now
+        };
+        if !wait.is_zero() {
+            tracing::debug!(
+                wait_ms = wait.as_millis() as u64,
+                "queuing upstream request"
+            );
+            tokio::time::sleep(wait).await;
+        }
+        permit
+    }
+
+    pub fn stats(&self) -> ScrapeStats {
+        self.stats
+            .lock()
+            .expect("scrape stats lock poisoned")
+            .clone()
+    }
+
+    /// Oldest date the upstream still serves on `today`; earlier weeks are only available from
+    /// the store.
+    pub fn oldest_available_date(today: NaiveDate) -> NaiveDate {
+        today - chrono::Duration::days(14)
+    }
+
+    /// `target`, checked to be a Monday the upstream still serves, or the Monday of `today`.
+    pub fn get_valid_monday(
+        target: Option<NaiveDate>,
+        today: NaiveDate,
+    ) -> Result<NaiveDate, ScrapeError> {
         if let Some(given) = target {
             if given.weekday().num_days_from_monday() != 0 {
                 return Err(ScrapeError::InvalidMonday);
             }
-            let two_weeks_ago = today - chrono::Duration::days(14);
-            if given < two_weeks_ago {
+            if given < Self::oldest_available_date(today) {
                 return Err(ScrapeError::TooOld);
             }
             Ok(given)
         } else {
-            Ok(monday)
+            Ok(week_start(today))
         }
     }
 
@@ -65,14 +426,14 @@ impl CrossfitScraper {
         if start_parts.len() != 2 || end_parts.len() != 2 {
             return None;
         }
-        let start_hour = start_parts[0].parse::<i32>().ok()?;
-        let start_min = start_parts[1].parse::<i32>().ok()?;
-        let end_hour = end_parts[0].parse::<i32>().ok()?;
-        let end_min = end_parts[1].parse::<i32>().ok()?;
-
-        let start_total = start_hour * 60 + start_min;
-        let end_total = end_hour * 60 + end_min;
-        (end_total - start_total).try_into().ok()
+        // Bounded to times of day (`24:00` included, but no later), so numbers like
+        // `99999999:00` can't overflow
+        let minutes = |parts: &[&str]| {
+            let hour = parts[0].parse::<u32>().ok()?;
+            let minute = parts[1].parse::<u32>().ok()?;
+            (hour < 24 && minute < 60 || hour == 24 && minute == 0).then(|| hour * 60 + minute)
+        };
+        minutes(&end_parts)?.checked_sub(minutes(&start_parts)?)
     }
 
     fn parse_agenda_date(&self, text: &str) -> Option<NaiveDate> {
@@ -80,15 +441,118 @@ impl CrossfitScraper {
         NaiveDate::parse_from_str(caps.as_str(), "%Y-%m-%d").ok()
     }
 
+    /// Time left before the upstream may be asked again after a 429.
+    fn backoff_remaining(&self) -> Option<Duration> {
+        let stats = self.stats.lock().expect("scrape stats lock poisoned");
+        let remaining = (stats.backoff_until? - Utc::now()).to_std().ok()?;
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Raises a drift alarm when the week's table had rows but no class could be read, or far
+    /// fewer classes than the previous scrape of the week; the most likely cause is upstream
+    /// markup the selectors no longer match.
+    fn check_drift(&self, monday: NaiveDate, rows: usize, classes: usize) {
+        let previous = {
+            let mut counts = self
+                .class_counts
+                .lock()
+                .expect("class counts lock poisoned");
+            counts.retain(|week, _| *week >= monday - chrono::Duration::weeks(8));
+            counts.insert(monday, classes)
+        };
+        let drift = if classes == 0 && rows > 0 {
+            Some(format!(
+                "no classes read from {rows} table rows of the week of {monday}"
+            ))
+        } else {
+            previous
+                .filter(|&previous| {
+                    previous >= DRIFT_MIN_CLASSES
+                        && (classes as f64) < previous as f64 * DRIFT_RATIO
+                })
+                .map(|previous| {
+                    format!("{classes} classes in the week of {monday}, down from {previous}")
+                })
+        };
+
+        let mut stats = self.stats.lock().expect("scrape stats lock poisoned");
+        if let Some(drift) = &drift {
+            tracing::error!(%monday, rows, classes, "parser drift: {drift}");
+            stats.drift_alarms += 1;
+        }
+        stats.drift = drift;
+    }
+
     async fn fetch_html(&self, url: &Url) -> Result<String, ScrapeError> {
-        let response = self
-            .client
-            .get(url.as_str())
-            .send()
-            .await?
-            .error_for_status()?;
-        let body = response.text().await?;
-        Ok(body)
+        // Every scrape shares the pause, background refreshes included
+        if let Some(remaining) = self.backoff_remaining() {
+            return Err(ScrapeError::RateLimited(remaining));
+        }
+        let _permit = self.wait_for_turn().await;
+        let span = info_span!(
+            "scrape",
+            otel.kind = "client",
+            url = %url,
+            http.status_code = field::Empty,
+            duration_ms = field::Empty,
+            otel.status_code = field::Empty,
+        );
+        let started = Instant::now();
+        let result = async {
+            let mut request = self.client.get(url.as_str());
+            // Lets upstream logs and traces be correlated with the API request
+            if let Some(traceparent) = otel::traceparent(&Span::current()) {
+                request = request.header("traceparent", traceparent);
+            }
+            if let Some(id) = otel::current_request_id() {
+                request = request.header("x-request-id", id);
+            }
+            let response = request.send().await?;
+            Span::current().record("http.status_code", response.status().as_u16());
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, Utc::now()))
+                    .unwrap_or(DEFAULT_BACKOFF);
+                return Err(ScrapeError::RateLimited(retry_after));
+            }
+            if let Err(err) = response.error_for_status_ref()
+                && response.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE
+            {
+                let body = response.text().await?;
+                return Err(if is_maintenance_page(&body) {
+                    ScrapeError::UpstreamMaintenance
+                } else {
+                    err.into()
+                });
+            }
+            let body = response.error_for_status()?.text().await?;
+            Ok(body)
+        }
+        .instrument(span.clone())
+        .await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        span.record("duration_ms", duration_ms);
+        let mut stats = self.stats.lock().expect("scrape stats lock poisoned");
+        stats.last_latency_ms = Some(duration_ms);
+        match &result {
+            Ok(_) => stats.activity.succeeded(),
+            Err(err) => {
+                span.record("otel.status_code", "error");
+                stats.activity.failed(err);
+                if let ScrapeError::RateLimited(retry_after) = err {
+                    stats.rate_limited += 1;
+                    stats.backoff_until = Some(Utc::now() + *retry_after);
+                    tracing::warn!(
+                        retry_after_secs = retry_after.as_secs(),
+                        "upstream rate limited scrapes, pausing"
+                    );
+                }
+            }
+        }
+        result
     }
 
     fn resolve_location(&self, html: &str) -> Option<String> {
@@ -119,6 +583,13 @@ impl CrossfitScraper {
         Some(address)
     }
 
+    /// The opening hours listed on the club's contact page.
+    pub async fn fetch_opening_hours(&self) -> Result<Vec<OpeningHours>, ScrapeError> {
+        let url = self.base_url.join("kontakt").unwrap();
+        let html = self.fetch_html(&url).await?;
+        Ok(parse_opening_hours(&html))
+    }
+
     pub async fn fetch_location(&self) -> Option<String> {
         let html = self
             .fetch_html(&self.base_url)
@@ -128,25 +599,153 @@ impl CrossfitScraper {
         self.resolve_location(&html)
     }
 
-    pub async fn fetch_timetable(
+    /// Scrapes the week of `start_date` (the current week by default), keeping the warnings
+    /// about rows that could not be read.
+    pub async fn fetch_week_page(
         &self,
         start_date: Option<NaiveDate>,
         location: Option<String>,
-    ) -> Result<Vec<ClassItem>, ScrapeError> {
-        let monday = Self::get_valid_monday(start_date)?;
-
-        let url = Url::parse_with_params(
-            &format!("{}/kalendarz-zajec", self.base_url),
-            &[("day", monday.to_string()), ("view", "Agenda".to_string())],
-        )
-        .unwrap();
+    ) -> Result<ScrapedWeek, ScrapeError> {
+        let monday = Self::get_valid_monday(start_date, local_today(self.time_zone))?;
+        let url = self.week_url(monday);
 
         let html = self.fetch_html(&url).await?;
         let loc = match location {
             Some(loc) => Some(loc),
             None => self.fetch_location().await,
         };
-        self.parse_timetable_html(&html, monday, loc, &url)
+        let page = self.parse_timetable_page(&html, monday, loc.clone(), &url);
+        #[cfg(feature = "headless")]
+        let page = match &self.browser {
+            Some(browser) if matches!(page, Err(ScrapeError::MissingTable)) => {
+                let _permit = self.wait_for_turn().await;
+                match browser.render(&url).await {
+                    Ok(html) => self.parse_timetable_page(&html, monday, loc, &url),
+                    Err(err) => {
+                        tracing::warn!(error = %err, %url, "headless browser fallback failed");
+                        page
+                    }
+                }
+            }
+            _ => page,
+        };
+        let mut week = page?;
+        self.add_descriptions(&mut week.classes, &url).await;
+        Ok(week)
+    }
+
+    // Classes without a detail link carry the agenda's URL and are left as they are
+    async fn add_descriptions(&self, classes: &mut [ClassItem], agenda_url: &Url) {
+        let Some(ttl) = self.detail_ttl else {
+            return;
+        };
+        let urls: BTreeSet<&str> = classes
+            .iter()
+            .map(|class| class.source_url.as_str())
+            .filter(|url| *url != agenda_url.as_str())
+            .collect();
+        let lookups = urls
+            .into_iter()
+            .map(|url| async move { (url.to_string(), self.class_description(url, ttl).await) });
+        let descriptions: HashMap<String, Option<String>> =
+            join_all(lookups).await.into_iter().collect();
+        for class in classes {
+            if let Some(description) = descriptions.get(&class.source_url) {
+                class.description = description.clone();
+            }
+        }
+    }
+
+    async fn class_description(&self, url: &str, ttl: Duration) -> Option<String> {
+        if let Some((read_at, description)) = self
+            .descriptions
+            .lock()
+            .expect("description cache lock poisoned")
+            .get(url)
+            && read_at.elapsed() < ttl
+        {
+            return description.clone();
+        }
+        let html = match Url::parse(url) {
+            Ok(url) => self.fetch_html(&url).await,
+            Err(_) => return None,
+        };
+        // A missing description is no reason to fail the week; it's retried on the next scrape
+        let description = match html {
+            Ok(html) => parse_description(&html),
+            Err(err) => {
+                tracing::warn!(error = %err, url, "failed to fetch class details");
+                return None;
+            }
+        };
+        self.descriptions
+            .lock()
+            .expect("description cache lock poisoned")
+            .insert(url.to_string(), (Instant::now(), description.clone()));
+        description
+    }
+
+    /// The raw agenda page of the week of `start_date` (the current week by default) and its
+    /// Monday, e.g. to record it as a parser fixture.
+    pub async fn fetch_week_html(
+        &self,
+        start_date: Option<NaiveDate>,
+    ) -> Result<(NaiveDate, String), ScrapeError> {
+        let monday = Self::get_valid_monday(start_date, local_today(self.time_zone))?;
+        let html = self.fetch_html(&self.week_url(monday)).await?;
+        Ok((monday, html))
+    }
+
+    /// Fetches and parses the agenda of the current week, reporting what its markup holds.
+    pub async fn self_test(&self) -> SelfTest {
+        let monday = week_start(local_today(self.time_zone));
+        let url = self.week_url(monday);
+        let mut report = SelfTest::new(&url, monday);
+        let started = Instant::now();
+        let html = self.fetch_html(&url).await;
+        report.latency_ms = started.elapsed().as_millis() as u64;
+        match html {
+            Ok(html) => {
+                report.inspect(&html);
+                report.parsed(self.parse_timetable_page(&html, monday, None, &url));
+            }
+            Err(err) => report.failed(err),
+        }
+        report
+    }
+
+    /// Scrapes the week of `start_date` (the current week by default) like `fetch_week_page`,
+    /// reporting a failed fetch or an unreadable page instead of returning it.
+    pub async fn preview(
+        &self,
+        start_date: Option<NaiveDate>,
+    ) -> Result<ScrapePreview, ScrapeError> {
+        let monday = Self::get_valid_monday(start_date, local_today(self.time_zone))?;
+        let mut report = ScrapePreview::new(&self.week_url(monday), monday);
+        let started = Instant::now();
+        let week = self.fetch_week_page(Some(monday), None).await;
+        report.duration_ms = started.elapsed().as_millis() as u64;
+        report.parsed(week);
+        Ok(report)
+    }
+
+    /// The agenda page of the week of `monday`.
+    pub fn week_url(&self, monday: NaiveDate) -> Url {
+        let mut url = (*self.base_url).clone();
+        let path = format!("{}/kalendarz-zajec", url.path());
+        url.set_path(&path);
+        url.query_pairs_mut()
+            .append_pair("day", &monday.to_string())
+            .append_pair("view", "Agenda");
+        url
+    }
+
+    pub async fn fetch_timetable(
+        &self,
+        start_date: Option<NaiveDate>,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError> {
+        Ok(self.fetch_week_page(start_date, location).await?.classes)
     }
 
     pub fn parse_timetable_html(
@@ -156,28 +755,54 @@ impl CrossfitScraper {
         location: Option<String>,
         source_url: &Url,
     ) -> Result<Vec<ClassItem>, ScrapeError> {
+        Ok(self
+            .parse_timetable_page(html, expected_monday, location, source_url)?
+            .classes)
+    }
+
+    /// Reads the classes of an agenda page. Rows that can't be read are skipped, logged and
+    /// reported as warnings.
+    pub fn parse_timetable_page(
+        &self,
+        html: &str,
+        expected_monday: NaiveDate,
+        location: Option<String>,
+        source_url: &Url,
+    ) -> Result<ScrapedWeek, ScrapeError> {
         let document = Html::parse_document(html);
-        let table_sel = Selector::parse("table.calendar_table_agenda").unwrap();
+        let table_sel = Selector::parse(TABLE_SELECTOR).unwrap();
         let row_sel = Selector::parse("tr").unwrap();
         let cell_sel = Selector::parse("td").unwrap();
-        let event_sel = Selector::parse("p.event_name").unwrap();
-        let link_sel = Selector::parse("a.schedule-agenda-link").unwrap();
+        let event_sel = Selector::parse(EVENT_NAME_SELECTOR).unwrap();
+        let link_sel = Selector::parse(CLASS_LINK_SELECTOR).unwrap();
 
-        let table = document
-            .select(&table_sel)
-            .next()
-            .ok_or(ScrapeError::MissingTable)?;
+        let Some(table) = document.select(&table_sel).next() else {
+            if is_maintenance_page(html) {
+                return Err(ScrapeError::UpstreamMaintenance);
+            }
+            return Err(ScrapeError::MissingTable);
+        };
 
         let mut current_date: Option<NaiveDate> = None;
         let mut records: Vec<ClassItem> = Vec::new();
+        let mut warnings: Vec<ParseWarning> = Vec::new();
+        let mut closures: Vec<Closure> = Vec::new();
+        let mut rows = 0;
 
         for row in table.select(&row_sel) {
             let cells: Vec<_> = row.select(&cell_sel).collect();
             if cells.is_empty() {
                 continue;
             }
+            rows += 1;
+            let mut skip = |date: Option<NaiveDate>, reason: &str| {
+                let warning = ParseWarning::new(date, reason, &row.html());
+                tracing::warn!(date = ?warning.date, reason, html = %warning.html, "skipped unreadable timetable row");
+                warnings.push(warning);
+            };
 
-            let (time_cell, content_cell) = if cells[0].value().attr("rowspan").is_some() {
+            let has_date_cell = cells[0].value().attr("rowspan").is_some();
+            let (time_cell, content_cell) = if has_date_cell {
                 let date_text = cells[0]
                     .text()
                     .collect::<Vec<_>>()
@@ -186,6 +811,7 @@ impl CrossfitScraper {
                     .to_string();
                 current_date = self.parse_agenda_date(&date_text);
                 if current_date.is_none() {
+                    skip(None, "unreadable date");
                     continue;
                 }
                 if let Some(date_val) = current_date
@@ -199,7 +825,24 @@ impl CrossfitScraper {
                 (cells.first(), cells.get(1))
             };
 
+            let row_text = cells[usize::from(has_date_cell)..]
+                .iter()
+                .flat_map(|cell| cell.text())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Some(date) = current_date
+                && let Some(reason) = closure_notice(&row_text)
+            {
+                // Not a class row, so a closed week doesn't look like parser drift
+                rows -= 1;
+                if !closures.iter().any(|closure| closure.date == date) {
+                    closures.push(Closure { date, reason });
+                }
+                continue;
+            }
+
             let (Some(time_cell), Some(content_cell)) = (time_cell, content_cell) else {
+                skip(current_date, "missing time or class cell");
                 continue;
             };
 
@@ -214,25 +857,32 @@ impl CrossfitScraper {
             let start_time_str = time_range.split('-').next().unwrap_or("").trim();
             let time_parts: Vec<&str> = start_time_str.split(':').collect();
             if time_parts.len() != 2 {
+                skip(current_date, "unreadable start time");
                 continue;
             }
             let hour = time_parts[0].parse::<u32>().ok();
             let minute = time_parts[1].parse::<u32>().ok();
             let Some(date_base) = current_date else {
+                skip(None, "class listed before any date");
                 continue;
             };
             let start_dt = match (hour, minute) {
                 (Some(h), Some(m)) => {
                     let Some(time) = NaiveTime::from_hms_opt(h, m, 0) else {
+                        skip(current_date, "unreadable start time");
                         continue;
                     };
                     NaiveDateTime::new(date_base, time)
                 }
-                _ => continue,
+                _ => {
+                    skip(current_date, "unreadable start time");
+                    continue;
+                }
             };
 
             let event_elem = content_cell.select(&event_sel).next();
             let Some(event_elem) = event_elem else {
+                skip(current_date, "missing class name");
                 continue;
             };
             let event_name = event_elem
@@ -242,6 +892,7 @@ impl CrossfitScraper {
                 .trim()
                 .to_string();
             if event_name.is_empty() {
+                skip(current_date, "missing class name");
                 continue;
             }
 
@@ -262,16 +913,15 @@ impl CrossfitScraper {
                 .select(&link_sel)
                 .next()
                 .and_then(|a| a.value().attr("href"))
-                .map(|href| format!("{}{}", self.base_url, href))
-                .unwrap_or_else(|| source_url.to_string());
+                .and_then(|href| self.base_url.join(href).ok())
+                .unwrap_or_else(|| source_url.clone())
+                .to_string();
 
+            let class = ClassItem::new(start_dt, event_name, coach, duration_min, source_url);
             records.push(ClassItem {
-                date: start_dt,
-                event_name: event_name.clone(),
-                coach,
-                duration_min,
-                source_url,
+                kind: self.kind_rules.classify(&class.event_name),
                 location: location.clone(),
+                ..class.with_time_zone(self.time_zone)
             });
         }
 
@@ -281,20 +931,83 @@ impl CrossfitScraper {
                 .then(a.event_name.cmp(&b.event_name))
                 .then(a.coach.cmp(&b.coach))
         });
-        Ok(records)
+        // The agenda sometimes lists the same class twice
+        dedup_classes(&mut records);
+        // Counted before the rules apply, so excluded classes don't look like parser drift
+        self.check_drift(expected_monday, rows, records.len());
+        Ok(ScrapedWeek {
+            classes: self.class_rules.apply(records),
+            warnings,
+            closures,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
     use super::*;
+    use crate::models::ClassKind;
+
+    // Answers every request with an empty agenda, counting the connections opened
+    async fn counting_server() -> (Url, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let body = r#"<table class="calendar_table_agenda"></table>"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let mut request = Vec::new();
+                    let mut buf = [0; 4096];
+                    while let Ok(read) = stream.read(&mut buf).await
+                        && read > 0
+                    {
+                        request.extend_from_slice(&buf[..read]);
+                        if request.windows(4).any(|end| end == b"\r\n\r\n") {
+                            request.clear();
+                            stream.write_all(response.as_bytes()).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    #[tokio::test]
+    async fn test_scrapers_sharing_a_client_reuse_connections() {
+        let (url, connections) = counting_server().await;
+        let client = ClientSettings::new(&Settings::default()).build().unwrap();
+
+        for _ in 0..2 {
+            let scraper = CrossfitScraper::new(url.clone()).with_client(client.clone());
+            scraper.fetch_week_html(None).await.unwrap();
+        }
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
 
     #[test]
     fn test_get_valid_monday_valid() {
-        let today = chrono::Local::now().date_naive();
-        let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+        let today = NaiveDate::from_ymd_opt(2025, 11, 26).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        assert_eq!(
+            CrossfitScraper::get_valid_monday(Some(monday), today).unwrap(),
+            monday
+        );
         assert_eq!(
-            CrossfitScraper::get_valid_monday(Some(monday)).unwrap(),
+            CrossfitScraper::get_valid_monday(None, today).unwrap(),
             monday
         );
     }
@@ -302,10 +1015,36 @@ mod tests {
     #[test]
     fn test_get_valid_monday_not_monday() {
         let tuesday = NaiveDate::from_ymd_opt(2025, 11, 11).unwrap();
-        let err = CrossfitScraper::get_valid_monday(Some(tuesday)).unwrap_err();
+        let err = CrossfitScraper::get_valid_monday(Some(tuesday), tuesday).unwrap_err();
         assert!(matches!(err, ScrapeError::InvalidMonday));
     }
 
+    #[test]
+    fn test_get_valid_monday_too_old() {
+        let today = NaiveDate::from_ymd_opt(2025, 11, 26).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 10).unwrap();
+        let err = CrossfitScraper::get_valid_monday(Some(monday), today).unwrap_err();
+        assert!(matches!(err, ScrapeError::TooOld));
+    }
+
+    #[test]
+    fn test_current_week_in_gym_time_zone() {
+        // Half past midnight on Monday in Warsaw, the first night after the clocks went back,
+        // is still Sunday in a UTC container; the week follows the configured zone
+        let now = chrono::DateTime::parse_from_rfc3339("2025-10-26T23:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let today = |time_zone: Tz| now.with_timezone(&time_zone).date_naive();
+        assert_eq!(
+            CrossfitScraper::get_valid_monday(None, today(GYM_TIME_ZONE)).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 10, 27).unwrap()
+        );
+        assert_eq!(
+            CrossfitScraper::get_valid_monday(None, today(chrono_tz::UTC)).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 10, 20).unwrap()
+        );
+    }
+
     #[test]
     fn test_parse_time_range() {
         let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
@@ -361,4 +1100,331 @@ mod tests {
         assert_eq!(result[0].event_name, "WOD");
         assert_eq!(result[1].event_name, "HYROX");
     }
+
+    #[test]
+    fn test_parse_time_range_rejects_out_of_range_times() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        assert_eq!(scraper.parse_time_range("23:00 - 24:00"), Some(60));
+        assert_eq!(scraper.parse_time_range("99999999:00 - 99999999:30"), None);
+        assert_eq!(scraper.parse_time_range("07:00 - 06:00"), None);
+        assert_eq!(scraper.parse_time_range("06:75 - 07:00"), None);
+        assert_eq!(scraper.parse_time_range("23:30 - 24:30"), None);
+        assert_eq!(scraper.parse_time_range("24:00 - 24:59"), None);
+    }
+
+    // Deterministic counterpart of the `fuzz/` target, run with every `cargo test`: agenda
+    // markup cut, repeated and spliced with hostile tokens must never panic the parser
+    #[test]
+    fn test_parse_mutated_agenda_never_panics() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let source_url = scraper.week_url(monday);
+        let agenda = r#"<html><body><table class="calendar_table_agenda">
+            <tr><td rowspan="2">Pn, 2025-12-15</td><td>06:00 - 07:00</td>
+            <td><a class="schedule-agenda-link" href="/zajecia/1"><p class="event_name">WOD</p></a>Anna</td></tr>
+            <tr><td>07:00 - 08:00</td><td><p class="event_name">HYROX</p>Jan</td></tr>
+            <tr><td rowspan="1">Wt, 2025-12-16</td><td colspan="2">Klub nieczynny</td></tr>
+            </table></body></html>"#;
+        let tokens = [
+            "<tr>",
+            "</tr>",
+            "<td>",
+            "</td>",
+            r#"<td rowspan="3">"#,
+            r#"<p class="event_name">"#,
+            r#"<a class="schedule-agenda-link" href="//[::1">"#,
+            "</table>",
+            "2025-12-15",
+            "0000-01-01",
+            "9999-12-31",
+            "99999999:99",
+            "99999999:00",
+            "24:00",
+            "-",
+            ":",
+            "Zamknięte",
+            "&amp;",
+            "🏋",
+        ];
+        // Words are runs of letters, digits and colons, e.g. a date or `06:00`
+        let word_regex = Regex::new(r"[\w:]+").unwrap();
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+
+        for _ in 0..2000 {
+            let mut html = agenda.to_string();
+            for _ in 0..=next(4) {
+                let boundaries: Vec<usize> = html
+                    .char_indices()
+                    .map(|(at, _)| at)
+                    .chain([html.len()])
+                    .collect();
+                let start = next(boundaries.len());
+                let end = (start + next(40)).min(boundaries.len() - 1);
+                let (from, to) = (boundaries[start], boundaries[end]);
+                let words: Vec<_> = word_regex
+                    .find_iter(&html)
+                    .map(|word| word.range())
+                    .collect();
+                match next(5) {
+                    0 => html.insert_str(from, tokens[next(tokens.len())]),
+                    1 => html.replace_range(from..to, ""),
+                    2 => html.replace_range(from..to, tokens[next(tokens.len())]),
+                    3 if !words.is_empty() => html.replace_range(
+                        words[next(words.len())].clone(),
+                        tokens[next(tokens.len())],
+                    ),
+                    _ => {
+                        let copy = html[from..to].to_string();
+                        html.insert_str(to, &copy);
+                    }
+                }
+            }
+            let _ = scraper.parse_timetable_page(&html, monday, None, &source_url);
+        }
+    }
+
+    #[test]
+    fn test_parse_timetable_html_renames_classes() {
+        let settings = Settings {
+            rename_rules: vec!["WOD start=Beginners WOD".to_string()],
+            ..Settings::default()
+        };
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap())
+            .with_class_rules(ClassRules::from_settings(&settings).unwrap());
+        let html = r#"<table class="calendar_table_agenda">
+            <tr><td rowspan="1">Pn, 2025-12-15</td><td>06:00 - 07:00</td>
+            <td><p class="event_name">WOD start</p>Anna</td></tr>
+            </table>"#;
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+
+        let result = scraper
+            .parse_timetable_html(
+                html,
+                monday,
+                None,
+                &Url::parse("https://example.com/kalendarz").unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(result[0].event_name, "Beginners WOD");
+        assert_eq!(result[0].kind, ClassKind::Wod);
+    }
+
+    #[test]
+    fn test_parse_closure_rows() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let html = r#"<table class="calendar_table_agenda">
+            <tr><td rowspan="1">Śr, 2025-12-24</td><td>06:00 - 07:00</td>
+            <td><p class="event_name">WOD</p>Anna</td></tr>
+            <tr><td rowspan="1">Cz, 2025-12-25</td><td colspan="2">Klub nieczynny - Boże Narodzenie</td></tr>
+            <tr><td rowspan="1">Pt, 2025-12-26</td><td></td><td>Zamknięte</td></tr>
+            </table>"#;
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 22).unwrap();
+
+        let week = scraper
+            .parse_timetable_page(
+                html,
+                monday,
+                None,
+                &Url::parse("https://example.com/kalendarz").unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(week.classes.len(), 1);
+        assert!(week.warnings.is_empty());
+        assert_eq!(
+            week.closures,
+            vec![
+                Closure {
+                    date: NaiveDate::from_ymd_opt(2025, 12, 25).unwrap(),
+                    reason: "Klub nieczynny - Boże Narodzenie".to_string(),
+                },
+                Closure {
+                    date: NaiveDate::from_ymd_opt(2025, 12, 26).unwrap(),
+                    reason: "Zamknięte".to_string(),
+                },
+            ]
+        );
+        assert!(scraper.stats().drift.is_none());
+        // A class whose notice mentions a closure is still a class
+        assert_eq!(
+            closure_notice("18:00 - 19:00 Open Gym (closed group)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_timetable_html_repeated_row() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let row = r#"<td>06:00 - 07:00</td><td><p class="event_name">WOD</p>Anna</td>"#;
+        let html = format!(
+            r#"<table class="calendar_table_agenda">
+            <tr><td rowspan="2">Pn, 2025-12-15</td>{row}</tr>
+            <tr>{row}</tr>
+            </table>"#
+        );
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let result = scraper
+            .parse_timetable_html(
+                &html,
+                monday,
+                None,
+                &Url::parse("https://example.com/kalendarz").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_timetable_page_collects_warnings() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let html = r#"<table class="calendar_table_agenda">
+            <tr><td rowspan="3">Pn, 2025-12-15</td><td>06:00 - 07:00</td>
+                <td><p class="event_name">WOD</p>Anna</td></tr>
+            <tr><td>o 7 rano</td><td><p class="event_name">HYROX</p>Jan</td></tr>
+            <tr><td>08:00 - 09:00</td><td><span class="name">Open Gym</span></td></tr>
+            <tr><td rowspan="1">Wt, wkrótce</td><td>06:00 - 07:00</td></tr>
+            </table>"#;
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+
+        let week = scraper
+            .parse_timetable_page(
+                html,
+                monday,
+                None,
+                &Url::parse("https://example.com/kalendarz").unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(week.classes.len(), 1);
+        let reasons: Vec<_> = week.warnings.iter().map(|w| w.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "unreadable start time",
+                "missing class name",
+                "unreadable date"
+            ]
+        );
+        assert_eq!(week.warnings[0].date, Some(monday));
+        assert!(week.warnings[1].html.contains("Open Gym"));
+        assert_eq!(week.warnings[2].date, None);
+    }
+
+    #[test]
+    fn test_drift_alarm() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let url = Url::parse("https://example.com/kalendarz").unwrap();
+        let page = |classes: usize, class_markup: &str| {
+            let rows: String = (0..classes)
+                .map(|hour| {
+                    format!(
+                        "<tr><td>{:02}:00 - {:02}:00</td><td>{class_markup}</td></tr>",
+                        6 + hour,
+                        7 + hour
+                    )
+                })
+                .collect();
+            format!(
+                r#"<table class="calendar_table_agenda">
+                <tr><td rowspan="{classes}">Pn, 2025-12-15</td><td>05:00 - 06:00</td>
+                <td>{class_markup}</td></tr>{rows}</table>"#
+            )
+        };
+        let wod = r#"<p class="event_name">WOD</p>Anna"#;
+        let parse = |html: String| {
+            scraper
+                .parse_timetable_page(&html, monday, None, &url)
+                .unwrap()
+        };
+
+        parse(page(5, wod));
+        assert_eq!(scraper.stats().drift, None);
+
+        // A sudden drop compared with the previous scrape of the week
+        parse(page(1, wod));
+        let stats = scraper.stats();
+        assert_eq!(stats.drift_alarms, 1);
+        assert!(stats.drift.unwrap().contains("down from 6"));
+
+        // Rows without a single readable class
+        parse(page(5, r#"<span class="name">WOD</span>"#));
+        assert_eq!(scraper.stats().drift_alarms, 2);
+
+        // A normal scrape clears the alarm but keeps the count
+        parse(page(3, wod));
+        let stats = scraper.stats();
+        assert_eq!(stats.drift, None);
+        assert_eq!(stats.drift_alarms, 2);
+    }
+
+    #[test]
+    fn test_parse_description() {
+        let html = r#"<main>
+            <h1>WOD</h1>
+            <div itemprop="description">Fallback</div>
+            <div class="event-description">
+                Strength   and
+                conditioning.
+            </div>
+        </main>"#;
+
+        assert_eq!(
+            parse_description(html).as_deref(),
+            Some("Strength and conditioning.")
+        );
+        assert_eq!(
+            parse_description("<div class=\"event_description\"> </div>"),
+            None
+        );
+        assert_eq!(parse_description("<p>No description</p>"), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2025-11-24T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Mon, 24 Nov 2025 10:05:00 GMT", now),
+            Some(Duration::from_secs(300))
+        );
+        // A date already past allows an immediate retry; absurd pauses are capped
+        assert_eq!(
+            parse_retry_after("Mon, 24 Nov 2025 09:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("86400", now), Some(MAX_BACKOFF));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_parse_maintenance_page() {
+        let scraper = CrossfitScraper::new(Url::parse("https://example.com").unwrap());
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let url = Url::parse("https://example.com/kalendarz").unwrap();
+
+        let holding =
+            "<html><body><h1>Przerwa techniczna</h1><p>Zapraszamy wkrótce</p></body></html>";
+        let err = scraper
+            .parse_timetable_html(holding, monday, None, &url)
+            .unwrap_err();
+        assert!(matches!(err, ScrapeError::UpstreamMaintenance));
+
+        let err = scraper
+            .parse_timetable_html("<html><body></body></html>", monday, None, &url)
+            .unwrap_err();
+        assert!(matches!(err, ScrapeError::MissingTable));
+    }
 }
@@ -0,0 +1,248 @@
+//! A diagnostic of the live agenda page, for when the feed suddenly goes empty: what the
+//! page's markup holds and what the parser made of it. Also a preview of what the parser reads
+//! from another eFitness instance, for trying out a gym before configuring it.
+
+use chrono::NaiveDate;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::models::{ClassItem, Closure, ParseWarning};
+use crate::scraper::{
+    CLASS_LINK_SELECTOR, EVENT_NAME_SELECTOR, ScrapeError, ScrapedWeek, TABLE_SELECTOR,
+};
+
+// Rows of the agenda, and the cells starting a day (spanning its rows)
+const ROW_SELECTOR: &str = "table.calendar_table_agenda tr";
+const DATE_CELL_SELECTOR: &str = "table.calendar_table_agenda td[rowspan]";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SelectorMatch {
+    #[schema(example = "p.event_name")]
+    pub selector: String,
+    /// Elements of the page it matched
+    pub matches: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct SelfTest {
+    /// Whether the page was fetched and read into at least one class or closure
+    pub ok: bool,
+    /// The agenda page of the current week
+    pub url: String,
+    #[schema(value_type = String, format = "date")]
+    pub monday: NaiveDate,
+    pub latency_ms: u64,
+    /// Size of the fetched page; zero when the fetch failed
+    pub bytes: usize,
+    pub selectors: Vec<SelectorMatch>,
+    /// Table rows with cells, class rows and closures alike
+    pub rows: usize,
+    /// Text of each date cell as found, e.g. `Pn, 2025-11-24`
+    pub dates: Vec<String>,
+    pub classes: usize,
+    pub closures: usize,
+    /// Rows left out as unreadable
+    pub warnings: Vec<ParseWarning>,
+    /// Why the page couldn't be fetched or read
+    pub error: Option<String>,
+}
+
+impl SelfTest {
+    pub fn new(url: &Url, monday: NaiveDate) -> Self {
+        Self {
+            ok: false,
+            url: url.to_string(),
+            monday,
+            latency_ms: 0,
+            bytes: 0,
+            selectors: Vec::new(),
+            rows: 0,
+            dates: Vec::new(),
+            classes: 0,
+            closures: 0,
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Records what the markup of `html` holds, before it's parsed.
+    pub fn inspect(&mut self, html: &str) {
+        let document = Html::parse_document(html);
+        let select = |selector: &str| {
+            let selector = Selector::parse(selector).expect("self-test selectors are valid");
+            document.select(&selector).collect::<Vec<_>>()
+        };
+        self.bytes = html.len();
+        self.selectors = [
+            TABLE_SELECTOR,
+            ROW_SELECTOR,
+            DATE_CELL_SELECTOR,
+            EVENT_NAME_SELECTOR,
+            CLASS_LINK_SELECTOR,
+        ]
+        .into_iter()
+        .map(|selector| SelectorMatch {
+            selector: selector.to_string(),
+            matches: select(selector).len(),
+        })
+        .collect();
+        let cell = Selector::parse("td").expect("self-test selectors are valid");
+        self.rows = select(ROW_SELECTOR)
+            .iter()
+            .filter(|row| row.select(&cell).next().is_some())
+            .count();
+        self.dates = select(DATE_CELL_SELECTOR)
+            .iter()
+            .map(|cell| cell.text().collect::<String>().trim().to_string())
+            .collect();
+    }
+
+    /// Records the outcome of parsing the page.
+    pub fn parsed(&mut self, week: Result<ScrapedWeek, ScrapeError>) {
+        match week {
+            Ok(week) => {
+                self.classes = week.classes.len();
+                self.closures = week.closures.len();
+                self.warnings = week.warnings;
+                self.ok = self.classes + self.closures > 0;
+                if !self.ok {
+                    self.error = Some("no class or closure could be read".to_string());
+                }
+            }
+            Err(err) => self.failed(err),
+        }
+    }
+
+    pub fn failed(&mut self, err: ScrapeError) {
+        self.ok = false;
+        self.error = Some(err.to_string());
+    }
+}
+
+/// The classes parsed from one week of an eFitness instance that isn't configured.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ScrapePreview {
+    /// Whether at least one class or closure could be read
+    pub ok: bool,
+    /// The agenda page fetched
+    pub url: String,
+    #[schema(value_type = String, format = "date")]
+    pub monday: NaiveDate,
+    /// Time taken to fetch and parse the week, the gym's address included
+    pub duration_ms: u64,
+    pub classes: Vec<ClassItem>,
+    pub closures: Vec<Closure>,
+    /// Rows left out as unreadable
+    pub warnings: Vec<ParseWarning>,
+    /// Why the page couldn't be fetched or read
+    pub error: Option<String>,
+}
+
+impl ScrapePreview {
+    pub fn new(url: &Url, monday: NaiveDate) -> Self {
+        Self {
+            ok: false,
+            url: url.to_string(),
+            monday,
+            duration_ms: 0,
+            classes: Vec::new(),
+            closures: Vec::new(),
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Records the outcome of scraping the week.
+    pub fn parsed(&mut self, week: Result<ScrapedWeek, ScrapeError>) {
+        match week {
+            Ok(week) => {
+                self.ok = !(week.classes.is_empty() && week.closures.is_empty());
+                if !self.ok {
+                    self.error = Some("no class or closure could be read".to_string());
+                }
+                self.classes = week.classes;
+                self.closures = week.closures;
+                self.warnings = week.warnings;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_counts_selector_matches_and_dates() {
+        let html = r#"<table class="calendar_table_agenda">
+            <tr><th>Dzień</th></tr>
+            <tr>
+                <td rowspan="2">Pn, 2025-11-24</td>
+                <td>06:00 - 07:00</td>
+                <td><p class="event_name">WOD</p><a class="schedule-agenda-link" href="/wod">i</a></td>
+            </tr>
+            <tr>
+                <td>07:00 - 08:00</td>
+                <td><p class="event_name">HYROX</p></td>
+            </tr>
+            <tr><td rowspan="1">Wt 25.11</td><td>18:00</td><td>Open Gym</td></tr>
+        </table>"#;
+        let mut report = SelfTest::new(
+            &Url::parse("https://gym.example.com/kalendarz-zajec").unwrap(),
+            "2025-11-24".parse().unwrap(),
+        );
+
+        report.inspect(html);
+
+        let matches: Vec<_> = report.selectors.iter().map(|found| found.matches).collect();
+        assert_eq!(matches, [1, 4, 2, 2, 1]);
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.dates, ["Pn, 2025-11-24", "Wt 25.11"]);
+        assert_eq!(report.bytes, html.len());
+    }
+
+    #[test]
+    fn test_parsed_reports_empty_weeks() {
+        let mut report = SelfTest::new(
+            &Url::parse("https://gym.example.com/").unwrap(),
+            "2025-11-24".parse().unwrap(),
+        );
+
+        report.parsed(Ok(ScrapedWeek::default()));
+        assert!(!report.ok);
+        assert_eq!(
+            report.error.as_deref(),
+            Some("no class or closure could be read")
+        );
+
+        report.parsed(Err(ScrapeError::MissingTable));
+        assert!(!report.ok);
+        assert_eq!(report.error, Some(ScrapeError::MissingTable.to_string()));
+    }
+
+    #[test]
+    fn test_preview_keeps_parsed_classes() {
+        let mut report = ScrapePreview::new(
+            &Url::parse("https://other-gym.example.com/kalendarz-zajec").unwrap(),
+            "2025-11-24".parse().unwrap(),
+        );
+        let week = ScrapedWeek {
+            warnings: vec![ParseWarning::new(None, "missing class name", "<tr></tr>")],
+            closures: vec![Closure {
+                date: "2025-11-25".parse().unwrap(),
+                reason: "Święto".to_string(),
+            }],
+            ..ScrapedWeek::default()
+        };
+
+        report.parsed(Ok(week));
+
+        assert!(report.ok);
+        assert_eq!(report.error, None);
+        assert_eq!(report.closures.len(), 1);
+        assert_eq!(report.warnings[0].reason, "missing class name");
+    }
+}
@@ -0,0 +1,839 @@
+//! The HTTP server: request state, startup and configuration reload, and the router.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::handlers::{
+    create_share, export_google_sheets, export_snapshot, get_attendance, get_changes,
+    get_changes_rss, get_export, get_favorites, get_gym_hours, get_heatmap, get_links,
+    get_maintenance, get_new_class_triggers, get_next_class, get_next_class_badge,
+    get_personal_ical, get_recurring, get_subscribe_qr, get_summary, get_timetable, healthz_detail,
+    healthz_live, healthz_ready, import_snapshot, preview_scrape, record_attendance, revoke_share,
+    root, self_test, set_favorites, set_maintenance, suggest_classes,
+};
+use axum::http::StatusCode;
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
+    serve::ListenerExt,
+};
+use futures::future::try_join_all;
+use tokio::signal::unix::{SignalKind, signal};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower_http::LatencyUnit;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::timeout::TimeoutLayer;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Level, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, fmt};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::auth::require_auth;
+use crate::cache_control::{CacheRules, cache_headers};
+use crate::cron::CronSchedule;
+use crate::email::SmtpMailer;
+use crate::export::{Exporter, ExporterRegistry};
+use crate::feeds::FeedCache;
+use crate::gyms::Gyms;
+use crate::health::{BackgroundTasks, TaskStatuses};
+use crate::ip_filter::{IpFilter, ip_allowlist};
+use crate::limits::limit_uri_length;
+use crate::listener::{self, UnixSocketListener};
+use crate::logging::{JsonFields, JsonFormat, LogFormat};
+use crate::maintenance::{Maintenance, MaintenanceStatus, PausableSource, maintenance_guard};
+use crate::matrix::MatrixNotifier;
+use crate::mqtt::MqttPublisher;
+use crate::notify::{ChangeFilter, Dispatcher};
+use crate::notion::NotionSync;
+use crate::oidc::OidcValidator;
+use crate::openapi::ApiDoc;
+use crate::otel::OtlpLayer;
+use crate::outlook::OutlookSync;
+use crate::push::PushNotifier;
+use crate::rate_limit::{RateLimiter, rate_limit};
+use crate::reload::SwappableRouter;
+use crate::request_id::REQUEST_ID_HEADER;
+use crate::settings::Settings;
+use crate::share::ShareSigner;
+use crate::sheets::GoogleSheets;
+use crate::slack::SlackNotifier;
+use crate::source::{LocationCache, OpeningHoursCache, TimetableSource};
+use crate::storage::ClassStore;
+use crate::telegram::TelegramNotifier;
+use crate::{
+    email, matrix, mqtt, notion, outlook, refresh, reload, reminders, request_id, retention, slack,
+    source, storage, telegram,
+};
+
+const IMPORT_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub settings: Settings,
+    pub source: Arc<dyn TimetableSource>,
+    // Output formats of `/timetable.{format}`
+    pub exporters: Arc<ExporterRegistry>,
+    // The source's address, used for classes when `APP_LOCATION` is unset
+    pub location: Arc<LocationCache>,
+    // The source's opening hours, cached as long as the address
+    pub opening_hours: Arc<OpeningHoursCache>,
+    // Rendered `/timetable.{format}` and `/me/timetable.ical` feeds
+    pub feeds: Arc<FeedCache>,
+    // Gyms described in the gyms file, by id
+    pub gyms: Arc<Gyms>,
+    // Switched through `/admin/maintenance`; the source reads nothing upstream while it's on
+    pub maintenance: Arc<Maintenance>,
+    pub oidc: Option<Arc<OidcValidator>>,
+    pub share: Option<Arc<ShareSigner>>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub ip_filter: Arc<IpFilter>,
+    pub store: Option<Arc<dyn ClassStore>>,
+    pub sheets: Option<Arc<GoogleSheets>>,
+    // Fans out schedule changes detected while recording scraped weeks
+    pub notifier: Arc<Dispatcher>,
+    // Background tasks started with these settings, reported by `/healthz/detail`
+    pub tasks: TaskStatuses,
+}
+
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let settings = Settings::from_env()?;
+
+    let env_filter = if settings.debug { "debug" } else { "info" };
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(env_filter))
+        .with((settings.log_format == LogFormat::Text).then(|| fmt::layer().without_time()))
+        .with(
+            (settings.log_format == LogFormat::Json)
+                .then(|| fmt::layer().fmt_fields(JsonFields).event_format(JsonFormat)),
+        )
+        .with(
+            settings
+                .otlp_endpoint
+                .clone()
+                .map(|endpoint| OtlpLayer::new(endpoint, settings.otlp_service_name.clone())),
+        )
+        .init();
+
+    let (state, tasks) = ServerBuilder::new(settings.clone()).build()?;
+    let swappable = SwappableRouter::new(router(state.clone()));
+    let app = swappable.router();
+    tokio::spawn(reload_on_sighup(swappable, state, tasks));
+
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let tls = tls_paths(&settings)?;
+    if let Some(path) = &settings.unix_socket_path {
+        info!("Starting CrossFit Timetable API on unix:{path}");
+        // `tap_io` makes custom listeners provide `ConnectInfo<SocketAddr>`
+        let listener = UnixSocketListener::bind(path.as_ref())?;
+        axum::serve(listener.tap_io(|_| {}), app).await?;
+        return Ok(());
+    }
+
+    let listeners = listener::bind_tcp(&settings.bind_addr, settings.port)?;
+    try_join_all(listeners.into_iter().map(|listener| {
+        let app = app.clone();
+        async move {
+            let addr = listener.local_addr()?;
+            match tls {
+                Some((cert_path, key_path)) => {
+                    info!("Starting CrossFit Timetable API on https://{addr}");
+                    serve_tls(listener, cert_path, key_path, app).await
+                }
+                None => {
+                    info!("Starting CrossFit Timetable API on {addr}");
+                    Ok(axum::serve(listener, app).await?)
+                }
+            }
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+type TlsPaths<'a> = Option<(&'a String, &'a String)>;
+
+fn tls_paths(settings: &Settings) -> Result<TlsPaths<'_>, Box<dyn std::error::Error>> {
+    let tls = match (&settings.tls_cert_path, &settings.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => Some((cert_path, key_path)),
+        (None, None) => None,
+        _ => return Err("APP_TLS_CERT_PATH and APP_TLS_KEY_PATH must be set together".into()),
+    };
+    if tls.is_some() && settings.unix_socket_path.is_some() {
+        return Err("HTTPS is not supported on APP_UNIX_SOCKET_PATH".into());
+    }
+    Ok(tls)
+}
+
+/// Checks the settings the way startup would, without binding or connecting anything.
+pub async fn check_settings(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    tls_paths(settings)?;
+    IpFilter::new(&settings.ip_allowlist, &settings.trusted_proxies)?;
+    let source = source::from_settings(settings)?;
+    // The scheduled tasks are aborted as soon as the set is dropped
+    build_dispatcher(settings, &source, &mut BackgroundTasks::default())?;
+    OutlookSync::from_settings(settings)?;
+    NotionSync::from_settings(settings)?;
+    GoogleSheets::from_settings(settings)?;
+    Ok(())
+}
+
+/// Builds the request state from settings, for serving the API from another application.
+pub struct ServerBuilder {
+    settings: Settings,
+    source: Option<Arc<dyn TimetableSource>>,
+    exporters: ExporterRegistry,
+}
+
+impl ServerBuilder {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            source: None,
+            exporters: ExporterRegistry::default(),
+        }
+    }
+
+    /// Reads the classes from `source` instead of the configured one.
+    pub fn source(mut self, source: Arc<dyn TimetableSource>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Serves an additional format at `/timetable.{format}`.
+    pub fn exporter(mut self, exporter: impl Exporter + 'static) -> Self {
+        self.exporters.register(exporter);
+        self
+    }
+
+    /// The state for `router`, and the configured background tasks (refreshes, notifiers,
+    /// calendar syncs, reminders), which run until the returned set is dropped. Fails on invalid
+    /// settings, including the default token outside debug mode. Must be called within a
+    /// Tokio runtime.
+    pub fn build(self) -> Result<(AppState, BackgroundTasks), Box<dyn std::error::Error>> {
+        start(self, None)
+    }
+}
+
+// Builds the request state and starts its background tasks, which stop when the returned
+// set is dropped. On reload the timetable source, database connection, OIDC client, share link
+// revocations and rate limiter counters of `previous` are kept where their settings didn't change.
+fn start(
+    builder: ServerBuilder,
+    previous: Option<&AppState>,
+) -> Result<(AppState, BackgroundTasks), Box<dyn std::error::Error>> {
+    let ServerBuilder {
+        settings,
+        source,
+        exporters,
+    } = builder;
+    // Embedding applications may build settings by hand; an internet-facing server with the
+    // published token must not start either way
+    settings.validate()?;
+    // Kept across reloads, so a runtime switch survives unless the setting itself changed
+    let maintenance = match previous {
+        Some(state) => {
+            if state.settings.maintenance_mode != settings.maintenance_mode {
+                state.maintenance.set(MaintenanceStatus {
+                    enabled: settings.maintenance_mode,
+                    message: None,
+                });
+            }
+            Arc::clone(&state.maintenance)
+        }
+        None => Arc::new(Maintenance::new(settings.maintenance_mode)),
+    };
+    let pausable = |source| -> Arc<dyn TimetableSource> {
+        Arc::new(PausableSource::new(source, Arc::clone(&maintenance)))
+    };
+    let mut state = AppState {
+        source: match (source, previous) {
+            (Some(source), _) => pausable(source),
+            (None, Some(state))
+                if state.settings.scraper_base_url == settings.scraper_base_url
+                    && state.settings.scrape_min_interval_ms == settings.scrape_min_interval_ms
+                    && state.settings.scrape_max_concurrency == settings.scrape_max_concurrency
+                    && state.settings.scrape_proxy_url == settings.scrape_proxy_url
+                    && state.settings.scrape_proxy_username == settings.scrape_proxy_username
+                    && state.settings.scrape_proxy_password == settings.scrape_proxy_password
+                    && state.settings.scrape_pool_max_idle == settings.scrape_pool_max_idle
+                    && state.settings.scrape_pool_idle_secs == settings.scrape_pool_idle_secs
+                    && state.settings.scrape_tcp_keepalive_secs
+                        == settings.scrape_tcp_keepalive_secs
+                    && state.settings.class_details == settings.class_details
+                    && state.settings.class_details_cache_hours
+                        == settings.class_details_cache_hours
+                    && state.settings.webdriver_url == settings.webdriver_url
+                    && state.settings.webdriver_wait_secs == settings.webdriver_wait_secs
+                    && state.settings.demo_mode == settings.demo_mode
+                    && state.settings.class_kind_rules == settings.class_kind_rules
+                    && state.settings.rename_rules == settings.rename_rules
+                    && state.settings.excluded_events == settings.excluded_events
+                    && state.settings.duration_overrides == settings.duration_overrides =>
+            {
+                Arc::clone(&state.source)
+            }
+            _ => pausable(source::from_settings(&settings)?),
+        },
+        maintenance: Arc::clone(&maintenance),
+        feeds: Arc::new(FeedCache::new(Duration::from_secs(
+            settings.feed_cache_seconds,
+        ))),
+        gyms: Arc::new(Gyms::load(settings.gyms_file.as_deref())?),
+        exporters: Arc::new(exporters),
+        location: Arc::new(LocationCache::new(Duration::from_secs(
+            settings.location_cache_minutes * 60,
+        ))),
+        opening_hours: Arc::new(OpeningHoursCache::new(Duration::from_secs(
+            settings.location_cache_minutes * 60,
+        ))),
+        oidc: match previous {
+            Some(state) if state.settings.oidc_issuer_url == settings.oidc_issuer_url => {
+                state.oidc.clone()
+            }
+            _ => settings
+                .oidc_issuer_url
+                .clone()
+                .map(|issuer| Arc::new(OidcValidator::new(issuer))),
+        },
+        // Kept while the key is unchanged, so links revoked at runtime stay revoked
+        share: match previous {
+            Some(state) if state.settings.share_signing_key == settings.share_signing_key => {
+                state.share.clone().inspect(|share| {
+                    settings
+                        .revoked_shares
+                        .iter()
+                        .for_each(|id| share.revoke(id));
+                })
+            }
+            _ => settings
+                .share_signing_key
+                .as_deref()
+                .map(|key| Arc::new(ShareSigner::new(key, settings.revoked_shares.clone()))),
+        },
+        rate_limiter: match previous {
+            Some(state)
+                if (
+                    state.settings.rate_limit_requests,
+                    state.settings.rate_limit_window_secs,
+                ) == (
+                    settings.rate_limit_requests,
+                    settings.rate_limit_window_secs,
+                ) =>
+            {
+                state.rate_limiter.clone()
+            }
+            _ => (settings.rate_limit_requests > 0).then(|| {
+                Arc::new(RateLimiter::new(
+                    settings.rate_limit_requests,
+                    Duration::from_secs(settings.rate_limit_window_secs),
+                ))
+            }),
+        },
+        ip_filter: Arc::new(IpFilter::new(
+            &settings.ip_allowlist,
+            &settings.trusted_proxies,
+        )?),
+        // Reconnecting would drop in-flight work, so the database is fixed at startup
+        store: match previous {
+            Some(state) => state.store.clone(),
+            None => settings
+                .database_url
+                .as_deref()
+                .map(storage::connect)
+                .transpose()?,
+        },
+        sheets: GoogleSheets::from_settings(&settings)?.map(Arc::new),
+        notifier: Arc::new(Dispatcher::default()),
+        tasks: TaskStatuses::default(),
+        settings,
+    };
+    let settings = &state.settings;
+    let mut tasks = BackgroundTasks::default();
+
+    // Links revoked through the API before the last restart
+    if let (Some(store), Some(share)) = (&state.store, &state.share) {
+        let (store, share) = (Arc::clone(store), Arc::clone(share));
+        tasks.spawn("share_revocations", async move {
+            match store.revoked_shares().await {
+                Ok(ids) => ids.iter().for_each(|id| share.revoke(id)),
+                Err(err) => warn!(error = %err, "failed to load revoked share links"),
+            }
+        });
+    }
+
+    if let Some(store) = &state.store
+        && settings.retention_days > 0
+    {
+        let (store, retention_days) = (Arc::clone(store), settings.retention_days);
+        tasks.supervise("retention_janitor", move || {
+            retention::run_janitor(Arc::clone(&store), retention_days)
+        });
+    }
+
+    let notifier = Arc::new(build_dispatcher(settings, &state.source, &mut tasks)?);
+
+    if let Some(refresh_cron) = &settings.refresh_cron {
+        let schedule: CronSchedule = refresh_cron
+            .parse()
+            .map_err(|err| format!("Invalid APP_REFRESH_CRON: {err}"))?;
+        let (source, store, location, notifier, timezone) = (
+            Arc::clone(&state.source),
+            state.store.clone(),
+            settings.location.clone(),
+            Arc::clone(&notifier),
+            settings.timezone,
+        );
+        tasks.supervise("refresh", move || {
+            refresh::run_refresh(
+                Arc::clone(&source),
+                store.clone(),
+                location.clone(),
+                Arc::clone(&notifier),
+                schedule.clone(),
+                timezone,
+            )
+        });
+    }
+
+    if let Some(outlook) = OutlookSync::from_settings(settings)? {
+        let (outlook, source, location, weeks, timezone) = (
+            Arc::new(outlook),
+            Arc::clone(&state.source),
+            settings.location.clone(),
+            settings.outlook_sync_weeks,
+            settings.timezone,
+        );
+        let interval = Duration::from_secs(u64::from(settings.outlook_sync_minutes.max(1)) * 60);
+        tasks.supervise("outlook_sync", move || {
+            outlook::run_sync(
+                Arc::clone(&outlook),
+                Arc::clone(&source),
+                location.clone(),
+                weeks,
+                interval,
+                timezone,
+            )
+        });
+    }
+
+    if let Some(notion) = NotionSync::from_settings(settings)? {
+        let (notion, source, location, weeks, timezone) = (
+            Arc::new(notion),
+            Arc::clone(&state.source),
+            settings.location.clone(),
+            settings.notion_sync_weeks,
+            settings.timezone,
+        );
+        let interval = Duration::from_secs(u64::from(settings.notion_sync_minutes.max(1)) * 60);
+        tasks.supervise("notion_sync", move || {
+            notion::run_sync(
+                Arc::clone(&notion),
+                Arc::clone(&source),
+                location.clone(),
+                weeks,
+                interval,
+                timezone,
+            )
+        });
+    }
+
+    if let Some(store) = &state.store
+        && settings.reminder_lead_minutes > 0
+        && !notifier.is_empty()
+    {
+        let (store, source, location, notifier) = (
+            Arc::clone(store),
+            Arc::clone(&state.source),
+            settings.location.clone(),
+            Arc::clone(&notifier),
+        );
+        let (lead_minutes, timezone) = (settings.reminder_lead_minutes, settings.timezone);
+        tasks.supervise("reminders", move || {
+            reminders::run_reminders(
+                Arc::clone(&store),
+                Arc::clone(&source),
+                location.clone(),
+                Arc::clone(&notifier),
+                lead_minutes,
+                timezone,
+            )
+        });
+    }
+
+    state.notifier = notifier;
+    state.tasks = tasks.statuses();
+    Ok((state, tasks))
+}
+
+// Re-reads the settings (config file, secret files, .env) on SIGHUP and swaps in a
+// freshly built router; a configuration that fails to load keeps the current one
+async fn reload_on_sighup(
+    swappable: SwappableRouter,
+    mut state: AppState,
+    mut tasks: BackgroundTasks,
+) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            warn!(error = %err, "cannot listen for SIGHUP, configuration reload disabled");
+            // Keep the background tasks running
+            std::future::pending::<()>().await;
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        let reloaded = Settings::from_env()
+            .map_err(Into::into)
+            .and_then(|settings| start(ServerBuilder::new(settings), Some(&state)));
+        match reloaded {
+            Ok((new_state, new_tasks)) => {
+                let ignored = reload::restart_required(&state.settings, &new_state.settings);
+                if !ignored.is_empty() {
+                    warn!(settings = ?ignored, "changed settings take effect after a restart");
+                }
+                swappable.replace(router(new_state.clone()));
+                // Dropping the old set stops the previous digests, reminders and janitor
+                (state, tasks) = (new_state, new_tasks);
+                info!("reloaded configuration");
+            }
+            Err(err) => {
+                warn!(error = %err, "failed to reload configuration, keeping the current one")
+            }
+        }
+    }
+    drop(tasks);
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(
+    listener: tokio::net::TcpListener,
+    cert_path: &str,
+    key_path: &str,
+    app: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = crate::tls::TlsListener::new(listener, cert_path.into(), key_path.into())?;
+    axum::serve(listener.tap_io(|_| {}), app).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(
+    _listener: tokio::net::TcpListener,
+    _cert_path: &str,
+    _key_path: &str,
+    _app: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err(
+        "APP_TLS_CERT_PATH is set but this build lacks HTTPS support (build with --features tls)"
+            .into(),
+    )
+}
+
+// Registers every configured notification channel and starts its scheduled posts
+fn build_dispatcher(
+    settings: &Settings,
+    source: &Arc<dyn TimetableSource>,
+    tasks: &mut BackgroundTasks,
+) -> Result<Dispatcher, Box<dyn std::error::Error>> {
+    let mut dispatcher = Dispatcher::new(settings.notify_retries, Duration::from_secs(2));
+
+    if let Some(telegram) = TelegramNotifier::from_settings(settings)? {
+        let telegram = Arc::new(telegram);
+        if settings.telegram_weekly_digest {
+            let (telegram, source, location) = (
+                Arc::clone(&telegram),
+                Arc::clone(source),
+                settings.location.clone(),
+            );
+            let (digest_hour, timezone) = (settings.telegram_digest_hour, settings.timezone);
+            tasks.supervise("telegram_digest", move || {
+                telegram::run_weekly_digest(
+                    Arc::clone(&telegram),
+                    Arc::clone(&source),
+                    location.clone(),
+                    digest_hour,
+                    timezone,
+                )
+            });
+        }
+        dispatcher.add(
+            telegram,
+            ChangeFilter::new(
+                &settings.telegram_only_events,
+                &settings.telegram_only_kinds,
+            )?,
+        );
+    }
+    if let Some(push) = PushNotifier::from_settings(settings)? {
+        dispatcher.add(
+            Arc::new(push),
+            ChangeFilter::new(&settings.push_only_events, &settings.push_only_kinds)?,
+        );
+    }
+    if let Some(webhook_url) = settings.slack_webhook_url.clone() {
+        let slack = Arc::new(SlackNotifier::new(webhook_url));
+        if settings.slack_daily_schedule {
+            let schedule: CronSchedule = settings
+                .slack_daily_cron
+                .parse()
+                .map_err(|err| format!("Invalid APP_SLACK_DAILY_CRON: {err}"))?;
+            let (slack, source, location, timezone) = (
+                Arc::clone(&slack),
+                Arc::clone(source),
+                settings.location.clone(),
+                settings.timezone,
+            );
+            tasks.supervise("slack_daily_schedule", move || {
+                slack::run_daily_schedule(
+                    Arc::clone(&slack),
+                    Arc::clone(&source),
+                    location.clone(),
+                    schedule.clone(),
+                    timezone,
+                )
+            });
+        }
+        dispatcher.add(
+            slack,
+            ChangeFilter::new(&settings.slack_only_events, &settings.slack_only_kinds)?,
+        );
+    }
+    if let Some(mailer) = SmtpMailer::from_settings(settings)? {
+        let mailer = Arc::new(mailer);
+        let schedule: CronSchedule = settings
+            .email_digest_schedule
+            .parse()
+            .map_err(email::EmailError::InvalidSchedule)?;
+        let (digest_mailer, source, location) = (
+            Arc::clone(&mailer),
+            Arc::clone(source),
+            settings.location.clone(),
+        );
+        let (include_diff, timezone) = (settings.email_digest_include_diff, settings.timezone);
+        tasks.supervise("email_digest", move || {
+            email::run_digest(
+                Arc::clone(&digest_mailer),
+                Arc::clone(&source),
+                location.clone(),
+                schedule.clone(),
+                include_diff,
+                timezone,
+            )
+        });
+        if settings.email_change_alerts {
+            dispatcher.add(
+                mailer,
+                ChangeFilter::new(&settings.email_only_events, &settings.email_only_kinds)?,
+            );
+        }
+    }
+
+    if let Some(mqtt) = MqttPublisher::from_settings(settings)? {
+        let mqtt = Arc::new(mqtt);
+        let (publisher, source, location, timezone) = (
+            Arc::clone(&mqtt),
+            Arc::clone(source),
+            settings.location.clone(),
+            settings.timezone,
+        );
+        let interval = Duration::from_secs(u64::from(settings.mqtt_refresh_minutes.max(1)) * 60);
+        tasks.supervise("mqtt_publisher", move || {
+            mqtt::run_publisher(
+                Arc::clone(&publisher),
+                Arc::clone(&source),
+                location.clone(),
+                interval,
+                timezone,
+            )
+        });
+        dispatcher.add(
+            mqtt,
+            ChangeFilter::new(&settings.mqtt_only_events, &settings.mqtt_only_kinds)?,
+        );
+    }
+    if let Some(matrix) = MatrixNotifier::from_settings(settings)? {
+        let matrix = Arc::new(matrix);
+        if settings.matrix_daily_summary {
+            let schedule: CronSchedule = settings
+                .matrix_daily_cron
+                .parse()
+                .map_err(|err| format!("Invalid APP_MATRIX_DAILY_CRON: {err}"))?;
+            let (matrix, source, location, timezone) = (
+                Arc::clone(&matrix),
+                Arc::clone(source),
+                settings.location.clone(),
+                settings.timezone,
+            );
+            tasks.supervise("matrix_daily_summary", move || {
+                matrix::run_daily_summary(
+                    Arc::clone(&matrix),
+                    Arc::clone(&source),
+                    location.clone(),
+                    schedule.clone(),
+                    timezone,
+                )
+            });
+        }
+        dispatcher.add(
+            matrix,
+            ChangeFilter::new(&settings.matrix_only_events, &settings.matrix_only_kinds)?,
+        );
+    }
+
+    Ok(dispatcher)
+}
+
+/// The complete API with its middleware, ready to be served or nested into another axum
+/// application, e.g. `Router::new().nest("/gym", router(state))`.
+pub fn router(state: AppState) -> Router {
+    let trace_layer = TraceLayer::new_for_http()
+        .make_span_with(request_id::make_span)
+        .on_response(
+            DefaultOnResponse::new()
+                .level(Level::INFO)
+                .latency_unit(LatencyUnit::Millis),
+        );
+
+    let api = Router::new()
+        .route("/timetable", get(get_timetable))
+        .route("/timetable.{format}", get(get_export))
+        .route("/timetable/links", get(get_links))
+        .route("/shortcuts/next", get(get_next_class))
+        .route("/badge/next-class.svg", get(get_next_class_badge))
+        .route("/summary", get(get_summary))
+        .route("/suggest", post(suggest_classes))
+        .route("/recurring", get(get_recurring))
+        .route("/stats/heatmap", get(get_heatmap))
+        .route("/gym/hours", get(get_gym_hours))
+        .route("/history/changes", get(get_changes))
+        .route("/changes.rss", get(get_changes_rss))
+        .route("/triggers/new-classes", get(get_new_class_triggers))
+        .route("/me/favorites", get(get_favorites).post(set_favorites))
+        .route("/me/timetable.ical", get(get_personal_ical))
+        .route("/me/attendance", get(get_attendance))
+        .route("/me/attendance/{class_id}", post(record_attendance))
+        .route("/healthz/detail", get(healthz_detail))
+        .route("/admin/export", get(export_snapshot))
+        .route("/admin/selftest", get(self_test))
+        .route("/admin/scrape", get(preview_scrape))
+        .route(
+            "/admin/maintenance",
+            get(get_maintenance).put(set_maintenance),
+        )
+        .route("/admin/export/google-sheets", post(export_google_sheets))
+        .route(
+            "/admin/import",
+            // Archives of a long-running instance easily exceed the default 2 MB body limit
+            post(import_snapshot).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/share", post(create_share))
+        .route("/share/{id}", delete(revoke_share))
+        .route("/subscribe.qr.png", get(get_subscribe_qr))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit));
+
+    let mut router = Router::new().route("/", get(root)).merge(api);
+
+    if state.settings.enable_swagger {
+        let openapi = ApiDoc::openapi();
+        let swagger = SwaggerUi::new("/docs").url("/openapi.json", openapi);
+        let swagger = Router::from(swagger);
+        if state.oidc.is_some() {
+            // With SSO enabled, the API documentation is only available to authenticated users
+            router = router
+                .merge(swagger.layer(middleware::from_fn_with_state(state.clone(), require_auth)));
+        } else {
+            router = router.merge(swagger);
+        }
+    }
+
+    // Rules were checked by `Settings::validate` at startup
+    let cache_rules = CacheRules::from_settings(&state.settings).unwrap_or_else(|err| {
+        warn!(error = %err, "ignoring cache control rules");
+        CacheRules::default()
+    });
+    if !cache_rules.is_empty() {
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(cache_rules),
+            cache_headers,
+        ));
+    }
+
+    let mut router = router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_guard,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), ip_allowlist))
+        // Health probes are registered after the allowlist so orchestrators can always reach them
+        .route("/healthz/live", get(healthz_live))
+        .route("/healthz/ready", get(healthz_ready))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            limit_uri_length,
+        ))
+        .layer(DefaultBodyLimit::max(state.settings.max_body_bytes));
+
+    if state.settings.max_concurrent_requests > 0 {
+        // One semaphore shared by every route, unlike `ConcurrencyLimitLayer` which is per clone
+        router = router.layer(GlobalConcurrencyLimitLayer::new(
+            state.settings.max_concurrent_requests,
+        ));
+    }
+    if state.settings.request_timeout_secs > 0 {
+        // Outside the concurrency limit so requests waiting for a slot time out as well
+        router = router.layer(TimeoutLayer::with_status_code(
+            StatusCode::GATEWAY_TIMEOUT,
+            Duration::from_secs(state.settings.request_timeout_secs),
+        ));
+    }
+
+    router
+        .with_state(state)
+        .layer(middleware::from_fn(request_id::request_id_in_errors))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(trace_layer)
+        // Outermost, so the id exists before the trace span is created
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload_keeps_revoked_share_links() {
+        let settings = Settings {
+            auth_token: "test-token-123".to_string(),
+            share_signing_key: Some("share-key".to_string()),
+            revoked_shares: vec!["configured".to_string()],
+            ..Settings::default()
+        };
+        let (state, _tasks) = start(ServerBuilder::new(settings.clone()), None).unwrap();
+        state.share.as_ref().unwrap().revoke("runtime");
+
+        let reloaded = Settings {
+            revoked_shares: vec!["added".to_string()],
+            ..settings.clone()
+        };
+        let (state, _tasks) = start(ServerBuilder::new(reloaded), Some(&state)).unwrap();
+
+        let share = state.share.as_ref().unwrap();
+        assert!(share.is_revoked("configured"));
+        assert!(share.is_revoked("runtime"));
+        assert!(share.is_revoked("added"));
+
+        // A new key invalidates every earlier link anyway
+        let rotated = Settings {
+            share_signing_key: Some("new-key".to_string()),
+            ..settings
+        };
+        let (state, _tasks) = start(ServerBuilder::new(rotated), Some(&state)).unwrap();
+        assert!(!state.share.as_ref().unwrap().is_revoked("runtime"));
+    }
+}
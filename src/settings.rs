@@ -1,15 +1,90 @@
-use config::{Config, ConfigError, Environment};
-use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use config::{Config, ConfigError, Environment, File};
+use serde::{Deserialize, Deserializer, Serialize};
 use url::Url;
 
+use crate::cache_control::CacheRules;
+use crate::i18n::Lang;
+use crate::logging::LogFormat;
+use crate::models::GYM_TIME_ZONE;
+use crate::preferences::TokenPreferences;
+
+/// The published default token, refused outside debug mode.
+pub const DEFAULT_AUTH_TOKEN: &str = "default-token-change-me";
+
+/// Longest lifetime of a share link, ten years.
+pub const MAX_SHARE_TTL_HOURS: u32 = 24 * 365 * 10;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Settings {
     pub scraper_base_url: Url,
+    // Upstream requests are sent at least this many milliseconds apart, queuing the rest;
+    // 0 sends them right away
+    pub scrape_min_interval_ms: u64,
+    // Upstream requests in flight at once, e.g. for the weeks of one request; 0 means unlimited
+    pub scrape_max_concurrency: usize,
+    // HTTP(S) or SOCKS5 proxy for upstream requests, e.g. `socks5h://proxy:1080` (direct when unset)
+    pub scrape_proxy_url: Option<Url>,
+    pub scrape_proxy_username: Option<String>,
+    pub scrape_proxy_password: Option<String>,
+    // Idle upstream connections kept open for reuse, and for how many seconds
+    pub scrape_pool_max_idle: usize,
+    pub scrape_pool_idle_secs: u64,
+    // Interval of TCP keepalive probes on upstream connections; 0 sends none
+    pub scrape_tcp_keepalive_secs: u64,
+    // Cron expression in local time, see `cron::CronSchedule`, scraping the current and next
+    // week in the background (disabled when unset)
+    pub refresh_cron: Option<String>,
+    // Follow each class's detail link for its description (one more upstream request per class)
+    pub class_details: bool,
+    // How long a detail page is reused before it's read again
+    pub class_details_cache_hours: u64,
+    // WebDriver server rendering agenda pages that lack the table, e.g. `http://localhost:9515`
+    // for chromedriver (needs the `headless` feature)
+    pub webdriver_url: Option<Url>,
+    // Longest wait for the rendered page to show the table
+    pub webdriver_wait_secs: u64,
+    // Serve a generated weekly schedule instead of scraping `scraper_base_url`
+    pub demo_mode: bool,
+    // Start in maintenance mode (switchable at runtime through `/admin/maintenance`)
+    pub maintenance_mode: bool,
+    // IANA zone of the gym's wall-clock times; "today", Mondays and schedules are reckoned in
+    // it rather than in the server's own zone
+    pub timezone: Tz,
     pub debug: bool,
+    // `text` for humans, `json` for one JSON object per line with timestamps (Loki, Elastic)
+    pub log_format: LogFormat,
+    // OpenTelemetry collector receiving spans over OTLP/HTTP, e.g. `http://otel-collector:4318/`
+    pub otlp_endpoint: Option<Url>,
+    pub otlp_service_name: String,
     pub auth_token: String,
+    // Token rotation: the previous token keeps working until `previous_auth_token_valid_until`
+    // (or indefinitely when no end is set) so subscriptions can be migrated gradually
+    pub previous_auth_token: Option<String>,
+    pub previous_auth_token_valid_until: Option<DateTime<Utc>>,
+    // HTTP Basic credentials for calendar clients that can't send Bearer tokens
+    pub basic_auth_username: Option<String>,
+    pub basic_auth_password: Option<String>,
+    // Serve /timetable and the other read routes without authentication (admin routes stay
+    // protected)
+    pub public_read: bool,
     pub enable_swagger: bool,
+    // Addresses the server listens on, e.g. `127.0.0.1,::1`; `::` takes IPv6 and IPv4 alike
+    #[serde(deserialize_with = "ip_list")]
+    pub bind_addr: Vec<IpAddr>,
     pub port: u16,
+    // Listen on this Unix domain socket instead of TCP (for a reverse proxy on the same host)
+    pub unix_socket_path: Option<String>,
+    // PEM certificate chain and private key; HTTPS is served when both are set (needs the `tls` feature)
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
     pub location: Option<String>,
+    // How long the address looked up on the gym's page is reused when `location` is unset
+    pub location_cache_minutes: u64,
     // Geographic coordinates for CrossFit 2.0 Rzeszów
     // Source: Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland
     // Used for X-APPLE-STRUCTURED-LOCATION in iCal exports
@@ -20,34 +95,537 @@ pub struct Settings {
     pub gym_title: String,
     // CrossFit gym location address (hardcoded)
     pub gym_location: String,
+    // TOML file describing each gym, see `gyms::Gyms` (`gyms.toml` when present if unset)
+    pub gyms_file: Option<String>,
+    // Extra `pattern=kind` rules classifying classes by name, checked before the built-in ones
+    #[serde(default, deserialize_with = "string_list")]
+    pub class_kind_rules: Vec<String>,
+    // Semicolon-separated `pattern=new name` rules renaming classes whose whole name matches the
+    // case-insensitive regex, e.g. `WOD start=Beginners WOD`
+    #[serde(default, deserialize_with = "semicolon_list")]
+    pub rename_rules: Vec<String>,
+    // Class names (matched whole, ignoring case) or class types left out of every response
+    #[serde(default, deserialize_with = "string_list")]
+    pub excluded_events: Vec<String>,
+    // `class=minutes` entries replacing the listed (or missing) length of classes with that
+    // name or type, e.g. `Open Gym=90,kids=45`
+    #[serde(default, deserialize_with = "string_list")]
+    pub duration_overrides: Vec<String>,
+    // Minutes before each class a reminder fires in iCalendar exports (no reminders when unset)
+    pub ical_alarm_minutes: Option<u32>,
+    // Name calendar apps show for iCalendar feeds
+    pub ical_calendar_name: String,
+    // Put before each class name in iCalendar feeds
+    pub ical_event_prefix: String,
+    // `#rrggbb` color calendar apps show iCalendar feeds in (their own choice when unset)
+    pub ical_color: Option<String>,
+    // Language of day names, class types and descriptions in the HTML and iCalendar feeds and
+    // `/summary`, `pl` or `en`
+    pub lang: Lang,
+    // How long a rendered feed answers `HEAD` and repeated `GET` requests; 0 renders each
+    pub feed_cache_seconds: u64,
+    // OpenID Connect issuer (e.g. Authentik/Keycloak realm URL)
+    // When set, Bearer tokens are also accepted if the provider's userinfo endpoint accepts them
+    pub oidc_issuer_url: Option<Url>,
+    // Externally visible base URL, used when building links handed out to clients
+    pub public_url: Option<Url>,
+    // HMAC key for signed calendar subscription links (sharing disabled when unset)
+    pub share_signing_key: Option<String>,
+    pub share_ttl_hours: u32,
+    // Share link ids rejected even if their signature is valid
+    #[serde(default, deserialize_with = "string_list")]
+    pub revoked_shares: Vec<String>,
+    // CIDR ranges allowed to reach the service (empty allows everyone)
+    #[serde(default, deserialize_with = "string_list")]
+    pub ip_allowlist: Vec<String>,
+    // Reverse proxies whose X-Forwarded-For header is trusted
+    #[serde(default, deserialize_with = "string_list")]
+    pub trusted_proxies: Vec<String>,
+    // Persistence of scraped classes: `memory:`, `sqlite://<path>` or `postgres://...` (disabled when unset)
+    pub database_url: Option<String>,
+    // Stored scrapes and change history older than this many days are pruned; 0 keeps everything
+    pub retention_days: u32,
+    // Requests allowed per token (or client IP) within the window; 0 disables rate limiting
+    pub rate_limit_requests: u32,
+    pub rate_limit_window_secs: u64,
+    // Requests taking longer than this many seconds are answered with 504; 0 disables the timeout
+    pub request_timeout_secs: u64,
+    // Requests served at once across all routes; further ones wait for a free slot; 0 means unlimited
+    pub max_concurrent_requests: usize,
+    // Largest accepted request body (the snapshot import has its own, larger limit)
+    pub max_body_bytes: usize,
+    // Longest accepted request URI, path and query included
+    pub max_uri_bytes: usize,
+    // Semicolon-separated `/path=directives` rules adding Cache-Control/Expires to responses
+    #[serde(default, deserialize_with = "semicolon_list")]
+    pub cache_control: Vec<String>,
+    // Semicolon-separated `token?query` entries: extra tokens and the query parameters applied
+    // when their requests leave them out, e.g. `alice-secret?weeks=2&kind=hyrox`
+    #[serde(default, deserialize_with = "semicolon_list")]
+    pub token_preferences: Vec<String>,
+    // Telegram notifications about schedule changes (requires a bot token and chat id)
+    pub telegram_enabled: bool,
+    pub telegram_bot_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    pub telegram_api_url: Url,
+    // Post the coming week's timetable every Sunday at this hour (local time)
+    pub telegram_weekly_digest: bool,
+    pub telegram_digest_hour: u32,
+    // Message templates, see `telegram::render_change` and `telegram::render_digest`
+    pub telegram_change_template: String,
+    pub telegram_digest_template: String,
+    // Weekly digest emails through an SMTP relay (disabled when no host is set)
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub email_from: Option<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub email_recipients: Vec<String>,
+    // Cron expression in local time, see `cron::CronSchedule`
+    pub email_digest_schedule: String,
+    // Append the differences from the previous week to the digest
+    pub email_digest_include_diff: bool,
+    // Push notifications about schedule changes: an ntfy topic URL and/or a Gotify server
+    pub ntfy_url: Option<Url>,
+    pub ntfy_token: Option<String>,
+    pub gotify_url: Option<Url>,
+    pub gotify_token: Option<String>,
+    // Slack incoming webhook for change alerts and a daily schedule post (disabled when unset)
+    pub slack_webhook_url: Option<Url>,
+    pub slack_daily_schedule: bool,
+    // Cron expression in local time, see `cron::CronSchedule`
+    pub slack_daily_cron: String,
+    // Matrix room receiving change alerts and a daily summary (disabled when no homeserver
+    // is set); the access token belongs to a bot account that has joined the room
+    pub matrix_homeserver_url: Option<Url>,
+    pub matrix_access_token: Option<String>,
+    pub matrix_room_id: Option<String>,
+    pub matrix_daily_summary: bool,
+    // Cron expression in local time, see `cron::CronSchedule`
+    pub matrix_daily_cron: String,
+    // MQTT broker (`mqtt://` or `mqtts://`) receiving retained schedule messages and
+    // change events (disabled when unset)
+    pub mqtt_broker_url: Option<Url>,
+    pub mqtt_username: Option<String>,
+    pub mqtt_password: Option<String>,
+    pub mqtt_topic_prefix: String,
+    // PEM bundle trusted for `mqtts://` instead of the system roots
+    pub mqtt_ca_path: Option<String>,
+    pub mqtt_refresh_minutes: u32,
+    // Microsoft 365 calendar kept in sync through the Graph API (disabled when no tenant
+    // is set); needs an app registration with the Calendars.ReadWrite application permission
+    pub outlook_tenant_id: Option<String>,
+    pub outlook_client_id: Option<String>,
+    pub outlook_client_secret: Option<String>,
+    // User principal name or id owning the calendar, and the calendar (default: primary)
+    pub outlook_user: Option<String>,
+    pub outlook_calendar_id: Option<String>,
+    // IANA time zone of the class times
+    pub outlook_time_zone: String,
+    pub outlook_sync_weeks: u8,
+    pub outlook_sync_minutes: u32,
+    // Notion database the classes are upserted into (disabled when no token is set)
+    pub notion_token: Option<String>,
+    pub notion_database_id: Option<String>,
+    pub notion_sync_weeks: u8,
+    pub notion_sync_minutes: u32,
+    // `POST /admin/export/google-sheets` target (disabled when no spreadsheet is set) and
+    // the JSON key of a service account the spreadsheet is shared with
+    pub google_sheets_spreadsheet_id: Option<String>,
+    pub google_sheets_tab: String,
+    pub google_service_account_path: Option<String>,
+    // Also email every detected change to `email_recipients`, not just the weekly digest
+    pub email_change_alerts: bool,
+    // Per-channel filters: class names and change kinds (`added`, `removed`, `time_changed`,
+    // `coach_changed`) a channel is notified about; empty lists allow everything
+    #[serde(default, deserialize_with = "string_list")]
+    pub telegram_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub telegram_only_kinds: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub push_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub push_only_kinds: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub slack_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub slack_only_kinds: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub email_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub email_only_kinds: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub mqtt_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub mqtt_only_kinds: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub matrix_only_events: Vec<String>,
+    #[serde(default, deserialize_with = "string_list")]
+    pub matrix_only_kinds: Vec<String>,
+    // Failed notifications are retried this many times with exponential backoff
+    pub notify_retries: u32,
+    // Remind about classes matching stored favorites this many minutes before they start; 0 disables
+    pub reminder_lead_minutes: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            scraper_base_url: Url::parse("https://crossfit2-rzeszow.cms.efitness.com.pl")
+                .expect("default scraper URL is valid"),
+            scrape_min_interval_ms: 0,
+            scrape_max_concurrency: 2,
+            scrape_proxy_url: None,
+            scrape_proxy_username: None,
+            scrape_proxy_password: None,
+            scrape_pool_max_idle: 4,
+            scrape_pool_idle_secs: 300,
+            scrape_tcp_keepalive_secs: 60,
+            refresh_cron: None,
+            class_details: false,
+            class_details_cache_hours: 24,
+            webdriver_url: None,
+            webdriver_wait_secs: 10,
+            demo_mode: false,
+            maintenance_mode: false,
+            timezone: GYM_TIME_ZONE,
+            debug: false,
+            log_format: LogFormat::Text,
+            otlp_endpoint: None,
+            otlp_service_name: "crossfit-timetable".to_string(),
+            auth_token: DEFAULT_AUTH_TOKEN.to_string(),
+            previous_auth_token: None,
+            previous_auth_token_valid_until: None,
+            basic_auth_username: None,
+            basic_auth_password: None,
+            public_read: false,
+            enable_swagger: true,
+            bind_addr: vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)],
+            port: 8080,
+            unix_socket_path: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            location: None,
+            location_cache_minutes: 1440,
+            gym_latitude: 50.0386,
+            gym_longitude: 22.0026,
+            gym_title: "CrossFit 2.0 Rzeszów".to_string(),
+            gym_location: "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string(),
+            gyms_file: None,
+            class_kind_rules: Vec::new(),
+            rename_rules: Vec::new(),
+            excluded_events: Vec::new(),
+            duration_overrides: Vec::new(),
+            ical_alarm_minutes: None,
+            ical_calendar_name: "CrossFit 2.0 Rzeszów Timetable".to_string(),
+            ical_event_prefix: "CrossFit: ".to_string(),
+            ical_color: None,
+            lang: Lang::En,
+            feed_cache_seconds: 60,
+            oidc_issuer_url: None,
+            public_url: None,
+            share_signing_key: None,
+            share_ttl_hours: 24 * 365,
+            revoked_shares: Vec::new(),
+            ip_allowlist: Vec::new(),
+            trusted_proxies: Vec::new(),
+            database_url: None,
+            retention_days: 365,
+            rate_limit_requests: 60,
+            rate_limit_window_secs: 60,
+            request_timeout_secs: 30,
+            max_concurrent_requests: 256,
+            max_body_bytes: 2 * 1024 * 1024,
+            max_uri_bytes: 8 * 1024,
+            cache_control: Vec::new(),
+            token_preferences: Vec::new(),
+            telegram_enabled: false,
+            telegram_bot_token: None,
+            telegram_chat_id: None,
+            telegram_api_url: Url::parse("https://api.telegram.org")
+                .expect("default Telegram API URL is valid"),
+            telegram_weekly_digest: true,
+            telegram_digest_hour: 18,
+            telegram_change_template: "{summary}".to_string(),
+            telegram_digest_template: "Timetable for the week of {week}:\n{classes}".to_string(),
+            smtp_host: None,
+            smtp_port: 25,
+            smtp_username: None,
+            smtp_password: None,
+            email_from: None,
+            email_recipients: Vec::new(),
+            email_digest_schedule: "0 18 * * SUN".to_string(),
+            email_digest_include_diff: true,
+            ntfy_url: None,
+            ntfy_token: None,
+            gotify_url: None,
+            gotify_token: None,
+            slack_webhook_url: None,
+            slack_daily_schedule: true,
+            slack_daily_cron: "0 7 * * *".to_string(),
+            matrix_homeserver_url: None,
+            matrix_access_token: None,
+            matrix_room_id: None,
+            matrix_daily_summary: true,
+            matrix_daily_cron: "0 7 * * *".to_string(),
+            mqtt_broker_url: None,
+            mqtt_username: None,
+            mqtt_password: None,
+            mqtt_topic_prefix: "crossfit-timetable".to_string(),
+            mqtt_ca_path: None,
+            mqtt_refresh_minutes: 15,
+            outlook_tenant_id: None,
+            outlook_client_id: None,
+            outlook_client_secret: None,
+            outlook_user: None,
+            outlook_calendar_id: None,
+            outlook_time_zone: "Europe/Warsaw".to_string(),
+            outlook_sync_weeks: 2,
+            outlook_sync_minutes: 30,
+            notion_token: None,
+            notion_database_id: None,
+            notion_sync_weeks: 1,
+            notion_sync_minutes: 30,
+            google_sheets_spreadsheet_id: None,
+            google_sheets_tab: "Timetable".to_string(),
+            google_service_account_path: None,
+            email_change_alerts: false,
+            telegram_only_events: Vec::new(),
+            telegram_only_kinds: Vec::new(),
+            push_only_events: Vec::new(),
+            push_only_kinds: Vec::new(),
+            slack_only_events: Vec::new(),
+            slack_only_kinds: Vec::new(),
+            email_only_events: Vec::new(),
+            email_only_kinds: Vec::new(),
+            mqtt_only_events: Vec::new(),
+            mqtt_only_kinds: Vec::new(),
+            matrix_only_events: Vec::new(),
+            matrix_only_kinds: Vec::new(),
+            notify_retries: 3,
+            reminder_lead_minutes: 0,
+        }
+    }
 }
 
 impl Settings {
+    pub fn basic_auth_enabled(&self) -> bool {
+        self.basic_auth_username.is_some() && self.basic_auth_password.is_some()
+    }
+
+    /// Loads and validates the settings.
     pub fn from_env() -> Result<Self, ConfigError> {
+        let settings = Self::load()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Loads the settings without validating them, for commands that don't serve the API.
+    pub fn load() -> Result<Self, ConfigError> {
         let _ = dotenvy::dotenv();
 
-        let config = Config::builder()
-            // Load from environment variables with APP_ prefix
-            .add_source(Environment::with_prefix("APP").separator("_"))
-            .set_default(
-                "scraper_base_url",
-                "https://crossfit2-rzeszow.cms.efitness.com.pl",
-            )?
-            .set_default("debug", false)?
-            .set_default("auth_token", "default-token-change-me")?
-            .set_default("enable_swagger", true)?
-            .set_default("port", 8080)?
-            .set_default("gym_latitude", 50.0386)?
-            .set_default("gym_longitude", 22.0026)?
-            .set_default("gym_title", "CrossFit 2.0 Rzeszów")?
-            .set_default(
-                "gym_location",
-                "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland",
-            )?
-            .build()?;
-
-        config.try_deserialize()
+        // An explicit APP_CONFIG must exist; otherwise `config.toml`/`config.yaml` in the
+        // working directory is used when present
+        let file = match std::env::var("APP_CONFIG") {
+            Ok(path) => File::from(Path::new(&path)).required(true),
+            Err(_) => File::with_name("config").required(false),
+        };
+
+        let mut builder = Config::builder()
+            // Defaults come from `Settings::default()`
+            .add_source(Config::try_from(&Settings::default())?)
+            .add_source(file)
+            // Load from environment variables with APP_ prefix, overriding the config file
+            // (APP_AUTH_TOKEN maps to `auth_token`; `__` is reserved for nesting)
+            .add_source(
+                Environment::with_prefix("APP")
+                    .prefix_separator("_")
+                    .separator("__"),
+            );
+
+        // Docker/Kubernetes secrets: APP_<KEY>_FILE points at a file holding the value
+        for key in SECRET_KEYS {
+            if let Some(value) = read_secret_file(key)? {
+                builder = builder.set_override(*key, value)?;
+            }
+        }
+
+        builder.build()?.try_deserialize()
     }
+
+    /// Checks the values their types don't rule out, reporting every problem at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        let urls = [
+            ("APP_SCRAPER_BASE_URL", Some(&self.scraper_base_url)),
+            ("APP_TELEGRAM_API_URL", Some(&self.telegram_api_url)),
+            ("APP_OTLP_ENDPOINT", self.otlp_endpoint.as_ref()),
+            ("APP_WEBDRIVER_URL", self.webdriver_url.as_ref()),
+            ("APP_OIDC_ISSUER_URL", self.oidc_issuer_url.as_ref()),
+            ("APP_PUBLIC_URL", self.public_url.as_ref()),
+            ("APP_NTFY_URL", self.ntfy_url.as_ref()),
+            ("APP_GOTIFY_URL", self.gotify_url.as_ref()),
+            ("APP_SLACK_WEBHOOK_URL", self.slack_webhook_url.as_ref()),
+            (
+                "APP_MATRIX_HOMESERVER_URL",
+                self.matrix_homeserver_url.as_ref(),
+            ),
+        ];
+        for (name, url) in urls {
+            // Values are left out, as some of these URLs carry secrets
+            if let Some(url) = url
+                && (!matches!(url.scheme(), "http" | "https") || url.host().is_none())
+            {
+                problems.push(format!("{name} must be an http:// or https:// URL"));
+            }
+        }
+        if let Some(url) = &self.scrape_proxy_url
+            && (!matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h")
+                || url.host().is_none())
+        {
+            problems.push(
+                "APP_SCRAPE_PROXY_URL must be an http://, https://, socks5:// or socks5h:// URL"
+                    .to_string(),
+            );
+        }
+        if !(-90.0..=90.0).contains(&self.gym_latitude) {
+            problems.push(format!(
+                "APP_GYM_LATITUDE must be between -90 and 90, got {}",
+                self.gym_latitude
+            ));
+        }
+        if !(-180.0..=180.0).contains(&self.gym_longitude) {
+            problems.push(format!(
+                "APP_GYM_LONGITUDE must be between -180 and 180, got {}",
+                self.gym_longitude
+            ));
+        }
+        if let Some(color) = &self.ical_color
+            && !is_hex_color(color)
+        {
+            problems.push(format!(
+                "APP_ICAL_COLOR must be a `#rrggbb` color, got `{color}`"
+            ));
+        }
+        if !(1..=MAX_SHARE_TTL_HOURS).contains(&self.share_ttl_hours) {
+            problems.push(format!(
+                "APP_SHARE_TTL_HOURS must be between 1 and {MAX_SHARE_TTL_HOURS}"
+            ));
+        }
+        if self.port == 0 && self.unix_socket_path.is_none() {
+            problems.push("APP_PORT must not be 0".to_string());
+        }
+        if self.bind_addr.is_empty() && self.unix_socket_path.is_none() {
+            problems.push("APP_BIND_ADDR must list at least one address".to_string());
+        }
+        if self.auth_token.is_empty() {
+            problems.push("APP_AUTH_TOKEN must not be empty".to_string());
+        } else if self.auth_token == DEFAULT_AUTH_TOKEN && !self.debug {
+            problems.push(format!(
+                "APP_AUTH_TOKEN is still the published default `{DEFAULT_AUTH_TOKEN}`; set a \
+                 secret token (or APP_DEBUG=true for local development)"
+            ));
+        }
+        if let Err(err) = CacheRules::from_settings(self) {
+            problems.push(err.to_string());
+        }
+        if let Err(err) = TokenPreferences::from_settings(self) {
+            problems.push(err.to_string());
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(ConfigError::Message(format!(
+            "invalid configuration:\n  - {}",
+            problems.join("\n  - ")
+        )))
+    }
+}
+
+/// Whether `value` is a `#rrggbb` color, as calendar apps take it.
+pub fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Settings that may be provided through `APP_<KEY>_FILE`
+const SECRET_KEYS: &[&str] = &[
+    "auth_token",
+    "previous_auth_token",
+    "basic_auth_password",
+    "share_signing_key",
+    "database_url",
+    "telegram_bot_token",
+    "smtp_password",
+    "ntfy_token",
+    "gotify_token",
+    "slack_webhook_url",
+    "matrix_access_token",
+    "mqtt_password",
+    "outlook_client_secret",
+    "notion_token",
+    "scrape_proxy_password",
+];
+
+fn read_secret_file(key: &str) -> Result<Option<String>, ConfigError> {
+    let var = format!("APP_{}_FILE", key.to_uppercase());
+    let Ok(path) = std::env::var(&var) else {
+        return Ok(None);
+    };
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Message(format!("{var}: cannot read {path}: {err}")))?;
+    // Secret files usually end with a newline that is not part of the value
+    Ok(Some(contents.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Accepts either a list or a comma-separated string (as environment variables can't hold lists).
+fn string_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    delimited_list(deserializer, ',')
+}
+
+fn ip_list<'de, D>(deserializer: D) -> Result<Vec<IpAddr>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    string_list(deserializer)?
+        .iter()
+        .map(|addr| addr.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+// For lists whose entries contain commas themselves
+fn semicolon_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    delimited_list(deserializer, ';')
+}
+
+fn delimited_list<'de, D>(deserializer: D, delimiter: char) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringList {
+        List(Vec<String>),
+        Joined(String),
+    }
+
+    Ok(match StringList::deserialize(deserializer)? {
+        StringList::List(items) => items,
+        StringList::Joined(joined) => joined
+            .split(delimiter)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
 }
 
 #[cfg(test)]
@@ -55,6 +633,7 @@ mod tests {
     use super::*;
     use serial_test::serial;
     use std::env;
+    use std::net::Ipv4Addr;
 
     // Note: Environment variable tests use unsafe blocks because env::set_var and
     // env::remove_var are marked unsafe in Rust 1.32+. Using serial_test to prevent
@@ -75,21 +654,47 @@ mod tests {
             env::remove_var("APP_GYM_LONGITUDE");
             env::remove_var("APP_GYM_TITLE");
             env::remove_var("APP_GYM_LOCATION");
+            env::remove_var("APP_OIDC_ISSUER_URL");
+            env::remove_var("APP_REVOKED_SHARES");
         }
 
         // Act
-        let settings = Settings::from_env().unwrap();
+        let settings = Settings::load().unwrap();
 
         // Assert - should use default values
         assert_eq!(
             settings.scraper_base_url,
             Url::parse("https://crossfit2-rzeszow.cms.efitness.com.pl").unwrap()
         );
+        assert_eq!(settings.scrape_min_interval_ms, 0);
+        assert_eq!(settings.scrape_max_concurrency, 2);
+        assert_eq!(settings.scrape_proxy_url, None);
+        assert_eq!(settings.scrape_proxy_username, None);
+        assert_eq!(settings.scrape_proxy_password, None);
+        assert_eq!(settings.scrape_pool_max_idle, 4);
+        assert_eq!(settings.scrape_pool_idle_secs, 300);
+        assert_eq!(settings.scrape_tcp_keepalive_secs, 60);
+        assert_eq!(settings.refresh_cron, None);
+        assert!(!settings.class_details);
+        assert_eq!(settings.class_details_cache_hours, 24);
+        assert_eq!(settings.webdriver_url, None);
+        assert_eq!(settings.webdriver_wait_secs, 10);
+        assert!(!settings.demo_mode);
+        assert!(!settings.maintenance_mode);
+        assert_eq!(settings.timezone, chrono_tz::Europe::Warsaw);
         assert!(!settings.debug);
+        assert_eq!(settings.log_format, LogFormat::Text);
+        assert_eq!(settings.otlp_endpoint, None);
+        assert_eq!(settings.otlp_service_name, "crossfit-timetable");
         assert_eq!(settings.auth_token, "default-token-change-me");
         assert!(settings.enable_swagger);
+        assert_eq!(settings.bind_addr, [IpAddr::V6(Ipv6Addr::UNSPECIFIED)]);
         assert_eq!(settings.port, 8080);
+        assert_eq!(settings.unix_socket_path, None);
+        assert_eq!(settings.tls_cert_path, None);
+        assert_eq!(settings.tls_key_path, None);
         assert_eq!(settings.location, None);
+        assert_eq!(settings.location_cache_minutes, 1440);
         assert_eq!(settings.gym_latitude, 50.0386);
         assert_eq!(settings.gym_longitude, 22.0026);
         assert_eq!(settings.gym_title, "CrossFit 2.0 Rzeszów");
@@ -97,6 +702,76 @@ mod tests {
             settings.gym_location,
             "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland"
         );
+        assert_eq!(settings.gyms_file, None);
+        assert!(settings.class_kind_rules.is_empty());
+        assert!(settings.rename_rules.is_empty());
+        assert!(settings.excluded_events.is_empty());
+        assert!(settings.duration_overrides.is_empty());
+        assert_eq!(settings.ical_alarm_minutes, None);
+        assert_eq!(
+            settings.ical_calendar_name,
+            "CrossFit 2.0 Rzeszów Timetable"
+        );
+        assert_eq!(settings.ical_event_prefix, "CrossFit: ");
+        assert_eq!(settings.ical_color, None);
+        assert_eq!(settings.lang, Lang::En);
+        assert_eq!(settings.feed_cache_seconds, 60);
+        assert_eq!(settings.oidc_issuer_url, None);
+        assert_eq!(settings.previous_auth_token, None);
+        assert_eq!(settings.previous_auth_token_valid_until, None);
+        assert!(!settings.public_read);
+        assert!(!settings.basic_auth_enabled());
+        assert_eq!(settings.share_signing_key, None);
+        assert_eq!(settings.share_ttl_hours, 8760);
+        assert!(settings.revoked_shares.is_empty());
+        assert!(settings.ip_allowlist.is_empty());
+        assert!(settings.trusted_proxies.is_empty());
+        assert_eq!(settings.database_url, None);
+        assert_eq!(settings.retention_days, 365);
+        assert_eq!(settings.rate_limit_requests, 60);
+        assert_eq!(settings.rate_limit_window_secs, 60);
+        assert_eq!(settings.request_timeout_secs, 30);
+        assert_eq!(settings.max_concurrent_requests, 256);
+        assert_eq!(settings.max_body_bytes, 2 * 1024 * 1024);
+        assert_eq!(settings.max_uri_bytes, 8 * 1024);
+        assert!(settings.cache_control.is_empty());
+        assert!(settings.token_preferences.is_empty());
+        assert!(!settings.telegram_enabled);
+        assert_eq!(settings.telegram_bot_token, None);
+        assert_eq!(settings.telegram_chat_id, None);
+        assert!(settings.telegram_weekly_digest);
+        assert_eq!(settings.telegram_digest_hour, 18);
+        assert_eq!(settings.telegram_change_template, "{summary}");
+        assert_eq!(settings.smtp_host, None);
+        assert_eq!(settings.smtp_port, 25);
+        assert!(settings.email_recipients.is_empty());
+        assert_eq!(settings.email_digest_schedule, "0 18 * * SUN");
+        assert!(settings.email_digest_include_diff);
+        assert_eq!(settings.ntfy_url, None);
+        assert_eq!(settings.gotify_url, None);
+        assert_eq!(settings.slack_webhook_url, None);
+        assert!(settings.slack_daily_schedule);
+        assert_eq!(settings.slack_daily_cron, "0 7 * * *");
+        assert_eq!(settings.matrix_homeserver_url, None);
+        assert!(settings.matrix_daily_summary);
+        assert_eq!(settings.matrix_daily_cron, "0 7 * * *");
+        assert_eq!(settings.mqtt_broker_url, None);
+        assert_eq!(settings.mqtt_topic_prefix, "crossfit-timetable");
+        assert_eq!(settings.mqtt_refresh_minutes, 15);
+        assert_eq!(settings.outlook_tenant_id, None);
+        assert_eq!(settings.outlook_time_zone, "Europe/Warsaw");
+        assert_eq!(settings.outlook_sync_weeks, 2);
+        assert_eq!(settings.outlook_sync_minutes, 30);
+        assert_eq!(settings.notion_token, None);
+        assert_eq!(settings.notion_sync_weeks, 1);
+        assert_eq!(settings.notion_sync_minutes, 30);
+        assert_eq!(settings.google_sheets_spreadsheet_id, None);
+        assert_eq!(settings.google_sheets_tab, "Timetable");
+        assert!(!settings.email_change_alerts);
+        assert!(settings.telegram_only_events.is_empty());
+        assert!(settings.slack_only_kinds.is_empty());
+        assert_eq!(settings.notify_retries, 3);
+        assert_eq!(settings.reminder_lead_minutes, 0);
     }
 
     #[test]
@@ -119,6 +794,7 @@ mod tests {
             gym_longitude: 22.0026,
             gym_title: "CrossFit 2.0 Rzeszów".to_string(),
             gym_location: "Boya-Żeleńskiego 15, 35-105 Rzeszów, Poland".to_string(),
+            ..Settings::default()
         };
 
         // Assert struct fields work as expected
@@ -155,27 +831,28 @@ mod tests {
             env::remove_var("APP_GYM_LONGITUDE");
             env::remove_var("APP_GYM_TITLE");
             env::remove_var("APP_GYM_LOCATION");
+            env::remove_var("APP_OIDC_ISSUER_URL");
         }
 
         // Test true
         unsafe {
             env::set_var("APP_DEBUG", "true");
         }
-        let settings = Settings::from_env().unwrap();
+        let settings = Settings::load().unwrap();
         assert!(settings.debug);
 
         // Test false
         unsafe {
             env::set_var("APP_DEBUG", "false");
         }
-        let settings = Settings::from_env().unwrap();
+        let settings = Settings::load().unwrap();
         assert!(!settings.debug);
 
         // Test case insensitivity (depends on config crate behavior)
         unsafe {
             env::set_var("APP_ENABLE_SWAGGER", "True");
         }
-        let settings = Settings::from_env().unwrap();
+        let settings = Settings::load().unwrap();
         assert!(settings.enable_swagger);
 
         // Cleanup
@@ -200,6 +877,7 @@ mod tests {
             env::remove_var("APP_GYM_LONGITUDE");
             env::remove_var("APP_GYM_TITLE");
             env::remove_var("APP_GYM_LOCATION");
+            env::remove_var("APP_OIDC_ISSUER_URL");
         }
 
         // Arrange
@@ -208,7 +886,7 @@ mod tests {
         }
 
         // Act
-        let settings = Settings::from_env().unwrap();
+        let settings = Settings::load().unwrap();
 
         // Assert
         assert_eq!(settings.port, 3000);
@@ -218,4 +896,356 @@ mod tests {
             env::remove_var("APP_PORT");
         }
     }
+
+    #[test]
+    #[serial]
+    fn test_settings_log_format_parsing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_LOG_FORMAT", "json");
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(settings.log_format, LogFormat::Json);
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_LOG_FORMAT");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_timezone_parsing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_TIMEZONE", "America/New_York");
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(settings.timezone, chrono_tz::America::New_York);
+
+        // Arrange - not an IANA zone
+        unsafe {
+            env::set_var("APP_TIMEZONE", "CEST");
+        }
+
+        // Act & Assert
+        assert!(Settings::load().is_err());
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_TIMEZONE");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_cache_control_parsing() {
+        // Arrange
+        unsafe {
+            env::set_var(
+                "APP_CACHE_CONTROL",
+                "/timetable.ical=public, max-age=900; /me/*=no-store",
+            );
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(
+            settings.cache_control,
+            vec!["/timetable.ical=public, max-age=900", "/me/*=no-store"]
+        );
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_CACHE_CONTROL");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_bind_addr_parsing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_BIND_ADDR", "127.0.0.1, ::1");
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(
+            settings.bind_addr,
+            [
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ]
+        );
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_BIND_ADDR");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_multi_word_keys() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_AUTH_TOKEN", "from-env");
+            env::set_var("APP_GYM_LATITUDE", "51.5");
+            env::set_var("APP_OIDC_ISSUER_URL", "https://sso.example.com/realms/gym");
+            env::set_var(
+                "APP_PREVIOUS_AUTH_TOKEN_VALID_UNTIL",
+                "2026-02-01T00:00:00Z",
+            );
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(settings.auth_token, "from-env");
+        assert_eq!(settings.gym_latitude, 51.5);
+        assert_eq!(
+            settings.oidc_issuer_url,
+            Some(Url::parse("https://sso.example.com/realms/gym").unwrap())
+        );
+        assert_eq!(
+            settings.previous_auth_token_valid_until,
+            Some("2026-02-01T00:00:00Z".parse().unwrap())
+        );
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_AUTH_TOKEN");
+            env::remove_var("APP_GYM_LATITUDE");
+            env::remove_var("APP_OIDC_ISSUER_URL");
+            env::remove_var("APP_PREVIOUS_AUTH_TOKEN_VALID_UNTIL");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_list_parsing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_REVOKED_SHARES", "first, second,,third");
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(settings.revoked_shares, vec!["first", "second", "third"]);
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_REVOKED_SHARES");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_config_file_under_env() {
+        // Arrange
+        let path = env::temp_dir().join("crossfit-timetable-test-config.toml");
+        std::fs::write(
+            &path,
+            "port = 9100\nauth_token = \"from-file\"\ntrusted_proxies = [\"10.0.0.1\", \"10.0.0.2\"]\n",
+        )
+        .unwrap();
+        unsafe {
+            env::set_var("APP_CONFIG", &path);
+            env::set_var("APP_AUTH_TOKEN", "from-env");
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert - environment variables win over the file
+        assert_eq!(settings.port, 9100);
+        assert_eq!(settings.auth_token, "from-env");
+        assert_eq!(settings.trusted_proxies, vec!["10.0.0.1", "10.0.0.2"]);
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_CONFIG");
+            env::remove_var("APP_AUTH_TOKEN");
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_yaml_config_file() {
+        // Arrange
+        let path = env::temp_dir().join("crossfit-timetable-test-config.yaml");
+        std::fs::write(&path, "telegram_only_kinds:\n  - removed\n  - added\n").unwrap();
+        unsafe {
+            env::set_var("APP_CONFIG", &path);
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert
+        assert_eq!(settings.telegram_only_kinds, vec!["removed", "added"]);
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_CONFIG");
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_config_file_missing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_CONFIG", "/nonexistent/config.toml");
+        }
+
+        // Act
+        let result = Settings::load();
+
+        // Assert
+        assert!(result.is_err());
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_CONFIG");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_secret_from_file() {
+        // Arrange
+        let path = env::temp_dir().join("crossfit-timetable-test-auth-token");
+        std::fs::write(&path, "token-from-file\n").unwrap();
+        unsafe {
+            env::set_var("APP_AUTH_TOKEN", "token-from-env");
+            env::set_var("APP_AUTH_TOKEN_FILE", &path);
+        }
+
+        // Act
+        let settings = Settings::load().unwrap();
+
+        // Assert - the file takes precedence and the trailing newline is stripped
+        assert_eq!(settings.auth_token, "token-from-file");
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_AUTH_TOKEN");
+            env::remove_var("APP_AUTH_TOKEN_FILE");
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_settings_secret_file_missing() {
+        // Arrange
+        unsafe {
+            env::set_var("APP_SHARE_SIGNING_KEY_FILE", "/nonexistent/share-key");
+        }
+
+        // Act
+        let result = Settings::load();
+
+        // Assert
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("APP_SHARE_SIGNING_KEY_FILE"));
+
+        // Cleanup
+        unsafe {
+            env::remove_var("APP_SHARE_SIGNING_KEY_FILE");
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        assert!(
+            Settings {
+                debug: true,
+                ..Settings::default()
+            }
+            .validate()
+            .is_ok()
+        );
+
+        let settings = Settings {
+            scraper_base_url: Url::parse("ftp://example.com").unwrap(),
+            slack_webhook_url: Some(Url::parse("file:///hooks/T0KEN").unwrap()),
+            scrape_proxy_url: Some(Url::parse("ftp://proxy.example.com").unwrap()),
+            gym_latitude: 95.0,
+            gym_longitude: f64::NAN,
+            port: 0,
+            share_ttl_hours: u32::MAX,
+            ..Settings::default()
+        };
+        let ConfigError::Message(report) = settings.validate().unwrap_err() else {
+            panic!("expected a message");
+        };
+        assert!(report.starts_with("invalid configuration:"));
+        for name in [
+            "APP_SCRAPER_BASE_URL",
+            "APP_SLACK_WEBHOOK_URL",
+            "APP_SCRAPE_PROXY_URL",
+            "APP_GYM_LATITUDE",
+            "APP_GYM_LONGITUDE",
+            "APP_PORT",
+            "APP_SHARE_TTL_HOURS",
+            "APP_AUTH_TOKEN",
+        ] {
+            assert!(report.contains(name), "{name} missing from {report}");
+        }
+        assert!(!report.contains("T0KEN"));
+
+        // A Unix socket needs no port
+        let settings = Settings {
+            auth_token: "s3cret".to_string(),
+            port: 0,
+            unix_socket_path: Some("/run/timetable.sock".to_string()),
+            ..Settings::default()
+        };
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn test_from_env_refuses_default_token() {
+        unsafe {
+            env::remove_var("APP_AUTH_TOKEN");
+            env::remove_var("APP_DEBUG");
+        }
+        assert!(Settings::from_env().is_err());
+
+        unsafe {
+            env::set_var("APP_AUTH_TOKEN", "s3cret");
+        }
+        assert!(Settings::from_env().is_ok());
+
+        unsafe {
+            env::remove_var("APP_AUTH_TOKEN");
+            env::set_var("APP_DEBUG", "true");
+        }
+        assert!(Settings::from_env().is_ok());
+
+        unsafe {
+            env::remove_var("APP_DEBUG");
+        }
+    }
 }
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use url::form_urlencoded;
+
+use crate::models::Branding;
+
+const BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ShareError {
+    #[error("Invalid share link signature")]
+    InvalidSignature,
+    #[error("Share link has expired")]
+    Expired,
+    #[error("Share link has been revoked")]
+    Revoked,
+}
+
+/// Signs and verifies calendar subscription links so the auth token never ends up in shared URLs.
+pub struct ShareSigner {
+    key: Vec<u8>,
+    revoked: RwLock<HashSet<String>>,
+}
+
+impl ShareSigner {
+    pub fn new(key: &str, revoked: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            key: key.as_bytes().to_vec(),
+            revoked: RwLock::new(revoked.into_iter().collect()),
+        }
+    }
+
+    fn payload(share_id: &str, expires: i64, weeks: u8, branding: &Branding) -> String {
+        let payload = format!("{share_id}:{expires}:{weeks}");
+        // Unbranded links keep the payload they were signed with before branding existed
+        if branding.is_empty() {
+            return payload;
+        }
+        let branding = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(branding.query_pairs())
+            .finish();
+        format!("{payload}:{branding}")
+    }
+
+    pub fn sign(&self, share_id: &str, expires: i64, weeks: u8, branding: &Branding) -> String {
+        let mac = hmac_sha256(
+            &self.key,
+            Self::payload(share_id, expires, weeks, branding).as_bytes(),
+        );
+        URL_SAFE_NO_PAD.encode(mac)
+    }
+
+    pub fn verify(
+        &self,
+        share_id: &str,
+        expires: i64,
+        weeks: u8,
+        branding: &Branding,
+        signature: &str,
+        now: i64,
+    ) -> Result<(), ShareError> {
+        let expected = self.sign(share_id, expires, weeks, branding);
+        if !constant_time_eq(expected.as_bytes(), signature.as_bytes()) {
+            return Err(ShareError::InvalidSignature);
+        }
+        if now > expires {
+            return Err(ShareError::Expired);
+        }
+        if self.is_revoked(share_id) {
+            return Err(ShareError::Revoked);
+        }
+        Ok(())
+    }
+
+    pub fn revoke(&self, share_id: &str) {
+        self.revoked
+            .write()
+            .expect("revocation list lock poisoned")
+            .insert(share_id.to_string());
+    }
+
+    pub fn is_revoked(&self, share_id: &str) -> bool {
+        self.revoked
+            .read()
+            .expect("revocation list lock poisoned")
+            .contains(share_id)
+    }
+}
+
+// HMAC-SHA256 as defined in RFC 2104
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let inner_hash = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner_hash);
+    outer.finalize().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231_vector() {
+        // RFC 4231, test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_verify_signed_link() {
+        let signer = ShareSigner::new("share-key", []);
+        let sig = signer.sign("abc", 2_000, 2, &Branding::default());
+
+        assert_eq!(
+            signer.verify("abc", 2_000, 2, &Branding::default(), &sig, 1_000),
+            Ok(())
+        );
+        assert_eq!(
+            signer.verify("abc", 2_000, 6, &Branding::default(), &sig, 1_000),
+            Err(ShareError::InvalidSignature)
+        );
+        assert_eq!(
+            signer.verify("abc", 2_000, 2, &Branding::default(), &sig, 3_000),
+            Err(ShareError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_branding_is_signed() {
+        let signer = ShareSigner::new("share-key", []);
+        let branding = Branding {
+            calendar_name: Some("Morning Crew".to_string()),
+            color: Some("#ff6600".to_string()),
+            ..Branding::default()
+        };
+        let sig = signer.sign("abc", 2_000, 1, &branding);
+
+        assert_eq!(
+            signer.verify("abc", 2_000, 1, &branding, &sig, 1_000),
+            Ok(())
+        );
+        let tampered = Branding {
+            calendar_name: Some("Evening Crew".to_string()),
+            ..branding.clone()
+        };
+        assert_eq!(
+            signer.verify("abc", 2_000, 1, &tampered, &sig, 1_000),
+            Err(ShareError::InvalidSignature)
+        );
+        assert_eq!(
+            signer.verify("abc", 2_000, 1, &Branding::default(), &sig, 1_000),
+            Err(ShareError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_revoked_link() {
+        let signer = ShareSigner::new("share-key", ["old".to_string()]);
+        let sig = signer.sign("abc", 2_000, 1, &Branding::default());
+        signer.revoke("abc");
+
+        assert!(signer.is_revoked("old"));
+        assert_eq!(
+            signer.verify("abc", 2_000, 1, &Branding::default(), &sig, 1_000),
+            Err(ShareError::Revoked)
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::Tz;
+use serde_json::{Value, json};
+use thiserror::Error;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::cron::CronSchedule;
+use crate::diff::ScheduleChange;
+use crate::models::{ClassItem, local_now};
+use crate::notify::{Notifier, NotifyError, reminder_text};
+use crate::source::TimetableSource;
+
+// Slack truncates section text beyond this many characters
+const SECTION_LIMIT: usize = 3000;
+
+#[derive(Debug, Error)]
+pub enum SlackError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Slack webhook replied {status}: {body}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+}
+
+/// Posts Block Kit messages to a Slack incoming webhook.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: Url,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            webhook_url,
+        }
+    }
+
+    pub async fn send_daily_schedule(
+        &self,
+        day: NaiveDate,
+        classes: &[ClassItem],
+    ) -> Result<(), SlackError> {
+        self.post(&daily_schedule_message(day, classes)).await
+    }
+
+    async fn post(&self, message: &Value) -> Result<(), SlackError> {
+        let response = self
+            .client
+            .post(self.webhook_url.clone())
+            .json(message)
+            .send()
+            .await
+            // The webhook URL is the credential, keep it out of the logs
+            .map_err(reqwest::Error::without_url)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(SlackError::Rejected { status, body });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        Ok(self.post(&changes_message(monday, changes)).await?)
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        let heading = format!("{} in {minutes_before} minutes", class.event_name);
+        let message = message(&heading, &[reminder_text(class, minutes_before)]);
+        Ok(self.post(&message).await?)
+    }
+}
+
+fn changes_message(monday: NaiveDate, changes: &[ScheduleChange]) -> Value {
+    let heading = format!("Schedule changes for the week of {monday}");
+    let lines: Vec<String> = changes
+        .iter()
+        .map(|change| format!("• {}", change.describe()))
+        .collect();
+    message(&heading, &lines)
+}
+
+fn daily_schedule_message(day: NaiveDate, classes: &[ClassItem]) -> Value {
+    let heading = format!("Classes on {}", day.format("%A %d.%m"));
+    let mut lines: Vec<String> = classes
+        .iter()
+        .map(|class| {
+            format!(
+                "*{}*  {} — {}",
+                class.date.format("%H:%M"),
+                class.event_name,
+                class.coach
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("No classes scheduled".to_string());
+    }
+    message(&heading, &lines)
+}
+
+// A header block followed by as many mrkdwn sections as the lines need
+fn message(heading: &str, lines: &[String]) -> Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {"type": "plain_text", "text": heading},
+    })];
+    let mut section = String::new();
+    for line in lines {
+        if !section.is_empty() && section.len() + line.len() + 1 > SECTION_LIMIT {
+            blocks.push(section_block(&std::mem::take(&mut section)));
+        }
+        if !section.is_empty() {
+            section.push('\n');
+        }
+        section.push_str(line);
+    }
+    if !section.is_empty() {
+        blocks.push(section_block(&section));
+    }
+    // `text` is shown in notifications and by clients that can't render blocks
+    json!({"text": heading, "blocks": blocks})
+}
+
+fn section_block(text: &str) -> Value {
+    json!({"type": "section", "text": {"type": "mrkdwn", "text": text}})
+}
+
+/// Posts the day's classes whenever `schedule` fires.
+pub async fn run_daily_schedule(
+    notifier: Arc<SlackNotifier>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    schedule: CronSchedule,
+    time_zone: Tz,
+) {
+    loop {
+        let now = local_now(time_zone);
+        let Some(next) = schedule.next_in(now) else {
+            warn!("Slack schedule never fires again");
+            return;
+        };
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        let day = next.date_naive();
+        let monday = day - chrono::Duration::days(day.weekday().num_days_from_monday().into());
+        let classes = match source.fetch_week(monday, location.clone()).await {
+            Ok(classes) => classes,
+            Err(err) => {
+                warn!(error = %err, %day, "failed to fetch timetable for Slack");
+                continue;
+            }
+        };
+        let today: Vec<ClassItem> = classes
+            .into_iter()
+            .filter(|class| class.date.date() == day)
+            .collect();
+        match notifier.send_daily_schedule(day, &today).await {
+            Ok(()) => info!(%day, classes = today.len(), "posted daily schedule to Slack"),
+            Err(err) => warn!(error = %err, "failed to post daily schedule to Slack"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_daily_schedule_blocks() {
+        let day = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let message = daily_schedule_message(
+            day,
+            &[
+                class("2025-11-24 06:00", "WOD", "Anna"),
+                class("2025-11-24 18:00", "HYROX", "Piotr"),
+            ],
+        );
+
+        assert_eq!(message["text"], "Classes on Monday 24.11");
+        assert_eq!(message["blocks"][0]["type"], "header");
+        assert_eq!(
+            message["blocks"][1]["text"]["text"],
+            "*06:00*  WOD — Anna\n*18:00*  HYROX — Piotr"
+        );
+    }
+
+    #[test]
+    fn test_long_messages_are_split_into_sections() {
+        let lines: Vec<String> = (0..100).map(|i| format!("{i:>60}")).collect();
+        let message = message("Heading", &lines);
+        let blocks = message["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert!(
+            blocks[1..]
+                .iter()
+                .all(|block| block["text"]["text"].as_str().unwrap().len() <= SECTION_LIMIT)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_alert() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/services/T000/B000/XXX")
+                .body_includes("Schedule changes for the week of 2025-11-24")
+                .body_includes("• Cancelled: WOD on Mon 24.11 06:00 (Anna)");
+            then.status(200).body("ok");
+        });
+        let notifier =
+            SlackNotifier::new(Url::parse(&server.url("/services/T000/B000/XXX")).unwrap());
+
+        notifier
+            .notify_changes(
+                NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+                &[ScheduleChange::Removed {
+                    class: class("2025-11-24 06:00", "WOD", "Anna"),
+                }],
+            )
+            .await
+            .unwrap();
+
+        mock.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_webhook() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST);
+            then.status(404).body("no_service");
+        });
+        let notifier = SlackNotifier::new(Url::parse(&server.base_url()).unwrap());
+
+        let result = notifier
+            .send_daily_schedule(NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(), &[])
+            .await;
+
+        assert!(matches!(result, Err(SlackError::Rejected { body, .. }) if body == "no_service"));
+    }
+}
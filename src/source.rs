@@ -0,0 +1,503 @@
+//! Where the classes come from: the eFitness scraper, or any other gym system.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use crate::health::ScrapeStats;
+use crate::hours::OpeningHours;
+use crate::kinds::KindRules;
+use crate::models::{ClassItem, GYM_TIME_ZONE, dedup_classes};
+use crate::rules::{ClassRules, RuleError};
+use crate::scraper::{CrossfitScraper, ScrapeError, ScrapedWeek};
+use crate::selftest::SelfTest;
+use crate::settings::Settings;
+
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error(transparent)]
+    Rules(#[from] RuleError),
+    #[error("cannot set up the upstream HTTP client (check APP_SCRAPE_PROXY_URL): {0}")]
+    Client(#[from] reqwest::Error),
+    #[cfg(not(feature = "headless"))]
+    #[error(
+        "APP_WEBDRIVER_URL is set but this build lacks the browser fallback (build with --features headless)"
+    )]
+    HeadlessUnsupported,
+}
+
+/// A gym's timetable, fetched a week at a time.
+#[async_trait]
+pub trait TimetableSource: Send + Sync {
+    /// Classes of the week starting on `monday`. `location` overrides the address attached
+    /// to each class; without it the source uses the one it publishes, if any.
+    async fn fetch_week(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError>;
+
+    /// Like `fetch_week`, also reporting rows of the source's timetable that could not be
+    /// read and were left out, and the days the gym is closed.
+    async fn fetch_week_with_warnings(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<ScrapedWeek, ScrapeError> {
+        Ok(ScrapedWeek {
+            classes: self.fetch_week(monday, location).await?,
+            ..ScrapedWeek::default()
+        })
+    }
+
+    /// The gym's address as published by the source.
+    async fn fetch_location(&self) -> Option<String> {
+        None
+    }
+
+    /// When the gym is open, as published by the source; none when it publishes nothing.
+    async fn fetch_opening_hours(&self) -> Result<Vec<OpeningHours>, ScrapeError> {
+        Ok(Vec::new())
+    }
+
+    /// A diagnostic of the upstream page behind the source; sources without one have none.
+    async fn self_test(&self) -> Option<SelfTest> {
+        None
+    }
+
+    /// Upstream requests so far, reported by `/healthz/detail`.
+    fn stats(&self) -> ScrapeStats {
+        ScrapeStats::default()
+    }
+
+    /// Classes of `weeks` consecutive weeks starting with the week of `from`, each listed
+    /// once; fails as a whole if any week can't be fetched.
+    async fn fetch_weeks(
+        &self,
+        from: NaiveDate,
+        weeks: u8,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError> {
+        let mut classes = Vec::new();
+        for week in 0..i64::from(weeks) {
+            let monday = from + chrono::Duration::weeks(week);
+            classes.extend(self.fetch_week(monday, location.clone()).await?);
+        }
+        dedup_classes(&mut classes);
+        Ok(classes)
+    }
+}
+
+#[async_trait]
+impl TimetableSource for CrossfitScraper {
+    async fn fetch_week(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError> {
+        self.fetch_timetable(Some(monday), location).await
+    }
+
+    async fn fetch_week_with_warnings(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<ScrapedWeek, ScrapeError> {
+        self.fetch_week_page(Some(monday), location).await
+    }
+
+    async fn fetch_location(&self) -> Option<String> {
+        CrossfitScraper::fetch_location(self).await
+    }
+
+    async fn fetch_opening_hours(&self) -> Result<Vec<OpeningHours>, ScrapeError> {
+        CrossfitScraper::fetch_opening_hours(self).await
+    }
+
+    async fn self_test(&self) -> Option<SelfTest> {
+        Some(CrossfitScraper::self_test(self).await)
+    }
+
+    fn stats(&self) -> ScrapeStats {
+        CrossfitScraper::stats(self)
+    }
+}
+
+/// The address a source publishes, looked up again only once the cached one is older than
+/// the TTL. Failed lookups are not cached.
+pub struct LocationCache {
+    ttl: std::time::Duration,
+    cached: Mutex<Option<(Instant, String)>>,
+}
+
+impl LocationCache {
+    pub fn new(ttl: std::time::Duration) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self, source: &dyn TimetableSource) -> Option<String> {
+        if let Some((fetched, location)) = &*self.cached.lock().expect("location lock poisoned")
+            && fetched.elapsed() < self.ttl
+        {
+            return Some(location.clone());
+        }
+        let location = source.fetch_location().await?;
+        *self.cached.lock().expect("location lock poisoned") =
+            Some((Instant::now(), location.clone()));
+        Some(location)
+    }
+}
+
+/// The opening hours a source publishes, fetched again only once the cached ones are older
+/// than the TTL. Failed lookups are not cached.
+pub struct OpeningHoursCache {
+    ttl: std::time::Duration,
+    cached: Mutex<Option<(Instant, Vec<OpeningHours>)>>,
+}
+
+impl OpeningHoursCache {
+    pub fn new(ttl: std::time::Duration) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(
+        &self,
+        source: &dyn TimetableSource,
+    ) -> Result<Vec<OpeningHours>, ScrapeError> {
+        if let Some((fetched, hours)) = &*self.cached.lock().expect("opening hours lock poisoned")
+            && fetched.elapsed() < self.ttl
+        {
+            return Ok(hours.clone());
+        }
+        let hours = source.fetch_opening_hours().await?;
+        *self.cached.lock().expect("opening hours lock poisoned") =
+            Some((Instant::now(), hours.clone()));
+        Ok(hours)
+    }
+}
+
+/// The configured source: generated classes with `APP_DEMO_MODE`, otherwise the scraper.
+pub fn from_settings(settings: &Settings) -> Result<Arc<dyn TimetableSource>, SourceError> {
+    if settings.demo_mode {
+        return Ok(Arc::new(
+            FakeSource::default()
+                .with_kind_rules(KindRules::from_settings(settings).map_err(RuleError::from)?)
+                .with_class_rules(ClassRules::from_settings(settings)?)
+                .with_time_zone(settings.timezone),
+        ));
+    }
+    Ok(Arc::new(CrossfitScraper::from_settings(settings)?))
+}
+
+// Weekdays (from Monday, inclusive) and the hours the demo gym is open on them
+const DEMO_HOURS: [(Weekday, Weekday, &str, &str); 3] = [
+    (Weekday::Mon, Weekday::Fri, "06:00", "22:00"),
+    (Weekday::Sat, Weekday::Sat, "08:00", "14:00"),
+    (Weekday::Sun, Weekday::Sun, "09:00", "13:00"),
+];
+
+const DEMO_COACHES: [&str; 4] = ["Anna", "Marek", "Piotr", "Kasia"];
+const DEMO_URL: &str = "https://example.com/crossfit-timetable-demo";
+
+// Day of the week (0 = Monday), start, class and length in minutes, next to the WODs
+// held every weekday at 6:00, 7:00, 17:00 and 18:00
+const DEMO_SPECIALS: [(i64, &str, &str, u32); 11] = [
+    (0, "19:00", "Weightlifting", 60),
+    (1, "19:00", "HYROX", 60),
+    (2, "16:00", "Kids", 45),
+    (2, "19:00", "Weightlifting", 60),
+    (3, "19:00", "HYROX", 60),
+    (4, "16:00", "Kids", 45),
+    (4, "19:00", "Open Gym", 90),
+    (5, "09:00", "WOD", 60),
+    (5, "10:30", "HYROX", 60),
+    (5, "12:00", "Open Gym", 90),
+    (6, "10:00", "Open Gym", 90),
+];
+
+/// A made-up but plausible week of classes, the same every week, for trying the API
+/// without a real gym behind it.
+#[derive(Clone)]
+pub struct FakeSource {
+    kind_rules: KindRules,
+    class_rules: ClassRules,
+    time_zone: Tz,
+}
+
+impl Default for FakeSource {
+    fn default() -> Self {
+        Self {
+            kind_rules: KindRules::default(),
+            class_rules: ClassRules::default(),
+            time_zone: GYM_TIME_ZONE,
+        }
+    }
+}
+
+impl FakeSource {
+    pub fn with_kind_rules(mut self, kind_rules: KindRules) -> Self {
+        self.kind_rules = kind_rules;
+        self
+    }
+
+    pub fn with_class_rules(mut self, class_rules: ClassRules) -> Self {
+        self.class_rules = class_rules;
+        self
+    }
+
+    pub fn with_time_zone(mut self, time_zone: Tz) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    fn class(
+        &self,
+        monday: NaiveDate,
+        day: i64,
+        start: &str,
+        name: &str,
+        coach: &str,
+        minutes: u32,
+    ) -> ClassItem {
+        let start = NaiveTime::parse_from_str(start, "%H:%M").expect("demo times are valid");
+        let mut class = ClassItem::new(
+            (monday + Duration::days(day)).and_time(start),
+            name.to_string(),
+            coach.to_string(),
+            Some(minutes),
+            DEMO_URL.to_string(),
+        );
+        class.kind = self.kind_rules.classify(name);
+        class.with_time_zone(self.time_zone)
+    }
+}
+
+#[async_trait]
+impl TimetableSource for FakeSource {
+    async fn fetch_week(
+        &self,
+        monday: NaiveDate,
+        location: Option<String>,
+    ) -> Result<Vec<ClassItem>, ScrapeError> {
+        let mut classes = Vec::new();
+        for day in 0..5 {
+            for (slot, start) in ["06:00", "07:00", "17:00", "18:00"].into_iter().enumerate() {
+                let coach = DEMO_COACHES[(day as usize + slot) % DEMO_COACHES.len()];
+                classes.push(self.class(monday, day, start, "WOD", coach, 60));
+            }
+        }
+        for (index, (day, start, name, minutes)) in DEMO_SPECIALS.into_iter().enumerate() {
+            let coach = DEMO_COACHES[index % DEMO_COACHES.len()];
+            classes.push(self.class(monday, day, start, name, coach, minutes));
+        }
+        classes.sort_by_key(|class| class.date);
+        for class in &mut classes {
+            class.location = location.clone();
+        }
+        Ok(self.class_rules.apply(classes))
+    }
+
+    async fn fetch_opening_hours(&self) -> Result<Vec<OpeningHours>, ScrapeError> {
+        let time = |time| NaiveTime::parse_from_str(time, "%H:%M").expect("demo times are valid");
+        let mut hours = Vec::new();
+        for (first, last, opens, closes) in DEMO_HOURS {
+            let mut weekday = first;
+            loop {
+                hours.push(OpeningHours {
+                    weekday,
+                    opens: time(opens),
+                    closes: time(closes),
+                });
+                if weekday == last {
+                    break;
+                }
+                weekday = weekday.succ();
+            }
+        }
+        Ok(hours)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One WOD every Monday, labelled with the requested location
+    struct Mondays;
+
+    #[async_trait]
+    impl TimetableSource for Mondays {
+        async fn fetch_week(
+            &self,
+            monday: NaiveDate,
+            location: Option<String>,
+        ) -> Result<Vec<ClassItem>, ScrapeError> {
+            let mut class = ClassItem::new(
+                monday.and_hms_opt(6, 0, 0).unwrap(),
+                "WOD".to_string(),
+                "Anna".to_string(),
+                Some(60),
+                "https://example.com".to_string(),
+            );
+            class.location = location;
+            Ok(vec![class])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_weeks_fetches_consecutive_weeks() {
+        let from = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+
+        let classes = Mondays
+            .fetch_weeks(from, 3, Some("Box".to_string()))
+            .await
+            .unwrap();
+
+        let dates: Vec<_> = classes.iter().map(|class| class.date.date()).collect();
+        assert_eq!(
+            dates,
+            vec![
+                from,
+                NaiveDate::from_ymd_opt(2025, 12, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 12, 8).unwrap(),
+            ]
+        );
+        assert!(
+            classes
+                .iter()
+                .all(|class| class.location.as_deref() == Some("Box"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_source_week() {
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+
+        let classes = FakeSource::default()
+            .fetch_week(monday, None)
+            .await
+            .unwrap();
+
+        assert_eq!(classes.len(), 31);
+        assert!(classes.is_sorted_by_key(|class| class.date));
+        assert!(classes.iter().all(|class| {
+            let days = (class.date.date() - monday).num_days();
+            (0..7).contains(&days)
+        }));
+        let kinds: std::collections::BTreeSet<_> =
+            classes.iter().map(|class| class.kind.as_str()).collect();
+        assert_eq!(
+            kinds.into_iter().collect::<Vec<_>>(),
+            vec!["hyrox", "kids", "open_gym", "weightlifting", "wod"]
+        );
+        // Every week looks the same, with ids of its own
+        let next = FakeSource::default()
+            .fetch_week(monday + Duration::weeks(1), None)
+            .await
+            .unwrap();
+        assert_eq!(next[0].event_name, classes[0].event_name);
+        assert_ne!(next[0].id, classes[0].id);
+    }
+
+    #[test]
+    fn test_from_settings_picks_demo_source() {
+        let settings = Settings {
+            demo_mode: true,
+            ..Settings::default()
+        };
+        assert!(from_settings(&settings).is_ok());
+
+        let settings = Settings {
+            class_kind_rules: vec!["no equals sign".to_string()],
+            demo_mode: true,
+            ..Settings::default()
+        };
+        assert!(from_settings(&settings).is_err());
+    }
+
+    #[cfg(not(feature = "headless"))]
+    #[test]
+    fn test_from_settings_refuses_webdriver_without_headless_feature() {
+        let settings = Settings {
+            webdriver_url: Some(url::Url::parse("http://localhost:9515").unwrap()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            from_settings(&settings),
+            Err(SourceError::HeadlessUnsupported)
+        ));
+    }
+
+    // Publishes its address, counting the lookups
+    #[derive(Default)]
+    struct Addressed(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl TimetableSource for Addressed {
+        async fn fetch_week(
+            &self,
+            _monday: NaiveDate,
+            _location: Option<String>,
+        ) -> Result<Vec<ClassItem>, ScrapeError> {
+            Ok(Vec::new())
+        }
+
+        async fn fetch_location(&self) -> Option<String> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Some("Box".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_location_cache_reuses_lookup_within_ttl() {
+        let source = Addressed::default();
+        let cache = LocationCache::new(std::time::Duration::from_secs(60));
+
+        assert_eq!(cache.get(&source).await.as_deref(), Some("Box"));
+        assert_eq!(cache.get(&source).await.as_deref(), Some("Box"));
+        assert_eq!(source.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let expired = LocationCache::new(std::time::Duration::ZERO);
+        expired.get(&source).await;
+        expired.get(&source).await;
+        assert_eq!(source.0.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // Nothing is cached when the source publishes no address
+        assert_eq!(
+            LocationCache::new(std::time::Duration::from_secs(60))
+                .get(&Mondays)
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opening_hours_cache_reuses_lookup_within_ttl() {
+        let cache = OpeningHoursCache::new(std::time::Duration::from_secs(60));
+
+        let hours = cache.get(&FakeSource::default()).await.unwrap();
+        // Served from the cache, though this source publishes none
+        let cached = cache.get(&Mondays).await.unwrap();
+
+        assert_eq!(hours.len(), 7);
+        assert_eq!(hours[5].weekday, Weekday::Sat);
+        assert_eq!(cached, hours);
+    }
+
+    #[tokio::test]
+    async fn test_defaults_for_sources_without_location_or_stats() {
+        assert_eq!(Mondays.fetch_location().await, None);
+        assert!(Mondays.fetch_opening_hours().await.unwrap().is_empty());
+        assert_eq!(Mondays.stats().activity.last_success, None);
+    }
+}
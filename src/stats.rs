@@ -0,0 +1,86 @@
+//! Aggregates of the timetable for dashboards.
+
+use chrono::{Datelike, NaiveDate, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::ClassItem;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Classes per weekday and local start hour, for a "when is the gym programmed" heatmap.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Heatmap {
+    /// Mondays of the counted weeks
+    #[schema(value_type = Vec<String>, format = "date")]
+    pub weeks: Vec<NaiveDate>,
+    /// Row labels, Monday first
+    #[schema(value_type = Vec<String>, example = json!(["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]))]
+    pub weekdays: Vec<Weekday>,
+    /// 7 rows (weekdays) of 24 columns (start hours 0-23)
+    pub counts: Vec<Vec<usize>>,
+    /// The highest count, for scaling colours
+    pub max: usize,
+    pub total: usize,
+}
+
+impl Heatmap {
+    pub fn new(classes: &[ClassItem], weeks: Vec<NaiveDate>) -> Self {
+        let mut counts = vec![vec![0; 24]; WEEKDAYS.len()];
+        for class in classes {
+            let day = class.date.weekday().num_days_from_monday() as usize;
+            counts[day][class.date.hour() as usize] += 1;
+        }
+        Self {
+            weeks,
+            weekdays: WEEKDAYS.to_vec(),
+            max: counts.iter().flatten().copied().max().unwrap_or_default(),
+            total: classes.len(),
+            counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn class(date: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            "WOD".to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_heatmap_counts_per_weekday_and_hour() {
+        let classes = [
+            class("2025-11-24 06:00"),
+            class("2025-12-01 06:00"),
+            class("2025-12-01 06:30"),
+            class("2025-11-30 10:00"),
+        ];
+
+        let heatmap = Heatmap::new(&classes, vec!["2025-11-24".parse().unwrap()]);
+
+        assert_eq!(heatmap.counts.len(), 7);
+        assert!(heatmap.counts.iter().all(|row| row.len() == 24));
+        assert_eq!(heatmap.counts[0][6], 3);
+        assert_eq!(heatmap.counts[6][10], 1);
+        assert_eq!(heatmap.max, 3);
+        assert_eq!(heatmap.total, 4);
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::{ClassStore, PruneStats, SNAPSHOT_VERSION, Snapshot, StoreError, StoredWeek};
+use crate::attendance::AttendanceRecord;
+use crate::diff::{ScheduleChange, diff_classes};
+use crate::models::{ChangeRecord, ClassItem, Favorites};
+
+/// Non-persistent store, used in tests and when history only needs to outlive single requests.
+#[derive(Default)]
+pub struct MemoryStore {
+    weeks: Mutex<BTreeMap<NaiveDate, Vec<StoredWeek>>>,
+    changes: Mutex<ChangeLog>,
+    favorites: Mutex<HashMap<String, Favorites>>,
+    attendance: Mutex<HashMap<String, Vec<AttendanceRecord>>>,
+    revoked_shares: Mutex<BTreeSet<String>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Default)]
+struct ChangeLog {
+    // Ids keep growing when older records are pruned, like an autoincrement column
+    last_id: u64,
+    records: Vec<(u64, ChangeRecord)>,
+}
+
+impl ChangeLog {
+    fn push(&mut self, record: ChangeRecord) {
+        self.last_id += 1;
+        self.records.push((self.last_id, record));
+    }
+}
+
+#[async_trait]
+impl ClassStore for MemoryStore {
+    async fn record_week(
+        &self,
+        monday: NaiveDate,
+        scraped_at: DateTime<Utc>,
+        classes: &[ClassItem],
+    ) -> Result<Vec<ScheduleChange>, StoreError> {
+        let mut weeks = self.weeks.lock().expect("store lock poisoned");
+        let versions = weeks.entry(monday).or_default();
+        let changes = match versions.last_mut() {
+            Some(latest) if latest.classes == classes => {
+                latest.checked_at = scraped_at;
+                return Ok(Vec::new());
+            }
+            Some(latest) => diff_classes(&latest.classes, classes),
+            None => Vec::new(),
+        };
+        versions.push(StoredWeek {
+            monday,
+            scraped_at,
+            checked_at: scraped_at,
+            classes: classes.to_vec(),
+        });
+
+        let mut log = self.changes.lock().expect("store lock poisoned");
+        for change in &changes {
+            log.push(ChangeRecord {
+                detected_at: scraped_at,
+                week: monday,
+                change: change.clone(),
+            });
+        }
+        Ok(changes)
+    }
+
+    async fn latest_week(&self, monday: NaiveDate) -> Result<Option<StoredWeek>, StoreError> {
+        let weeks = self.weeks.lock().expect("store lock poisoned");
+        Ok(weeks
+            .get(&monday)
+            .and_then(|versions| versions.last())
+            .cloned())
+    }
+
+    async fn weeks(&self) -> Result<Vec<NaiveDate>, StoreError> {
+        let weeks = self.weeks.lock().expect("store lock poisoned");
+        Ok(weeks.keys().copied().collect())
+    }
+
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<ChangeRecord>, StoreError> {
+        let log = self.changes.lock().expect("store lock poisoned");
+        Ok(log
+            .records
+            .iter()
+            .map(|(_, record)| record)
+            .filter(|record| record.detected_at >= since)
+            .cloned()
+            .collect())
+    }
+
+    async fn recent_changes(
+        &self,
+        kind: &str,
+        limit: usize,
+    ) -> Result<Vec<(u64, ChangeRecord)>, StoreError> {
+        let log = self.changes.lock().expect("store lock poisoned");
+        Ok(log
+            .records
+            .iter()
+            .rev()
+            .filter(|(_, record)| record.change.kind() == kind)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn favorites(&self, owner: &str) -> Result<Option<Favorites>, StoreError> {
+        let favorites = self.favorites.lock().expect("store lock poisoned");
+        Ok(favorites.get(owner).cloned())
+    }
+
+    async fn set_favorites(&self, owner: &str, favorites: &Favorites) -> Result<(), StoreError> {
+        self.favorites
+            .lock()
+            .expect("store lock poisoned")
+            .insert(owner.to_string(), favorites.clone());
+        Ok(())
+    }
+
+    async fn all_favorites(&self) -> Result<BTreeMap<String, Favorites>, StoreError> {
+        let favorites = self.favorites.lock().expect("store lock poisoned");
+        Ok(favorites.clone().into_iter().collect())
+    }
+
+    async fn record_attendance(
+        &self,
+        owner: &str,
+        record: &AttendanceRecord,
+    ) -> Result<(), StoreError> {
+        let mut attendance = self.attendance.lock().expect("store lock poisoned");
+        let records = attendance.entry(owner.to_string()).or_default();
+        if !records.iter().any(|r| r.class_id == record.class_id) {
+            records.push(record.clone());
+        }
+        Ok(())
+    }
+
+    async fn attendance(&self, owner: &str) -> Result<Vec<AttendanceRecord>, StoreError> {
+        let attendance = self.attendance.lock().expect("store lock poisoned");
+        Ok(attendance.get(owner).cloned().unwrap_or_default())
+    }
+
+    async fn revoke_share(&self, id: &str) -> Result<(), StoreError> {
+        self.revoked_shares
+            .lock()
+            .expect("store lock poisoned")
+            .insert(id.to_string());
+        Ok(())
+    }
+
+    async fn revoked_shares(&self) -> Result<Vec<String>, StoreError> {
+        let revoked = self.revoked_shares.lock().expect("store lock poisoned");
+        Ok(revoked.iter().cloned().collect())
+    }
+
+    async fn prune(&self, before: DateTime<Utc>) -> Result<PruneStats, StoreError> {
+        let mut stats = PruneStats::default();
+        let cutoff = before.date_naive();
+        self.weeks
+            .lock()
+            .expect("store lock poisoned")
+            .retain(|monday, versions| {
+                if *monday >= cutoff {
+                    return true;
+                }
+                stats.scrapes += versions.len() as u64;
+                stats.classes += versions.iter().map(|v| v.classes.len() as u64).sum::<u64>();
+                false
+            });
+
+        let mut log = self.changes.lock().expect("store lock poisoned");
+        let count = log.records.len();
+        log.records
+            .retain(|(_, record)| record.detected_at >= before);
+        stats.changes = (count - log.records.len()) as u64;
+        Ok(stats)
+    }
+
+    async fn export(&self) -> Result<Snapshot, StoreError> {
+        let weeks = self.weeks.lock().expect("store lock poisoned");
+        let favorites = self.favorites.lock().expect("store lock poisoned");
+        let attendance = self.attendance.lock().expect("store lock poisoned");
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            weeks: weeks.values().flatten().cloned().collect(),
+            changes: self
+                .changes
+                .lock()
+                .expect("store lock poisoned")
+                .records
+                .iter()
+                .map(|(_, record)| record.clone())
+                .collect(),
+            favorites: favorites.clone().into_iter().collect(),
+            attendance: attendance.clone().into_iter().collect(),
+            revoked_shares: self
+                .revoked_shares
+                .lock()
+                .expect("store lock poisoned")
+                .iter()
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn import(&self, snapshot: &Snapshot) -> Result<(), StoreError> {
+        let mut weeks = self.weeks.lock().expect("store lock poisoned");
+        weeks.clear();
+        for week in &snapshot.weeks {
+            weeks.entry(week.monday).or_default().push(week.clone());
+        }
+        let mut log = self.changes.lock().expect("store lock poisoned");
+        log.records.clear();
+        for record in &snapshot.changes {
+            log.push(record.clone());
+        }
+        *self.favorites.lock().expect("store lock poisoned") =
+            snapshot.favorites.clone().into_iter().collect();
+        *self.attendance.lock().expect("store lock poisoned") =
+            snapshot.attendance.clone().into_iter().collect();
+        *self.revoked_shares.lock().expect("store lock poisoned") =
+            snapshot.revoked_shares.iter().cloned().collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, TimeZone};
+
+    use super::*;
+
+    fn class(event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            event_name.to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_record_and_load_week() {
+        let store = MemoryStore::new();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let first = Utc::now();
+        let second = first + chrono::Duration::minutes(15);
+
+        store
+            .record_week(monday, first, &[class("WOD")])
+            .await
+            .unwrap();
+        store
+            .record_week(monday, second, &[class("WOD")])
+            .await
+            .unwrap();
+
+        let stored = store.latest_week(monday).await.unwrap().unwrap();
+        assert_eq!(stored.scraped_at, first);
+        assert_eq!(stored.checked_at, second);
+        assert_eq!(stored.classes, vec![class("WOD")]);
+        assert_eq!(store.weeks().await.unwrap(), vec![monday]);
+    }
+
+    #[tokio::test]
+    async fn test_changed_week_creates_new_version() {
+        let store = MemoryStore::new();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let first = Utc::now();
+        let second = first + chrono::Duration::minutes(15);
+
+        store
+            .record_week(monday, first, &[class("WOD")])
+            .await
+            .unwrap();
+        let changes = store
+            .record_week(monday, second, &[class("HYROX")])
+            .await
+            .unwrap();
+
+        let stored = store.latest_week(monday).await.unwrap().unwrap();
+        assert_eq!(stored.scraped_at, second);
+        assert_eq!(stored.classes, vec![class("HYROX")]);
+        assert_eq!(changes.len(), 2);
+        let logged = store.changes_since(first).await.unwrap();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[0].detected_at, second);
+        assert!(
+            store
+                .changes_since(second + chrono::Duration::seconds(1))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_changes() {
+        let store = MemoryStore::new();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 11, 24, 12, 0, 0).unwrap();
+
+        for (minute, classes) in [(0, vec![]), (1, vec![class("WOD")]), (2, vec![])] {
+            store
+                .record_week(monday, now + chrono::Duration::minutes(minute), &classes)
+                .await
+                .unwrap();
+        }
+        store
+            .record_week(
+                monday,
+                now + chrono::Duration::minutes(3),
+                &[class("HYROX")],
+            )
+            .await
+            .unwrap();
+
+        let added = store.recent_changes("added", 10).await.unwrap();
+        assert_eq!(added.len(), 2);
+        assert_eq!(added[0].0, 3);
+        assert_eq!(added[0].1.change.class().event_name, "HYROX");
+        assert_eq!(added[1].0, 1);
+        assert_eq!(store.recent_changes("added", 1).await.unwrap().len(), 1);
+
+        // Pruning never hands out an id twice
+        store
+            .prune(now + chrono::Duration::minutes(10))
+            .await
+            .unwrap();
+        store
+            .record_week(monday, now + chrono::Duration::minutes(11), &[])
+            .await
+            .unwrap();
+        let removed = store.recent_changes("removed", 10).await.unwrap();
+        assert_eq!(removed[0].0, 4);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_shares() {
+        let store = MemoryStore::new();
+
+        store.revoke_share("b").await.unwrap();
+        store.revoke_share("a").await.unwrap();
+        store.revoke_share("b").await.unwrap();
+
+        assert_eq!(store.revoked_shares().await.unwrap(), ["a", "b"]);
+        assert_eq!(store.export().await.unwrap().revoked_shares, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let store = MemoryStore::new();
+        let old_monday = NaiveDate::from_ymd_opt(2024, 11, 25).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 11, 24, 12, 0, 0).unwrap();
+        let old = now - chrono::Duration::days(364);
+
+        store
+            .record_week(old_monday, old, &[class("WOD")])
+            .await
+            .unwrap();
+        store
+            .record_week(old_monday, old, &[class("HYROX")])
+            .await
+            .unwrap();
+        store
+            .record_week(monday, now, &[class("WOD")])
+            .await
+            .unwrap();
+
+        let stats = store
+            .prune(now - chrono::Duration::days(300))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            PruneStats {
+                scrapes: 2,
+                classes: 2,
+                changes: 2
+            }
+        );
+        assert_eq!(store.weeks().await.unwrap(), vec![monday]);
+    }
+}
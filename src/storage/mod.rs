@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::attendance::AttendanceRecord;
+use crate::diff::ScheduleChange;
+use crate::models::{ChangeRecord, ClassItem, Favorites};
+
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod sql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use memory::MemoryStore;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("Invalid stored data: {0}")]
+    Corrupt(String),
+    #[error("Unsupported database URL: {0}")]
+    UnsupportedUrl(String),
+}
+
+/// A single scrape of one week as persisted by the store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StoredWeek {
+    #[schema(value_type = String, format = "date")]
+    pub monday: NaiveDate,
+    /// When this version of the week was first scraped
+    #[schema(value_type = String, format = "date-time")]
+    pub scraped_at: DateTime<Utc>,
+    /// When the upstream last returned this exact version
+    #[schema(value_type = String, format = "date-time")]
+    pub checked_at: DateTime<Utc>,
+    pub classes: Vec<ClassItem>,
+}
+
+/// Full copy of the stored data, used to move an instance to another host or database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Every stored version of every week, oldest first
+    pub weeks: Vec<StoredWeek>,
+    pub changes: Vec<ChangeRecord>,
+    /// Favorites keyed by owner (see `auth::authorize_owner`)
+    pub favorites: BTreeMap<String, Favorites>,
+    /// Attended classes keyed by owner
+    pub attendance: BTreeMap<String, Vec<AttendanceRecord>>,
+    /// Ids of revoked share links
+    #[serde(default)]
+    pub revoked_shares: Vec<String>,
+}
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Number of rows removed by `ClassStore::prune`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PruneStats {
+    pub scrapes: u64,
+    pub classes: u64,
+    pub changes: u64,
+}
+
+#[async_trait]
+pub trait ClassStore: Send + Sync {
+    /// Records a scrape of the week starting at `monday`. A scrape identical to the latest
+    /// stored version only refreshes its `checked_at` timestamp; a different one is stored as
+    /// a new version and the changes against the previous version are logged and returned.
+    /// Recording is atomic, also across processes sharing the database, so of concurrent
+    /// identical scrapes only one returns the changes.
+    async fn record_week(
+        &self,
+        monday: NaiveDate,
+        scraped_at: DateTime<Utc>,
+        classes: &[ClassItem],
+    ) -> Result<Vec<ScheduleChange>, StoreError>;
+
+    /// Latest stored version of the week starting at `monday`.
+    async fn latest_week(&self, monday: NaiveDate) -> Result<Option<StoredWeek>, StoreError>;
+
+    /// Mondays of all stored weeks, oldest first.
+    async fn weeks(&self) -> Result<Vec<NaiveDate>, StoreError>;
+
+    /// Logged schedule changes detected at or after `since`, oldest first.
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<ChangeRecord>, StoreError>;
+
+    /// The `limit` most recently logged changes of `kind` (see `ScheduleChange::kind`) with
+    /// their ids, newest first. Ids only ever grow, so polling clients can deduplicate by them.
+    async fn recent_changes(
+        &self,
+        kind: &str,
+        limit: usize,
+    ) -> Result<Vec<(u64, ChangeRecord)>, StoreError>;
+
+    /// Favorites saved for `owner` (see `auth::authorize_owner`).
+    async fn favorites(&self, owner: &str) -> Result<Option<Favorites>, StoreError>;
+
+    /// Replaces the favorites saved for `owner`.
+    async fn set_favorites(&self, owner: &str, favorites: &Favorites) -> Result<(), StoreError>;
+
+    /// Favorites of every owner.
+    async fn all_favorites(&self) -> Result<BTreeMap<String, Favorites>, StoreError>;
+
+    /// Marks a class as attended by `owner`; marking the same class again is a no-op.
+    async fn record_attendance(
+        &self,
+        owner: &str,
+        record: &AttendanceRecord,
+    ) -> Result<(), StoreError>;
+
+    /// Classes attended by `owner`.
+    async fn attendance(&self, owner: &str) -> Result<Vec<AttendanceRecord>, StoreError>;
+
+    /// Records the share link `id` as revoked; revoking it again is a no-op.
+    async fn revoke_share(&self, id: &str) -> Result<(), StoreError>;
+
+    /// Ids of all revoked share links.
+    async fn revoked_shares(&self) -> Result<Vec<String>, StoreError>;
+
+    /// Deletes scrapes of weeks starting before `before` and changes detected before it.
+    /// Favorites, attendance and revoked share links are kept.
+    async fn prune(&self, before: DateTime<Utc>) -> Result<PruneStats, StoreError>;
+
+    /// Reads all stored data.
+    async fn export(&self) -> Result<Snapshot, StoreError>;
+
+    /// Replaces all stored data with the snapshot.
+    async fn import(&self, snapshot: &Snapshot) -> Result<(), StoreError>;
+}
+
+/// Opens the store described by `url`: `memory:`, `sqlite://<path>`, `sqlite::memory:`
+/// or a `postgres://` connection URI.
+pub fn connect(url: &str) -> Result<Arc<dyn ClassStore>, StoreError> {
+    if url == "memory:" {
+        return Ok(Arc::new(MemoryStore::new()));
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = url
+        .strip_prefix("sqlite://")
+        .or(url.strip_prefix("sqlite:"))
+    {
+        let connection = sqlite::SqliteConnection::open(path)?;
+        return Ok(Arc::new(sql::SqlStore::new(connection)?));
+    }
+    #[cfg(feature = "postgres")]
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        let connection = postgres::PostgresConnection::connect(url)?;
+        return Ok(Arc::new(sql::SqlStore::new(connection)?));
+    }
+
+    let mut supported = vec!["memory:"];
+    if cfg!(feature = "sqlite") {
+        supported.push("sqlite://<path>");
+    }
+    if cfg!(feature = "postgres") {
+        supported.push("postgres://...");
+    }
+    Err(StoreError::UnsupportedUrl(format!(
+        "{url} (supported: {})",
+        supported.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_memory() {
+        assert!(connect("memory:").is_ok());
+    }
+
+    #[test]
+    fn test_connect_unsupported() {
+        assert!(matches!(
+            connect("mysql://localhost/timetable"),
+            Err(StoreError::UnsupportedUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Thin binding to the system `libpq`, enabled with the `postgres` feature.
+//!
+//! The library is linked, not bundled: building with the feature needs `libpq` and its
+//! development files (e.g. `libpq-dev` or `libpq-devel`), and the binary needs the shared
+//! library at run time.
+//!
+//! Safety rests on three invariants: a `PostgresConnection` owns its `PGconn` and finishes it
+//! once, in `Drop`; results live in a `QueryResult` guard that clears them before `query`
+//! returns, and rows are copied out first, so no pointer into a result outlives it; and every
+//! call needs `&mut self`, so a connection is never used by two threads at once.
+
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::ptr;
+
+use super::StoreError;
+use super::sql::{Dialect, Row, SqlConnection, SqlValue};
+
+#[repr(C)]
+struct PgConn {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct PgResult {
+    _private: [u8; 0],
+}
+
+const CONNECTION_OK: c_int = 0;
+const PGRES_COMMAND_OK: c_int = 1;
+const PGRES_TUPLES_OK: c_int = 2;
+
+#[link(name = "pq")]
+unsafe extern "C" {
+    fn PQisthreadsafe() -> c_int;
+    fn PQconnectdb(conninfo: *const c_char) -> *mut PgConn;
+    fn PQstatus(conn: *const PgConn) -> c_int;
+    fn PQreset(conn: *mut PgConn);
+    fn PQerrorMessage(conn: *const PgConn) -> *const c_char;
+    fn PQfinish(conn: *mut PgConn);
+    fn PQexec(conn: *mut PgConn, query: *const c_char) -> *mut PgResult;
+    fn PQexecParams(
+        conn: *mut PgConn,
+        command: *const c_char,
+        n_params: c_int,
+        param_types: *const c_void,
+        param_values: *const *const c_char,
+        param_lengths: *const c_int,
+        param_formats: *const c_int,
+        result_format: c_int,
+    ) -> *mut PgResult;
+    fn PQresultStatus(res: *const PgResult) -> c_int;
+    fn PQresultErrorMessage(res: *const PgResult) -> *const c_char;
+    fn PQntuples(res: *const PgResult) -> c_int;
+    fn PQnfields(res: *const PgResult) -> c_int;
+    fn PQgetisnull(res: *const PgResult, row: c_int, col: c_int) -> c_int;
+    fn PQgetvalue(res: *const PgResult, row: c_int, col: c_int) -> *const c_char;
+    fn PQgetlength(res: *const PgResult, row: c_int, col: c_int) -> c_int;
+    fn PQclear(res: *mut PgResult);
+}
+
+pub struct PostgresConnection {
+    conn: *mut PgConn,
+}
+
+// SAFETY: a thread-safe libpq lets a connection move between threads as long as two threads
+// never use it at once, which `&mut self` (and `SqlStore`'s mutex) rules out. `connect`
+// refuses a libpq built without thread safety, where that wouldn't hold.
+unsafe impl Send for PostgresConnection {}
+
+impl PostgresConnection {
+    /// Connects using a libpq connection string or `postgres://` URI.
+    pub fn connect(url: &str) -> Result<Self, StoreError> {
+        if unsafe { PQisthreadsafe() } != 1 {
+            return Err(StoreError::Database(
+                "libpq was built without thread safety".into(),
+            ));
+        }
+        let conninfo = CString::new(url)
+            .map_err(|_| StoreError::Database("connection string contains NUL".into()))?;
+        let conn = unsafe { PQconnectdb(conninfo.as_ptr()) };
+        if conn.is_null() {
+            return Err(StoreError::Database("out of memory".into()));
+        }
+        let connection = Self { conn };
+        if unsafe { PQstatus(conn) } != CONNECTION_OK {
+            return Err(connection.error());
+        }
+        Ok(connection)
+    }
+
+    fn error(&self) -> StoreError {
+        let message = unsafe { CStr::from_ptr(PQerrorMessage(self.conn)) };
+        StoreError::Database(message.to_string_lossy().trim_end().to_string())
+    }
+
+    // Re-establishes a dropped connection (e.g. after a database restart) before the next command
+    fn ensure_connected(&mut self) -> Result<(), StoreError> {
+        if unsafe { PQstatus(self.conn) } == CONNECTION_OK {
+            return Ok(());
+        }
+        unsafe { PQreset(self.conn) };
+        if unsafe { PQstatus(self.conn) } != CONNECTION_OK {
+            return Err(self.error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PostgresConnection {
+    fn drop(&mut self) {
+        unsafe { PQfinish(self.conn) };
+    }
+}
+
+/// Clears the result on every exit path.
+struct QueryResult(*mut PgResult);
+
+impl QueryResult {
+    fn check(self, connection: &PostgresConnection) -> Result<Self, StoreError> {
+        if self.0.is_null() {
+            return Err(connection.error());
+        }
+        match unsafe { PQresultStatus(self.0) } {
+            PGRES_COMMAND_OK | PGRES_TUPLES_OK => Ok(self),
+            _ => {
+                let message = unsafe { CStr::from_ptr(PQresultErrorMessage(self.0)) };
+                Err(StoreError::Database(
+                    message.to_string_lossy().trim_end().to_string(),
+                ))
+            }
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let (rows, fields) = unsafe { (PQntuples(self.0), PQnfields(self.0)) };
+        (0..rows)
+            .map(|row| {
+                (0..fields)
+                    .map(|col| unsafe {
+                        if PQgetisnull(self.0, row, col) == 1 {
+                            return None;
+                        }
+                        let value = PQgetvalue(self.0, row, col);
+                        let len = PQgetlength(self.0, row, col) as usize;
+                        let bytes = std::slice::from_raw_parts(value.cast::<u8>(), len);
+                        Some(String::from_utf8_lossy(bytes).into_owned())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Drop for QueryResult {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { PQclear(self.0) };
+        }
+    }
+}
+
+impl SqlConnection for PostgresConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn execute_batch(&mut self, sql: &str) -> Result<(), StoreError> {
+        self.ensure_connected()?;
+        let sql =
+            CString::new(sql).map_err(|_| StoreError::Database("statement contains NUL".into()))?;
+        QueryResult(unsafe { PQexec(self.conn, sql.as_ptr()) }).check(self)?;
+        Ok(())
+    }
+
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Row>, StoreError> {
+        self.ensure_connected()?;
+        let sql =
+            CString::new(sql).map_err(|_| StoreError::Database("statement contains NUL".into()))?;
+        // Parameters are sent as text and typed by the server from their context
+        let values = params
+            .iter()
+            .map(|param| match param {
+                SqlValue::Null => Ok(None),
+                SqlValue::Int(value) => Ok(Some(CString::new(value.to_string()).unwrap())),
+                SqlValue::Text(value) => CString::new(value.as_str())
+                    .map(Some)
+                    .map_err(|_| StoreError::Database("parameter contains NUL".into())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let pointers: Vec<*const c_char> = values
+            .iter()
+            .map(|value| value.as_ref().map_or(ptr::null(), |v| v.as_ptr()))
+            .collect();
+
+        let result = QueryResult(unsafe {
+            PQexecParams(
+                self.conn,
+                sql.as_ptr(),
+                pointers.len() as c_int,
+                ptr::null(),
+                pointers.as_ptr(),
+                ptr::null(),
+                ptr::null(),
+                0,
+            )
+        })
+        .check(self)?;
+        Ok(result.rows())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime, SubsecRound, Utc};
+
+    use super::*;
+    use crate::models::ClassItem;
+    use crate::storage::ClassStore;
+    use crate::storage::sql::SqlStore;
+
+    // Runs only when a scratch database is provided, e.g.
+    // TEST_POSTGRES_URL=postgres://postgres@localhost/timetable_test cargo test --features postgres
+    fn test_connection() -> Option<PostgresConnection> {
+        let url = std::env::var("TEST_POSTGRES_URL").ok()?;
+        Some(PostgresConnection::connect(&url).expect("failed to connect to TEST_POSTGRES_URL"))
+    }
+
+    #[test]
+    fn test_connect_error() {
+        let result = PostgresConnection::connect("postgres://nobody@127.0.0.1:1/none");
+        assert!(matches!(result, Err(StoreError::Database(_))));
+    }
+
+    #[test]
+    fn test_query_roundtrip() {
+        let Some(mut connection) = test_connection() else {
+            return;
+        };
+        connection
+            .execute_batch("CREATE TEMPORARY TABLE t (a TEXT, b BIGINT)")
+            .unwrap();
+        connection
+            .query(
+                "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4)",
+                &["zażółć".into(), 1.into(), SqlValue::Null, 2.into()],
+            )
+            .unwrap();
+
+        let rows = connection
+            .query("SELECT a, b FROM t ORDER BY b", &[])
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("zażółć".to_string()), Some("1".to_string())],
+                vec![None, Some("2".to_string())],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_postgres_store() {
+        let Some(connection) = test_connection() else {
+            return;
+        };
+        let store = SqlStore::new(connection).unwrap();
+        // The database outlives the test, so use a week no other run has written
+        let monday = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap()
+            + Duration::weeks(Utc::now().timestamp_subsec_micros().into());
+        let first = Utc::now().trunc_subsecs(6);
+        let class = ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            "WOD".to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+
+        store
+            .record_week(monday, first, std::slice::from_ref(&class))
+            .await
+            .unwrap();
+        store
+            .record_week(
+                monday,
+                first + Duration::minutes(15),
+                std::slice::from_ref(&class),
+            )
+            .await
+            .unwrap();
+
+        let stored = store.latest_week(monday).await.unwrap().unwrap();
+        assert_eq!(stored.scraped_at, first);
+        assert_eq!(stored.checked_at, first + Duration::minutes(15));
+        assert_eq!(stored.classes, vec![class.clone()]);
+        assert!(store.weeks().await.unwrap().contains(&monday));
+
+        let moved = ClassItem {
+            coach: "Other".to_string(),
+            ..class.shifted(Duration::hours(1))
+        };
+        store
+            .record_week(monday, first + Duration::minutes(30), &[moved])
+            .await
+            .unwrap();
+        let added = store.recent_changes("added", 1).await.unwrap();
+        assert_eq!(added[0].1.week, monday);
+    }
+}
@@ -0,0 +1,668 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+use super::{ClassStore, PruneStats, SNAPSHOT_VERSION, Snapshot, StoreError, StoredWeek};
+use crate::attendance::AttendanceRecord;
+use crate::diff::{ScheduleChange, diff_classes};
+use crate::models::{ChangeRecord, ClassItem, Favorites};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Int(i64),
+    Text(String),
+}
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        SqlValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        SqlValue::Text(value)
+    }
+}
+
+impl From<i64> for SqlValue {
+    fn from(value: i64) -> Self {
+        SqlValue::Int(value)
+    }
+}
+
+/// Result rows with every column rendered as text (`None` for NULL).
+pub type Row = Vec<Option<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+/// Minimal blocking connection interface shared by the SQLite and PostgreSQL backends.
+pub trait SqlConnection: Send + 'static {
+    fn dialect(&self) -> Dialect;
+    /// Runs one or more statements without parameters (schema, transactions).
+    fn execute_batch(&mut self, sql: &str) -> Result<(), StoreError>;
+    /// Runs a single statement with `?` placeholders and returns its rows.
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Row>, StoreError>;
+}
+
+fn schema(dialect: Dialect) -> String {
+    let id_column = match dialect {
+        Dialect::Sqlite => "INTEGER PRIMARY KEY AUTOINCREMENT",
+        Dialect::Postgres => "BIGSERIAL PRIMARY KEY",
+    };
+    format!(
+        "CREATE TABLE IF NOT EXISTS scrapes (
+            id {id_column},
+            week TEXT NOT NULL,
+            scraped_at TEXT NOT NULL,
+            checked_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS scrapes_week ON scrapes (week, scraped_at);
+        CREATE TABLE IF NOT EXISTS classes (
+            scrape_id BIGINT NOT NULL REFERENCES scrapes (id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            starts_at TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            coach TEXT NOT NULL,
+            payload TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS classes_scrape ON classes (scrape_id);
+        CREATE TABLE IF NOT EXISTS changes (
+            id {id_column},
+            week TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            payload TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS changes_detected ON changes (detected_at);
+        CREATE TABLE IF NOT EXISTS favorites (
+            owner TEXT PRIMARY KEY,
+            payload TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS attendance (
+            owner TEXT NOT NULL,
+            class_id TEXT NOT NULL,
+            starts_at TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            PRIMARY KEY (owner, class_id)
+        );
+        CREATE TABLE IF NOT EXISTS revoked_shares (
+            id TEXT PRIMARY KEY
+        );"
+    )
+}
+
+// Fixed-width UTC timestamps so that text comparison matches chronological order
+pub(crate) fn format_timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+pub(crate) fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, StoreError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|err| StoreError::Corrupt(format!("timestamp {value}: {err}")))
+}
+
+pub(crate) fn column(row: &Row, index: usize) -> Result<&str, StoreError> {
+    row.get(index)
+        .and_then(|value| value.as_deref())
+        .ok_or_else(|| StoreError::Corrupt(format!("missing column {index}")))
+}
+
+/// `ClassStore` implemented on top of any `SqlConnection`.
+pub struct SqlStore<C: SqlConnection> {
+    connection: Arc<Mutex<C>>,
+}
+
+impl<C: SqlConnection> SqlStore<C> {
+    pub fn new(mut connection: C) -> Result<Self, StoreError> {
+        connection.execute_batch(&schema(connection.dialect()))?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs blocking database work off the async runtime.
+    async fn with_connection<T, F>(&self, work: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Transaction<'_, C>) -> Result<T, StoreError> + Send + 'static,
+    {
+        self.transaction(false, work).await
+    }
+
+    /// `with_connection` for work that reads and then writes depending on what it read: other
+    /// processes sharing the database wait until it is committed.
+    async fn with_write_lock<T, F>(&self, work: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Transaction<'_, C>) -> Result<T, StoreError> + Send + 'static,
+    {
+        self.transaction(true, work).await
+    }
+
+    async fn transaction<T, F>(&self, write_lock: bool, work: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Transaction<'_, C>) -> Result<T, StoreError> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+        tokio::task::spawn_blocking(move || {
+            let mut guard = connection.lock().expect("database lock poisoned");
+            let mut tx = Transaction {
+                connection: &mut *guard,
+            };
+            let begin = match (write_lock, tx.connection.dialect()) {
+                (false, _) => "BEGIN",
+                (true, Dialect::Sqlite) => "BEGIN IMMEDIATE",
+                // Blocks other writers of scrapes but not readers
+                (true, Dialect::Postgres) => {
+                    "BEGIN; LOCK TABLE scrapes IN SHARE ROW EXCLUSIVE MODE"
+                }
+            };
+            if let Err(err) = tx.connection.execute_batch(begin) {
+                let _ = tx.connection.execute_batch("ROLLBACK");
+                return Err(err);
+            }
+            match work(&mut tx) {
+                Ok(value) => {
+                    tx.connection.execute_batch("COMMIT")?;
+                    Ok(value)
+                }
+                Err(err) => {
+                    let _ = tx.connection.execute_batch("ROLLBACK");
+                    Err(err)
+                }
+            }
+        })
+        .await
+        .map_err(|err| StoreError::Database(format!("database task failed: {err}")))?
+    }
+}
+
+/// Connection handle used inside `SqlStore::with_connection`, translating placeholders.
+pub struct Transaction<'a, C: SqlConnection> {
+    connection: &'a mut C,
+}
+
+impl<C: SqlConnection> Transaction<'_, C> {
+    pub fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Row>, StoreError> {
+        let sql = match self.connection.dialect() {
+            Dialect::Sqlite => sql.to_string(),
+            Dialect::Postgres => numbered_placeholders(sql),
+        };
+        self.connection.query(&sql, params)
+    }
+
+    fn load_classes(&mut self, scrape_id: &str) -> Result<Vec<ClassItem>, StoreError> {
+        let rows = self.query(
+            "SELECT payload FROM classes WHERE scrape_id = ? ORDER BY position",
+            &[SqlValue::Int(parse_id(scrape_id)?)],
+        )?;
+        rows.iter()
+            .map(|row| {
+                serde_json::from_str(column(row, 0)?)
+                    .map_err(|err| StoreError::Corrupt(format!("class payload: {err}")))
+            })
+            .collect()
+    }
+
+    fn latest_week(&mut self, monday: NaiveDate) -> Result<Option<StoredWeek>, StoreError> {
+        let rows = self.query(
+            "SELECT id, scraped_at, checked_at FROM scrapes WHERE week = ? \
+             ORDER BY scraped_at DESC, id DESC LIMIT 1",
+            &[monday.to_string().into()],
+        )?;
+        let Some(row) = rows.first() else {
+            return Ok(None);
+        };
+        let classes = self.load_classes(column(row, 0)?)?;
+        Ok(Some(StoredWeek {
+            monday,
+            scraped_at: parse_timestamp(column(row, 1)?)?,
+            checked_at: parse_timestamp(column(row, 2)?)?,
+            classes,
+        }))
+    }
+
+    fn changes_since(&mut self, since: DateTime<Utc>) -> Result<Vec<ChangeRecord>, StoreError> {
+        let rows = self.query(
+            "SELECT week, detected_at, payload FROM changes WHERE detected_at >= ? \
+             ORDER BY detected_at, id",
+            &[format_timestamp(since).into()],
+        )?;
+        rows.iter().map(|row| change_record(row, 0)).collect()
+    }
+
+    fn recent_changes(
+        &mut self,
+        kind: &str,
+        limit: usize,
+    ) -> Result<Vec<(u64, ChangeRecord)>, StoreError> {
+        let rows = self.query(
+            "SELECT id, week, detected_at, payload FROM changes WHERE kind = ? \
+             ORDER BY id DESC LIMIT ?",
+            &[kind.into(), (limit as i64).into()],
+        )?;
+        rows.iter()
+            .map(|row| Ok((parse_id(column(row, 0)?)? as u64, change_record(row, 1)?)))
+            .collect()
+    }
+
+    fn insert_scrape(
+        &mut self,
+        monday: NaiveDate,
+        scraped_at: DateTime<Utc>,
+        checked_at: DateTime<Utc>,
+        classes: &[ClassItem],
+    ) -> Result<(), StoreError> {
+        let rows = self.query(
+            "INSERT INTO scrapes (week, scraped_at, checked_at) VALUES (?, ?, ?) RETURNING id",
+            &[
+                monday.to_string().into(),
+                format_timestamp(scraped_at).into(),
+                format_timestamp(checked_at).into(),
+            ],
+        )?;
+        let scrape_id = parse_id(column(
+            rows.first()
+                .ok_or_else(|| StoreError::Database("insert returned no id".into()))?,
+            0,
+        )?)?;
+        for (position, item) in classes.iter().enumerate() {
+            let payload = serde_json::to_string(item)
+                .map_err(|err| StoreError::Corrupt(format!("class payload: {err}")))?;
+            self.query(
+                "INSERT INTO classes (scrape_id, position, starts_at, event_name, coach, payload) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                &[
+                    scrape_id.into(),
+                    (position as i64).into(),
+                    item.date.format("%Y-%m-%dT%H:%M:%S").to_string().into(),
+                    item.event_name.as_str().into(),
+                    item.coach.as_str().into(),
+                    payload.into(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn insert_change(&mut self, record: &ChangeRecord) -> Result<(), StoreError> {
+        let payload = serde_json::to_string(&record.change)
+            .map_err(|err| StoreError::Corrupt(format!("change payload: {err}")))?;
+        self.query(
+            "INSERT INTO changes (week, detected_at, kind, payload) VALUES (?, ?, ?, ?)",
+            &[
+                record.week.to_string().into(),
+                format_timestamp(record.detected_at).into(),
+                record.change.kind().into(),
+                payload.into(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load_favorites(&mut self) -> Result<BTreeMap<String, Favorites>, StoreError> {
+        let mut favorites = BTreeMap::new();
+        for row in self.query("SELECT owner, payload FROM favorites", &[])? {
+            let payload = serde_json::from_str(column(&row, 1)?)
+                .map_err(|err| StoreError::Corrupt(format!("favorites payload: {err}")))?;
+            favorites.insert(column(&row, 0)?.to_string(), payload);
+        }
+        Ok(favorites)
+    }
+
+    fn upsert_favorites(&mut self, owner: &str, favorites: &Favorites) -> Result<(), StoreError> {
+        let payload = serde_json::to_string(favorites)
+            .map_err(|err| StoreError::Corrupt(format!("favorites payload: {err}")))?;
+        self.query(
+            "INSERT INTO favorites (owner, payload) VALUES (?, ?) \
+             ON CONFLICT (owner) DO UPDATE SET payload = excluded.payload",
+            &[owner.into(), payload.into()],
+        )?;
+        Ok(())
+    }
+
+    fn insert_attendance(
+        &mut self,
+        owner: &str,
+        record: &AttendanceRecord,
+    ) -> Result<(), StoreError> {
+        let payload = serde_json::to_string(record)
+            .map_err(|err| StoreError::Corrupt(format!("attendance payload: {err}")))?;
+        self.query(
+            "INSERT INTO attendance (owner, class_id, starts_at, payload) VALUES (?, ?, ?, ?) \
+             ON CONFLICT (owner, class_id) DO NOTHING",
+            &[
+                owner.into(),
+                record.class_id.as_str().into(),
+                record.date.format("%Y-%m-%dT%H:%M:%S").to_string().into(),
+                payload.into(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn insert_revoked_share(&mut self, id: &str) -> Result<(), StoreError> {
+        self.query(
+            "INSERT INTO revoked_shares (id) VALUES (?) ON CONFLICT (id) DO NOTHING",
+            &[id.into()],
+        )?;
+        Ok(())
+    }
+
+    fn load_revoked_shares(&mut self) -> Result<Vec<String>, StoreError> {
+        let rows = self.query("SELECT id FROM revoked_shares ORDER BY id", &[])?;
+        rows.iter()
+            .map(|row| column(row, 0).map(str::to_string))
+            .collect()
+    }
+}
+
+// Reads the `week, detected_at, payload` columns of the changes table, starting at `offset`
+fn change_record(row: &Row, offset: usize) -> Result<ChangeRecord, StoreError> {
+    let week = column(row, offset)?;
+    Ok(ChangeRecord {
+        week: NaiveDate::parse_from_str(week, "%Y-%m-%d")
+            .map_err(|err| StoreError::Corrupt(format!("week {week}: {err}")))?,
+        detected_at: parse_timestamp(column(row, offset + 1)?)?,
+        change: serde_json::from_str(column(row, offset + 2)?)
+            .map_err(|err| StoreError::Corrupt(format!("change payload: {err}")))?,
+    })
+}
+
+fn parse_id(value: &str) -> Result<i64, StoreError> {
+    value
+        .parse()
+        .map_err(|_| StoreError::Corrupt(format!("invalid id {value}")))
+}
+
+// PostgreSQL uses $1, $2, ... instead of ?
+fn numbered_placeholders(sql: &str) -> String {
+    let mut index = 0;
+    let mut out = String::with_capacity(sql.len() + 8);
+    for ch in sql.chars() {
+        if ch == '?' {
+            index += 1;
+            out.push_str(&format!("${index}"));
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+#[async_trait]
+impl<C: SqlConnection> ClassStore for SqlStore<C> {
+    async fn record_week(
+        &self,
+        monday: NaiveDate,
+        scraped_at: DateTime<Utc>,
+        classes: &[ClassItem],
+    ) -> Result<Vec<ScheduleChange>, StoreError> {
+        let classes = classes.to_vec();
+        // Replicas recording the same scrape must not both log (and announce) its changes
+        self.with_write_lock(move |tx| {
+            let latest = tx.latest_week(monday)?;
+            if let Some(latest) = &latest
+                && latest.classes == classes
+            {
+                tx.query(
+                    "UPDATE scrapes SET checked_at = ? WHERE week = ? AND scraped_at = ?",
+                    &[
+                        format_timestamp(scraped_at).into(),
+                        monday.to_string().into(),
+                        format_timestamp(latest.scraped_at).into(),
+                    ],
+                )?;
+                return Ok(Vec::new());
+            }
+
+            tx.insert_scrape(monday, scraped_at, scraped_at, &classes)?;
+
+            let changes = latest
+                .map(|latest| diff_classes(&latest.classes, &classes))
+                .unwrap_or_default();
+            for change in &changes {
+                tx.insert_change(&ChangeRecord {
+                    detected_at: scraped_at,
+                    week: monday,
+                    change: change.clone(),
+                })?;
+            }
+            Ok(changes)
+        })
+        .await
+    }
+
+    async fn latest_week(&self, monday: NaiveDate) -> Result<Option<StoredWeek>, StoreError> {
+        self.with_connection(move |tx| tx.latest_week(monday)).await
+    }
+
+    async fn weeks(&self) -> Result<Vec<NaiveDate>, StoreError> {
+        self.with_connection(|tx| {
+            let rows = tx.query("SELECT DISTINCT week FROM scrapes ORDER BY week", &[])?;
+            rows.iter()
+                .map(|row| {
+                    let week = column(row, 0)?;
+                    NaiveDate::parse_from_str(week, "%Y-%m-%d")
+                        .map_err(|err| StoreError::Corrupt(format!("week {week}: {err}")))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<ChangeRecord>, StoreError> {
+        self.with_connection(move |tx| tx.changes_since(since))
+            .await
+    }
+
+    async fn recent_changes(
+        &self,
+        kind: &str,
+        limit: usize,
+    ) -> Result<Vec<(u64, ChangeRecord)>, StoreError> {
+        let kind = kind.to_string();
+        self.with_connection(move |tx| tx.recent_changes(&kind, limit))
+            .await
+    }
+
+    async fn favorites(&self, owner: &str) -> Result<Option<Favorites>, StoreError> {
+        let owner = owner.to_string();
+        self.with_connection(move |tx| {
+            let rows = tx.query(
+                "SELECT payload FROM favorites WHERE owner = ?",
+                &[owner.into()],
+            )?;
+            rows.first()
+                .map(|row| {
+                    serde_json::from_str(column(row, 0)?)
+                        .map_err(|err| StoreError::Corrupt(format!("favorites payload: {err}")))
+                })
+                .transpose()
+        })
+        .await
+    }
+
+    async fn set_favorites(&self, owner: &str, favorites: &Favorites) -> Result<(), StoreError> {
+        let owner = owner.to_string();
+        let favorites = favorites.clone();
+        self.with_connection(move |tx| tx.upsert_favorites(&owner, &favorites))
+            .await
+    }
+
+    async fn all_favorites(&self) -> Result<BTreeMap<String, Favorites>, StoreError> {
+        self.with_connection(|tx| tx.load_favorites()).await
+    }
+
+    async fn record_attendance(
+        &self,
+        owner: &str,
+        record: &AttendanceRecord,
+    ) -> Result<(), StoreError> {
+        let owner = owner.to_string();
+        let record = record.clone();
+        self.with_connection(move |tx| tx.insert_attendance(&owner, &record))
+            .await
+    }
+
+    async fn attendance(&self, owner: &str) -> Result<Vec<AttendanceRecord>, StoreError> {
+        let owner = owner.to_string();
+        self.with_connection(move |tx| {
+            let rows = tx.query(
+                "SELECT payload FROM attendance WHERE owner = ? ORDER BY starts_at",
+                &[owner.into()],
+            )?;
+            rows.iter()
+                .map(|row| {
+                    serde_json::from_str(column(row, 0)?)
+                        .map_err(|err| StoreError::Corrupt(format!("attendance payload: {err}")))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn revoke_share(&self, id: &str) -> Result<(), StoreError> {
+        let id = id.to_string();
+        self.with_connection(move |tx| tx.insert_revoked_share(&id))
+            .await
+    }
+
+    async fn revoked_shares(&self) -> Result<Vec<String>, StoreError> {
+        self.with_connection(|tx| tx.load_revoked_shares()).await
+    }
+
+    async fn prune(&self, before: DateTime<Utc>) -> Result<PruneStats, StoreError> {
+        self.with_connection(move |tx| {
+            let week: SqlValue = before.date_naive().to_string().into();
+            let classes = tx.query(
+                "DELETE FROM classes WHERE scrape_id IN (SELECT id FROM scrapes WHERE week < ?) \
+                 RETURNING scrape_id",
+                std::slice::from_ref(&week),
+            )?;
+            let scrapes = tx.query("DELETE FROM scrapes WHERE week < ? RETURNING id", &[week])?;
+            let changes = tx.query(
+                "DELETE FROM changes WHERE detected_at < ? RETURNING id",
+                &[format_timestamp(before).into()],
+            )?;
+            Ok(PruneStats {
+                scrapes: scrapes.len() as u64,
+                classes: classes.len() as u64,
+                changes: changes.len() as u64,
+            })
+        })
+        .await
+    }
+
+    async fn export(&self) -> Result<Snapshot, StoreError> {
+        self.with_connection(|tx| {
+            let scrapes = tx.query(
+                "SELECT id, week, scraped_at, checked_at FROM scrapes ORDER BY week, scraped_at, id",
+                &[],
+            )?;
+            let mut weeks = Vec::with_capacity(scrapes.len());
+            for row in &scrapes {
+                let week = column(row, 1)?;
+                weeks.push(StoredWeek {
+                    monday: NaiveDate::parse_from_str(week, "%Y-%m-%d")
+                        .map_err(|err| StoreError::Corrupt(format!("week {week}: {err}")))?,
+                    scraped_at: parse_timestamp(column(row, 2)?)?,
+                    checked_at: parse_timestamp(column(row, 3)?)?,
+                    classes: tx.load_classes(column(row, 0)?)?,
+                });
+            }
+
+            let favorites = tx.load_favorites()?;
+
+            let mut attendance: BTreeMap<String, Vec<AttendanceRecord>> = BTreeMap::new();
+            for row in tx.query(
+                "SELECT owner, payload FROM attendance ORDER BY owner, starts_at",
+                &[],
+            )? {
+                let record = serde_json::from_str(column(&row, 1)?)
+                    .map_err(|err| StoreError::Corrupt(format!("attendance payload: {err}")))?;
+                attendance
+                    .entry(column(&row, 0)?.to_string())
+                    .or_default()
+                    .push(record);
+            }
+
+            Ok(Snapshot {
+                version: SNAPSHOT_VERSION,
+                weeks,
+                changes: tx.changes_since(DateTime::UNIX_EPOCH)?,
+                favorites,
+                attendance,
+                revoked_shares: tx.load_revoked_shares()?,
+            })
+        })
+        .await
+    }
+
+    async fn import(&self, snapshot: &Snapshot) -> Result<(), StoreError> {
+        let snapshot = snapshot.clone();
+        self.with_connection(move |tx| {
+            tx.query("DELETE FROM classes", &[])?;
+            for table in [
+                "scrapes",
+                "changes",
+                "favorites",
+                "attendance",
+                "revoked_shares",
+            ] {
+                tx.query(&format!("DELETE FROM {table}"), &[])?;
+            }
+            for week in &snapshot.weeks {
+                tx.insert_scrape(week.monday, week.scraped_at, week.checked_at, &week.classes)?;
+            }
+            for change in &snapshot.changes {
+                tx.insert_change(change)?;
+            }
+            for (owner, favorites) in &snapshot.favorites {
+                tx.upsert_favorites(owner, favorites)?;
+            }
+            for (owner, records) in &snapshot.attendance {
+                for record in records {
+                    tx.insert_attendance(owner, record)?;
+                }
+            }
+            for id in &snapshot.revoked_shares {
+                tx.insert_revoked_share(id)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::SubsecRound;
+
+    use super::*;
+
+    #[test]
+    fn test_numbered_placeholders() {
+        assert_eq!(
+            numbered_placeholders("SELECT * FROM t WHERE a = ? AND b = ?"),
+            "SELECT * FROM t WHERE a = $1 AND b = $2"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_roundtrip() {
+        let now = Utc::now().trunc_subsecs(6);
+        let formatted = format_timestamp(now);
+        assert_eq!(parse_timestamp(&formatted).unwrap(), now);
+    }
+}
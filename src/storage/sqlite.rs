@@ -0,0 +1,473 @@
+//! Thin binding to the system `libsqlite3`, enabled with the `sqlite` feature.
+//!
+//! The library is linked, not bundled: building with the feature needs `libsqlite3` and its
+//! development files (e.g. `libsqlite3-dev` or `sqlite-devel`), and the binary needs the
+//! shared library at run time.
+//!
+//! Safety rests on three invariants: a `SqliteConnection` owns its handle and closes it once,
+//! in `Drop`; prepared statements live in a guard that finalizes them before `query`
+//! returns, so none outlives its call or its connection; and every call needs `&mut self`,
+//! so a handle is never used by two threads at once.
+
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::ptr;
+
+use super::StoreError;
+use super::sql::{Dialect, Row, SqlConnection, SqlValue};
+
+#[repr(C)]
+struct Sqlite3 {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct Sqlite3Stmt {
+    _private: [u8; 0],
+}
+
+const SQLITE_OK: c_int = 0;
+const SQLITE_ROW: c_int = 100;
+const SQLITE_DONE: c_int = 101;
+const SQLITE_NULL: c_int = 5;
+const SQLITE_OPEN_READWRITE: c_int = 0x0000_0002;
+const SQLITE_OPEN_CREATE: c_int = 0x0000_0004;
+const SQLITE_OPEN_URI: c_int = 0x0000_0040;
+const SQLITE_OPEN_NOMUTEX: c_int = 0x0000_8000;
+// Tells SQLite to copy bound text before `sqlite3_bind_text` returns
+const SQLITE_TRANSIENT: isize = -1;
+
+#[link(name = "sqlite3")]
+unsafe extern "C" {
+    fn sqlite3_open_v2(
+        filename: *const c_char,
+        db: *mut *mut Sqlite3,
+        flags: c_int,
+        vfs: *const c_char,
+    ) -> c_int;
+    fn sqlite3_close(db: *mut Sqlite3) -> c_int;
+    fn sqlite3_threadsafe() -> c_int;
+    fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+    fn sqlite3_busy_timeout(db: *mut Sqlite3, ms: c_int) -> c_int;
+    fn sqlite3_exec(
+        db: *mut Sqlite3,
+        sql: *const c_char,
+        callback: *const c_void,
+        arg: *mut c_void,
+        errmsg: *mut *mut c_char,
+    ) -> c_int;
+    fn sqlite3_free(ptr: *mut c_void);
+    fn sqlite3_prepare_v2(
+        db: *mut Sqlite3,
+        sql: *const c_char,
+        n_byte: c_int,
+        stmt: *mut *mut Sqlite3Stmt,
+        tail: *mut *const c_char,
+    ) -> c_int;
+    fn sqlite3_bind_null(stmt: *mut Sqlite3Stmt, index: c_int) -> c_int;
+    fn sqlite3_bind_int64(stmt: *mut Sqlite3Stmt, index: c_int, value: i64) -> c_int;
+    fn sqlite3_bind_text(
+        stmt: *mut Sqlite3Stmt,
+        index: c_int,
+        value: *const c_char,
+        n: c_int,
+        destructor: isize,
+    ) -> c_int;
+    fn sqlite3_step(stmt: *mut Sqlite3Stmt) -> c_int;
+    fn sqlite3_column_count(stmt: *mut Sqlite3Stmt) -> c_int;
+    fn sqlite3_column_type(stmt: *mut Sqlite3Stmt, index: c_int) -> c_int;
+    fn sqlite3_column_text(stmt: *mut Sqlite3Stmt, index: c_int) -> *const u8;
+    fn sqlite3_column_bytes(stmt: *mut Sqlite3Stmt, index: c_int) -> c_int;
+    fn sqlite3_finalize(stmt: *mut Sqlite3Stmt) -> c_int;
+}
+
+pub struct SqliteConnection {
+    db: *mut Sqlite3,
+}
+
+// SAFETY: with SQLITE_OPEN_NOMUTEX a handle may be used from any thread, just not from two
+// at a time, which `&mut self` (and `SqlStore`'s mutex) rules out. `open` refuses a library
+// built single-threaded (SQLITE_THREADSAFE=0), where that wouldn't hold.
+unsafe impl Send for SqliteConnection {}
+
+impl SqliteConnection {
+    /// Opens (and creates) the database at `path`; `:memory:` opens a private in-memory database.
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        if unsafe { sqlite3_threadsafe() } == 0 {
+            return Err(StoreError::Database(
+                "libsqlite3 was built without thread support".into(),
+            ));
+        }
+        let filename = CString::new(path)
+            .map_err(|_| StoreError::Database("database path contains NUL".into()))?;
+        let mut db = ptr::null_mut();
+        let flags =
+            SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE | SQLITE_OPEN_URI | SQLITE_OPEN_NOMUTEX;
+        let rc = unsafe { sqlite3_open_v2(filename.as_ptr(), &mut db, flags, ptr::null()) };
+        let connection = Self { db };
+        if rc != SQLITE_OK {
+            return Err(connection.error());
+        }
+        unsafe { sqlite3_busy_timeout(db, 5_000) };
+        connection.execute_batch_raw("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        Ok(connection)
+    }
+
+    fn error(&self) -> StoreError {
+        if self.db.is_null() {
+            return StoreError::Database("out of memory".into());
+        }
+        let message = unsafe { CStr::from_ptr(sqlite3_errmsg(self.db)) };
+        StoreError::Database(message.to_string_lossy().into_owned())
+    }
+
+    fn execute_batch_raw(&self, sql: &str) -> Result<(), StoreError> {
+        let sql =
+            CString::new(sql).map_err(|_| StoreError::Database("statement contains NUL".into()))?;
+        let mut errmsg = ptr::null_mut();
+        let rc = unsafe {
+            sqlite3_exec(
+                self.db,
+                sql.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                &mut errmsg,
+            )
+        };
+        if rc == SQLITE_OK {
+            return Ok(());
+        }
+        if errmsg.is_null() {
+            return Err(self.error());
+        }
+        let message = unsafe { CStr::from_ptr(errmsg) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { sqlite3_free(errmsg.cast()) };
+        Err(StoreError::Database(message))
+    }
+}
+
+impl Drop for SqliteConnection {
+    fn drop(&mut self) {
+        unsafe { sqlite3_close(self.db) };
+    }
+}
+
+/// Finalizes the prepared statement on every exit path.
+struct Statement(*mut Sqlite3Stmt);
+
+impl Drop for Statement {
+    fn drop(&mut self) {
+        unsafe { sqlite3_finalize(self.0) };
+    }
+}
+
+impl SqlConnection for SqliteConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    fn execute_batch(&mut self, sql: &str) -> Result<(), StoreError> {
+        self.execute_batch_raw(sql)
+    }
+
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Row>, StoreError> {
+        let sql =
+            CString::new(sql).map_err(|_| StoreError::Database("statement contains NUL".into()))?;
+        let mut raw = ptr::null_mut();
+        let rc =
+            unsafe { sqlite3_prepare_v2(self.db, sql.as_ptr(), -1, &mut raw, ptr::null_mut()) };
+        if rc != SQLITE_OK {
+            return Err(self.error());
+        }
+        let statement = Statement(raw);
+
+        for (i, param) in params.iter().enumerate() {
+            let index = (i + 1) as c_int;
+            let rc = match param {
+                SqlValue::Null => unsafe { sqlite3_bind_null(statement.0, index) },
+                SqlValue::Int(value) => unsafe { sqlite3_bind_int64(statement.0, index, *value) },
+                SqlValue::Text(value) => unsafe {
+                    sqlite3_bind_text(
+                        statement.0,
+                        index,
+                        value.as_ptr().cast(),
+                        value.len() as c_int,
+                        SQLITE_TRANSIENT,
+                    )
+                },
+            };
+            if rc != SQLITE_OK {
+                return Err(self.error());
+            }
+        }
+
+        let mut rows = Vec::new();
+        loop {
+            match unsafe { sqlite3_step(statement.0) } {
+                SQLITE_ROW => {
+                    let count = unsafe { sqlite3_column_count(statement.0) };
+                    let row = (0..count)
+                        .map(|col| unsafe {
+                            if sqlite3_column_type(statement.0, col) == SQLITE_NULL {
+                                return None;
+                            }
+                            let text = sqlite3_column_text(statement.0, col);
+                            let len = sqlite3_column_bytes(statement.0, col) as usize;
+                            let bytes = std::slice::from_raw_parts(text, len);
+                            Some(String::from_utf8_lossy(bytes).into_owned())
+                        })
+                        .collect();
+                    rows.push(row);
+                }
+                SQLITE_DONE => return Ok(rows),
+                _ => return Err(self.error()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{NaiveDate, NaiveDateTime, SubsecRound, TimeZone, Utc};
+
+    use super::*;
+    use crate::attendance::AttendanceRecord;
+    use crate::models::{ClassItem, Favorites};
+    use crate::storage::sql::SqlStore;
+    use crate::storage::{ClassStore, PruneStats};
+
+    fn class(event_name: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str("2025-11-24 06:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            event_name.to_string(),
+            "Coach".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_query_roundtrip() {
+        let mut connection = SqliteConnection::open(":memory:").unwrap();
+        connection
+            .execute_batch("CREATE TABLE t (a TEXT, b INTEGER)")
+            .unwrap();
+        connection
+            .query(
+                "INSERT INTO t (a, b) VALUES (?, ?), (?, ?)",
+                &["zażółć".into(), 1.into(), SqlValue::Null, 2.into()],
+            )
+            .unwrap();
+
+        let rows = connection
+            .query("SELECT a, b FROM t ORDER BY b", &[])
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Some("zażółć".to_string()), Some("1".to_string())],
+                vec![None, Some("2".to_string())],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        let store = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let first = Utc::now().trunc_subsecs(6);
+        let second = first + chrono::Duration::minutes(15);
+
+        store
+            .record_week(monday, first, &[class("WOD")])
+            .await
+            .unwrap();
+        store
+            .record_week(monday, second, &[class("WOD")])
+            .await
+            .unwrap();
+
+        let stored = store.latest_week(monday).await.unwrap().unwrap();
+        assert_eq!(stored.scraped_at, first);
+        assert_eq!(stored.checked_at, second);
+        assert_eq!(stored.classes, vec![class("WOD")]);
+
+        let changes = store
+            .record_week(monday, second, &[class("HYROX")])
+            .await
+            .unwrap();
+        let stored = store.latest_week(monday).await.unwrap().unwrap();
+        assert_eq!(stored.classes, vec![class("HYROX")]);
+        assert_eq!(store.weeks().await.unwrap(), vec![monday]);
+
+        let logged = store.changes_since(first).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            logged.iter().map(|r| r.change.clone()).collect::<Vec<_>>(),
+            changes
+        );
+        assert_eq!(logged[0].detected_at, second);
+
+        let added = store.recent_changes("added", 10).await.unwrap();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].1.change.class(), &class("HYROX"));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_concurrent_recording_logs_changes_once() {
+        let path = std::env::temp_dir().join(format!("store-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        // Two connections stand in for two processes sharing the file
+        let first = SqlStore::new(SqliteConnection::open(path).unwrap()).unwrap();
+        let second = SqlStore::new(SqliteConnection::open(path).unwrap()).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let now = Utc::now().trunc_subsecs(6);
+        first
+            .record_week(monday, now, &[class("WOD")])
+            .await
+            .unwrap();
+
+        let changed = [class("HYROX")];
+        let (a, b) = tokio::join!(
+            first.record_week(monday, now + chrono::Duration::minutes(1), &changed),
+            second.record_week(monday, now + chrono::Duration::minutes(1), &changed),
+        );
+
+        let logged = [a.unwrap(), b.unwrap()];
+        assert_eq!(
+            logged.iter().filter(|changes| !changes.is_empty()).count(),
+            1
+        );
+        assert_eq!(first.changes_since(now).await.unwrap().len(), 2);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{path}{suffix}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_favorites() {
+        let store = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        let favorites = Favorites {
+            event_names: vec!["HYROX".to_string()],
+            ..Favorites::default()
+        };
+
+        assert_eq!(store.favorites("token:a").await.unwrap(), None);
+        store
+            .set_favorites("token:a", &Favorites::default())
+            .await
+            .unwrap();
+        store.set_favorites("token:a", &favorites).await.unwrap();
+
+        assert_eq!(
+            store.favorites("token:a").await.unwrap(),
+            Some(favorites.clone())
+        );
+        assert_eq!(store.favorites("token:b").await.unwrap(), None);
+        assert_eq!(
+            store.all_favorites().await.unwrap(),
+            BTreeMap::from([("token:a".to_string(), favorites)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_attendance() {
+        let store = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        let record = AttendanceRecord::new(&class("WOD"), Utc::now().trunc_subsecs(6));
+
+        store.record_attendance("token:a", &record).await.unwrap();
+        store.record_attendance("token:a", &record).await.unwrap();
+
+        assert_eq!(store.attendance("token:a").await.unwrap(), vec![record]);
+        assert!(store.attendance("token:b").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_revoked_shares() {
+        let store = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+
+        store.revoke_share("b").await.unwrap();
+        store.revoke_share("a").await.unwrap();
+        store.revoke_share("b").await.unwrap();
+
+        assert_eq!(store.revoked_shares().await.unwrap(), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_prune() {
+        let store = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        let old_monday = NaiveDate::from_ymd_opt(2024, 11, 25).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 11, 24, 12, 0, 0).unwrap();
+        let old = Utc.with_ymd_and_hms(2024, 11, 25, 12, 0, 0).unwrap();
+
+        store
+            .record_week(old_monday, old, &[class("WOD")])
+            .await
+            .unwrap();
+        store
+            .record_week(old_monday, old, &[class("HYROX")])
+            .await
+            .unwrap();
+        store
+            .record_week(monday, now, &[class("WOD")])
+            .await
+            .unwrap();
+
+        let stats = store
+            .prune(Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            PruneStats {
+                scrapes: 2,
+                classes: 2,
+                changes: 2
+            }
+        );
+        assert_eq!(store.weeks().await.unwrap(), vec![monday]);
+        assert!(store.changes_since(old).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_snapshot_roundtrip() {
+        let source = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 11, 24, 12, 0, 0).unwrap();
+        source
+            .record_week(monday, now, &[class("WOD")])
+            .await
+            .unwrap();
+        source
+            .record_week(monday, now, &[class("HYROX")])
+            .await
+            .unwrap();
+        source
+            .set_favorites("token:a", &Favorites::default())
+            .await
+            .unwrap();
+        source
+            .record_attendance("token:a", &AttendanceRecord::new(&class("WOD"), now))
+            .await
+            .unwrap();
+        source.revoke_share("a").await.unwrap();
+
+        let snapshot = source.export().await.unwrap();
+        let target = SqlStore::new(SqliteConnection::open(":memory:").unwrap()).unwrap();
+        target
+            .record_week(monday, now, &[class("stale")])
+            .await
+            .unwrap();
+        target.import(&snapshot).await.unwrap();
+
+        assert_eq!(snapshot.weeks.len(), 2);
+        assert_eq!(snapshot.changes.len(), 2);
+        assert_eq!(snapshot.revoked_shares, ["a"]);
+        assert_eq!(target.export().await.unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,190 @@
+//! Classes that fit a member's free time: the timetable minus whatever overlaps their busy
+//! intervals, given as JSON or read from an exported calendar.
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use icalendar::{Calendar, CalendarDateTime, Component, DatePerhapsTime};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{ClassItem, local_time};
+
+/// A span of time the member can't train in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct BusyInterval {
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T08:00:00+01:00")]
+    pub start: DateTime<FixedOffset>,
+    #[schema(value_type = String, format = "date-time", example = "2025-11-24T16:00:00+01:00")]
+    pub end: DateTime<FixedOffset>,
+}
+
+impl BusyInterval {
+    /// Whether any part of `class` falls in the interval; touching ends don't count, so a class
+    /// right after a meeting still fits.
+    pub fn overlaps(&self, class: &ClassItem) -> bool {
+        self.start < class.end && class.start < self.end
+    }
+}
+
+/// JSON body of `POST /suggest`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SuggestRequest {
+    pub busy: Vec<BusyInterval>,
+}
+
+/// The classes not overlapping any of the `busy` intervals, in their original order.
+pub fn free_classes(mut classes: Vec<ClassItem>, busy: &[BusyInterval]) -> Vec<ClassItem> {
+    classes.retain(|class| !busy.iter().any(|interval| interval.overlaps(class)));
+    classes
+}
+
+/// The busy intervals of the events of an iCalendar file. Times without a zone, and zones the
+/// tz database doesn't know, are read in `time_zone`; all-day events block whole days. Events
+/// marked free (`TRANSP:TRANSPARENT`) or cancelled are left out, and recurring events only
+/// count at their first occurrence.
+pub fn busy_from_ics(text: &str, time_zone: Tz) -> Result<Vec<BusyInterval>, String> {
+    let calendar: Calendar = text
+        .parse()
+        .map_err(|err| format!("invalid iCalendar: {err}"))?;
+    let mut busy = Vec::new();
+    for event in calendar.events() {
+        if event.property_value("TRANSP") == Some("TRANSPARENT")
+            || event.property_value("STATUS") == Some("CANCELLED")
+        {
+            continue;
+        }
+        let Some(start) = event.get_start() else {
+            continue;
+        };
+        let end = match (event.get_end(), &start) {
+            (Some(end), _) => resolve(&end, time_zone),
+            // Without an end, an all-day event takes its day and a timed one an instant
+            (None, DatePerhapsTime::Date(date)) => {
+                resolve(&DatePerhapsTime::Date(*date + Duration::days(1)), time_zone)
+            }
+            (None, DatePerhapsTime::DateTime(_)) => resolve(&start, time_zone),
+        };
+        busy.push(BusyInterval {
+            start: resolve(&start, time_zone),
+            end,
+        });
+    }
+    Ok(busy)
+}
+
+fn resolve(value: &DatePerhapsTime, time_zone: Tz) -> DateTime<FixedOffset> {
+    let (local, zone): (NaiveDateTime, Tz) = match value {
+        DatePerhapsTime::Date(date) => (date.and_time(NaiveTime::MIN), time_zone),
+        DatePerhapsTime::DateTime(CalendarDateTime::Utc(time)) => {
+            return time.fixed_offset();
+        }
+        DatePerhapsTime::DateTime(CalendarDateTime::Floating(local)) => (*local, time_zone),
+        DatePerhapsTime::DateTime(CalendarDateTime::WithTimezone { date_time, tzid }) => {
+            (*date_time, tzid.parse().unwrap_or(time_zone))
+        }
+    };
+    local_time(local, zone).fixed_offset()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GYM_TIME_ZONE;
+
+    fn class(date: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            "WOD".to_string(),
+            "Anna".to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn interval(start: &str, end: &str) -> BusyInterval {
+        BusyInterval {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_free_classes() {
+        let classes = vec![
+            class("2025-11-24 06:00"),
+            class("2025-11-24 07:00"),
+            class("2025-11-24 16:00"),
+            class("2025-11-24 17:00"),
+        ];
+        let busy = [interval(
+            "2025-11-24T07:30:00+01:00",
+            "2025-11-24T16:00:00+01:00",
+        )];
+
+        let free = free_classes(classes, &busy);
+
+        let times: Vec<_> = free.iter().map(|class| class.date.to_string()).collect();
+        assert_eq!(
+            times,
+            [
+                "2025-11-24 06:00:00",
+                "2025-11-24 16:00:00",
+                "2025-11-24 17:00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlap_across_offsets() {
+        // 06:30 UTC is 07:30 in Warsaw, during the 07:00 class
+        let busy = interval("2025-11-24T06:30:00+00:00", "2025-11-24T06:45:00+00:00");
+
+        assert!(busy.overlaps(&class("2025-11-24 07:00")));
+        assert!(!busy.overlaps(&class("2025-11-24 06:00")));
+    }
+
+    #[test]
+    fn test_busy_from_ics() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//Test//EN\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:work\r\n\
+            DTSTART;TZID=Europe/Warsaw:20251124T080000\r\n\
+            DTEND;TZID=Europe/Warsaw:20251124T160000\r\n\
+            SUMMARY:Work\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:dentist\r\n\
+            DTSTART:20251125T170000Z\r\n\
+            DTEND:20251125T180000Z\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:trip\r\n\
+            DTSTART;VALUE=DATE:20251129\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:reminder\r\n\
+            DTSTART:20251126T060000\r\n\
+            DTEND:20251126T070000\r\n\
+            TRANSP:TRANSPARENT\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let busy = busy_from_ics(ics, GYM_TIME_ZONE).unwrap();
+
+        assert_eq!(
+            busy,
+            [
+                interval("2025-11-24T08:00:00+01:00", "2025-11-24T16:00:00+01:00"),
+                interval("2025-11-25T17:00:00+00:00", "2025-11-25T18:00:00+00:00"),
+                interval("2025-11-29T00:00:00+01:00", "2025-11-30T00:00:00+01:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_busy_from_invalid_ics() {
+        assert!(busy_from_ics("not a calendar", GYM_TIME_ZONE).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! A plain-text summary of a week for pasting into the gym's newsletter or a social post:
+//! how many classes of each type, what's new and what changed compared to the week before.
+
+use chrono::{Duration, NaiveDate};
+
+use crate::diff::{ScheduleChange, week_over_week};
+use crate::i18n::{Lang, Message, date_time, describe_change, kind_label};
+use crate::models::{ClassItem, ClassKind};
+
+/// The summary of the week of `monday`, compared to the `previous` week's classes when they
+/// are known.
+pub fn render_summary(
+    monday: NaiveDate,
+    classes: &[ClassItem],
+    previous: Option<&[ClassItem]>,
+    lang: Lang,
+) -> String {
+    let sunday = monday + Duration::days(6);
+    let range = format!("{}–{}", monday.format("%d.%m"), sunday.format("%d.%m.%Y"));
+    let mut text = match lang {
+        Lang::Pl => format!("Tydzień {range} – liczba zajęć: {}\n", classes.len()),
+        Lang::En if classes.len() == 1 => format!("Week of {range}: 1 class\n"),
+        Lang::En => format!("Week of {range}: {} classes\n", classes.len()),
+    };
+    if classes.is_empty() {
+        return text;
+    }
+
+    text.push_str(&format!("\n{}:\n", Message::ByType.text(lang)));
+    for kind in ClassKind::ALL {
+        let count = classes.iter().filter(|class| class.kind == kind).count();
+        if count > 0 {
+            text.push_str(&format!("- {}: {count}\n", kind_label(kind, lang)));
+        }
+    }
+
+    let Some(previous) = previous else {
+        return text;
+    };
+    let (added, changed): (Vec<_>, Vec<_>) = week_over_week(previous, classes)
+        .into_iter()
+        .partition(|change| matches!(change, ScheduleChange::Added { .. }));
+    if !added.is_empty() {
+        text.push_str(&format!("\n{}:\n", Message::NewThisWeek.text(lang)));
+        for change in &added {
+            let class = change.class();
+            text.push_str(&format!(
+                "- {} {} ({})\n",
+                class.event_name,
+                date_time(class.date, lang),
+                class.coach
+            ));
+        }
+    }
+    if !changed.is_empty() {
+        text.push_str(&format!(
+            "\n{}:\n",
+            Message::ChangesSinceLastWeek.text(lang)
+        ));
+        for change in &changed {
+            text.push_str(&format!("- {}\n", describe_change(change, lang)));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str, kind: ClassKind) -> ClassItem {
+        let mut class = ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        );
+        class.kind = kind;
+        class
+    }
+
+    fn weeks() -> (Vec<ClassItem>, Vec<ClassItem>) {
+        let previous = vec![
+            class("2025-11-17 06:00", "WOD", "Anna", ClassKind::Wod),
+            class("2025-11-17 07:00", "WOD", "Anna", ClassKind::Wod),
+            class("2025-11-19 18:00", "Hyrox", "Piotr", ClassKind::Hyrox),
+        ];
+        let current = vec![
+            class("2025-11-24 06:00", "WOD", "Marek", ClassKind::Wod),
+            class("2025-11-24 07:00", "WOD", "Anna", ClassKind::Wod),
+            class("2025-11-26 18:00", "Hyrox", "Piotr", ClassKind::Hyrox),
+            class("2025-11-29 10:00", "Kids", "Ola", ClassKind::Kids),
+        ];
+        (previous, current)
+    }
+
+    #[test]
+    fn test_summary_in_english() {
+        let (previous, current) = weeks();
+
+        let text = render_summary(
+            "2025-11-24".parse().unwrap(),
+            &current,
+            Some(&previous),
+            Lang::En,
+        );
+
+        assert_eq!(
+            text,
+            "Week of 24.11–30.11.2025: 4 classes\n\
+             \nBy type:\n- WOD: 2\n- HYROX: 1\n- Kids: 1\n\
+             \nNew this week:\n- Kids Sat 29.11 10:00 (Ola)\n\
+             \nChanges compared to last week:\n\
+             - Coach change: WOD on Mon 24.11 06:00, Anna → Marek\n"
+        );
+    }
+
+    #[test]
+    fn test_summary_in_polish() {
+        let (previous, current) = weeks();
+
+        let text = render_summary(
+            "2025-11-24".parse().unwrap(),
+            &current,
+            Some(&previous),
+            Lang::Pl,
+        );
+
+        assert!(text.starts_with("Tydzień 24.11–30.11.2025 – liczba zajęć: 4\n"));
+        assert!(text.contains("\nWedług rodzaju:\n- WOD: 2\n- HYROX: 1\n- Dzieci: 1\n"));
+        assert!(text.contains("\nNowe zajęcia:\n- Kids Sb 29.11 10:00 (Ola)\n"));
+        assert!(text.contains("- Zmiana trenera: WOD Pn 24.11 06:00, Anna → Marek\n"));
+    }
+
+    #[test]
+    fn test_empty_week() {
+        let text = render_summary("2025-11-24".parse().unwrap(), &[], Some(&[]), Lang::En);
+
+        assert_eq!(text, "Week of 24.11–30.11.2025: 0 classes\n");
+    }
+
+    #[test]
+    fn test_summary_without_previous_week() {
+        let (_, current) = weeks();
+
+        let text = render_summary("2025-11-24".parse().unwrap(), &current, None, Lang::En);
+
+        assert_eq!(
+            text,
+            "Week of 24.11–30.11.2025: 4 classes\n\
+             \nBy type:\n- WOD: 2\n- HYROX: 1\n- Kids: 1\n"
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::diff::ScheduleChange;
+use crate::models::{ClassItem, local_now, local_time};
+use crate::notify::{Notifier, NotifyError, reminder_text};
+use crate::settings::Settings;
+use crate::source::TimetableSource;
+
+// Telegram rejects messages longer than this many characters
+const MESSAGE_LIMIT: usize = 4096;
+
+#[derive(Debug, Error)]
+pub enum TelegramError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Telegram API error: {0}")]
+    Api(String),
+    #[error("Telegram notifications are enabled but {0} is not set")]
+    MissingSetting(&'static str),
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    description: Option<String>,
+}
+
+/// Posts schedule changes and the weekly digest to a Telegram chat through the Bot API.
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    api_url: Url,
+    bot_token: String,
+    chat_id: String,
+    change_template: String,
+    digest_template: String,
+}
+
+impl TelegramNotifier {
+    /// Returns `None` unless `telegram_enabled` is set.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, TelegramError> {
+        if !settings.telegram_enabled {
+            return Ok(None);
+        }
+        let bot_token = settings
+            .telegram_bot_token
+            .clone()
+            .ok_or(TelegramError::MissingSetting("APP_TELEGRAM_BOT_TOKEN"))?;
+        let chat_id = settings
+            .telegram_chat_id
+            .clone()
+            .ok_or(TelegramError::MissingSetting("APP_TELEGRAM_CHAT_ID"))?;
+        Ok(Some(Self {
+            client: reqwest::Client::new(),
+            api_url: settings.telegram_api_url.clone(),
+            bot_token,
+            chat_id,
+            change_template: settings.telegram_change_template.clone(),
+            digest_template: settings.telegram_digest_template.clone(),
+        }))
+    }
+
+    pub async fn send_digest(
+        &self,
+        monday: NaiveDate,
+        classes: &[ClassItem],
+    ) -> Result<(), TelegramError> {
+        self.send(&render_digest(&self.digest_template, monday, classes))
+            .await
+    }
+
+    async fn send(&self, text: &str) -> Result<(), TelegramError> {
+        let url = self
+            .api_url
+            // `./` keeps the `<id>:` prefix of the token from being parsed as a URL scheme
+            .join(&format!("./bot{}/sendMessage", self.bot_token))
+            .map_err(|err| TelegramError::Api(err.to_string()))?;
+        for chunk in split_message(text, MESSAGE_LIMIT) {
+            let response = self
+                .client
+                .post(url.clone())
+                .json(&serde_json::json!({
+                    "chat_id": self.chat_id,
+                    "text": chunk,
+                    "disable_web_page_preview": true,
+                }))
+                .send()
+                .await
+                // Errors would otherwise carry the URL, and with it the bot token, into the logs
+                .map_err(reqwest::Error::without_url)?;
+            let status = response.status();
+            let body: ApiResponse = response.json().await.map_err(reqwest::Error::without_url)?;
+            if !body.ok {
+                return Err(TelegramError::Api(
+                    body.description.unwrap_or_else(|| status.to_string()),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    /// Sends one message listing all changes detected in a week.
+    async fn notify_changes(
+        &self,
+        monday: NaiveDate,
+        changes: &[ScheduleChange],
+    ) -> Result<(), NotifyError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let lines: Vec<String> = changes
+            .iter()
+            .map(|change| render_change(&self.change_template, change))
+            .collect();
+        let text = format!(
+            "Schedule changes for the week of {monday}:\n{}",
+            lines.join("\n")
+        );
+        Ok(self.send(&text).await?)
+    }
+
+    async fn remind(&self, class: &ClassItem, minutes_before: u32) -> Result<(), NotifyError> {
+        Ok(self.send(&reminder_text(class, minutes_before)).await?)
+    }
+}
+
+/// Template placeholders: `{summary}`, `{kind}`, `{event_name}`, `{coach}`, `{date}`.
+pub fn render_change(template: &str, change: &ScheduleChange) -> String {
+    let class = change.class();
+    template
+        .replace("{summary}", &change.describe())
+        .replace("{kind}", change.kind())
+        .replace("{event_name}", &class.event_name)
+        .replace("{coach}", &class.coach)
+        .replace("{date}", &class.date.format("%a %d.%m %H:%M").to_string())
+}
+
+/// Template placeholders: `{week}` (the Monday) and `{classes}` (one line per class).
+pub fn render_digest(template: &str, monday: NaiveDate, classes: &[ClassItem]) -> String {
+    let lines: Vec<String> = classes
+        .iter()
+        .map(|class| {
+            format!(
+                "{} {} ({})",
+                class.date.format("%a %d.%m %H:%M"),
+                class.event_name,
+                class.coach
+            )
+        })
+        .collect();
+    let classes = if lines.is_empty() {
+        "No classes scheduled".to_string()
+    } else {
+        lines.join("\n")
+    };
+    template
+        .replace("{week}", &monday.to_string())
+        .replace("{classes}", &classes)
+}
+
+// Splits on line boundaries so no message exceeds `limit` characters
+fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let line: String = line.chars().take(limit).collect();
+        let needed = line.chars().count() + usize::from(!current.is_empty());
+        if current.chars().count() + needed > limit {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The next Sunday at `hour`:00 strictly after `now`.
+fn next_digest_at(now: NaiveDateTime, hour: u32) -> NaiveDateTime {
+    let days_until_sunday =
+        (Weekday::Sun.num_days_from_monday() + 7 - now.weekday().num_days_from_monday()) % 7;
+    let time = NaiveTime::from_hms_opt(hour.min(23), 0, 0).expect("valid digest hour");
+    let candidate = (now.date() + Duration::days(days_until_sunday.into())).and_time(time);
+    if candidate > now {
+        candidate
+    } else {
+        candidate + Duration::weeks(1)
+    }
+}
+
+/// Every Sunday at `digest_hour` posts the coming week's timetable.
+pub async fn run_weekly_digest(
+    notifier: Arc<TelegramNotifier>,
+    source: Arc<dyn TimetableSource>,
+    location: Option<String>,
+    digest_hour: u32,
+    time_zone: Tz,
+) {
+    loop {
+        let now = local_now(time_zone);
+        let next = local_time(next_digest_at(now.naive_local(), digest_hour), time_zone);
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        let monday = next.date_naive() + Duration::days(1);
+        match source.fetch_week(monday, location.clone()).await {
+            Ok(classes) => match notifier.send_digest(monday, &classes).await {
+                Ok(()) => info!(%monday, classes = classes.len(), "sent Telegram digest"),
+                Err(err) => warn!(error = %err, "failed to send Telegram digest"),
+            },
+            Err(err) => warn!(error = %err, %monday, "failed to fetch timetable for digest"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    fn class(date: &str, event_name: &str, coach: &str) -> ClassItem {
+        ClassItem::new(
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap(),
+            event_name.to_string(),
+            coach.to_string(),
+            Some(60),
+            "https://example.com".to_string(),
+        )
+    }
+
+    fn notifier(api_url: &str) -> TelegramNotifier {
+        let settings = Settings {
+            telegram_enabled: true,
+            telegram_bot_token: Some("123:abc".to_string()),
+            telegram_chat_id: Some("-10042".to_string()),
+            telegram_api_url: Url::parse(api_url).unwrap(),
+            ..Settings::default()
+        };
+        TelegramNotifier::from_settings(&settings).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_disabled_or_incomplete_settings() {
+        assert!(
+            TelegramNotifier::from_settings(&Settings::default())
+                .unwrap()
+                .is_none()
+        );
+        let settings = Settings {
+            telegram_enabled: true,
+            telegram_bot_token: Some("123:abc".to_string()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            TelegramNotifier::from_settings(&settings),
+            Err(TelegramError::MissingSetting("APP_TELEGRAM_CHAT_ID"))
+        ));
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let change = ScheduleChange::Removed {
+            class: class("2025-11-24 06:00", "WOD", "Anna"),
+        };
+        assert_eq!(
+            render_change("[{kind}] {event_name} with {coach}, {date}", &change),
+            "[removed] WOD with Anna, Mon 24.11 06:00"
+        );
+
+        let monday = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        assert_eq!(
+            render_digest(
+                "Week {week}\n{classes}",
+                monday,
+                &[
+                    class("2025-11-24 06:00", "WOD", "Anna"),
+                    class("2025-11-25 18:00", "HYROX", "Piotr"),
+                ]
+            ),
+            "Week 2025-11-24\nMon 24.11 06:00 WOD (Anna)\nTue 25.11 18:00 HYROX (Piotr)"
+        );
+        assert_eq!(
+            render_digest("{classes}", monday, &[]),
+            "No classes scheduled"
+        );
+    }
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("ab\ncd\nef", 5), vec!["ab\ncd", "ef"]);
+        assert_eq!(split_message("abcdefgh", 4), vec!["abcd"]);
+    }
+
+    #[test]
+    fn test_next_digest_at() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        // Wednesday -> the coming Sunday
+        assert_eq!(
+            next_digest_at(at("2025-11-26 10:00"), 18),
+            at("2025-11-30 18:00")
+        );
+        // Sunday before the hour -> the same day
+        assert_eq!(
+            next_digest_at(at("2025-11-30 17:59"), 18),
+            at("2025-11-30 18:00")
+        );
+        // Sunday at the hour -> next week
+        assert_eq!(
+            next_digest_at(at("2025-11-30 18:00"), 18),
+            at("2025-12-07 18:00")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notify_changes_posts_message() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/bot123:abc/sendMessage")
+                .json_body_includes(r#"{"chat_id": "-10042"}"#)
+                .body_includes("Coach change: WOD on Mon 24.11 06:00, Anna → Marek");
+            then.status(200).json_body(serde_json::json!({"ok": true}));
+        });
+
+        let changes = vec![ScheduleChange::CoachChanged {
+            class: class("2025-11-24 06:00", "WOD", "Marek"),
+            previous_coach: "Anna".to_string(),
+        }];
+        notifier(&server.base_url())
+            .notify_changes(NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(), &changes)
+            .await
+            .unwrap();
+
+        mock.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST);
+            then.status(400).json_body(
+                serde_json::json!({"ok": false, "description": "Bad Request: chat not found"}),
+            );
+        });
+
+        let result = notifier(&server.base_url())
+            .send_digest(NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(), &[])
+            .await;
+
+        assert!(
+            matches!(result, Err(TelegramError::Api(message)) if message.contains("chat not found"))
+        );
+    }
+}
@@ -1,4 +1,10 @@
 use crate::error::ApiError;
+use crate::models::{Branding, ClassKind};
+use crate::settings::{MAX_SHARE_TTL_HOURS, is_hex_color};
+use crate::suggest::BusyInterval;
+
+// Longest calendar name or event prefix a feed may be branded with
+const MAX_BRANDING_LEN: usize = 100;
 
 pub fn validate_weeks(value: u8) -> Result<u8, ApiError> {
     if (1..=6).contains(&value) {
@@ -8,6 +14,80 @@ pub fn validate_weeks(value: u8) -> Result<u8, ApiError> {
     }
 }
 
+pub fn validate_alarm(minutes: u32) -> Result<u32, ApiError> {
+    if minutes <= 24 * 60 {
+        Ok(minutes)
+    } else {
+        Err(ApiError::BadRequest(
+            "alarm must be at most 1440 minutes".into(),
+        ))
+    }
+}
+
+pub fn validate_limit(value: usize) -> Result<usize, ApiError> {
+    if (1..=100).contains(&value) {
+        Ok(value)
+    } else {
+        Err(ApiError::BadRequest(
+            "limit must be between 1 and 100".into(),
+        ))
+    }
+}
+
+pub fn validate_ttl_hours(hours: u32) -> Result<u32, ApiError> {
+    if (1..=MAX_SHARE_TTL_HOURS).contains(&hours) {
+        Ok(hours)
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "ttl_hours must be between 1 and {MAX_SHARE_TTL_HOURS}"
+        )))
+    }
+}
+
+pub fn validate_branding(branding: Branding) -> Result<Branding, ApiError> {
+    let texts = [
+        ("calendar_name", &branding.calendar_name),
+        ("event_prefix", &branding.event_prefix),
+    ];
+    for (name, text) in texts {
+        if let Some(text) = text
+            && (text.chars().count() > MAX_BRANDING_LEN || text.chars().any(char::is_control))
+        {
+            return Err(ApiError::BadRequest(format!(
+                "{name} must be at most {MAX_BRANDING_LEN} characters without line breaks"
+            )));
+        }
+    }
+    if let Some(color) = &branding.color
+        && !is_hex_color(color)
+    {
+        return Err(ApiError::BadRequest(
+            "color must be a `#rrggbb` color".into(),
+        ));
+    }
+    Ok(branding)
+}
+
+/// Parses a comma-separated list of class kinds; empty when `value` is unset.
+pub fn validate_kinds(value: Option<&str>) -> Result<Vec<ClassKind>, ApiError> {
+    value
+        .into_iter()
+        .flat_map(|kinds| kinds.split(','))
+        .filter(|kind| !kind.trim().is_empty())
+        .map(|kind| kind.parse().map_err(ApiError::BadRequest))
+        .collect()
+}
+
+pub fn validate_busy(busy: Vec<BusyInterval>) -> Result<Vec<BusyInterval>, ApiError> {
+    match busy.iter().find(|interval| interval.end < interval.start) {
+        Some(interval) => Err(ApiError::BadRequest(format!(
+            "busy interval starting {} ends before it starts",
+            interval.start.to_rfc3339()
+        ))),
+        None => Ok(busy),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -19,4 +99,85 @@ mod tests {
         assert!(validate_weeks(0).is_err());
         assert!(validate_weeks(7).is_err());
     }
+
+    #[test]
+    fn test_validate_limit() {
+        assert!(validate_limit(1).is_ok());
+        assert!(validate_limit(100).is_ok());
+        assert!(validate_limit(0).is_err());
+        assert!(validate_limit(101).is_err());
+    }
+
+    #[test]
+    fn test_validate_ttl_hours() {
+        assert!(validate_ttl_hours(1).is_ok());
+        assert!(validate_ttl_hours(MAX_SHARE_TTL_HOURS).is_ok());
+        assert!(validate_ttl_hours(0).is_err());
+        assert!(validate_ttl_hours(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_validate_alarm() {
+        assert!(validate_alarm(0).is_ok());
+        assert!(validate_alarm(1440).is_ok());
+        assert!(validate_alarm(1441).is_err());
+    }
+
+    #[test]
+    fn test_validate_branding() {
+        let branding = Branding {
+            calendar_name: Some("Morning Crew".to_string()),
+            event_prefix: Some("MC: ".to_string()),
+            color: Some("#FF6600".to_string()),
+        };
+        assert_eq!(validate_branding(branding.clone()).unwrap(), branding);
+        assert!(
+            validate_branding(Branding {
+                color: Some("orange".to_string()),
+                ..Branding::default()
+            })
+            .is_err()
+        );
+        assert!(
+            validate_branding(Branding {
+                calendar_name: Some("Morning\nCrew".to_string()),
+                ..Branding::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_kinds() {
+        assert!(validate_kinds(None).unwrap().is_empty());
+        assert_eq!(
+            validate_kinds(Some("hyrox, Open_Gym")).unwrap(),
+            [ClassKind::Hyrox, ClassKind::OpenGym]
+        );
+        assert!(validate_kinds(Some("yoga")).is_err());
+    }
+
+    #[test]
+    fn test_validate_busy() {
+        let interval = |start: &str, end: &str| BusyInterval {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        };
+
+        assert!(validate_busy(vec![]).is_ok());
+        assert!(
+            validate_busy(vec![interval(
+                "2025-11-24T08:00:00+01:00",
+                "2025-11-24T08:00:00+01:00"
+            )])
+            .is_ok()
+        );
+        assert!(
+            validate_busy(vec![interval(
+                "2025-11-24T08:00:00+01:00",
+                "2025-11-24T07:00:00+01:00"
+            )])
+            .is_err()
+        );
+    }
 }
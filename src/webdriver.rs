@@ -0,0 +1,207 @@
+//! Renders pages in a headless browser through a WebDriver server (chromedriver, geckodriver
+//! or Selenium), for eFitness skins that build the calendar client-side.
+
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use thiserror::Error;
+use url::Url;
+
+// What the rendered page must contain before its HTML is taken
+const TABLE_SELECTOR: &str = "table.calendar_table_agenda";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Error)]
+pub enum WebDriverError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("WebDriver replied {status}: {message}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("WebDriver reply lacks `{0}`")]
+    Malformed(&'static str),
+}
+
+/// Opens pages in a fresh headless browser session and returns their HTML once the timetable
+/// table shows up, or after `wait` without it.
+#[derive(Debug, Clone)]
+pub struct WebDriverRenderer {
+    client: reqwest::Client,
+    endpoint: Url,
+    wait: Duration,
+}
+
+impl WebDriverRenderer {
+    pub fn new(endpoint: Url, wait: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            wait,
+        }
+    }
+
+    pub async fn render(&self, url: &Url) -> Result<String, WebDriverError> {
+        let capabilities = json!({
+            "capabilities": {
+                "alwaysMatch": {
+                    "goog:chromeOptions": {"args": ["--headless=new", "--no-sandbox"]},
+                    "moz:firefoxOptions": {"args": ["-headless"]}
+                }
+            }
+        });
+        let session = self
+            .command(reqwest::Method::POST, "session", Some(capabilities))
+            .await?;
+        let id = session["sessionId"]
+            .as_str()
+            .ok_or(WebDriverError::Malformed("sessionId"))?
+            .to_string();
+
+        let html = self.page_source(&id, url).await;
+        // The browser is closed even when rendering failed
+        let closed = self
+            .command(reqwest::Method::DELETE, &format!("session/{id}"), None)
+            .await;
+        if let Err(err) = closed {
+            tracing::warn!(error = %err, "failed to close the WebDriver session");
+        }
+        html
+    }
+
+    async fn page_source(&self, id: &str, url: &Url) -> Result<String, WebDriverError> {
+        self.command(
+            reqwest::Method::POST,
+            &format!("session/{id}/url"),
+            Some(json!({"url": url.as_str()})),
+        )
+        .await?;
+
+        let started = Instant::now();
+        let find = json!({"using": "css selector", "value": TABLE_SELECTOR});
+        loop {
+            let found = self
+                .command(
+                    reqwest::Method::POST,
+                    &format!("session/{id}/element"),
+                    Some(find.clone()),
+                )
+                .await;
+            match found {
+                Ok(_) => break,
+                // Not rendered yet; the parser reports a missing table if it never is
+                Err(WebDriverError::Rejected { .. }) if started.elapsed() < self.wait => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(WebDriverError::Rejected { .. }) => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let source = self
+            .command(reqwest::Method::GET, &format!("session/{id}/source"), None)
+            .await?;
+        source
+            .as_str()
+            .map(str::to_string)
+            .ok_or(WebDriverError::Malformed("value"))
+    }
+
+    // Sends one WebDriver command, returning the `value` of its reply
+    async fn command(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, WebDriverError> {
+        let url = format!("{}/{path}", self.endpoint.as_str().trim_end_matches('/'));
+        let mut request = self.client.request(method, url);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let mut reply: Value = response.json().await?;
+        if !status.is_success() {
+            let message = reply["value"]["message"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            return Err(WebDriverError::Rejected { status, message });
+        }
+        Ok(reply["value"].take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render_returns_page_source_and_closes_session() {
+        let server = MockServer::start();
+        let session = server.mock(|when, then| {
+            when.method(POST).path("/session");
+            then.status(200)
+                .json_body(json!({"value": {"sessionId": "abc", "capabilities": {}}}));
+        });
+        let navigate = server.mock(|when, then| {
+            when.method(POST)
+                .path("/session/abc/url")
+                .json_body(json!({"url": "https://gym.example.com/kalendarz-zajec"}));
+            then.status(200).json_body(json!({"value": null}));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/session/abc/element");
+            then.status(200)
+                .json_body(json!({"value": {"element-6066-11e4-a52e-4f735466cecf": "1"}}));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/session/abc/source");
+            then.status(200)
+                .json_body(json!({"value": "<table class=\"calendar_table_agenda\"></table>"}));
+        });
+        let close = server.mock(|when, then| {
+            when.method(DELETE).path("/session/abc");
+            then.status(200).json_body(json!({"value": null}));
+        });
+        let renderer =
+            WebDriverRenderer::new(Url::parse(&server.base_url()).unwrap(), Duration::ZERO);
+
+        let html = renderer
+            .render(&Url::parse("https://gym.example.com/kalendarz-zajec").unwrap())
+            .await
+            .unwrap();
+
+        assert!(html.contains("calendar_table_agenda"));
+        session.assert();
+        navigate.assert();
+        close.assert();
+    }
+
+    #[tokio::test]
+    async fn test_render_reports_refused_session() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/session");
+            then.status(500).json_body(json!({
+                "value": {"error": "session not created", "message": "no Chrome binary"}
+            }));
+        });
+        let renderer =
+            WebDriverRenderer::new(Url::parse(&server.base_url()).unwrap(), Duration::ZERO);
+
+        let err = renderer
+            .render(&Url::parse("https://gym.example.com/").unwrap())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            WebDriverError::Rejected { message, .. } if message == "no Chrome binary"
+        ));
+    }
+}